@@ -0,0 +1,730 @@
+use crate::csv_profile::HyperLogLog;
+use crate::csv_sort::parse_number;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Upper bound on distinct groups, to keep runaway group-bys from exhausting memory
+const MAX_GROUPS: usize = 1_000_000;
+/// Distinct values a `count_distinct` keeps exactly before switching to an estimate
+const MAX_EXACT_DISTINCT: usize = 10_000;
+/// Default cap on distinct column keys of a pivot
+pub const DEFAULT_PIVOT_COLUMNS: usize = 100;
+const TOTAL_LABEL: &str = "Total";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateFn {
+    Count,
+    CountDistinct,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFn {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AggregateFn::Count => "count",
+            AggregateFn::CountDistinct => "count_distinct",
+            AggregateFn::Sum => "sum",
+            AggregateFn::Avg => "avg",
+            AggregateFn::Min => "min",
+            AggregateFn::Max => "max",
+        }
+    }
+}
+
+/// One output column: `count` without a column counts rows
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Aggregate {
+    pub func: AggregateFn,
+    pub column: Option<usize>,
+}
+
+/// Result table of an aggregation, group columns first
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct AggregateTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Pivot totals, kept apart from the keyed cells so a key named "Total" can't
+    /// be mistaken for them
+    pub totals: Option<PivotTotals>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PivotTotals {
+    /// One per row, in row order
+    pub rows: Vec<String>,
+    /// One per column key, in header order
+    pub columns: Vec<String>,
+    pub grand: String,
+}
+
+impl AggregateTable {
+    /// Header and data records for a flat file, with pivot totals written as a
+    /// trailing `Total` column and row
+    pub fn records(&self) -> Vec<Vec<String>> {
+        let mut records = Vec::with_capacity(self.rows.len() + 2);
+        let Some(totals) = &self.totals else {
+            records.push(self.headers.clone());
+            records.extend(self.rows.iter().cloned());
+            return records;
+        };
+        let mut headers = self.headers.clone();
+        headers.push(TOTAL_LABEL.to_string());
+        records.push(headers);
+        for (row, total) in self.rows.iter().zip(&totals.rows) {
+            let mut row = row.clone();
+            row.push(total.clone());
+            records.push(row);
+        }
+        let mut last = vec![TOTAL_LABEL.to_string()];
+        last.extend(totals.columns.iter().cloned());
+        last.push(totals.grand.clone());
+        records.push(last);
+        records
+    }
+}
+
+/// Validated group-by columns and aggregates
+#[derive(Clone, Debug)]
+pub struct AggregatePlan {
+    group_by: Vec<usize>,
+    aggregates: Vec<Aggregate>,
+}
+
+impl AggregatePlan {
+    pub fn new(
+        group_by: Vec<usize>,
+        aggregates: Vec<Aggregate>,
+        num_columns: usize,
+    ) -> Result<Self, String> {
+        if aggregates.is_empty() {
+            return Err("Choose at least one aggregate".to_string());
+        }
+        if let Some(column) = group_by.iter().find(|&&column| column >= num_columns) {
+            return Err(format!("Group-by column {column} is out of range"));
+        }
+        for aggregate in &aggregates {
+            validate_aggregate(aggregate, num_columns)?;
+        }
+        Ok(AggregatePlan {
+            group_by,
+            aggregates,
+        })
+    }
+
+    /// Output headers, e.g. `status`, `count`, `sum(amount)`
+    pub fn headers(&self, names: &[String]) -> Vec<String> {
+        let name = |column: usize| column_name(names, column);
+        self.group_by
+            .iter()
+            .map(|&column| name(column))
+            .chain(
+                self.aggregates
+                    .iter()
+                    .map(|aggregate| match aggregate.column {
+                        Some(column) => format!("{}({})", aggregate.func.as_str(), name(column)),
+                        None => aggregate.func.as_str().to_string(),
+                    }),
+            )
+            .collect()
+    }
+}
+
+fn column_name(names: &[String], column: usize) -> String {
+    names
+        .get(column)
+        .filter(|name| !name.is_empty())
+        .cloned()
+        .unwrap_or_else(|| format!("Column {}", column + 1))
+}
+
+fn validate_aggregate(aggregate: &Aggregate, num_columns: usize) -> Result<(), String> {
+    match aggregate.column {
+        Some(column) if column >= num_columns => {
+            Err(format!("Aggregate column {column} is out of range"))
+        }
+        None if aggregate.func != AggregateFn::Count => {
+            Err(format!("{} needs a column", aggregate.func.as_str()))
+        }
+        _ => Ok(()),
+    }
+}
+
+enum Accumulator {
+    Count(u64),
+    Distinct(HashSet<Box<str>>),
+    /// `count_distinct` past `MAX_EXACT_DISTINCT` values, reported with a `~` prefix
+    ApproxDistinct(HyperLogLog),
+    Sum {
+        sum: f64,
+        count: u64,
+    },
+    Min(Option<f64>),
+    Max(Option<f64>),
+}
+
+impl Accumulator {
+    fn new(func: AggregateFn) -> Self {
+        match func {
+            AggregateFn::Count => Accumulator::Count(0),
+            AggregateFn::CountDistinct => Accumulator::Distinct(HashSet::new()),
+            AggregateFn::Sum | AggregateFn::Avg => Accumulator::Sum { sum: 0.0, count: 0 },
+            AggregateFn::Min => Accumulator::Min(None),
+            AggregateFn::Max => Accumulator::Max(None),
+        }
+    }
+
+    /// `cell` is None for row counts; empty cells and non-numbers are skipped
+    fn push(&mut self, cell: Option<&str>) {
+        match (&mut *self, cell) {
+            (Accumulator::Count(count), None) => *count += 1,
+            (Accumulator::Count(count), Some(cell)) if !cell.trim().is_empty() => *count += 1,
+            (Accumulator::Distinct(values), Some(cell))
+                if !cell.trim().is_empty() && !values.contains(cell) =>
+            {
+                values.insert(cell.into());
+                if values.len() > MAX_EXACT_DISTINCT {
+                    let mut estimate = HyperLogLog::new();
+                    values.iter().for_each(|value| estimate.insert(value));
+                    *self = Accumulator::ApproxDistinct(estimate);
+                }
+            }
+            (Accumulator::ApproxDistinct(estimate), Some(cell)) if !cell.trim().is_empty() => {
+                estimate.insert(cell);
+            }
+            (Accumulator::Sum { sum, count }, Some(cell)) => {
+                if let Some(number) = parse_number(cell.trim()) {
+                    *sum += number;
+                    *count += 1;
+                }
+            }
+            (Accumulator::Min(min), Some(cell)) => {
+                if let Some(number) = parse_number(cell.trim()) {
+                    *min = Some(min.map_or(number, |min| min.min(number)));
+                }
+            }
+            (Accumulator::Max(max), Some(cell)) => {
+                if let Some(number) = parse_number(cell.trim()) {
+                    *max = Some(max.map_or(number, |max| max.max(number)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn merge(&mut self, other: Accumulator) {
+        match (self, other) {
+            (Accumulator::Count(count), Accumulator::Count(other)) => *count += other,
+            (accumulator @ Accumulator::Distinct(_), Accumulator::ApproxDistinct(mut estimate)) => {
+                if let Accumulator::Distinct(values) = accumulator {
+                    values.iter().for_each(|value| estimate.insert(value));
+                }
+                *accumulator = Accumulator::ApproxDistinct(estimate);
+            }
+            (Accumulator::ApproxDistinct(estimate), Accumulator::Distinct(other)) => {
+                other.iter().for_each(|value| estimate.insert(value));
+            }
+            (Accumulator::ApproxDistinct(estimate), Accumulator::ApproxDistinct(other)) => {
+                estimate.merge(&other);
+            }
+            (accumulator @ Accumulator::Distinct(_), Accumulator::Distinct(other)) => {
+                for value in other {
+                    accumulator.push(Some(&value));
+                }
+            }
+            (
+                Accumulator::Sum { sum, count },
+                Accumulator::Sum {
+                    sum: other_sum,
+                    count: other_count,
+                },
+            ) => {
+                *sum += other_sum;
+                *count += other_count;
+            }
+            (Accumulator::Min(min), Accumulator::Min(Some(other))) => {
+                *min = Some(min.map_or(other, |min| min.min(other)));
+            }
+            (Accumulator::Max(max), Accumulator::Max(Some(other))) => {
+                *max = Some(max.map_or(other, |max| max.max(other)));
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(&self, func: AggregateFn) -> String {
+        match self {
+            Accumulator::Count(count) => count.to_string(),
+            Accumulator::Distinct(values) => values.len().to_string(),
+            Accumulator::ApproxDistinct(estimate) => format!("~{}", estimate.estimate()),
+            Accumulator::Sum { count: 0, .. } => String::new(),
+            Accumulator::Sum { sum, count } => {
+                if func == AggregateFn::Avg {
+                    format_number(sum / *count as f64)
+                } else {
+                    format_number(*sum)
+                }
+            }
+            Accumulator::Min(value) | Accumulator::Max(value) => {
+                value.map(format_number).unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// Whole numbers print without a fraction so counts-like sums stay readable
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Per-group accumulators for one slice of rows; slices are combined with `merge`
+pub struct GroupTable<'a> {
+    plan: &'a AggregatePlan,
+    groups: HashMap<Vec<Box<str>>, Vec<Accumulator>>,
+}
+
+impl<'a> GroupTable<'a> {
+    pub fn new(plan: &'a AggregatePlan) -> Self {
+        GroupTable {
+            plan,
+            groups: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, row: &[String]) -> Result<(), String> {
+        let key: Vec<Box<str>> = self
+            .plan
+            .group_by
+            .iter()
+            .map(|&column| row.get(column).map(String::as_str).unwrap_or("").into())
+            .collect();
+        if !self.groups.contains_key(&key) && self.groups.len() >= MAX_GROUPS {
+            return Err(format!("Too many groups (more than {MAX_GROUPS})"));
+        }
+        let accumulators = self.groups.entry(key).or_insert_with(|| {
+            self.plan
+                .aggregates
+                .iter()
+                .map(|aggregate| Accumulator::new(aggregate.func))
+                .collect()
+        });
+        for (accumulator, aggregate) in accumulators.iter_mut().zip(&self.plan.aggregates) {
+            let cell = aggregate
+                .column
+                .map(|column| row.get(column).map(String::as_str).unwrap_or(""));
+            accumulator.push(cell);
+        }
+        Ok(())
+    }
+
+    pub fn merge(mut self, other: GroupTable<'a>) -> Result<Self, String> {
+        for (key, accumulators) in other.groups {
+            match self.groups.get_mut(&key) {
+                Some(existing) => {
+                    for (existing, accumulator) in existing.iter_mut().zip(accumulators) {
+                        existing.merge(accumulator);
+                    }
+                }
+                None => {
+                    if self.groups.len() >= MAX_GROUPS {
+                        return Err(format!("Too many groups (more than {MAX_GROUPS})"));
+                    }
+                    self.groups.insert(key, accumulators);
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Result rows ordered by group key. Without group-by columns there is always
+    /// exactly one row, so an empty file still reports a count of 0.
+    pub fn finish(self) -> Vec<Vec<String>> {
+        let mut groups: Vec<_> = self.groups.into_iter().collect();
+        if groups.is_empty() && self.plan.group_by.is_empty() {
+            let accumulators = self
+                .plan
+                .aggregates
+                .iter()
+                .map(|aggregate| Accumulator::new(aggregate.func))
+                .collect();
+            groups.push((Vec::new(), accumulators));
+        }
+        groups.sort_by(|a, b| a.0.cmp(&b.0));
+        groups
+            .into_iter()
+            .map(|(key, accumulators)| {
+                key.into_iter()
+                    .map(String::from)
+                    .chain(
+                        accumulators
+                            .iter()
+                            .zip(&self.plan.aggregates)
+                            .map(|(accumulator, aggregate)| accumulator.finish(aggregate.func)),
+                    )
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Row-key column, column-key column and the aggregate computed for each cell
+#[derive(Clone, Debug)]
+pub struct PivotPlan {
+    row_key: usize,
+    column_key: usize,
+    aggregate: Aggregate,
+    max_columns: usize,
+}
+
+impl PivotPlan {
+    pub fn new(
+        row_key: usize,
+        column_key: usize,
+        aggregate: Aggregate,
+        max_columns: usize,
+        num_columns: usize,
+    ) -> Result<Self, String> {
+        if row_key >= num_columns || column_key >= num_columns {
+            return Err("Pivot key column is out of range".to_string());
+        }
+        validate_aggregate(&aggregate, num_columns)?;
+        Ok(PivotPlan {
+            row_key,
+            column_key,
+            aggregate,
+            max_columns,
+        })
+    }
+}
+
+fn accumulator_for<'m>(
+    map: &'m mut HashMap<Box<str>, Accumulator>,
+    key: &str,
+    func: AggregateFn,
+) -> &'m mut Accumulator {
+    if !map.contains_key(key) {
+        map.insert(key.into(), Accumulator::new(func));
+    }
+    map.get_mut(key).unwrap()
+}
+
+fn merge_accumulators(
+    into: &mut HashMap<Box<str>, Accumulator>,
+    from: HashMap<Box<str>, Accumulator>,
+) {
+    for (key, accumulator) in from {
+        match into.get_mut(&key) {
+            Some(existing) => existing.merge(accumulator),
+            None => {
+                into.insert(key, accumulator);
+            }
+        }
+    }
+}
+
+/// Cross-tab of one slice of rows. Totals keep their own accumulators because
+/// averages, minimums and distinct counts can't be derived from the cells.
+pub struct PivotTable<'a> {
+    plan: &'a PivotPlan,
+    cells: HashMap<Box<str>, HashMap<Box<str>, Accumulator>>,
+    row_totals: HashMap<Box<str>, Accumulator>,
+    column_totals: HashMap<Box<str>, Accumulator>,
+    total: Accumulator,
+}
+
+impl<'a> PivotTable<'a> {
+    pub fn new(plan: &'a PivotPlan) -> Self {
+        PivotTable {
+            plan,
+            cells: HashMap::new(),
+            row_totals: HashMap::new(),
+            column_totals: HashMap::new(),
+            total: Accumulator::new(plan.aggregate.func),
+        }
+    }
+
+    fn check_limits(&self) -> Result<(), String> {
+        if self.column_totals.len() > self.plan.max_columns {
+            return Err(format!(
+                "Column key has more than {} distinct values",
+                self.plan.max_columns
+            ));
+        }
+        if self.row_totals.len() > MAX_GROUPS {
+            return Err(format!("Too many rows (more than {MAX_GROUPS})"));
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, row: &[String]) -> Result<(), String> {
+        let cell_of = |column: usize| row.get(column).map(String::as_str).unwrap_or("");
+        let row_key = cell_of(self.plan.row_key);
+        let column_key = cell_of(self.plan.column_key);
+        let value = self.plan.aggregate.column.map(cell_of);
+        let func = self.plan.aggregate.func;
+
+        if !self.cells.contains_key(row_key) {
+            self.cells.insert(row_key.into(), HashMap::new());
+        }
+        let row_cells = self.cells.get_mut(row_key).unwrap();
+        accumulator_for(row_cells, column_key, func).push(value);
+        accumulator_for(&mut self.row_totals, row_key, func).push(value);
+        accumulator_for(&mut self.column_totals, column_key, func).push(value);
+        self.total.push(value);
+        self.check_limits()
+    }
+
+    pub fn merge(mut self, other: PivotTable<'a>) -> Result<Self, String> {
+        for (row_key, row_cells) in other.cells {
+            match self.cells.get_mut(&row_key) {
+                Some(existing) => merge_accumulators(existing, row_cells),
+                None => {
+                    self.cells.insert(row_key, row_cells);
+                }
+            }
+        }
+        merge_accumulators(&mut self.row_totals, other.row_totals);
+        merge_accumulators(&mut self.column_totals, other.column_totals);
+        self.total.merge(other.total);
+        self.check_limits()?;
+        Ok(self)
+    }
+
+    /// Table with one column per column key, rows ordered by key, and the totals
+    pub fn finish(self, names: &[String]) -> AggregateTable {
+        let func = self.plan.aggregate.func;
+        let mut column_keys: Vec<&Box<str>> = self.column_totals.keys().collect();
+        column_keys.sort();
+        let mut row_keys: Vec<&Box<str>> = self.row_totals.keys().collect();
+        row_keys.sort();
+
+        let mut headers = vec![column_name(names, self.plan.row_key)];
+        headers.extend(column_keys.iter().map(|key| key.to_string()));
+
+        let rows = row_keys
+            .iter()
+            .map(|&row_key| {
+                let row_cells = &self.cells[row_key];
+                let mut row = vec![row_key.to_string()];
+                row.extend(column_keys.iter().map(|&column_key| {
+                    row_cells
+                        .get(column_key)
+                        .map(|cell| cell.finish(func))
+                        .unwrap_or_default()
+                }));
+                row
+            })
+            .collect();
+        let totals = PivotTotals {
+            rows: row_keys
+                .iter()
+                .map(|&row_key| self.row_totals[row_key].finish(func))
+                .collect(),
+            columns: column_keys
+                .iter()
+                .map(|&column_key| self.column_totals[column_key].finish(func))
+                .collect(),
+            grand: self.total.finish(func),
+        };
+
+        AggregateTable {
+            headers,
+            rows,
+            totals: Some(totals),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn aggregate(func: AggregateFn, column: Option<usize>) -> Aggregate {
+        Aggregate { func, column }
+    }
+
+    #[test]
+    fn groups_and_merges_slices() {
+        let plan = AggregatePlan::new(
+            vec![0],
+            vec![
+                aggregate(AggregateFn::Count, None),
+                aggregate(AggregateFn::CountDistinct, Some(1)),
+                aggregate(AggregateFn::Sum, Some(2)),
+                aggregate(AggregateFn::Avg, Some(2)),
+                aggregate(AggregateFn::Min, Some(2)),
+                aggregate(AggregateFn::Max, Some(2)),
+            ],
+            3,
+        )
+        .expect("plan");
+        let names = row(&["status", "user", "amount"]);
+        assert_eq!(
+            plan.headers(&names),
+            row(&[
+                "status",
+                "count",
+                "count_distinct(user)",
+                "sum(amount)",
+                "avg(amount)",
+                "min(amount)",
+                "max(amount)"
+            ])
+        );
+
+        let mut left = GroupTable::new(&plan);
+        left.push(&row(&["open", "a", "10"])).unwrap();
+        left.push(&row(&["closed", "b", "n/a"])).unwrap();
+        let mut right = GroupTable::new(&plan);
+        right.push(&row(&["open", "b", "1,000.5"])).unwrap();
+        right.push(&row(&["open", "a", ""])).unwrap();
+
+        let rows = left.merge(right).unwrap().finish();
+        assert_eq!(
+            rows,
+            vec![
+                row(&["closed", "1", "1", "", "", "", ""]),
+                row(&["open", "3", "2", "1010.5", "505.25", "10", "1000.5"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_plans() {
+        assert!(AggregatePlan::new(vec![0], Vec::new(), 2).is_err());
+        assert!(AggregatePlan::new(vec![5], vec![aggregate(AggregateFn::Count, None)], 2).is_err());
+        assert!(
+            AggregatePlan::new(Vec::new(), vec![aggregate(AggregateFn::Sum, None)], 2).is_err()
+        );
+
+        let plan =
+            AggregatePlan::new(Vec::new(), vec![aggregate(AggregateFn::Count, None)], 2).unwrap();
+        let mut table = GroupTable::new(&plan);
+        table.push(&row(&["a", "b"])).unwrap();
+        table.push(&row(&["c"])).unwrap();
+        assert_eq!(table.finish(), vec![row(&["2"])]);
+    }
+
+    #[test]
+    fn ungrouped_aggregate_of_no_rows_is_one_row() {
+        let plan = AggregatePlan::new(
+            Vec::new(),
+            vec![
+                aggregate(AggregateFn::Count, None),
+                aggregate(AggregateFn::CountDistinct, Some(0)),
+                aggregate(AggregateFn::Sum, Some(0)),
+            ],
+            1,
+        )
+        .unwrap();
+        assert_eq!(GroupTable::new(&plan).finish(), vec![row(&["0", "0", ""])]);
+
+        let grouped =
+            AggregatePlan::new(vec![0], vec![aggregate(AggregateFn::Count, None)], 1).unwrap();
+        assert!(GroupTable::new(&grouped).finish().is_empty());
+    }
+
+    #[test]
+    fn count_distinct_switches_to_an_estimate() {
+        let plan = AggregatePlan::new(
+            Vec::new(),
+            vec![aggregate(AggregateFn::CountDistinct, Some(0))],
+            1,
+        )
+        .unwrap();
+        let mut left = GroupTable::new(&plan);
+        let mut right = GroupTable::new(&plan);
+        for value in 0..MAX_EXACT_DISTINCT {
+            left.push(&row(&[&value.to_string()])).unwrap();
+            right.push(&row(&[&(value * 2).to_string()])).unwrap();
+        }
+        assert_eq!(
+            GroupTable::new(&plan).merge(right).unwrap().finish(),
+            vec![row(&[&MAX_EXACT_DISTINCT.to_string()])]
+        );
+
+        let mut right = GroupTable::new(&plan);
+        for value in 0..MAX_EXACT_DISTINCT {
+            right.push(&row(&[&(value * 2).to_string()])).unwrap();
+        }
+        let rows = left.merge(right).unwrap().finish();
+        let estimate: f64 = rows[0][0].strip_prefix('~').unwrap().parse().unwrap();
+        let exact = (MAX_EXACT_DISTINCT + MAX_EXACT_DISTINCT / 2) as f64;
+        assert!((estimate - exact).abs() < exact * 0.05, "{estimate}");
+    }
+
+    #[test]
+    fn pivot_with_totals_and_column_cap() {
+        let plan = PivotPlan::new(0, 1, aggregate(AggregateFn::Avg, Some(2)), 2, 3).expect("plan");
+        let mut left = PivotTable::new(&plan);
+        left.push(&row(&["east", "jan", "10"])).unwrap();
+        left.push(&row(&["west", "feb", "4"])).unwrap();
+        let mut right = PivotTable::new(&plan);
+        right.push(&row(&["east", "jan", "20"])).unwrap();
+        right.push(&row(&["east", "feb", "6"])).unwrap();
+
+        let table = left
+            .merge(right)
+            .unwrap()
+            .finish(&row(&["region", "month", "sales"]));
+        assert_eq!(table.headers, row(&["region", "feb", "jan"]));
+        assert_eq!(
+            table.rows,
+            vec![row(&["east", "6", "15"]), row(&["west", "4", ""])]
+        );
+        let totals = table.totals.as_ref().expect("totals");
+        assert_eq!(totals.rows, row(&["12", "4"]));
+        assert_eq!(totals.columns, row(&["5", "15"]));
+        assert_eq!(totals.grand, "10");
+        assert_eq!(
+            table.records(),
+            vec![
+                row(&["region", "feb", "jan", "Total"]),
+                row(&["east", "6", "15", "12"]),
+                row(&["west", "4", "", "4"]),
+                row(&["Total", "5", "15", "10"]),
+            ]
+        );
+
+        let mut capped = PivotTable::new(&plan);
+        capped.push(&row(&["east", "jan", "1"])).unwrap();
+        capped.push(&row(&["east", "feb", "1"])).unwrap();
+        assert!(capped
+            .push(&row(&["east", "mar", "1"]))
+            .unwrap_err()
+            .contains("more than 2"));
+    }
+
+    #[test]
+    fn pivot_keys_named_total_stay_keyed_cells() {
+        let plan = PivotPlan::new(0, 1, aggregate(AggregateFn::Count, None), 10, 2).unwrap();
+        let mut table = PivotTable::new(&plan);
+        table.push(&row(&["Total", "Total"])).unwrap();
+        table.push(&row(&["Total", "x"])).unwrap();
+        table.push(&row(&["a", "x"])).unwrap();
+
+        let table = table.finish(&row(&["k", "c"]));
+        assert_eq!(table.headers, row(&["k", "Total", "x"]));
+        assert_eq!(
+            table.rows,
+            vec![row(&["Total", "1", "1"]), row(&["a", "", "1"])]
+        );
+        let totals = table.totals.expect("totals");
+        assert_eq!(totals.rows, row(&["2", "1"]));
+        assert_eq!(totals.columns, row(&["1", "2"]));
+        assert_eq!(totals.grand, "3");
+    }
+}
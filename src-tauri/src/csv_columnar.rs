@@ -0,0 +1,608 @@
+use crate::csv_export::{ExportFormat, RowWriter};
+use crate::csv_sort::{parse_timestamp, DayOrder};
+use arrow_array::builder::{
+    BooleanBuilder, Float64Builder, Int64Builder, StringBuilder, TimestampSecondBuilder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{BrotliLevel, Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Time zone of timestamp columns whose values carry UTC offsets
+const UTC: &str = "+00:00";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnType {
+    Integer,
+    Float,
+    Boolean,
+    Timestamp(TimestampFormat),
+    Text,
+}
+
+/// How the values of a timestamp column are read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimestampFormat {
+    /// Day order of dates that put the year last, the same for the whole column
+    pub day_order: DayOrder,
+    /// Values carry UTC offsets and are stored as UTC instants
+    pub utc: bool,
+}
+
+impl ColumnType {
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::Integer => DataType::Int64,
+            ColumnType::Float => DataType::Float64,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Timestamp(format) => {
+                DataType::Timestamp(TimeUnit::Second, format.utc.then(|| UTC.into()))
+            }
+            ColumnType::Text => DataType::Utf8,
+        }
+    }
+}
+
+/// Column types still possible after the values seen so far
+#[derive(Clone, Debug)]
+struct Candidates {
+    seen: bool,
+    integer: bool,
+    float: bool,
+    boolean: bool,
+    /// Every value is a date when read month first or day first
+    month_first: bool,
+    day_first: bool,
+    /// Dates seen with and without a UTC offset, which cannot share a column
+    with_offset: bool,
+    without_offset: bool,
+}
+
+impl Default for Candidates {
+    fn default() -> Self {
+        Candidates {
+            seen: false,
+            integer: true,
+            float: true,
+            boolean: true,
+            month_first: true,
+            day_first: true,
+            with_offset: false,
+            without_offset: false,
+        }
+    }
+}
+
+impl Candidates {
+    fn push(&mut self, value: &str) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        self.seen = true;
+        self.integer = self.integer && parse_integer(value).is_some();
+        self.float = self.float && parse_float(value).is_some();
+        self.boolean = self.boolean && parse_boolean(value).is_some();
+        self.push_timestamp(value);
+    }
+
+    fn push_timestamp(&mut self, value: &str) {
+        let parse = |possible: bool, order| {
+            if possible {
+                parse_timestamp(value, order)
+            } else {
+                None
+            }
+        };
+        let month_first = parse(self.month_first, DayOrder::MonthFirst);
+        let day_first = parse(self.day_first, DayOrder::DayFirst);
+        self.month_first = month_first.is_some();
+        self.day_first = day_first.is_some();
+        match month_first.or(day_first) {
+            Some((_, true)) => self.with_offset = true,
+            Some((_, false)) => self.without_offset = true,
+            None => {}
+        }
+    }
+
+    fn column_type(&self) -> ColumnType {
+        if !self.seen {
+            ColumnType::Text
+        } else if self.integer {
+            ColumnType::Integer
+        } else if self.float {
+            ColumnType::Float
+        } else if self.boolean {
+            ColumnType::Boolean
+        } else if (self.month_first || self.day_first) && !(self.with_offset && self.without_offset)
+        {
+            // Dates that read either way keep the month first, like sorting does
+            ColumnType::Timestamp(TimestampFormat {
+                day_order: if self.month_first {
+                    DayOrder::MonthFirst
+                } else {
+                    DayOrder::DayFirst
+                },
+                utc: self.with_offset,
+            })
+        } else {
+            ColumnType::Text
+        }
+    }
+}
+
+/// Integers without a "+" sign or leading zeros, so codes like "007" stay text
+pub fn parse_integer(value: &str) -> Option<i64> {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    if digits.len() > 1 && digits.starts_with('0') {
+        return None;
+    }
+    if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// Numbers in JSON's grammar, which also rules out leading zeros, "NaN" and "inf"
+pub fn parse_float(value: &str) -> Option<f64> {
+    serde_json::from_str::<serde_json::Number>(value)
+        .ok()?
+        .as_f64()
+}
+
+pub fn parse_boolean(value: &str) -> Option<bool> {
+    if value.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Infers the narrowest type of every column from the rows written to it. Empty cells
+/// are nulls and do not narrow a column; columns without values become text.
+#[derive(Debug, Default)]
+pub struct SchemaInference {
+    columns: Vec<Candidates>,
+}
+
+impl SchemaInference {
+    pub fn schema(&self, headers: &[&str]) -> ColumnarSchema {
+        let types = (0..headers.len())
+            .map(|idx| {
+                self.columns
+                    .get(idx)
+                    .map_or(ColumnType::Text, Candidates::column_type)
+            })
+            .collect::<Vec<_>>();
+        let mut used = HashSet::new();
+        let fields = headers
+            .iter()
+            .zip(&types)
+            .enumerate()
+            .map(|(idx, (header, column_type))| {
+                // Field names must be unique and non-empty for most readers
+                let base = if header.trim().is_empty() {
+                    format!("column_{}", idx + 1)
+                } else {
+                    header.to_string()
+                };
+                let mut name = base.clone();
+                let mut suffix = 2;
+                while !used.insert(name.clone()) {
+                    name = format!("{base}_{suffix}");
+                    suffix += 1;
+                }
+                Field::new(name, column_type.data_type(), true)
+            })
+            .collect::<Vec<_>>();
+        ColumnarSchema {
+            schema: Arc::new(Schema::new(fields)),
+            types,
+        }
+    }
+}
+
+impl RowWriter for SchemaInference {
+    fn begin(&mut self, headers: &[&str]) -> Result<(), String> {
+        self.columns = vec![Candidates::default(); headers.len()];
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[&str]) -> Result<(), String> {
+        for (candidates, value) in self.columns.iter_mut().zip(row) {
+            candidates.push(value);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Arrow schema of an export along with the inferred type of each column
+#[derive(Clone, Debug)]
+pub struct ColumnarSchema {
+    schema: SchemaRef,
+    types: Vec<ColumnType>,
+}
+
+impl ColumnarSchema {
+    /// Field names, made unique and non-empty
+    pub fn names(&self) -> Vec<&str> {
+        self.schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect()
+    }
+
+    pub fn types(&self) -> &[ColumnType] {
+        &self.types
+    }
+}
+
+enum ColumnBuilder {
+    Integer(Int64Builder),
+    Float(Float64Builder),
+    Boolean(BooleanBuilder),
+    Timestamp(TimestampSecondBuilder, DayOrder),
+    Text(StringBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(column_type: ColumnType) -> Self {
+        match column_type {
+            ColumnType::Integer => ColumnBuilder::Integer(Int64Builder::new()),
+            ColumnType::Float => ColumnBuilder::Float(Float64Builder::new()),
+            ColumnType::Boolean => ColumnBuilder::Boolean(BooleanBuilder::new()),
+            ColumnType::Timestamp(format) => {
+                let builder = TimestampSecondBuilder::new();
+                let builder = if format.utc {
+                    builder.with_timezone(UTC)
+                } else {
+                    builder
+                };
+                ColumnBuilder::Timestamp(builder, format.day_order)
+            }
+            ColumnType::Text => ColumnBuilder::Text(StringBuilder::new()),
+        }
+    }
+
+    /// Appends a cell; false when it does not parse as the column's type
+    fn push(&mut self, value: &str) -> bool {
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            match self {
+                ColumnBuilder::Text(builder) => builder.append_value(value),
+                ColumnBuilder::Integer(builder) => builder.append_null(),
+                ColumnBuilder::Float(builder) => builder.append_null(),
+                ColumnBuilder::Boolean(builder) => builder.append_null(),
+                ColumnBuilder::Timestamp(builder, _) => builder.append_null(),
+            }
+            return true;
+        }
+        match self {
+            ColumnBuilder::Integer(builder) => parse_integer(trimmed)
+                .map(|parsed| builder.append_value(parsed))
+                .is_some(),
+            ColumnBuilder::Float(builder) => parse_float(trimmed)
+                .map(|parsed| builder.append_value(parsed))
+                .is_some(),
+            ColumnBuilder::Boolean(builder) => parse_boolean(trimmed)
+                .map(|parsed| builder.append_value(parsed))
+                .is_some(),
+            ColumnBuilder::Timestamp(builder, order) => parse_timestamp(trimmed, *order)
+                .map(|(parsed, _)| builder.append_value(parsed))
+                .is_some(),
+            ColumnBuilder::Text(builder) => {
+                builder.append_value(value);
+                true
+            }
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Integer(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Float(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Boolean(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Timestamp(builder, _) => Arc::new(builder.finish()),
+            ColumnBuilder::Text(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+enum BatchSink<W: Write + Send> {
+    Parquet(ArrowWriter<W>),
+    Ipc(FileWriter<W>),
+}
+
+/// Buffers rows into record batches of `batch_size` rows for Parquet or Arrow IPC
+struct ColumnarRowWriter<W: Write + Send> {
+    sink: BatchSink<W>,
+    schema: SchemaRef,
+    builders: Vec<ColumnBuilder>,
+    batch_size: usize,
+    rows: usize,
+}
+
+impl<W: Write + Send> ColumnarRowWriter<W> {
+    fn flush_batch(&mut self) -> Result<(), String> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let arrays = self
+            .builders
+            .iter_mut()
+            .map(ColumnBuilder::finish)
+            .collect::<Vec<_>>();
+        let batch =
+            RecordBatch::try_new(self.schema.clone(), arrays).map_err(|err| err.to_string())?;
+        self.rows = 0;
+        match &mut self.sink {
+            BatchSink::Parquet(writer) => writer.write(&batch).map_err(|err| err.to_string()),
+            BatchSink::Ipc(writer) => writer.write(&batch).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+impl<W: Write + Send> RowWriter for ColumnarRowWriter<W> {
+    fn begin(&mut self, _headers: &[&str]) -> Result<(), String> {
+        // Column names are already part of the schema
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[&str]) -> Result<(), String> {
+        for (idx, builder) in self.builders.iter_mut().enumerate() {
+            let value = row.get(idx).copied().unwrap_or("");
+            if !builder.push(value) {
+                return Err(format!(
+                    "Value \"{value}\" in column {} does not match its inferred type",
+                    self.schema.field(idx).name()
+                ));
+            }
+        }
+        self.rows += 1;
+        if self.rows >= self.batch_size {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.flush_batch()?;
+        match &mut self.sink {
+            BatchSink::Parquet(writer) => {
+                writer.finish().map(|_| ()).map_err(|err| err.to_string())
+            }
+            BatchSink::Ipc(writer) => writer.finish().map_err(|err| err.to_string()),
+        }
+    }
+}
+
+/// Parquet compression by name; snappy when none is given
+fn parse_compression(value: Option<&str>) -> Result<Compression, String> {
+    let Some(value) = value else {
+        return Ok(Compression::SNAPPY);
+    };
+    match value.trim().to_lowercase().as_str() {
+        "none" | "uncompressed" => Ok(Compression::UNCOMPRESSED),
+        "snappy" => Ok(Compression::SNAPPY),
+        "gzip" => Ok(Compression::GZIP(GzipLevel::default())),
+        "lz4" => Ok(Compression::LZ4_RAW),
+        "zstd" => Ok(Compression::ZSTD(ZstdLevel::default())),
+        "brotli" => Ok(Compression::BROTLI(BrotliLevel::default())),
+        _ => Err(format!("Unsupported compression: {value}")),
+    }
+}
+
+/// Writer for a Parquet or Arrow IPC export. Compression only applies to Parquet.
+pub fn columnar_writer<'a, W: Write + Send + 'a>(
+    format: ExportFormat,
+    compression: Option<&str>,
+    schema: &ColumnarSchema,
+    batch_size: usize,
+    out: W,
+) -> Result<Box<dyn RowWriter + 'a>, String> {
+    let sink = match format {
+        ExportFormat::Parquet => {
+            let properties = WriterProperties::builder()
+                .set_compression(parse_compression(compression)?)
+                .build();
+            BatchSink::Parquet(
+                ArrowWriter::try_new(out, schema.schema.clone(), Some(properties))
+                    .map_err(|err| err.to_string())?,
+            )
+        }
+        ExportFormat::ArrowIpc => {
+            BatchSink::Ipc(FileWriter::try_new(out, &schema.schema).map_err(|err| err.to_string())?)
+        }
+        _ => return Err("Not a columnar format".to_string()),
+    };
+    Ok(Box::new(ColumnarRowWriter {
+        sink,
+        schema: schema.schema.clone(),
+        builders: schema
+            .types
+            .iter()
+            .copied()
+            .map(ColumnBuilder::new)
+            .collect(),
+        batch_size: batch_size.max(1),
+        rows: 0,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, Int64Type, TimestampSecondType};
+    use arrow_array::Array;
+    use arrow_ipc::reader::FileReader;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::io::{Cursor, Seek};
+
+    const HEADERS: [&str; 5] = ["id", "price", "ok", "day", "id"];
+
+    fn rows() -> Vec<[&'static str; 5]> {
+        vec![
+            ["1", "2.5", "true", "2024-01-02", "007"],
+            ["", "3", "FALSE", "", "x"],
+            ["-4", "1e3", "false", "2024-02-29", ""],
+        ]
+    }
+
+    fn infer() -> ColumnarSchema {
+        let mut inference = SchemaInference::default();
+        inference.begin(&HEADERS).unwrap();
+        for row in rows() {
+            inference.write_row(&row).unwrap();
+        }
+        inference.schema(&HEADERS)
+    }
+
+    fn write(format: ExportFormat, batch_size: usize) -> Vec<u8> {
+        let schema = infer();
+        let mut out = Vec::new();
+        {
+            let mut writer =
+                columnar_writer(format, Some("zstd"), &schema, batch_size, &mut out).unwrap();
+            writer.begin(&HEADERS).unwrap();
+            for row in rows() {
+                writer.write_row(&row).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn infers_narrowest_types_and_unique_names() {
+        let schema = infer();
+        assert_eq!(
+            schema.types,
+            vec![
+                ColumnType::Integer,
+                ColumnType::Float,
+                ColumnType::Boolean,
+                ColumnType::Timestamp(TimestampFormat {
+                    day_order: DayOrder::MonthFirst,
+                    utc: false,
+                }),
+                ColumnType::Text,
+            ]
+        );
+        let names = schema
+            .schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["id", "price", "ok", "day", "id_2"]);
+    }
+
+    fn column_type(values: &[&str]) -> ColumnType {
+        let mut inference = SchemaInference::default();
+        inference.begin(&["when"]).unwrap();
+        for value in values {
+            inference.write_row(&[value]).unwrap();
+        }
+        inference.schema(&["when"]).types()[0]
+    }
+
+    #[test]
+    fn timestamp_columns_keep_one_day_order_and_offset_style() {
+        let timestamp = |day_order, utc| ColumnType::Timestamp(TimestampFormat { day_order, utc });
+        assert_eq!(
+            column_type(&["01/02/2024", "31/01/2024"]),
+            timestamp(DayOrder::DayFirst, false)
+        );
+        assert_eq!(
+            column_type(&["01/02/2024", "02/03/2024"]),
+            timestamp(DayOrder::MonthFirst, false)
+        );
+        assert_eq!(column_type(&["13/01/2024", "01/13/2024"]), ColumnType::Text);
+        assert_eq!(
+            column_type(&["2024-01-02T08:00:00+02:00", "2024-01-02T08:00:00Z"]),
+            timestamp(DayOrder::MonthFirst, true)
+        );
+        assert_eq!(
+            column_type(&["2024-01-02T08:00:00+02:00", "2024-01-02T08:00:00"]),
+            ColumnType::Text
+        );
+
+        let schema = {
+            let mut inference = SchemaInference::default();
+            inference.begin(&["when"]).unwrap();
+            inference.write_row(&["01/02/2024 06:00-0200"]).unwrap();
+            inference.write_row(&["31/01/2024 08:00:00Z"]).unwrap();
+            inference.schema(&["when"])
+        };
+        let mut out = Vec::new();
+        {
+            let mut writer =
+                columnar_writer(ExportFormat::ArrowIpc, None, &schema, 10, &mut out).unwrap();
+            writer.begin(&["when"]).unwrap();
+            writer.write_row(&["01/02/2024 06:00-0200"]).unwrap();
+            writer.write_row(&["31/01/2024 08:00:00Z"]).unwrap();
+            writer.finish().unwrap();
+        }
+        let batches = FileReader::try_new(Cursor::new(out), None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let column = batches[0].column(0).as_primitive::<TimestampSecondType>();
+        // 1 February 08:00 UTC and 31 January 08:00 UTC
+        assert_eq!(column.values(), &[1_706_774_400, 1_706_688_000]);
+        assert_eq!(
+            column.data_type(),
+            &DataType::Timestamp(TimeUnit::Second, Some(UTC.into()))
+        );
+    }
+
+    #[test]
+    fn parquet_round_trip_in_batches() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(&write(ExportFormat::Parquet, 2)).unwrap();
+        file.rewind().unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 3);
+        let ids = batches
+            .iter()
+            .flat_map(|batch| batch.column(0).as_primitive::<Int64Type>().iter())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![Some(1), None, Some(-4)]);
+    }
+
+    #[test]
+    fn arrow_ipc_round_trip() {
+        let reader =
+            FileReader::try_new(Cursor::new(write(ExportFormat::ArrowIpc, 10)), None).unwrap();
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(batches.len(), 1);
+        let prices = batches[0].column(1).as_primitive::<Float64Type>();
+        assert_eq!(prices.values(), &[2.5, 3.0, 1000.0]);
+        assert_eq!(batches[0].column(4).as_string::<i32>().value(0), "007");
+    }
+
+    #[test]
+    fn rejects_values_that_do_not_match_the_schema() {
+        let schema = infer();
+        let mut writer =
+            columnar_writer(ExportFormat::ArrowIpc, None, &schema, 10, Vec::new()).unwrap();
+        assert!(writer.write_row(&["x", "1", "true", "", ""]).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use serde::Serialize;
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+const COPY_BUFFER_SIZE: usize = 256 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else if matches!(magic, [b'B', b'Z', b'h', b'1'..=b'9', ..]) {
+            // "BZh" followed by the block size digit
+            Some(Compression::Bzip2)
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Compression::Xz)
+        } else {
+            None
+        }
+    }
+
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Compression::Gzip => &["gz", "gzip"],
+            Compression::Zstd => &["zst", "zstd"],
+            Compression::Bzip2 => &["bz2"],
+            Compression::Xz => &["xz"],
+        }
+    }
+}
+
+/// Compression of a file, told by its leading bytes rather than its extension
+pub fn sniff_compression(path: &str) -> Result<Option<Compression>, String> {
+    let mut file = File::open(path).map_err(|err| err.to_string())?;
+    let mut magic = [0u8; 6];
+    let mut read = 0;
+    while read < magic.len() {
+        match file
+            .read(&mut magic[read..])
+            .map_err(|err| err.to_string())?
+        {
+            0 => break,
+            count => read += count,
+        }
+    }
+    Ok(Compression::from_magic(&magic[..read]))
+}
+
+/// Whether the extension alone marks a file as compressed, for paths passed on the
+/// command line
+pub fn has_compressed_extension(path: &Path) -> bool {
+    let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+        return false;
+    };
+    [
+        Compression::Gzip,
+        Compression::Zstd,
+        Compression::Bzip2,
+        Compression::Xz,
+    ]
+    .iter()
+    .any(|compression| {
+        compression
+            .extensions()
+            .iter()
+            .any(|known| ext.eq_ignore_ascii_case(known))
+    })
+}
+
+/// File name of the decompressed copy: "data.csv.gz" becomes "data.csv"
+pub fn decompressed_name(path: &str, compression: Compression) -> String {
+    let path = Path::new(path);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "data".to_string());
+    let stripped = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| {
+            compression
+                .extensions()
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+        .map(|ext| name[..name.len() - ext.len() - 1].to_string())
+        .unwrap_or(name);
+    if Path::new(&stripped).extension().is_some() {
+        stripped
+    } else {
+        format!("{stripped}.csv")
+    }
+}
+
+fn decoder<'a, R: Read + 'a>(
+    compression: Compression,
+    reader: BufReader<R>,
+) -> Result<Box<dyn Read + 'a>, String> {
+    Ok(match compression {
+        // Multi-member streams come from appending gzip files, e.g. rotated logs
+        Compression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(
+            zstd::stream::read::Decoder::with_buffer(reader).map_err(|err| err.to_string())?,
+        ),
+        Compression::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(reader)),
+        Compression::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader)),
+    })
+}
+
+/// Reader that counts the compressed bytes consumed, for progress
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.set(self.count.get() + read as u64);
+        Ok(read)
+    }
+}
+
+/// Decompresses `path` into `out`. `progress` gets the compressed bytes read so far
+/// after every buffer and can stop the copy. Returns the decompressed size.
+pub fn decompress<W: Write>(
+    path: &str,
+    compression: Compression,
+    progress: &dyn Fn(u64) -> Result<(), String>,
+    out: &mut W,
+) -> Result<u64, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let count = Rc::new(Cell::new(0));
+    let reader = CountingReader {
+        inner: file,
+        count: count.clone(),
+    };
+    let mut decoder = decoder(compression, BufReader::new(reader))?;
+    let mut buf = vec![0u8; COPY_BUFFER_SIZE];
+    let mut written = 0u64;
+    loop {
+        let read = decoder
+            .read(&mut buf)
+            .map_err(|err| format!("Failed to decompress {}: {err}", file_name(path)))?;
+        if read == 0 {
+            break;
+        }
+        out.write_all(&buf[..read]).map_err(|err| err.to_string())?;
+        written += read as u64;
+        progress(count.get())?;
+    }
+    Ok(written)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(dir: &Path, name: &str, bytes: &[u8]) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, bytes).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn round_trip(path: &str) -> (Option<Compression>, String) {
+        let compression = sniff_compression(path).unwrap();
+        let mut out = Vec::new();
+        if let Some(compression) = compression {
+            decompress(path, compression, &|_| Ok(()), &mut out).unwrap();
+        }
+        (compression, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn sniffs_and_decompresses_every_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let csv = b"a,b\n1,2\n";
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        gzip.write_all(csv).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let zstd = zstd::encode_all(&csv[..], 0).unwrap();
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        bzip2.write_all(csv).unwrap();
+        let bzip2 = bzip2.finish().unwrap();
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 1);
+        xz.write_all(csv).unwrap();
+        let xz = xz.finish().unwrap();
+
+        for (name, bytes, expected) in [
+            ("data.csv.gz", gzip, Compression::Gzip),
+            ("data.csv.zst", zstd, Compression::Zstd),
+            ("data.csv.bz2", bzip2, Compression::Bzip2),
+            ("data.csv.xz", xz, Compression::Xz),
+        ] {
+            let path = write_temp(dir.path(), name, &bytes);
+            assert_eq!(
+                round_trip(&path),
+                (Some(expected), "a,b\n1,2\n".to_string())
+            );
+        }
+
+        let plain = write_temp(dir.path(), "plain.csv", csv);
+        assert_eq!(round_trip(&plain), (None, String::new()));
+        let header = write_temp(dir.path(), "bzh.csv", b"BZh,x\n1,2\n");
+        assert_eq!(sniff_compression(&header).unwrap(), None);
+        let empty = write_temp(dir.path(), "empty.csv", b"");
+        assert_eq!(sniff_compression(&empty).unwrap(), None);
+    }
+
+    #[test]
+    fn concatenated_gzip_members_are_read_in_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bytes = Vec::new();
+        for part in [&b"a\n1\n"[..], &b"2\n"[..]] {
+            let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            gzip.write_all(part).unwrap();
+            bytes.extend(gzip.finish().unwrap());
+        }
+        let path = write_temp(dir.path(), "log.gz", &bytes);
+        assert_eq!(round_trip(&path).1, "a\n1\n2\n");
+    }
+
+    #[test]
+    fn decompressed_names_drop_the_compression_extension() {
+        assert_eq!(
+            decompressed_name("/x/data.csv.gz", Compression::Gzip),
+            "data.csv"
+        );
+        assert_eq!(
+            decompressed_name("/x/data.tsv.ZST", Compression::Zstd),
+            "data.tsv"
+        );
+        assert_eq!(
+            decompressed_name("/x/export.gz", Compression::Gzip),
+            "export.csv"
+        );
+        assert_eq!(
+            decompressed_name("/x/data.bin", Compression::Xz),
+            "data.bin"
+        );
+        assert!(has_compressed_extension(Path::new("a.csv.bz2")));
+        assert!(!has_compressed_extension(Path::new("a.csv")));
+    }
+}
@@ -0,0 +1,619 @@
+use crate::csv_handler::{normalize_delimiter, normalize_quote, ParseSettings};
+use csv::{QuoteStyle, Terminator, WriterBuilder};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Output format of a CSV export. Unset fields keep the format the file was parsed with.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CsvExportOptions {
+    pub delimiter: Option<String>,
+    pub quote: Option<String>,
+    /// "necessary", "always", "non_numeric" or "never"
+    pub quote_style: Option<String>,
+    /// "lf", "crlf" or "cr"
+    pub line_ending: Option<String>,
+    pub include_headers: Option<bool>,
+}
+
+/// Output options of an export in any format
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    /// "csv" (default), "json", "ndjson", "markdown", "html", "parquet" or "arrow"
+    pub format: Option<String>,
+    /// Write numbers, booleans and empty cells as JSON values instead of strings
+    pub infer_types: Option<bool>,
+    /// Parquet compression: "snappy" (default), "zstd", "gzip", "lz4", "brotli" or "none"
+    pub compression: Option<String>,
+    #[serde(flatten)]
+    pub csv: CsvExportOptions,
+}
+
+/// Which rows an export writes, and in which order
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RowSource {
+    /// Every row in file order
+    #[default]
+    All,
+    /// The current sort order
+    Sorted,
+    /// Matches of the last search
+    Search,
+    /// Rows of the last duplicate check
+    Duplicates,
+    /// Explicit row ids, written in the given order
+    Rows { rows: Vec<usize> },
+}
+
+/// Cells of `row` in the order of `columns`, or the whole row without a projection.
+/// Cells missing from short rows are written empty.
+pub fn project_row<'a>(row: &'a [String], columns: Option<&[usize]>) -> Vec<&'a str> {
+    match columns {
+        Some(columns) => columns
+            .iter()
+            .map(|&column| row.get(column).map(String::as_str).unwrap_or(""))
+            .collect(),
+        None => row.iter().map(String::as_str).collect(),
+    }
+}
+
+/// Resolved writer settings for a CSV export
+#[derive(Clone, Debug)]
+pub struct CsvFormat {
+    delimiter: u8,
+    quote: u8,
+    quote_style: QuoteStyle,
+    terminator: Terminator,
+    pub include_headers: bool,
+}
+
+impl CsvFormat {
+    pub fn resolve(options: &CsvExportOptions, settings: &ParseSettings) -> Result<Self, String> {
+        let delimiter = match options.delimiter.as_deref() {
+            Some(value) => normalize_delimiter(value)
+                .ok_or_else(|| format!("Unsupported delimiter: {value}"))?,
+            None => settings.delimiter,
+        };
+        let quote = match options.quote.as_deref() {
+            Some(value) => {
+                normalize_quote(value).ok_or_else(|| format!("Unsupported quote: {value}"))?
+            }
+            None => settings.quote,
+        };
+        let quote_style = match options.quote_style.as_deref() {
+            Some(value) => parse_quote_style(value)
+                .ok_or_else(|| format!("Unsupported quote style: {value}"))?,
+            None => QuoteStyle::Necessary,
+        };
+        let terminator = match options.line_ending.as_deref() {
+            Some(value) => parse_line_ending(value)
+                .ok_or_else(|| format!("Unsupported line ending: {value}"))?,
+            // Files read with "auto" are written with plain newlines
+            None => parse_line_ending(&settings.line_ending).unwrap_or(Terminator::Any(b'\n')),
+        };
+        Ok(CsvFormat {
+            delimiter,
+            quote,
+            quote_style,
+            terminator,
+            include_headers: options.include_headers.unwrap_or(settings.has_headers),
+        })
+    }
+
+    pub fn writer<W: Write>(&self, inner: W) -> csv::Writer<W> {
+        WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .quote_style(self.quote_style)
+            .terminator(self.terminator)
+            .flexible(true)
+            .from_writer(inner)
+    }
+}
+
+fn parse_quote_style(value: &str) -> Option<QuoteStyle> {
+    match value.trim().to_lowercase().replace('-', "_").as_str() {
+        "necessary" | "minimal" => Some(QuoteStyle::Necessary),
+        "always" | "all" => Some(QuoteStyle::Always),
+        "non_numeric" | "nonnumeric" => Some(QuoteStyle::NonNumeric),
+        "never" | "none" => Some(QuoteStyle::Never),
+        _ => None,
+    }
+}
+
+fn parse_line_ending(value: &str) -> Option<Terminator> {
+    match value {
+        "\n" | "\\n" => return Some(Terminator::Any(b'\n')),
+        "\r\n" | "\\r\\n" => return Some(Terminator::CRLF),
+        "\r" | "\\r" => return Some(Terminator::Any(b'\r')),
+        _ => {}
+    }
+    match value.trim().to_lowercase().as_str() {
+        "lf" => Some(Terminator::Any(b'\n')),
+        "crlf" => Some(Terminator::CRLF),
+        "cr" => Some(Terminator::Any(b'\r')),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Ndjson,
+    Markdown,
+    Html,
+    Parquet,
+    ArrowIpc,
+}
+
+impl ExportFormat {
+    /// The format requested in `options`, CSV when none is given
+    pub fn resolve(options: &ExportOptions) -> Result<Self, String> {
+        match options.format.as_deref() {
+            Some(value) => {
+                ExportFormat::from_str(value).ok_or_else(|| format!("Unsupported format: {value}"))
+            }
+            None => Ok(ExportFormat::Csv),
+        }
+    }
+
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            "ndjson" | "jsonl" | "json_lines" => Some(ExportFormat::Ndjson),
+            "markdown" | "md" => Some(ExportFormat::Markdown),
+            "html" => Some(ExportFormat::Html),
+            "parquet" => Some(ExportFormat::Parquet),
+            "arrow" | "arrow_ipc" | "ipc" | "feather" => Some(ExportFormat::ArrowIpc),
+            _ => None,
+        }
+    }
+
+    /// Formats written in typed record batches, which need a schema up front
+    pub fn is_columnar(self) -> bool {
+        matches!(self, ExportFormat::Parquet | ExportFormat::ArrowIpc)
+    }
+}
+
+/// Sink for exported rows. `begin` receives the headers even when no header line is
+/// written, since JSON uses them as object keys.
+pub trait RowWriter {
+    fn begin(&mut self, headers: &[&str]) -> Result<(), String>;
+    fn write_row(&mut self, row: &[&str]) -> Result<(), String>;
+    fn finish(&mut self) -> Result<(), String>;
+}
+
+/// Builds the writer for a text format requested in `options`. Columnar formats need
+/// a schema first and are built by `csv_columnar::columnar_writer`.
+pub fn row_writer<'a, W: Write + 'a>(
+    options: &ExportOptions,
+    settings: &ParseSettings,
+    out: W,
+) -> Result<Box<dyn RowWriter + 'a>, String> {
+    let format = ExportFormat::resolve(options)?;
+    let infer_types = options.infer_types.unwrap_or(false);
+    Ok(match format {
+        ExportFormat::Csv => {
+            let format = CsvFormat::resolve(&options.csv, settings)?;
+            Box::new(CsvRowWriter {
+                writer: format.writer(out),
+                include_headers: format.include_headers,
+            })
+        }
+        ExportFormat::Json => Box::new(JsonRowWriter::new(out, false, infer_types)),
+        ExportFormat::Ndjson => Box::new(JsonRowWriter::new(out, true, infer_types)),
+        ExportFormat::Markdown => Box::new(MarkdownRowWriter { out }),
+        ExportFormat::Html => Box::new(HtmlRowWriter { out }),
+        ExportFormat::Parquet | ExportFormat::ArrowIpc => {
+            return Err("Columnar formats need a schema".to_string())
+        }
+    })
+}
+
+struct CsvRowWriter<W: Write> {
+    writer: csv::Writer<W>,
+    include_headers: bool,
+}
+
+impl<W: Write> RowWriter for CsvRowWriter<W> {
+    fn begin(&mut self, headers: &[&str]) -> Result<(), String> {
+        if self.include_headers {
+            self.write_row(headers)?;
+        }
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[&str]) -> Result<(), String> {
+        self.writer.write_record(row).map_err(|err| err.to_string())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.writer.flush().map_err(|err| err.to_string())
+    }
+}
+
+/// A JSON array of objects, or one object per line for NDJSON
+struct JsonRowWriter<W: Write> {
+    out: W,
+    lines: bool,
+    infer_types: bool,
+    /// Headers already encoded as JSON strings
+    keys: Vec<String>,
+    rows: usize,
+}
+
+impl<W: Write> JsonRowWriter<W> {
+    fn new(out: W, lines: bool, infer_types: bool) -> Self {
+        JsonRowWriter {
+            out,
+            lines,
+            infer_types,
+            keys: Vec::new(),
+            rows: 0,
+        }
+    }
+}
+
+impl<W: Write> RowWriter for JsonRowWriter<W> {
+    fn begin(&mut self, headers: &[&str]) -> Result<(), String> {
+        self.keys = headers.iter().map(|header| json_string(header)).collect();
+        if !self.lines {
+            self.out.write_all(b"[").map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[&str]) -> Result<(), String> {
+        let mut line = String::new();
+        if !self.lines {
+            line.push_str(if self.rows == 0 { "\n" } else { ",\n" });
+        }
+        line.push('{');
+        // Cells past the last header get generated keys
+        for idx in 0..self.keys.len().max(row.len()) {
+            if idx > 0 {
+                line.push(',');
+            }
+            match self.keys.get(idx) {
+                Some(key) => line.push_str(key),
+                None => line.push_str(&json_string(&format!("column_{}", idx + 1))),
+            }
+            line.push(':');
+            let cell = row.get(idx).copied().unwrap_or("");
+            line.push_str(&json_value(cell, self.infer_types));
+        }
+        line.push('}');
+        if self.lines {
+            line.push('\n');
+        }
+        self.rows += 1;
+        self.out
+            .write_all(line.as_bytes())
+            .map_err(|err| err.to_string())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        if !self.lines {
+            let end: &[u8] = if self.rows == 0 { b"]\n" } else { b"\n]\n" };
+            self.out.write_all(end).map_err(|err| err.to_string())?;
+        }
+        self.out.flush().map_err(|err| err.to_string())
+    }
+}
+
+fn json_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+/// Encodes a cell, optionally as a JSON number, boolean or null when it reads as one
+fn json_value(cell: &str, infer_types: bool) -> String {
+    if infer_types {
+        let trimmed = cell.trim();
+        if trimmed.is_empty() {
+            return "null".to_string();
+        }
+        if trimmed.eq_ignore_ascii_case("true") {
+            return "true".to_string();
+        }
+        if trimmed.eq_ignore_ascii_case("false") {
+            return "false".to_string();
+        }
+        // JSON's own number grammar rejects leading zeros, so codes like "007" stay text
+        if serde_json::from_str::<serde_json::Number>(trimmed).is_ok() {
+            return trimmed.to_string();
+        }
+    }
+    json_string(cell)
+}
+
+/// A GitHub-flavored Markdown table; the header line is always written
+struct MarkdownRowWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> MarkdownRowWriter<W> {
+    fn write_line(&mut self, cells: &[&str]) -> Result<(), String> {
+        let mut line = String::from("|");
+        for cell in cells {
+            line.push(' ');
+            line.push_str(&markdown_cell(cell));
+            line.push_str(" |");
+        }
+        line.push('\n');
+        self.out
+            .write_all(line.as_bytes())
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl<W: Write> RowWriter for MarkdownRowWriter<W> {
+    fn begin(&mut self, headers: &[&str]) -> Result<(), String> {
+        self.write_line(headers)?;
+        self.write_line(&vec!["---"; headers.len()])
+    }
+
+    fn write_row(&mut self, row: &[&str]) -> Result<(), String> {
+        self.write_line(row)
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.out.flush().map_err(|err| err.to_string())
+    }
+}
+
+fn markdown_cell(cell: &str) -> String {
+    cell.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+/// An HTML table with the headers in `<thead>`
+struct HtmlRowWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> HtmlRowWriter<W> {
+    fn write_line(&mut self, cells: &[&str], tag: &str) -> Result<(), String> {
+        let mut line = String::from("<tr>");
+        for cell in cells {
+            line.push_str(&format!("<{tag}>{}</{tag}>", html_escape(cell)));
+        }
+        line.push_str("</tr>\n");
+        self.out
+            .write_all(line.as_bytes())
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl<W: Write> RowWriter for HtmlRowWriter<W> {
+    fn begin(&mut self, headers: &[&str]) -> Result<(), String> {
+        self.out
+            .write_all(b"<table>\n<thead>\n")
+            .map_err(|err| err.to_string())?;
+        self.write_line(headers, "th")?;
+        self.out
+            .write_all(b"</thead>\n<tbody>\n")
+            .map_err(|err| err.to_string())
+    }
+
+    fn write_row(&mut self, row: &[&str]) -> Result<(), String> {
+        self.write_line(row, "td")
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.out
+            .write_all(b"</tbody>\n</table>\n")
+            .map_err(|err| err.to_string())?;
+        self.out.flush().map_err(|err| err.to_string())
+    }
+}
+
+fn html_escape(cell: &str) -> String {
+    let mut escaped = String::with_capacity(cell.len());
+    for ch in cell.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Writes `path` through a temporary file in the same directory, which replaces the
+/// target only once `write` has succeeded. On failure the target is left untouched.
+/// An existing target keeps its permissions.
+pub fn write_atomically<T, F>(path: &Path, write: F) -> Result<T, String>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<T, String>,
+{
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let temp = tempfile::NamedTempFile::new_in(dir).map_err(|err| err.to_string())?;
+    let mut out = BufWriter::new(temp.reopen().map_err(|err| err.to_string())?);
+    let value = write(&mut out)?;
+    out.flush().map_err(|err| err.to_string())?;
+    out.get_ref().sync_all().map_err(|err| err.to_string())?;
+    drop(out);
+    // The temporary file is created owner-only; keep the mode of the file it replaces
+    if let Ok(metadata) = std::fs::metadata(path) {
+        std::fs::set_permissions(temp.path(), metadata.permissions())
+            .map_err(|err| err.to_string())?;
+    }
+    temp.persist(path).map_err(|err| err.error.to_string())?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_handler::default_parse_settings;
+
+    fn write_rows(format: &CsvFormat, rows: &[&[&str]]) -> String {
+        let mut writer = format.writer(Vec::new());
+        for row in rows {
+            writer.write_record(*row).unwrap();
+        }
+        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn resolve_defaults_to_parse_settings() {
+        let mut settings = default_parse_settings();
+        settings.delimiter = b';';
+        settings.line_ending = "crlf".to_string();
+        let format = CsvFormat::resolve(&CsvExportOptions::default(), &settings).unwrap();
+        assert!(format.include_headers);
+        assert_eq!(
+            write_rows(&format, &[&["a", "b;c"], &["1", "2"]]),
+            "a;\"b;c\"\r\n1;2\r\n"
+        );
+    }
+
+    #[test]
+    fn resolve_applies_overrides() {
+        let options = CsvExportOptions {
+            delimiter: Some("\\t".to_string()),
+            quote: Some("'".to_string()),
+            quote_style: Some("non-numeric".to_string()),
+            line_ending: Some("cr".to_string()),
+            include_headers: Some(false),
+        };
+        let format = CsvFormat::resolve(&options, &default_parse_settings()).unwrap();
+        assert!(!format.include_headers);
+        assert_eq!(write_rows(&format, &[&["x", "1.5"]]), "'x'\t1.5\r");
+
+        let options = CsvExportOptions {
+            quote_style: Some("sometimes".to_string()),
+            ..CsvExportOptions::default()
+        };
+        assert!(CsvFormat::resolve(&options, &default_parse_settings()).is_err());
+    }
+
+    #[test]
+    fn export_options_carry_csv_fields() {
+        let options: ExportOptions =
+            serde_json::from_str(r#"{"format":"csv","delimiter":";","includeHeaders":false}"#)
+                .unwrap();
+        assert_eq!(options.format.as_deref(), Some("csv"));
+        assert_eq!(options.csv.delimiter.as_deref(), Some(";"));
+        assert_eq!(options.csv.include_headers, Some(false));
+    }
+
+    #[test]
+    fn row_source_and_projection() {
+        let source: RowSource = serde_json::from_str(r#"{"kind":"rows","rows":[3,1]}"#).unwrap();
+        assert!(matches!(source, RowSource::Rows { rows } if rows == vec![3, 1]));
+        let source: RowSource = serde_json::from_str(r#"{"kind":"duplicates"}"#).unwrap();
+        assert!(matches!(source, RowSource::Duplicates));
+
+        let row = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        assert_eq!(project_row(&row, None), vec!["a", "b", "c"]);
+        assert_eq!(project_row(&row, Some(&[2, 0, 5])), vec!["c", "a", ""]);
+    }
+
+    fn export(format: &str, infer_types: bool, rows: &[&[&str]]) -> String {
+        let options = ExportOptions {
+            format: Some(format.to_string()),
+            infer_types: Some(infer_types),
+            ..ExportOptions::default()
+        };
+        let mut out = Vec::new();
+        {
+            let mut writer = row_writer(&options, &default_parse_settings(), &mut out).unwrap();
+            writer.begin(rows[0]).unwrap();
+            for row in &rows[1..] {
+                writer.write_row(row).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn json_writers_key_rows_by_header() {
+        let rows: &[&[&str]] = &[
+            &["id", "name", "ok"],
+            &["1", "a\"b", "true"],
+            &["007", "", "no"],
+        ];
+        assert_eq!(
+            export("json", false, rows),
+            "[\n{\"id\":\"1\",\"name\":\"a\\\"b\",\"ok\":\"true\"},\n\
+             {\"id\":\"007\",\"name\":\"\",\"ok\":\"no\"}\n]\n"
+        );
+        assert_eq!(
+            export("ndjson", true, rows),
+            "{\"id\":1,\"name\":\"a\\\"b\",\"ok\":true}\n{\"id\":\"007\",\"name\":null,\"ok\":\"no\"}\n"
+        );
+        assert_eq!(export("json", true, &[&["a"]]), "[]\n");
+        assert_eq!(
+            export("ndjson", false, &[&["a"], &["1", "2"]]),
+            "{\"a\":\"1\",\"column_2\":\"2\"}\n"
+        );
+    }
+
+    #[test]
+    fn table_writers_escape_cells() {
+        let rows: &[&[&str]] = &[&["a", "b"], &["x|y", "<i>\nz"]];
+        assert_eq!(
+            export("markdown", false, rows),
+            "| a | b |\n| --- | --- |\n| x\\|y | <i><br>z |\n"
+        );
+        assert_eq!(
+            export("html", false, rows),
+            "<table>\n<thead>\n<tr><th>a</th><th>b</th></tr>\n</thead>\n<tbody>\n\
+             <tr><td>x|y</td><td>&lt;i&gt;\nz</td></tr>\n</tbody>\n</table>\n"
+        );
+    }
+
+    #[test]
+    fn write_atomically_replaces_only_on_success() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        std::fs::write(&path, "old").unwrap();
+
+        let failed: Result<(), String> = write_atomically(&path, |out| {
+            out.write_all(b"partial").unwrap();
+            Err("stopped".to_string())
+        });
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+
+        write_atomically(&path, |out| {
+            out.write_all(b"new").map_err(|err| err.to_string())
+        })
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomically_keeps_the_target_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shared.csv");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        write_atomically(&path, |out| {
+            out.write_all(b"new").map_err(|err| err.to_string())
+        })
+        .unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+    }
+}
@@ -0,0 +1,591 @@
+use crate::csv_sort::{parse_date, parse_number};
+use regex::{Regex, RegexBuilder};
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Matches,
+    NotMatches,
+    Contains,
+    StartsWith,
+    EndsWith,
+    IsEmpty,
+    IsNotEmpty,
+    Is,
+    IsNot,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Column(String),
+    Regex(String, String),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+#[derive(Debug)]
+pub enum FilterValue {
+    Text {
+        lower: String,
+        number: Option<f64>,
+        date: Option<i64>,
+    },
+    Pattern(Regex),
+    /// Cell text compared byte for byte, with no case folding or number parsing
+    Exact(String),
+    None,
+}
+
+impl FilterValue {
+    fn text(raw: &str) -> Self {
+        let trimmed = raw.trim();
+        FilterValue::Text {
+            lower: raw.to_lowercase(),
+            number: parse_number(trimmed),
+            date: parse_date(trimmed),
+        }
+    }
+
+    /// Case-insensitive pattern for the substring operators, so the literal is
+    /// folded once rather than every cell being lowercased
+    fn substring(raw: &str, op: CompareOp) -> Result<Self, String> {
+        let escaped = regex::escape(raw);
+        let pattern = match op {
+            CompareOp::StartsWith => format!("^{escaped}"),
+            CompareOp::EndsWith => format!("{escaped}$"),
+            _ => escaped,
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .build()
+            .map(FilterValue::Pattern)
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Parsed filter expression with column names resolved to indices, e.g.
+/// `status = "open" AND amount > 100 AND NOT email ~ /@test\./`
+#[derive(Debug)]
+pub enum FilterExpr {
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+    Not(Box<FilterExpr>),
+    Compare {
+        column: usize,
+        op: CompareOp,
+        value: FilterValue,
+    },
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let ch = chars[idx];
+        match ch {
+            c if c.is_whitespace() => idx += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                idx += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                idx += 1;
+            }
+            '"' | '\'' => {
+                let quote = ch;
+                let mut value = String::new();
+                idx += 1;
+                loop {
+                    match chars.get(idx) {
+                        None => return Err("Unterminated string literal".to_string()),
+                        Some('\\') if chars.get(idx + 1).is_some() => {
+                            value.push(chars[idx + 1]);
+                            idx += 2;
+                        }
+                        Some(c) if *c == quote => {
+                            idx += 1;
+                            break;
+                        }
+                        Some(c) => {
+                            value.push(*c);
+                            idx += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            '`' | '[' => {
+                let close = if ch == '`' { '`' } else { ']' };
+                let end = chars[idx + 1..]
+                    .iter()
+                    .position(|c| *c == close)
+                    .ok_or("Unterminated column name")?;
+                tokens.push(Token::Column(
+                    chars[idx + 1..idx + 1 + end].iter().collect(),
+                ));
+                idx += end + 2;
+            }
+            '/' => {
+                let mut pattern = String::new();
+                idx += 1;
+                loop {
+                    match chars.get(idx) {
+                        None => return Err("Unterminated regex literal".to_string()),
+                        Some('\\') if chars.get(idx + 1) == Some(&'/') => {
+                            pattern.push('/');
+                            idx += 2;
+                        }
+                        Some('/') => {
+                            idx += 1;
+                            break;
+                        }
+                        Some(c) => {
+                            pattern.push(*c);
+                            idx += 1;
+                        }
+                    }
+                }
+                let mut flags = String::new();
+                while let Some(c) = chars.get(idx).filter(|c| c.is_ascii_alphabetic()) {
+                    flags.push(*c);
+                    idx += 1;
+                }
+                tokens.push(Token::Regex(pattern, flags));
+            }
+            '=' | '!' | '<' | '>' | '~' | '&' | '|' => {
+                let next = chars.get(idx + 1).copied();
+                let (token, width) = match (ch, next) {
+                    ('=', Some('=')) => (Token::Op(CompareOp::Eq), 2),
+                    ('=', _) => (Token::Op(CompareOp::Eq), 1),
+                    ('!', Some('=')) => (Token::Op(CompareOp::Ne), 2),
+                    ('!', Some('~')) => (Token::Op(CompareOp::NotMatches), 2),
+                    ('!', _) => (Token::Not, 1),
+                    ('<', Some('>')) => (Token::Op(CompareOp::Ne), 2),
+                    ('<', Some('=')) => (Token::Op(CompareOp::Le), 2),
+                    ('<', _) => (Token::Op(CompareOp::Lt), 1),
+                    ('>', Some('=')) => (Token::Op(CompareOp::Ge), 2),
+                    ('>', _) => (Token::Op(CompareOp::Gt), 1),
+                    ('~', _) => (Token::Op(CompareOp::Matches), 1),
+                    ('&', Some('&')) => (Token::And, 2),
+                    ('|', Some('|')) => (Token::Or, 2),
+                    _ => return Err(format!("Unexpected character '{ch}'")),
+                };
+                tokens.push(token);
+                idx += width;
+            }
+            _ => {
+                let start = idx;
+                while idx < chars.len()
+                    && !chars[idx].is_whitespace()
+                    && !"()=!<>~&|\"'`[".contains(chars[idx])
+                {
+                    idx += 1;
+                }
+                let word = chars[start..idx].iter().collect::<String>();
+                let token = match word.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    "contains" => Token::Op(CompareOp::Contains),
+                    "startswith" => Token::Op(CompareOp::StartsWith),
+                    "endswith" => Token::Op(CompareOp::EndsWith),
+                    _ => Token::Word(word),
+                };
+                tokens.push(token);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    headers: &'a [String],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpr, String> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = FilterExpr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpr, String> {
+        let mut left = self.parse_not()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            let right = self.parse_not()?;
+            left = FilterExpr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<FilterExpr, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(FilterExpr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<FilterExpr, String> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err("Expected ')'".to_string()),
+                }
+            }
+            Some(Token::Word(name)) | Some(Token::Column(name)) | Some(Token::Quoted(name)) => {
+                let column = self.resolve_column(&name)?;
+                self.parse_comparison(column, &name)
+            }
+            Some(token) => Err(format!(
+                "Expected a column name, found {}",
+                describe(&token)
+            )),
+            None => Err("Unexpected end of filter expression".to_string()),
+        }
+    }
+
+    fn parse_comparison(&mut self, column: usize, name: &str) -> Result<FilterExpr, String> {
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("is") => {
+                let negated = self.peek() == Some(&Token::Not);
+                if negated {
+                    self.pos += 1;
+                }
+                match self.next() {
+                    Some(Token::Quoted(text)) => {
+                        return Ok(FilterExpr::Compare {
+                            column,
+                            op: if negated {
+                                CompareOp::IsNot
+                            } else {
+                                CompareOp::Is
+                            },
+                            value: FilterValue::Exact(text),
+                        });
+                    }
+                    Some(Token::Word(word)) if word.eq_ignore_ascii_case("empty") => {
+                        return Ok(FilterExpr::Compare {
+                            column,
+                            op: if negated {
+                                CompareOp::IsNotEmpty
+                            } else {
+                                CompareOp::IsEmpty
+                            },
+                            value: FilterValue::None,
+                        });
+                    }
+                    _ => return Err("Expected EMPTY or a quoted value after IS".to_string()),
+                }
+            }
+            Some(token) => {
+                return Err(format!(
+                    "Expected an operator after '{name}', found {}",
+                    describe(&token)
+                ))
+            }
+            None => return Err(format!("Expected an operator after '{name}'")),
+        };
+
+        let value = match self.next() {
+            Some(Token::Quoted(text)) | Some(Token::Word(text)) => match op {
+                CompareOp::Matches
+                | CompareOp::NotMatches
+                | CompareOp::Contains
+                | CompareOp::StartsWith
+                | CompareOp::EndsWith => FilterValue::substring(&text, op)?,
+                _ => FilterValue::text(&text),
+            },
+            Some(Token::Regex(pattern, flags)) => {
+                if !matches!(op, CompareOp::Matches | CompareOp::NotMatches) {
+                    return Err("Regex literals can only be used with ~ or !~".to_string());
+                }
+                FilterValue::Pattern(compile_regex(&pattern, &flags)?)
+            }
+            Some(token) => return Err(format!("Expected a value, found {}", describe(&token))),
+            None => return Err(format!("Expected a value after '{name}'")),
+        };
+
+        Ok(FilterExpr::Compare { column, op, value })
+    }
+
+    fn resolve_column(&self, name: &str) -> Result<usize, String> {
+        if let Some(idx) = self
+            .headers
+            .iter()
+            .position(|header| header.trim().eq_ignore_ascii_case(name.trim()))
+        {
+            return Ok(idx);
+        }
+        if let Some(number) = name.strip_prefix('#').and_then(|n| n.parse::<usize>().ok()) {
+            if number >= 1 && number <= self.headers.len() {
+                return Ok(number - 1);
+            }
+        }
+        Err(format!("Unknown column '{name}'"))
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => format!("'{word}'"),
+        Token::Quoted(text) => format!("\"{text}\""),
+        Token::Column(name) => format!("column `{name}`"),
+        Token::Regex(pattern, _) => format!("/{pattern}/"),
+        Token::Op(_) => "an operator".to_string(),
+        Token::And => "AND".to_string(),
+        Token::Or => "OR".to_string(),
+        Token::Not => "NOT".to_string(),
+        Token::LParen => "'('".to_string(),
+        Token::RParen => "')'".to_string(),
+    }
+}
+
+fn compile_regex(pattern: &str, flags: &str) -> Result<Regex, String> {
+    let mut builder = RegexBuilder::new(pattern);
+    for flag in flags.chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            'm' => builder.multi_line(true),
+            's' => builder.dot_matches_new_line(true),
+            'x' => builder.ignore_whitespace(true),
+            other => return Err(format!("Unsupported regex flag '{other}'")),
+        };
+    }
+    builder
+        .build()
+        .map_err(|err| format!("Invalid regex /{pattern}/: {err}"))
+}
+
+/// Parses a filter expression against the current headers.
+/// Columns are referenced by header name (case-insensitive), as `` `Name With Spaces` ``,
+/// `[Name]`, or by 1-based position (`#3`). `~` takes a regex literal or does a
+/// case-insensitive substring match for plain values. `IS "text"` matches the cell
+/// text exactly, where `=` ignores case and compares numbers and dates by value.
+pub fn parse_filter(expression: &str, headers: &[String]) -> Result<FilterExpr, String> {
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        return Err("Filter expression is empty".to_string());
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        headers,
+    };
+    let expr = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!(
+            "Unexpected {} in filter expression",
+            describe(token)
+        ));
+    }
+    Ok(expr)
+}
+
+/// Orders a cell against a number or date literal. None when either side is not a
+/// number or date, so ordering operators never match text like "n/a".
+fn compare_ordered(cell: &str, number: Option<f64>, date: Option<i64>) -> Option<Ordering> {
+    let trimmed = cell.trim();
+    if let Some(actual) =
+        number.and_then(|expected| parse_number(trimmed).map(|actual| actual.total_cmp(&expected)))
+    {
+        return Some(actual);
+    }
+    date.and_then(|expected| parse_date(trimmed).map(|actual| actual.cmp(&expected)))
+}
+
+/// Numbers and dates compare by value, anything else case-insensitively
+fn equals_text(cell: &str, lower: &str, number: Option<f64>, date: Option<i64>) -> bool {
+    match compare_ordered(cell, number, date) {
+        Some(ordering) => ordering.is_eq(),
+        None => cell.chars().flat_map(char::to_lowercase).eq(lower.chars()),
+    }
+}
+
+impl FilterExpr {
+    pub fn matches(&self, row: &[String]) -> bool {
+        match self {
+            FilterExpr::And(left, right) => left.matches(row) && right.matches(row),
+            FilterExpr::Or(left, right) => left.matches(row) || right.matches(row),
+            FilterExpr::Not(inner) => !inner.matches(row),
+            FilterExpr::Compare { column, op, value } => {
+                let cell = row.get(*column).map(String::as_str).unwrap_or("");
+                match (op, value) {
+                    (CompareOp::IsEmpty, _) => cell.trim().is_empty(),
+                    (CompareOp::IsNotEmpty, _) => !cell.trim().is_empty(),
+                    (
+                        CompareOp::Matches
+                        | CompareOp::Contains
+                        | CompareOp::StartsWith
+                        | CompareOp::EndsWith,
+                        FilterValue::Pattern(regex),
+                    ) => regex.is_match(cell),
+                    (CompareOp::NotMatches, FilterValue::Pattern(regex)) => !regex.is_match(cell),
+                    (CompareOp::Is, FilterValue::Exact(text)) => cell == text,
+                    (CompareOp::IsNot, FilterValue::Exact(text)) => cell != text,
+                    (
+                        op,
+                        FilterValue::Text {
+                            lower,
+                            number,
+                            date,
+                        },
+                    ) => match op {
+                        CompareOp::Eq => equals_text(cell, lower, *number, *date),
+                        CompareOp::Ne => !equals_text(cell, lower, *number, *date),
+                        CompareOp::Lt => {
+                            compare_ordered(cell, *number, *date).is_some_and(Ordering::is_lt)
+                        }
+                        CompareOp::Le => {
+                            compare_ordered(cell, *number, *date).is_some_and(Ordering::is_le)
+                        }
+                        CompareOp::Gt => {
+                            compare_ordered(cell, *number, *date).is_some_and(Ordering::is_gt)
+                        }
+                        CompareOp::Ge => {
+                            compare_ordered(cell, *number, *date).is_some_and(Ordering::is_ge)
+                        }
+                        _ => false,
+                    },
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+/// Builds an expression matching rows whose cell in `column` is exactly `value`
+pub fn equals_expression(column: usize, value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("#{} is \"{}\"", column + 1, escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{equals_expression, parse_filter};
+
+    fn headers() -> Vec<String> {
+        ["status", "amount", "email", "Created At"]
+            .iter()
+            .map(|value| value.to_string())
+            .collect()
+    }
+
+    fn row(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn evaluates_boolean_expression_with_regex() {
+        let expr = parse_filter(
+            r#"status = "open" AND amount > 100 AND NOT email ~ /@test\./"#,
+            &headers(),
+        )
+        .expect("parse");
+        assert!(expr.matches(&row(&["Open", "150", "a@example.com", ""])));
+        assert!(!expr.matches(&row(&["open", "99.5", "a@example.com", ""])));
+        assert!(!expr.matches(&row(&["open", "1,500", "b@test.com", ""])));
+        assert!(!expr.matches(&row(&["closed", "500", "a@example.com", ""])));
+    }
+
+    #[test]
+    fn ordering_operators_skip_text_cells() {
+        let expr = parse_filter("amount > 100", &headers()).expect("parse");
+        assert!(expr.matches(&row(&["", "$1,200", "", ""])));
+        assert!(!expr.matches(&row(&["", "n/a", "", ""])));
+        assert!(!expr.matches(&row(&["", "", "", ""])));
+        let expr = parse_filter("status >= a", &headers()).expect("parse");
+        assert!(!expr.matches(&row(&["open", "", "", ""])));
+
+        let expr =
+            parse_filter("email startswith AN and email endswith .ORG", &headers()).expect("parse");
+        assert!(expr.matches(&row(&["", "", "ann@ex.org", ""])));
+        assert!(!expr.matches(&row(&["", "", "joann@ex.org", ""])));
+        let expr = parse_filter("email ~ \"a.b\"", &headers()).expect("parse");
+        assert!(expr.matches(&row(&["", "", "xA.By", ""])));
+        assert!(!expr.matches(&row(&["", "", "axb", ""])));
+    }
+
+    #[test]
+    fn supports_or_parentheses_and_quoted_columns() {
+        let expr = parse_filter(
+            "(status = open OR status = pending) && `Created At` >= 2024-01-01 && #3 is not empty",
+            &headers(),
+        )
+        .expect("parse");
+        assert!(expr.matches(&row(&["pending", "0", "x@y.z", "02/01/2024"])));
+        assert!(!expr.matches(&row(&["pending", "0", "", "2024-02-01"])));
+        assert!(!expr.matches(&row(&["open", "0", "x@y.z", "2023-12-31"])));
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        assert!(parse_filter("missing = 1", &headers())
+            .unwrap_err()
+            .contains("Unknown column"));
+        assert!(parse_filter("status = ", &headers()).is_err());
+        assert!(parse_filter("email ~ /(/", &headers())
+            .unwrap_err()
+            .contains("Invalid regex"));
+        assert!(parse_filter("(status = open", &headers()).is_err());
+        assert!(parse_filter("status = open extra", &headers()).is_err());
+    }
+
+    #[test]
+    fn equals_expression_round_trips_quotes() {
+        let value = r#"say "hi" \ bye"#;
+        let expr = parse_filter(&equals_expression(2, value), &headers()).expect("parse");
+        assert!(expr.matches(&row(&["", "", value, ""])));
+        assert!(!expr.matches(&row(&["", "", "say hi", ""])));
+    }
+
+    #[test]
+    fn equals_expression_matches_exact_text() {
+        let expr = parse_filter(&equals_expression(1, "1"), &headers()).expect("parse");
+        assert!(expr.matches(&row(&["", "1", "", ""])));
+        assert!(!expr.matches(&row(&["", "1.0", "", ""])));
+        assert!(!expr.matches(&row(&["", " 1", "", ""])));
+        let expr = parse_filter(&equals_expression(0, "Open"), &headers()).expect("parse");
+        assert!(!expr.matches(&row(&["open", "", "", ""])));
+        let expr =
+            parse_filter(r#"status is not "empty" and #3 is empty"#, &headers()).expect("parse");
+        assert!(expr.matches(&row(&["Empty", "", "", ""])));
+        assert!(!expr.matches(&row(&["empty", "", "", ""])));
+    }
+}
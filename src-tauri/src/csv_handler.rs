@@ -1,16 +1,24 @@
+use crate::csv_jsonl::{infer_columns, JsonLayout, SAMPLE_LINES};
 use chardetng::EncodingDetector;
 use csv::{ByteRecord, Position, ReaderBuilder, StringRecord, Terminator};
 use encoding_rs::Encoding;
 use rayon::prelude::*;
 use rayon::slice::ParallelSliceMut;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 use std::fs::File;
-use std::io::{BufReader, Cursor, Read, Seek};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 const SAMPLE_SIZE: usize = 64 * 1024;
 pub const MAX_WARNING_COUNT: usize = 200;
+pub const CANCELLED_MESSAGE: &str = "Request cancelled";
+const CANCEL_CHECK_INTERVAL: usize = 4096;
+/// Lines of the sample used to infer fixed-width columns
+const FIXED_SAMPLE_LINES: usize = 200;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MalformedMode {
@@ -37,7 +45,7 @@ impl MalformedMode {
     }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct ParseOverrides {
     pub delimiter: Option<String>,
     pub quote: Option<String>,
@@ -48,6 +56,9 @@ pub struct ParseOverrides {
     pub malformed: Option<String>,
     pub max_field_size: Option<usize>,
     pub max_record_size: Option<usize>,
+    /// "auto" to infer fixed-width columns from the sample, or 1-based inclusive
+    /// character ranges such as "1-10,11-25,26-"
+    pub fixed_width: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -61,6 +72,16 @@ pub struct ParseInfo {
     pub malformed: String,
     pub max_field_size: usize,
     pub max_record_size: usize,
+    pub fixed_width: Option<String>,
+    pub json_lines: bool,
+}
+
+/// Column of a fixed-width file at character positions `start..end`; an open end
+/// runs to the end of the line
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FixedColumn {
+    pub start: usize,
+    pub end: Option<usize>,
 }
 
 #[derive(Clone, Debug)]
@@ -76,6 +97,10 @@ pub struct ParseSettings {
     pub malformed: MalformedMode,
     pub max_field_size: usize,
     pub max_record_size: usize,
+    /// Columns of a fixed-width file; None for delimited input
+    pub fixed_columns: Option<Vec<FixedColumn>>,
+    /// Flattened keys of a JSON Lines file, read as its columns; None for text
+    pub json_columns: Option<Vec<String>>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -99,6 +124,73 @@ pub struct DetectedSettings {
     pub encoding: &'static Encoding,
     pub encoding_label: String,
     pub has_headers: bool,
+    /// Columns inferred from whitespace alignment, used in fixed-width mode
+    pub fixed_columns: Vec<FixedColumn>,
+    pub fixed_has_headers: bool,
+    pub json_columns: Option<Vec<String>>,
+}
+
+/// How a search query is matched against a single decoded cell.
+/// Built once per search so regex patterns are compiled a single time.
+#[derive(Clone, Debug)]
+pub enum CellMatcher {
+    /// `query` is already lowercased when `match_case` is false
+    Text {
+        query: String,
+        match_case: bool,
+        whole_word: bool,
+    },
+    Regex(Regex),
+}
+
+impl CellMatcher {
+    pub fn new(query: &str, match_case: bool, whole_word: bool, regex: bool) -> Result<Self, String> {
+        if regex {
+            let pattern = if whole_word {
+                format!("^(?:{query})$")
+            } else {
+                query.to_string()
+            };
+            let compiled = RegexBuilder::new(&pattern)
+                .case_insensitive(!match_case)
+                .build()
+                .map_err(|err| format!("Invalid regex: {err}"))?;
+            return Ok(CellMatcher::Regex(compiled));
+        }
+        Ok(CellMatcher::Text {
+            query: if match_case {
+                query.to_string()
+            } else {
+                query.to_lowercase()
+            },
+            match_case,
+            whole_word,
+        })
+    }
+
+    pub fn is_match(&self, cell: &str) -> bool {
+        match self {
+            CellMatcher::Regex(regex) => regex.is_match(cell),
+            CellMatcher::Text {
+                query,
+                match_case,
+                whole_word,
+            } => {
+                if !match_case {
+                    let val_lower = cell.to_lowercase();
+                    if *whole_word {
+                        val_lower == *query
+                    } else {
+                        val_lower.contains(query.as_str())
+                    }
+                } else if *whole_word {
+                    cell == query
+                } else {
+                    cell.contains(query.as_str())
+                }
+            }
+        }
+    }
 }
 
 fn push_warning(warnings: &mut Vec<ParseWarning>, warning: ParseWarning) {
@@ -142,7 +234,7 @@ fn warning_from_error(err: &csv::Error, record: Option<u64>) -> ParseWarning {
     warning
 }
 
-fn normalize_delimiter(value: &str) -> Option<u8> {
+pub fn normalize_delimiter(value: &str) -> Option<u8> {
     match value.trim().to_lowercase().as_str() {
         "comma" | "," => Some(b','),
         "tab" | "\\t" | "tsv" => Some(b'\t'),
@@ -154,7 +246,7 @@ fn normalize_delimiter(value: &str) -> Option<u8> {
     }
 }
 
-fn normalize_quote(value: &str) -> Option<u8> {
+pub fn normalize_quote(value: &str) -> Option<u8> {
     match value.trim().to_lowercase().as_str() {
         "double" | "\"" => Some(b'"'),
         "single" | "'" => Some(b'\''),
@@ -172,6 +264,142 @@ fn normalize_escape(value: &str) -> Option<Option<u8>> {
     }
 }
 
+/// Parses 1-based inclusive character ranges such as "1-10,11-25,26-". Ranges must
+/// be in order and not overlap; only the last one may be left open.
+pub fn parse_fixed_columns(value: &str) -> Option<Vec<FixedColumn>> {
+    let parts = value
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    let mut columns: Vec<FixedColumn> = Vec::with_capacity(parts.len());
+    for (idx, part) in parts.iter().enumerate() {
+        let (start, end) = part.split_once('-')?;
+        let start = start.trim().parse::<usize>().ok()?.checked_sub(1)?;
+        let end = match end.trim() {
+            "" if idx + 1 == parts.len() => None,
+            end => Some(end.parse::<usize>().ok()?),
+        };
+        if end.is_some_and(|end| end <= start) {
+            return None;
+        }
+        if let Some(previous) = columns.last() {
+            if previous.end.is_none_or(|end| end > start) {
+                return None;
+            }
+        }
+        columns.push(FixedColumn { start, end });
+    }
+    if columns.is_empty() {
+        None
+    } else {
+        Some(columns)
+    }
+}
+
+pub fn format_fixed_columns(columns: &[FixedColumn]) -> String {
+    columns
+        .iter()
+        .map(|column| match column.end {
+            Some(end) => format!("{}-{}", column.start + 1, end),
+            None => format!("{}-", column.start + 1),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Infers columns from whitespace alignment: a character position that is blank on
+/// every sampled line separates columns, and each column starts where a run of
+/// non-blank positions starts. Trailing lines cut off by the sample are left out.
+fn infer_fixed_columns(sample: &str) -> Vec<FixedColumn> {
+    let mut lines = sample.lines().collect::<Vec<_>>();
+    if !sample.ends_with('\n') && lines.len() > 1 {
+        lines.pop();
+    }
+    let lines = lines
+        .into_iter()
+        .map(|line| line.strip_prefix('\u{feff}').unwrap_or(line))
+        .filter(|line| !line.trim().is_empty())
+        .take(FIXED_SAMPLE_LINES)
+        .collect::<Vec<_>>();
+
+    let mut used: Vec<bool> = Vec::new();
+    for line in &lines {
+        for (pos, ch) in line.chars().enumerate() {
+            if pos >= used.len() {
+                used.resize(pos + 1, false);
+            }
+            if !ch.is_whitespace() {
+                used[pos] = true;
+            }
+        }
+    }
+
+    let starts = (0..used.len())
+        .filter(|&pos| used[pos] && (pos == 0 || !used[pos - 1]))
+        .collect::<Vec<_>>();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(idx, &start)| FixedColumn {
+            start: if idx == 0 { 0 } else { start },
+            end: starts.get(idx + 1).copied(),
+        })
+        .collect()
+}
+
+/// Fixed-width lines are read by splitting on line-feed bytes, which only works in
+/// encodings that keep ASCII bytes as they are
+pub fn check_fixed_width_encoding(settings: &ParseSettings) -> Result<(), String> {
+    if settings.fixed_columns.is_some() && !settings.encoding.is_ascii_compatible() {
+        return Err(format!(
+            "Fixed-width columns cannot be read from {} text; choose another encoding or \
+             convert the file to UTF-8",
+            settings.encoding.name()
+        ));
+    }
+    Ok(())
+}
+
+fn split_fixed_line(
+    line: &[u8],
+    columns: &[FixedColumn],
+    encoding: &'static Encoding,
+    record: &mut ByteRecord,
+) {
+    record.clear();
+    // Positions count characters, which are bytes only in single-byte encodings
+    if encoding.is_single_byte() {
+        for column in columns {
+            let start = column.start.min(line.len());
+            let end = column.end.map_or(line.len(), |end| end.min(line.len())).max(start);
+            record.push_field(line[start..end].trim_ascii());
+        }
+        return;
+    }
+    // Other encodings are cut as UTF-8 text, and their fields encoded back
+    let text = if encoding == encoding_rs::UTF_8 {
+        Cow::Borrowed(line)
+    } else {
+        Cow::Owned(encoding.decode_without_bom_handling(line).0.into_owned().into_bytes())
+    };
+    let text = text.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&text);
+    let boundaries = (0..text.len())
+        .filter(|&idx| text[idx] & 0xc0 != 0x80)
+        .collect::<Vec<_>>();
+    let byte_at = |pos: usize| boundaries.get(pos).copied().unwrap_or(text.len());
+    for column in columns {
+        let start = byte_at(column.start);
+        let end = column.end.map_or(text.len(), byte_at).max(start);
+        let field = text[start..end].trim_ascii();
+        if encoding == encoding_rs::UTF_8 {
+            record.push_field(field);
+        } else {
+            record.push_field(&encoding.encode(&String::from_utf8_lossy(field)).0);
+        }
+    }
+}
+
 fn normalize_line_ending(value: &str) -> Option<(Terminator, String)> {
     let normalized = value.trim().to_lowercase();
     let result = match normalized.as_str() {
@@ -386,6 +614,21 @@ pub fn detect_parse_settings(path: &str) -> Result<DetectedSettings, Box<dyn std
         _ => true,
     };
 
+    let json_columns = infer_columns(&decoded, sample.len() == SAMPLE_SIZE);
+    let fixed_columns = infer_fixed_columns(&decoded);
+    let mut fixed_rows = decoded
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut record = ByteRecord::new();
+            split_fixed_line(line.as_bytes(), &fixed_columns, encoding_rs::UTF_8, &mut record);
+            StringRecord::from_byte_record_lossy(record)
+        });
+    let fixed_has_headers = match (fixed_rows.next(), fixed_rows.next()) {
+        (Some(first), Some(second)) => looks_like_header(&first, &second),
+        _ => true,
+    };
+
     Ok(DetectedSettings {
         delimiter,
         quote,
@@ -394,6 +637,9 @@ pub fn detect_parse_settings(path: &str) -> Result<DetectedSettings, Box<dyn std
         encoding,
         encoding_label,
         has_headers,
+        fixed_columns,
+        fixed_has_headers,
+        json_columns,
     })
 }
 
@@ -408,10 +654,13 @@ pub fn parse_info_from_settings(settings: &ParseSettings) -> ParseInfo {
         escape: settings.escape.map(format_char),
         line_ending: settings.line_ending.clone(),
         encoding: settings.encoding_label.clone(),
-        has_headers: settings.has_headers,
+        // JSON Lines columns are named by their keys rather than a header line
+        has_headers: settings.has_headers || settings.json_columns.is_some(),
         malformed: settings.malformed.as_str().to_string(),
         max_field_size: settings.max_field_size,
         max_record_size: settings.max_record_size,
+        fixed_width: settings.fixed_columns.as_deref().map(format_fixed_columns),
+        json_lines: settings.json_columns.is_some(),
     }
 }
 
@@ -430,6 +679,7 @@ pub fn apply_parse_overrides(
     let mut malformed = MalformedMode::Skip;
     let mut max_field_size = 256 * 1024;
     let mut max_record_size = 2 * 1024 * 1024;
+    let mut fixed_columns = None;
 
     if let Some(overrides) = overrides {
         if let Some(value) = overrides.delimiter.as_deref() {
@@ -481,9 +731,33 @@ pub fn apply_parse_overrides(
         if let Some(value) = overrides.max_record_size {
             max_record_size = value;
         }
+        if let Some(value) = overrides.fixed_width.as_deref() {
+            fixed_columns = if value.trim().eq_ignore_ascii_case("auto") {
+                Some(detected.fixed_columns.clone())
+                    .filter(|columns| !columns.is_empty())
+                    .or(Some(vec![FixedColumn { start: 0, end: None }]))
+            } else {
+                parse_fixed_columns(value)
+            };
+            if fixed_columns.is_some() && overrides.has_headers.is_none() {
+                has_headers = detected.fixed_has_headers;
+            }
+        }
     } else if let Some((term, _)) = normalize_line_ending(&detected.line_ending) {
         terminator = term;
     }
+    // Explicit fixed-width columns win over JSON detection
+    let json_columns = detected
+        .json_columns
+        .clone()
+        .filter(|_| fixed_columns.is_none());
+    if json_columns.is_some() {
+        // Copies and saves of the rows are written as plain CSV
+        has_headers = false;
+        delimiter = b',';
+        quote = b'"';
+        escape = None;
+    }
 
     ParseSettings {
         delimiter,
@@ -497,6 +771,8 @@ pub fn apply_parse_overrides(
         malformed,
         max_field_size,
         max_record_size,
+        fixed_columns,
+        json_columns,
     }
 }
 
@@ -513,6 +789,8 @@ pub fn default_parse_settings() -> ParseSettings {
         malformed: MalformedMode::Skip,
         max_field_size: 256 * 1024,
         max_record_size: 2 * 1024 * 1024,
+        fixed_columns: None,
+        json_columns: None,
     }
 }
 
@@ -527,23 +805,129 @@ pub fn settings_cache_hash(settings: &ParseSettings) -> u64 {
     settings.malformed.as_str().hash(&mut hasher);
     settings.max_field_size.hash(&mut hasher);
     settings.max_record_size.hash(&mut hasher);
+    settings.fixed_columns.hash(&mut hasher);
+    settings.json_columns.hash(&mut hasher);
     hasher.finish()
 }
 
+/// How whole lines are cut into columns when the input is not delimited
+enum LineFormat {
+    Fixed {
+        columns: Vec<FixedColumn>,
+        encoding: &'static Encoding,
+    },
+    Json(JsonLayout),
+}
+
+impl LineFormat {
+    fn split(&self, line: &[u8], record: &mut ByteRecord, unknown_keys: &mut Vec<String>) {
+        match self {
+            LineFormat::Fixed { columns, encoding } => {
+                split_fixed_line(line, columns, encoding, record)
+            }
+            LineFormat::Json(layout) => layout.split_line(line, record, unknown_keys),
+        }
+    }
+}
+
+/// Reads records of delimited input, or of fixed-width and JSON Lines input where
+/// every line is read whole and cut into columns. Either way record positions,
+/// seeking and decoding work the same.
+pub struct RecordReader<R> {
+    inner: csv::Reader<R>,
+    lines: Option<LineFormat>,
+    raw: ByteRecord,
+    line: Vec<u8>,
+    headers: ByteRecord,
+    unknown_keys: Vec<String>,
+}
+
+/// Joins the fields of a line read with NUL as the delimiter back into the line
+fn join_line(record: &ByteRecord, line: &mut Vec<u8>) {
+    line.clear();
+    for (idx, field) in record.iter().enumerate() {
+        if idx > 0 {
+            line.push(0);
+        }
+        line.extend_from_slice(field);
+    }
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn read_byte_record(&mut self, record: &mut ByteRecord) -> csv::Result<bool> {
+        let Some(lines) = self.lines.as_ref() else {
+            return self.inner.read_byte_record(record);
+        };
+        if !self.inner.read_byte_record(&mut self.raw)? {
+            return Ok(false);
+        }
+        join_line(&self.raw, &mut self.line);
+        lines.split(&self.line, record, &mut self.unknown_keys);
+        Ok(true)
+    }
+
+    /// Keys of the last JSON Lines record that have no column, because they were
+    /// first seen after the sample the columns come from
+    pub fn unknown_keys(&self) -> &[String] {
+        &self.unknown_keys
+    }
+
+    pub fn byte_headers(&mut self) -> csv::Result<&ByteRecord> {
+        let headers = self.inner.byte_headers()?;
+        let Some(lines) = self.lines.as_ref() else {
+            return Ok(headers);
+        };
+        join_line(headers, &mut self.line);
+        lines.split(&self.line, &mut self.headers, &mut self.unknown_keys);
+        Ok(&self.headers)
+    }
+
+    pub fn position(&self) -> &Position {
+        self.inner.position()
+    }
+}
+
+impl<R: Read + Seek> RecordReader<R> {
+    pub fn seek(&mut self, pos: Position) -> csv::Result<()> {
+        self.inner.seek(pos)
+    }
+}
+
 pub fn build_reader<R: Read>(
     reader: R,
     settings: &ParseSettings,
     has_headers: bool,
-) -> csv::Reader<R> {
+) -> RecordReader<R> {
     let mut builder = ReaderBuilder::new();
     builder
-        .delimiter(settings.delimiter)
-        .quote(settings.quote)
-        .escape(settings.escape)
         .terminator(settings.terminator)
-        .has_headers(has_headers)
-        .flexible(settings.malformed != MalformedMode::Strict);
-    builder.from_reader(reader)
+        .has_headers(has_headers);
+    let lines = match (&settings.fixed_columns, &settings.json_columns) {
+        (Some(columns), _) => Some(LineFormat::Fixed {
+            columns: columns.clone(),
+            encoding: settings.encoding,
+        }),
+        (None, Some(columns)) => Some(LineFormat::Json(JsonLayout::new(columns))),
+        (None, None) => None,
+    };
+    if lines.is_some() {
+        // Lines are read whole; NUL bytes are unlikely in text and joined back anyway
+        builder.delimiter(0).quoting(false).flexible(true);
+    } else {
+        builder
+            .delimiter(settings.delimiter)
+            .quote(settings.quote)
+            .escape(settings.escape)
+            .flexible(settings.malformed != MalformedMode::Strict);
+    }
+    RecordReader {
+        inner: builder.from_reader(reader),
+        lines,
+        raw: ByteRecord::new(),
+        line: Vec::new(),
+        headers: ByteRecord::new(),
+        unknown_keys: Vec::new(),
+    }
 }
 
 fn strip_bom(value: &str) -> &str {
@@ -772,6 +1156,9 @@ pub fn get_headers(
     settings: &ParseSettings,
     warnings: &mut Vec<ParseWarning>,
 ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if let Some(columns) = settings.json_columns.as_ref() {
+        return Ok(columns.clone());
+    }
     let file = File::open(path)?;
     let mut rdr = build_reader(file, settings, settings.has_headers);
 
@@ -821,11 +1208,12 @@ pub fn get_headers(
 }
 
 fn build_row_offsets_from_reader<R: Read>(
-    mut rdr: csv::Reader<R>,
+    mut rdr: RecordReader<R>,
     settings: &ParseSettings,
     expected_columns: Option<usize>,
     warnings: &mut Vec<ParseWarning>,
     progress_cb: Option<&dyn Fn(usize)>,
+    cancel: Option<&AtomicBool>,
 ) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
     if settings.has_headers {
         let _ = rdr.byte_headers().map_err(|err| {
@@ -837,11 +1225,32 @@ fn build_row_offsets_from_reader<R: Read>(
     let mut offsets = Vec::new();
     let mut record = ByteRecord::new();
     let mut row_index: u64 = 0;
+    let mut reported_keys = HashSet::new();
     loop {
         let pos = rdr.position().byte();
         match rdr.read_byte_record(&mut record) {
             Ok(false) => break,
             Ok(true) => {
+                for key in rdr.unknown_keys() {
+                    if reported_keys.insert(key.clone()) {
+                        push_warning(
+                            warnings,
+                            ParseWarning {
+                                record: Some(row_index),
+                                line: None,
+                                byte: Some(pos),
+                                field: None,
+                                kind: "unknown-key".to_string(),
+                                message: format!(
+                                    "Key \"{key}\" first appears after the {SAMPLE_LINES} lines \
+                                     the columns come from and is not shown"
+                                ),
+                                expected_len: None,
+                                len: None,
+                            },
+                        );
+                    }
+                }
                 let mut skip_row = false;
                 if let Some(expected) = expected_columns {
                     if record.len() != expected {
@@ -1007,6 +1416,9 @@ fn build_row_offsets_from_reader<R: Read>(
                     if let Some(cb) = progress_cb {
                         cb(row_index as usize);
                     }
+                    if cancel.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+                        return Err(CANCELLED_MESSAGE.into());
+                    }
                 }
                 row_index += 1;
             }
@@ -1025,7 +1437,7 @@ fn build_row_offsets_from_reader<R: Read>(
 }
 
 fn read_chunk_from_reader<R: Read>(
-    mut rdr: csv::Reader<R>,
+    mut rdr: RecordReader<R>,
     start: usize,
     count: usize,
     settings: &ParseSettings,
@@ -1113,7 +1525,7 @@ fn read_chunk_from_reader<R: Read>(
 }
 
 fn read_chunk_with_offsets_from_reader<R: Read + Seek>(
-    mut rdr: csv::Reader<R>,
+    mut rdr: RecordReader<R>,
     offsets: &[u64],
     start: usize,
     count: usize,
@@ -1184,7 +1596,7 @@ fn read_chunk_with_offsets_from_reader<R: Read + Seek>(
 }
 
 fn read_rows_by_index_from_reader<R: Read + Seek>(
-    mut rdr: csv::Reader<R>,
+    mut rdr: RecordReader<R>,
     offsets: &[u64],
     indices: &[usize],
     settings: &ParseSettings,
@@ -1272,14 +1684,12 @@ fn read_rows_by_index_from_reader<R: Read + Seek>(
 }
 
 fn search_range_with_offsets_from_reader<R: Read + Seek>(
-    mut rdr: csv::Reader<R>,
+    mut rdr: RecordReader<R>,
     offsets: &[u64],
     start: usize,
     end: usize,
     column_idx: Option<usize>,
-    query: &str,
-    match_case: bool,
-    whole_word: bool,
+    matcher: &CellMatcher,
     settings: &ParseSettings,
 ) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
     if start >= offsets.len() {
@@ -1300,43 +1710,14 @@ fn search_range_with_offsets_from_reader<R: Read + Seek>(
         let is_match = match column_idx {
             Some(index) => record
                 .get(index)
-                .and_then(|cell| {
+                .map(|cell| {
                     let (decoded, _, _) = settings.encoding.decode(cell);
-                    let val = decoded.as_ref();
-                    let matched = if !match_case {
-                         let val_lower = val.to_lowercase();
-                         if whole_word {
-                             val_lower == query
-                         } else {
-                             val_lower.contains(query)
-                         }
-                    } else {
-                         if whole_word {
-                              val == query
-                         } else {
-                              val.contains(query)
-                         }
-                    };
-                    Some(matched)
+                    matcher.is_match(decoded.as_ref())
                 })
                 .unwrap_or(false),
             None => record.iter().any(|cell| {
                 let (decoded, _, _) = settings.encoding.decode(cell);
-                let val = decoded.as_ref();
-                if !match_case {
-                     let val_lower = val.to_lowercase();
-                     if whole_word {
-                         val_lower == query
-                     } else {
-                         val_lower.contains(query)
-                     }
-                } else {
-                     if whole_word {
-                          val == query
-                     } else {
-                          val.contains(query)
-                     }
-                }
+                matcher.is_match(decoded.as_ref())
             }),
         };
         if is_match {
@@ -1347,17 +1728,51 @@ fn search_range_with_offsets_from_reader<R: Read + Seek>(
     Ok(matches)
 }
 
+fn filter_range_with_offsets_from_reader<R: Read + Seek>(
+    mut rdr: RecordReader<R>,
+    offsets: &[u64],
+    start: usize,
+    end: usize,
+    settings: &ParseSettings,
+    predicate: &(dyn Fn(&[String]) -> bool + Sync),
+) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    if start >= offsets.len() {
+        return Ok(Vec::new());
+    }
+
+    let end = usize::min(end, offsets.len());
+    let mut position = Position::new();
+    position.set_byte(offsets[start]);
+    rdr.seek(position)?;
+
+    let mut record = ByteRecord::new();
+    let mut matches = Vec::new();
+    for row_index in start..end {
+        if !rdr.read_byte_record(&mut record)? {
+            break;
+        }
+        let strip_bom = !settings.has_headers && row_index == 0;
+        let (decoded, _) = decode_record(&record, settings, strip_bom);
+        if predicate(&decoded) {
+            matches.push(row_index);
+        }
+    }
+
+    Ok(matches)
+}
+
 pub fn build_row_offsets(
     path: &str,
     settings: &ParseSettings,
     expected_columns: Option<usize>,
     warnings: &mut Vec<ParseWarning>,
     progress_cb: Option<&dyn Fn(usize)>,
+    cancel: Option<&AtomicBool>,
 ) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
     let rdr = build_reader(reader, settings, settings.has_headers);
-    build_row_offsets_from_reader(rdr, settings, expected_columns, warnings, progress_cb)
+    build_row_offsets_from_reader(rdr, settings, expected_columns, warnings, progress_cb, cancel)
 }
 
 pub fn build_row_offsets_mmap(
@@ -1366,9 +1781,43 @@ pub fn build_row_offsets_mmap(
     expected_columns: Option<usize>,
     warnings: &mut Vec<ParseWarning>,
     progress_cb: Option<&dyn Fn(usize)>,
+    cancel: Option<&AtomicBool>,
 ) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
     let rdr = build_reader(data, settings, settings.has_headers);
-    build_row_offsets_from_reader(rdr, settings, expected_columns, warnings, progress_cb)
+    build_row_offsets_from_reader(rdr, settings, expected_columns, warnings, progress_cb, cancel)
+}
+
+/// Parses rows starting at byte `start`, which must be the beginning of a record.
+/// Used to pick up rows appended to a file without re-reading what came before;
+/// `first_record` is the index of the record at `start`, for warning positions.
+pub fn build_row_offsets_from(
+    path: &str,
+    settings: &ParseSettings,
+    start: u64,
+    first_record: u64,
+    expected_columns: Option<usize>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail_settings = settings.clone();
+    tail_settings.has_headers = false;
+    let rdr = build_reader(BufReader::new(file), &tail_settings, false);
+    let mut tail_warnings = Vec::new();
+    let offsets = build_row_offsets_from_reader(
+        rdr,
+        &tail_settings,
+        expected_columns,
+        &mut tail_warnings,
+        None,
+        None,
+    )?;
+    for mut warning in tail_warnings {
+        warning.record = warning.record.map(|record| record + first_record);
+        warning.byte = warning.byte.map(|byte| byte + start);
+        push_warning(warnings, warning);
+    }
+    Ok(offsets.into_iter().map(|offset| offset + start).collect())
 }
 
 pub fn read_chunk(
@@ -1489,25 +1938,13 @@ pub fn search_range_with_offsets(
     start: usize,
     end: usize,
     column_idx: Option<usize>,
-    query: &str,
-    match_case: bool,
-    whole_word: bool,
+    matcher: &CellMatcher,
     settings: &ParseSettings,
 ) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
     let rdr = build_reader(reader, settings, false);
-    search_range_with_offsets_from_reader(
-        rdr,
-        offsets,
-        start,
-        end,
-        column_idx,
-        query,
-        match_case,
-        whole_word,
-        settings,
-    )
+    search_range_with_offsets_from_reader(rdr, offsets, start, end, column_idx, matcher, settings)
 }
 
 pub fn search_range_with_offsets_mmap(
@@ -1516,24 +1953,39 @@ pub fn search_range_with_offsets_mmap(
     start: usize,
     end: usize,
     column_idx: Option<usize>,
-    query: &str,
-    match_case: bool,
-    whole_word: bool,
+    matcher: &CellMatcher,
     settings: &ParseSettings,
 ) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
     let cursor = Cursor::new(data);
     let rdr = build_reader(cursor, settings, false);
-    search_range_with_offsets_from_reader(
-        rdr,
-        offsets,
-        start,
-        end,
-        column_idx,
-        query,
-        match_case,
-        whole_word,
-        settings,
-    )
+    search_range_with_offsets_from_reader(rdr, offsets, start, end, column_idx, matcher, settings)
+}
+
+pub fn filter_range_with_offsets(
+    path: &str,
+    offsets: &[u64],
+    start: usize,
+    end: usize,
+    settings: &ParseSettings,
+    predicate: &(dyn Fn(&[String]) -> bool + Sync),
+) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let rdr = build_reader(reader, settings, false);
+    filter_range_with_offsets_from_reader(rdr, offsets, start, end, settings, predicate)
+}
+
+pub fn filter_range_with_offsets_mmap(
+    data: &[u8],
+    offsets: &[u64],
+    start: usize,
+    end: usize,
+    settings: &ParseSettings,
+    predicate: &(dyn Fn(&[String]) -> bool + Sync),
+) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    let cursor = Cursor::new(data);
+    let rdr = build_reader(cursor, settings, false);
+    filter_range_with_offsets_from_reader(rdr, offsets, start, end, settings, predicate)
 }
 
 pub fn find_duplicates_hashed(
@@ -1541,6 +1993,7 @@ pub fn find_duplicates_hashed(
     offsets: &[u64],
     settings: &ParseSettings,
     column_idx: Option<usize>,
+    cancel: &AtomicBool,
 ) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
@@ -1552,7 +2005,7 @@ pub fn find_duplicates_hashed(
     let rdr = build_reader(reader, &safe_settings, false);
 
     // 1. Compute Hashes
-    let mut hashes = compute_hashes_from_reader(rdr, offsets, column_idx)?;
+    let mut hashes = compute_hashes_from_reader(rdr, offsets, column_idx, cancel)?;
 
 
 
@@ -1573,7 +2026,9 @@ pub fn find_duplicates_hashed(
         }
 
         if run_end > i + 1 {
-
+            if cancel.load(Ordering::Relaxed) {
+                return Err(CANCELLED_MESSAGE.into());
+            }
 
             // Found a collision group of size (run_end - i)
             let candidates: Vec<usize> = hashes[i..run_end].iter().map(|&(_, idx)| idx as usize).collect();
@@ -1614,6 +2069,7 @@ pub fn find_duplicates_hashed_mmap(
     offsets: &[u64],
     settings: &ParseSettings,
     column_idx: Option<usize>,
+    cancel: &AtomicBool,
 ) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
     let mut safe_settings = settings.clone();
     safe_settings.has_headers = false;
@@ -1649,6 +2105,9 @@ pub fn find_duplicates_hashed_mmap(
         .map(|(chunk_idx, batch_offsets)| {
             let start_row = chunk_idx * chunk_size;
             let mut local_hashes = Vec::with_capacity(batch_offsets.len());
+            if cancel.load(Ordering::Relaxed) {
+                return local_hashes;
+            }
             // Create a thread-local reader
             let cursor = Cursor::new(data);
              // We reuse settings but has_headers=false for data reading
@@ -1689,6 +2148,9 @@ pub fn find_duplicates_hashed_mmap(
         })
         .flatten()
         .collect();
+    if cancel.load(Ordering::Relaxed) {
+        return Err(CANCELLED_MESSAGE.into());
+    }
 
     // 2. Sort by hash
     hashes.par_sort_unstable_by_key(|k| k.0);
@@ -1723,6 +2185,9 @@ pub fn find_duplicates_hashed_mmap(
 
             // Extract the indices for this group
             let group_indices: Vec<usize> = hashes[range].iter().map(|&(_, idx)| idx as usize).collect();
+            if cancel.load(Ordering::Relaxed) {
+                return Vec::new();
+            }
 
             // Optimization: Since we know the offsets, we can read just those rows.
             // We'll create a local reader.
@@ -1786,15 +2251,20 @@ pub fn find_duplicates_hashed_mmap(
         .flatten()
         .collect();
 
+    if cancel.load(Ordering::Relaxed) {
+        return Err(CANCELLED_MESSAGE.into());
+    }
+
     let mut duplicates = confirmed_duplicates;
     duplicates.sort_unstable();
     Ok(duplicates)
 }
 
 fn compute_hashes_from_reader<R: Read + Seek>(
-    mut rdr: csv::Reader<R>,
+    mut rdr: RecordReader<R>,
     offsets: &[u64],
     column_idx: Option<usize>,
+    cancel: &AtomicBool,
 ) -> Result<Vec<(u64, u32)>, Box<dyn std::error::Error>> {
     let mut hashes = Vec::with_capacity(offsets.len());
     let mut record = ByteRecord::new();
@@ -1805,6 +2275,9 @@ fn compute_hashes_from_reader<R: Read + Seek>(
         rdr.seek(pos)?;
 
         for (i, _) in offsets.iter().enumerate() {
+            if i.is_multiple_of(CANCEL_CHECK_INTERVAL) && cancel.load(Ordering::Relaxed) {
+                return Err(CANCELLED_MESSAGE.into());
+            }
             // We use `read_byte_record` to reuse memory
             if !rdr.read_byte_record(&mut record)? {
                 break;
@@ -1857,6 +2330,9 @@ mod tests {
             encoding: encoding_rs::UTF_8,
             encoding_label: "utf-8".to_string(),
             has_headers: true,
+            fixed_columns: Vec::new(),
+            fixed_has_headers: true,
+            json_columns: None,
         };
         let overrides = ParseOverrides {
             delimiter: Some("tab".to_string()),
@@ -1868,6 +2344,7 @@ mod tests {
             malformed: Some("repair".to_string()),
             max_field_size: Some(10),
             max_record_size: Some(20),
+            fixed_width: None,
         };
 
         let settings = apply_parse_overrides(&detected, Some(overrides));
@@ -1911,6 +2388,7 @@ mod tests {
             Some(2),
             &mut warnings,
             None,
+            None,
         )
         .expect("build offsets");
         assert_eq!(offsets.len(), 3);
@@ -1930,6 +2408,169 @@ mod tests {
         assert_eq!(rows, vec![vec!["beta".to_string(), "2".to_string()]]);
     }
 
+    #[test]
+    fn build_offsets_from_resumes_after_append() {
+        let mut file = write_temp_csv("col1,col2\nalpha,1\nbeta,");
+        let path = file.path().to_str().unwrap().to_string();
+        let settings = default_parse_settings();
+        let mut warnings = Vec::new();
+        let offsets =
+            build_row_offsets(&path, &settings, Some(2), &mut warnings, None, None)
+                .expect("build offsets");
+        assert_eq!(offsets.len(), 2);
+
+        file.write_all(b"2\ngamma,3\n").expect("append csv");
+        file.flush().expect("flush csv");
+
+        let resume = *offsets.last().unwrap();
+        let tail = build_row_offsets_from(&path, &settings, resume, 1, Some(2), &mut warnings)
+            .expect("build tail offsets");
+        let full = build_row_offsets(&path, &settings, Some(2), &mut warnings, None, None)
+            .expect("rebuild offsets");
+        assert_eq!(tail[0], resume);
+        assert_eq!(&full[1..], &tail[..]);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn fixed_width_columns_are_inferred_and_parsed() {
+        let sample = "id  name      city\n1   Ann Lee   Oslo\n22  Bo        Bergen\n3   Cé";
+        let columns = infer_fixed_columns(sample);
+        assert_eq!(format_fixed_columns(&columns), "1-4,5-14,15-");
+        assert_eq!(parse_fixed_columns("1-4, 5-14,15-"), Some(columns));
+        assert_eq!(parse_fixed_columns("1-4,3-8"), None);
+        assert_eq!(parse_fixed_columns("1-,5-8"), None);
+        assert_eq!(parse_fixed_columns("0-4"), None);
+
+        let mut record = ByteRecord::new();
+        let columns = parse_fixed_columns("1-2,3-5,6-").unwrap();
+        split_fixed_line("\u{feff}éa ñbc".as_bytes(), &columns, encoding_rs::UTF_8, &mut record);
+        let fields = record.iter().collect::<Vec<_>>();
+        assert_eq!(fields, [&b"\xc3\xa9a"[..], "ñb".as_bytes(), b"c"]);
+        split_fixed_line(b"ab", &columns, encoding_rs::UTF_8, &mut record);
+        assert_eq!(record.iter().collect::<Vec<_>>(), [&b"ab"[..], b"", b""]);
+
+        // Double-byte characters count once and come back in the file's encoding
+        let (line, _, _) = encoding_rs::SHIFT_JIS.encode("日本 語abc");
+        split_fixed_line(&line, &columns, encoding_rs::SHIFT_JIS, &mut record);
+        let settings = ParseSettings {
+            encoding: encoding_rs::SHIFT_JIS,
+            ..default_parse_settings()
+        };
+        let (fields, _) = decode_record(&record, &settings, false);
+        assert_eq!(fields, ["日本", "語a", "bc"]);
+        split_fixed_line(b"\xe9a \xf1bc", &columns, encoding_rs::WINDOWS_1252, &mut record);
+        assert_eq!(record.iter().collect::<Vec<_>>(), [&b"\xe9a"[..], b"\xf1b", b"c"]);
+
+        let mut utf16 = ParseSettings {
+            encoding: encoding_rs::UTF_16LE,
+            ..default_parse_settings()
+        };
+        assert!(check_fixed_width_encoding(&utf16).is_ok());
+        utf16.fixed_columns = Some(columns);
+        assert!(check_fixed_width_encoding(&utf16).is_err());
+    }
+
+    #[test]
+    fn fixed_width_files_read_through_offsets() {
+        let file = write_temp_csv("id  name\n1   a,\"b\n22  c\n");
+        let path = file.path().to_str().unwrap();
+        let detected = detect_parse_settings(path).expect("detect");
+        let overrides = ParseOverrides {
+            fixed_width: Some("auto".to_string()),
+            ..Default::default()
+        };
+        let settings = apply_parse_overrides(&detected, Some(overrides));
+        assert_eq!(settings.fixed_columns, parse_fixed_columns("1-4,5-"));
+        assert!(settings.has_headers);
+        assert_eq!(
+            parse_info_from_settings(&settings).fixed_width.as_deref(),
+            Some("1-4,5-")
+        );
+
+        let mut warnings = Vec::new();
+        let offsets =
+            build_row_offsets(path, &settings, Some(2), &mut warnings, None, None)
+                .expect("build offsets");
+        assert_eq!(offsets.len(), 2);
+        let rows = read_chunk_with_offsets(path, &offsets, 0, 2, &settings, Some(2), &mut warnings)
+            .expect("read chunk");
+        assert_eq!(
+            rows,
+            vec![
+                vec!["1".to_string(), "a,\"b".to_string()],
+                vec!["22".to_string(), "c".to_string()],
+            ]
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn json_lines_files_read_flattened_rows() {
+        let file = write_temp_csv(concat!(
+            "{\"id\": 1, \"user\": {\"city\": \"Oslo, NO\"}}\n",
+            "{\"id\": 2, \"tags\": [\"x\"]}\r\n",
+            "oops\n",
+            "{\"id\": 3}\n",
+        ));
+        let path = file.path().to_str().unwrap();
+        let detected = detect_parse_settings(path).expect("detect");
+        assert_eq!(detected.json_columns, None);
+
+        let file = write_temp_csv(concat!(
+            "{\"id\": 1, \"user\": {\"city\": \"Oslo, NO\"}}\n",
+            "{\"id\": 2, \"tags\": [\"x\"]}\r\n",
+            "{\"id\": 3}\n",
+        ));
+        let path = file.path().to_str().unwrap();
+        let settings = apply_parse_overrides(&detect_parse_settings(path).expect("detect"), None);
+        assert!(!settings.has_headers);
+        assert!(parse_info_from_settings(&settings).json_lines);
+        let mut warnings = Vec::new();
+        let headers = get_headers(path, &settings, &mut warnings).expect("headers");
+        assert_eq!(headers, ["id", "user.city", "tags"]);
+
+        let offsets =
+            build_row_offsets(path, &settings, Some(3), &mut warnings, None, None)
+                .expect("build offsets");
+        assert_eq!(offsets.len(), 3);
+        let rows = read_rows_by_index(path, &offsets, &[2, 0, 1], &settings, Some(3), &mut warnings)
+            .expect("read rows");
+        assert_eq!(
+            rows,
+            vec![
+                vec!["3".to_string(), String::new(), String::new()],
+                vec!["1".to_string(), "Oslo, NO".to_string(), String::new()],
+                vec!["2".to_string(), String::new(), "[\"x\"]".to_string()],
+            ]
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn json_lines_keys_after_the_sample_are_reported_once() {
+        let mut contents = String::new();
+        for id in 0..SAMPLE_LINES + 2 {
+            if id < SAMPLE_LINES {
+                contents.push_str(&format!("{{\"id\": {id}}}\n"));
+            } else {
+                contents.push_str(&format!("{{\"id\": {id}, \"late\": true}}\n"));
+            }
+        }
+        let file = write_temp_csv(&contents);
+        let path = file.path().to_str().unwrap();
+        let settings = apply_parse_overrides(&detect_parse_settings(path).expect("detect"), None);
+        assert_eq!(settings.json_columns, Some(vec!["id".to_string()]));
+
+        let mut warnings = Vec::new();
+        let offsets = build_row_offsets(path, &settings, Some(1), &mut warnings, None, None)
+            .expect("build offsets");
+        assert_eq!(offsets.len(), SAMPLE_LINES + 2);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, "unknown-key");
+        assert_eq!(warnings[0].record, Some(SAMPLE_LINES as u64));
+    }
+
     #[test]
     fn search_range_whole_word_and_contains() {
         let file = write_temp_csv("col\r\nalpha\r\nalphabet\r\nbeta\r\n");
@@ -1941,6 +2582,7 @@ mod tests {
             Some(1),
             &mut warnings,
             None,
+            None,
         )
         .expect("build offsets");
 
@@ -1950,9 +2592,7 @@ mod tests {
             0,
             offsets.len(),
             Some(0),
-            "alpha",
-            false,
-            true,
+            &CellMatcher::new("alpha", false, true, false).unwrap(),
             &settings,
         )
         .expect("search whole word");
@@ -1964,13 +2604,75 @@ mod tests {
             0,
             offsets.len(),
             Some(0),
-            "alpha",
-            false,
-            false,
+            &CellMatcher::new("alpha", false, false, false).unwrap(),
             &settings,
         )
         .expect("search contains");
         assert_eq!(contains, vec![0, 1]);
+
+        let regex = search_range_with_offsets(
+            file.path().to_str().unwrap(),
+            &offsets,
+            0,
+            offsets.len(),
+            None,
+            &CellMatcher::new("^(ALPHA|beta)$", false, false, true).unwrap(),
+            &settings,
+        )
+        .expect("search regex");
+        assert_eq!(regex, vec![0, 2]);
+    }
+
+    #[test]
+    fn cell_matcher_regex_options() {
+        let anchored = CellMatcher::new("a.c", true, true, true).expect("regex");
+        assert!(anchored.is_match("abc"));
+        assert!(!anchored.is_match("xabc"));
+        assert!(!anchored.is_match("ABC"));
+
+        let err = CellMatcher::new("(unclosed", false, false, true).unwrap_err();
+        assert!(err.starts_with("Invalid regex"));
+    }
+
+    #[test]
+    fn filter_range_applies_predicate_to_decoded_rows() {
+        let file = write_temp_csv("name,amount\r\nalpha,5\r\nbeta,50\r\ngamma,500\r\n");
+        let settings = default_parse_settings();
+        let mut warnings = Vec::new();
+        let offsets = build_row_offsets(
+            file.path().to_str().unwrap(),
+            &settings,
+            Some(2),
+            &mut warnings,
+            None,
+            None,
+        )
+        .expect("build offsets");
+
+        let predicate = |row: &[String]| row[1].parse::<u32>().unwrap_or(0) > 10;
+        let matches = filter_range_with_offsets(
+            file.path().to_str().unwrap(),
+            &offsets,
+            1,
+            offsets.len(),
+            &settings,
+            &predicate,
+        )
+        .expect("filter range");
+        assert_eq!(matches, vec![1, 2]);
+
+        // The first data row sits right after the header and must be checked too
+        let predicate = |row: &[String]| row[0] != "beta";
+        let matches = filter_range_with_offsets(
+            file.path().to_str().unwrap(),
+            &offsets,
+            0,
+            offsets.len(),
+            &settings,
+            &predicate,
+        )
+        .expect("filter range");
+        assert_eq!(matches, vec![0, 2]);
     }
 
     #[test]
@@ -1999,6 +2701,7 @@ mod tests {
             Some(2),
             &mut warnings,
             None,
+            None,
         )
         .expect("build offsets");
 
@@ -2007,13 +2710,20 @@ mod tests {
             &offsets,
             &settings,
             None,
+            &AtomicBool::new(false),
         )
         .expect("find duplicates");
         assert_eq!(duplicates, vec![0, 1, 2, 4]);
 
         let data = std::fs::read(file.path()).expect("read file");
         let mmap_duplicates =
-            find_duplicates_hashed_mmap(&data, &offsets, &settings, None).expect("find mmap");
+            find_duplicates_hashed_mmap(&data, &offsets, &settings, None, &AtomicBool::new(false))
+                .expect("find mmap");
         assert_eq!(mmap_duplicates, vec![0, 1, 2, 4]);
+
+        let cancelled = AtomicBool::new(true);
+        let err = find_duplicates_hashed_mmap(&data, &offsets, &settings, None, &cancelled)
+            .expect_err("cancelled scan");
+        assert_eq!(err.to_string(), CANCELLED_MESSAGE);
     }
 }
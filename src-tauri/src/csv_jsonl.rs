@@ -0,0 +1,197 @@
+use csv::ByteRecord;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+/// Complete lines of the sample checked when telling JSON Lines from delimited text
+pub const SAMPLE_LINES: usize = 1000;
+
+/// Column layout of a JSON Lines file: one column per flattened key
+#[derive(Clone, Debug)]
+pub struct JsonLayout {
+    positions: HashMap<String, usize>,
+    width: usize,
+}
+
+impl JsonLayout {
+    pub fn new(columns: &[String]) -> Self {
+        JsonLayout {
+            positions: columns
+                .iter()
+                .enumerate()
+                .map(|(idx, name)| (name.clone(), idx))
+                .collect(),
+            width: columns.len(),
+        }
+    }
+
+    /// Cuts a line into the layout's columns. Keys missing from the line stay
+    /// empty and keys outside the layout are dropped and listed in `unknown`. A
+    /// line that is not a JSON object becomes a single field holding the line, so
+    /// the malformed-row mode decides whether it is skipped or padded.
+    pub fn split_line(&self, line: &[u8], record: &mut ByteRecord, unknown: &mut Vec<String>) {
+        record.clear();
+        unknown.clear();
+        let Some(object) = parse_object(line) else {
+            record.push_field(line);
+            return;
+        };
+        let mut fields = vec![String::new(); self.width];
+        flatten_object(
+            &object,
+            "",
+            &mut |key, value| match self.positions.get(key) {
+                Some(&idx) => fields[idx] = value,
+                None => unknown.push(key.to_string()),
+            },
+        );
+        for field in &fields {
+            record.push_field(field.as_bytes());
+        }
+    }
+}
+
+fn parse_object(line: &[u8]) -> Option<Map<String, Value>> {
+    let line = line.strip_prefix(b"\xef\xbb\xbf").unwrap_or(line);
+    match serde_json::from_slice(line.trim_ascii()) {
+        Ok(Value::Object(object)) => Some(object),
+        _ => None,
+    }
+}
+
+/// Text of a scalar cell; arrays are kept as JSON text
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// A key as one segment of a dotted path. Dots and backslashes in the key are
+/// escaped, so a literal `"a.b"` key does not collide with `{"a": {"b": ...}}`.
+fn key_segment(key: &str) -> Cow<'_, str> {
+    if key.contains(['.', '\\']) {
+        Cow::Owned(key.replace('\\', "\\\\").replace('.', "\\."))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// Calls `visit` with the dotted key and text of every leaf, nested objects first
+/// to last in document order: `{"user": {"id": 1}}` gives `user.id` and
+/// `{"user.id": 1}` gives `user\.id`
+fn flatten_object(object: &Map<String, Value>, prefix: &str, visit: &mut dyn FnMut(&str, String)) {
+    for (key, value) in object {
+        let key = if prefix.is_empty() {
+            key_segment(key).into_owned()
+        } else {
+            format!("{prefix}.{}", key_segment(key))
+        };
+        match value {
+            Value::Object(nested) if !nested.is_empty() => flatten_object(nested, &key, visit),
+            Value::Object(_) => visit(&key, String::new()),
+            other => visit(&key, value_text(other)),
+        }
+    }
+}
+
+/// Columns of a JSON Lines sample: the union of flattened keys, in the order they
+/// first appear. None when any complete line of the sample is not a JSON object or
+/// no keys were found, which means the file is read as delimited text. Keys first
+/// seen after the sample have no column; reading the rows reports them.
+pub fn infer_columns(sample: &str, truncated: bool) -> Option<Vec<String>> {
+    let mut lines = sample.lines().collect::<Vec<_>>();
+    // The last line of a sample that stops short of the end of the file may be cut off
+    if truncated && !sample.ends_with('\n') {
+        lines.pop();
+    }
+
+    let mut columns = Vec::new();
+    let mut seen = HashSet::new();
+    for line in lines
+        .into_iter()
+        .filter(|line| !line.trim().is_empty())
+        .take(SAMPLE_LINES)
+    {
+        let object = parse_object(line.as_bytes())?;
+        flatten_object(&object, "", &mut |key, _| {
+            if seen.insert(key.to_string()) {
+                columns.push(key.to_string());
+            }
+        });
+    }
+    if columns.is_empty() {
+        None
+    } else {
+        Some(columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_are_the_union_of_flattened_keys() {
+        let sample = concat!(
+            "{\"id\": 1, \"user\": {\"name\": \"Ann\", \"address\": {\"city\": \"Oslo\"}}}\n",
+            "\n",
+            "{\"id\": 2, \"tags\": [\"a\", \"b\"], \"user\": {\"name\": \"Bo\"}, \"ok\": true}\n",
+            "{\"id\": 3, \"user\": {\"na"
+        );
+        let columns = infer_columns(sample, true).unwrap();
+        assert_eq!(
+            columns,
+            ["id", "user.name", "user.address.city", "tags", "ok"]
+        );
+
+        let layout = JsonLayout::new(&columns);
+        let mut record = ByteRecord::new();
+        let mut unknown = Vec::new();
+        layout.split_line(
+            br#"{"ok": false, "tags": [1, {"x": null}], "id": 2.5, "user": {"name": null}, "extra": 1}"#,
+            &mut record,
+            &mut unknown,
+        );
+        let fields = record
+            .iter()
+            .map(|field| std::str::from_utf8(field).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, ["2.5", "", "", r#"[1,{"x":null}]"#, "false"]);
+        assert_eq!(unknown, ["extra"]);
+
+        layout.split_line(b"not json", &mut record, &mut unknown);
+        assert_eq!(record.len(), 1);
+        assert!(unknown.is_empty());
+    }
+
+    #[test]
+    fn dotted_keys_do_not_collide_with_nested_keys() {
+        let columns =
+            infer_columns("{\"a.b\": 1, \"a\": {\"b\": 2}, \"c\\\\d\": 3}\n", false).unwrap();
+        assert_eq!(columns, ["a\\.b", "a.b", "c\\\\d"]);
+
+        let layout = JsonLayout::new(&columns);
+        let mut record = ByteRecord::new();
+        layout.split_line(
+            br#"{"a": {"b": "nested"}, "a.b": "literal"}"#,
+            &mut record,
+            &mut Vec::new(),
+        );
+        assert_eq!(&record[0], b"literal");
+        assert_eq!(&record[1], b"nested");
+    }
+
+    #[test]
+    fn delimited_text_is_not_json_lines() {
+        assert_eq!(infer_columns("a,b\n1,2\n", false), None);
+        assert_eq!(infer_columns("{\"a\": 1}\n[1, 2]\n", false), None);
+        assert_eq!(infer_columns("{\"a\": 1, \"b\": ", true), None);
+        assert_eq!(infer_columns("", false), None);
+        assert_eq!(
+            infer_columns("{\"a\": 1}", false),
+            Some(vec!["a".to_string()])
+        );
+    }
+}
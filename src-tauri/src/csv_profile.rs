@@ -0,0 +1,483 @@
+use crate::csv_sort::{parse_date, parse_number};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+pub const DEFAULT_TOP_VALUES: usize = 10;
+/// Distinct values counted exactly; past this, only values already seen keep counting
+const MAX_TRACKED_VALUES: usize = 100_000;
+/// Values longer than this are truncated before counting
+const MAX_VALUE_LEN: usize = 256;
+/// HyperLogLog register bits: 2^12 registers, about 1.6% standard error
+const HLL_BITS: u32 = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InferredType {
+    Empty,
+    Integer,
+    Float,
+    Boolean,
+    Date,
+    Text,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ValueCount {
+    pub value: String,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnProfile {
+    pub column: usize,
+    pub name: String,
+    pub inferred_type: InferredType,
+    pub rows: usize,
+    /// Empty or whitespace-only cells
+    pub null_count: usize,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub mean: Option<f64>,
+    pub stddev: Option<f64>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub distinct_estimate: usize,
+    pub top_values: Vec<ValueCount>,
+    /// False when the column had too many distinct values to count them all
+    pub top_values_exact: bool,
+}
+
+/// Ordering of a value-count listing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueCountOrder {
+    CountDesc,
+    CountAsc,
+    ValueAsc,
+    ValueDesc,
+}
+
+impl ValueCountOrder {
+    pub fn from_str(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "count_asc" => ValueCountOrder::CountAsc,
+            "value_asc" | "value" => ValueCountOrder::ValueAsc,
+            "value_desc" => ValueCountOrder::ValueDesc,
+            _ => ValueCountOrder::CountDesc,
+        }
+    }
+}
+
+/// Sorts value counts; ties on count fall back to the value so paging is stable
+pub fn sort_value_counts(counts: &mut [ValueCount], order: ValueCountOrder) {
+    match order {
+        ValueCountOrder::CountDesc => {
+            counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)))
+        }
+        ValueCountOrder::CountAsc => {
+            counts.sort_by(|a, b| a.count.cmp(&b.count).then_with(|| a.value.cmp(&b.value)))
+        }
+        ValueCountOrder::ValueAsc => counts.sort_by(|a, b| a.value.cmp(&b.value)),
+        ValueCountOrder::ValueDesc => counts.sort_by(|a, b| b.value.cmp(&a.value)),
+    }
+}
+
+fn is_boolean(value: &str) -> bool {
+    ["true", "false", "yes", "no"]
+        .iter()
+        .any(|marker| value.eq_ignore_ascii_case(marker))
+}
+
+fn truncate_value(value: &str) -> &str {
+    if value.len() <= MAX_VALUE_LEN {
+        return value;
+    }
+    let mut end = MAX_VALUE_LEN;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+/// Approximate distinct counter
+pub(crate) struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub(crate) fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; 1 << HLL_BITS],
+        }
+    }
+
+    pub(crate) fn insert(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - HLL_BITS)) as usize;
+        let rank = ((hash << HLL_BITS) | (1 << (HLL_BITS - 1))).leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// Union with another counter, as if every value had been inserted here
+    pub(crate) fn merge(&mut self, other: &HyperLogLog) {
+        for (rank, other) in self.registers.iter_mut().zip(&other.registers) {
+            *rank = (*rank).max(*other);
+        }
+    }
+
+    pub(crate) fn estimate(&self) -> usize {
+        let m = self.registers.len() as f64;
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-(rank as i32)))
+            .sum();
+        let raw = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&rank| rank == 0).count();
+        // Linear counting is more accurate for small cardinalities
+        if raw <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as usize
+        } else {
+            raw.round() as usize
+        }
+    }
+}
+
+/// Smallest and largest value seen, compared by `K` and reported as the original text
+struct Extremes<K> {
+    min: Option<(K, String)>,
+    max: Option<(K, String)>,
+}
+
+impl<K: PartialOrd + Clone> Extremes<K> {
+    fn new() -> Self {
+        Extremes {
+            min: None,
+            max: None,
+        }
+    }
+
+    fn push(&mut self, key: K, value: &str) {
+        if self.min.as_ref().is_none_or(|(min, _)| key < *min) {
+            self.min = Some((key.clone(), value.to_string()));
+        }
+        if self.max.as_ref().is_none_or(|(max, _)| key > *max) {
+            self.max = Some((key, value.to_string()));
+        }
+    }
+
+    fn into_strings(self) -> (Option<String>, Option<String>) {
+        (
+            self.min.map(|(_, value)| value),
+            self.max.map(|(_, value)| value),
+        )
+    }
+}
+
+impl Extremes<String> {
+    /// Like `push`, but only allocates when the extremes change
+    fn push_text(&mut self, key: &str, value: &str) {
+        if self.min.as_ref().is_none_or(|(min, _)| key < min.as_str()) {
+            self.min = Some((key.to_string(), value.to_string()));
+        }
+        if self.max.as_ref().is_none_or(|(max, _)| key > max.as_str()) {
+            self.max = Some((key.to_string(), value.to_string()));
+        }
+    }
+}
+
+/// Streaming statistics for one column; feed every cell with `push`
+pub struct ColumnProfiler {
+    column: usize,
+    name: String,
+    rows: usize,
+    null_count: usize,
+    integers: usize,
+    floats: usize,
+    booleans: usize,
+    dates: usize,
+    numbers: Extremes<f64>,
+    date_values: Extremes<i64>,
+    text_values: Extremes<String>,
+    mean: f64,
+    m2: f64,
+    numeric_count: usize,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    distinct: HyperLogLog,
+    counts: HashMap<Box<str>, usize>,
+    counts_exact: bool,
+}
+
+impl ColumnProfiler {
+    pub fn new(column: usize, name: String) -> Self {
+        ColumnProfiler {
+            column,
+            name,
+            rows: 0,
+            null_count: 0,
+            integers: 0,
+            floats: 0,
+            booleans: 0,
+            dates: 0,
+            numbers: Extremes::new(),
+            date_values: Extremes::new(),
+            text_values: Extremes::new(),
+            mean: 0.0,
+            m2: 0.0,
+            numeric_count: 0,
+            min_length: None,
+            max_length: None,
+            distinct: HyperLogLog::new(),
+            counts: HashMap::new(),
+            counts_exact: true,
+        }
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn push(&mut self, value: &str) {
+        self.rows += 1;
+        let length = value.chars().count();
+        self.min_length = Some(self.min_length.map_or(length, |min| min.min(length)));
+        self.max_length = Some(self.max_length.map_or(length, |max| max.max(length)));
+
+        // Only empty cells are nulls; markers such as "NA" can be real values
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            self.null_count += 1;
+            return;
+        }
+
+        let key = truncate_value(value);
+        self.distinct.insert(key);
+        if let Some(count) = self.counts.get_mut(key) {
+            *count += 1;
+        } else if self.counts.len() < MAX_TRACKED_VALUES {
+            self.counts.insert(key.into(), 1);
+        } else {
+            self.counts_exact = false;
+        }
+
+        if let Some(number) = parse_number(trimmed) {
+            if trimmed.contains(['.', 'e', 'E']) {
+                self.floats += 1;
+            } else {
+                self.integers += 1;
+            }
+            self.numbers.push(number, trimmed);
+            // Welford's running mean and variance
+            self.numeric_count += 1;
+            let delta = number - self.mean;
+            self.mean += delta / self.numeric_count as f64;
+            self.m2 += delta * (number - self.mean);
+        } else if is_boolean(trimmed) {
+            self.booleans += 1;
+            self.text_values.push_text(&trimmed.to_lowercase(), trimmed);
+        } else if let Some(date) = parse_date(trimmed) {
+            self.dates += 1;
+            self.date_values.push(date, trimmed);
+        } else {
+            self.text_values.push_text(key, key);
+        }
+    }
+
+    fn inferred_type(&self) -> InferredType {
+        let non_null = self.rows - self.null_count;
+        if non_null == 0 {
+            InferredType::Empty
+        } else if self.integers == non_null {
+            InferredType::Integer
+        } else if self.integers + self.floats == non_null {
+            InferredType::Float
+        } else if self.booleans == non_null {
+            InferredType::Boolean
+        } else if self.dates == non_null {
+            InferredType::Date
+        } else {
+            InferredType::Text
+        }
+    }
+
+    pub fn finish(self, top_n: usize) -> ColumnProfile {
+        let inferred_type = self.inferred_type();
+        let numeric = matches!(inferred_type, InferredType::Integer | InferredType::Float);
+        let (min, max) = match inferred_type {
+            InferredType::Integer | InferredType::Float => self.numbers.into_strings(),
+            InferredType::Date => self.date_values.into_strings(),
+            InferredType::Empty => (None, None),
+            InferredType::Boolean | InferredType::Text => {
+                // Mixed columns compare every value as text
+                let mut extremes = self.text_values;
+                let (number_min, number_max) = self.numbers.into_strings();
+                let (date_min, date_max) = self.date_values.into_strings();
+                for value in [number_min, number_max, date_min, date_max]
+                    .into_iter()
+                    .flatten()
+                {
+                    extremes.push_text(&value, &value);
+                }
+                extremes.into_strings()
+            }
+        };
+        let (mean, stddev) = if numeric && self.numeric_count > 0 {
+            let variance = if self.numeric_count > 1 {
+                self.m2 / (self.numeric_count - 1) as f64
+            } else {
+                0.0
+            };
+            (Some(self.mean), Some(variance.sqrt()))
+        } else {
+            (None, None)
+        };
+
+        let mut top_values: Vec<ValueCount> = self
+            .counts
+            .into_iter()
+            .map(|(value, count)| ValueCount {
+                value: value.into_string(),
+                count,
+            })
+            .collect();
+        sort_value_counts(&mut top_values, ValueCountOrder::CountDesc);
+        top_values.truncate(top_n);
+
+        ColumnProfile {
+            column: self.column,
+            name: self.name,
+            inferred_type,
+            rows: self.rows,
+            null_count: self.null_count,
+            min,
+            max,
+            mean,
+            stddev,
+            min_length: self.min_length,
+            max_length: self.max_length,
+            distinct_estimate: self.distinct.estimate(),
+            top_values,
+            top_values_exact: self.counts_exact,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(values: &[&str]) -> ColumnProfile {
+        let mut profiler = ColumnProfiler::new(0, "col".to_string());
+        for value in values {
+            profiler.push(value);
+        }
+        profiler.finish(2)
+    }
+
+    #[test]
+    fn numeric_column_statistics() {
+        let profile = profile(&["3", "1", "", "  ", "4", "1,000"]);
+        assert_eq!(profile.inferred_type, InferredType::Integer);
+        assert_eq!(profile.rows, 6);
+        assert_eq!(profile.null_count, 2);
+        assert_eq!(profile.min.as_deref(), Some("1"));
+        assert_eq!(profile.max.as_deref(), Some("1,000"));
+        assert_eq!(profile.mean, Some(252.0));
+        assert!((profile.stddev.unwrap() - 498.668).abs() < 0.001);
+        assert_eq!(profile.min_length, Some(0));
+        assert_eq!(profile.max_length, Some(5));
+        assert_eq!(profile.distinct_estimate, 4);
+    }
+
+    #[test]
+    fn mixed_column_falls_back_to_text() {
+        let profile = profile(&["b", "a", "b", "2", "2024-01-05", "b", "a"]);
+        assert_eq!(profile.inferred_type, InferredType::Text);
+        assert_eq!(profile.min.as_deref(), Some("2"));
+        assert_eq!(profile.max.as_deref(), Some("b"));
+        assert_eq!(profile.mean, None);
+        assert_eq!(
+            profile.top_values,
+            vec![
+                ValueCount {
+                    value: "b".to_string(),
+                    count: 3
+                },
+                ValueCount {
+                    value: "a".to_string(),
+                    count: 2
+                },
+            ]
+        );
+        assert!(profile.top_values_exact);
+    }
+
+    #[test]
+    fn date_and_boolean_columns() {
+        let dates = profile(&["2024-03-01", "2023-12-31", "2024-01-15"]);
+        assert_eq!(dates.inferred_type, InferredType::Date);
+        assert_eq!(dates.min.as_deref(), Some("2023-12-31"));
+        assert_eq!(dates.max.as_deref(), Some("2024-03-01"));
+
+        let flags = profile(&["yes", "No", "", "true"]);
+        assert_eq!(flags.inferred_type, InferredType::Boolean);
+        assert_eq!(flags.null_count, 1);
+    }
+
+    #[test]
+    fn null_markers_are_values() {
+        let profile = profile(&["NA", "none", "", "nil", "NULL"]);
+        assert_eq!(profile.null_count, 1);
+        assert_eq!(profile.inferred_type, InferredType::Text);
+        assert_eq!(profile.distinct_estimate, 4);
+    }
+
+    #[test]
+    fn value_count_orders() {
+        let mut counts = vec![
+            ValueCount {
+                value: "b".to_string(),
+                count: 1,
+            },
+            ValueCount {
+                value: "c".to_string(),
+                count: 2,
+            },
+            ValueCount {
+                value: "a".to_string(),
+                count: 1,
+            },
+        ];
+        let values = |counts: &[ValueCount]| {
+            counts
+                .iter()
+                .map(|item| item.value.as_str())
+                .collect::<Vec<_>>()
+                .join("")
+        };
+        sort_value_counts(&mut counts, ValueCountOrder::from_str("count_desc"));
+        assert_eq!(values(&counts), "cab");
+        sort_value_counts(&mut counts, ValueCountOrder::from_str("count_asc"));
+        assert_eq!(values(&counts), "abc");
+        sort_value_counts(&mut counts, ValueCountOrder::from_str("value_desc"));
+        assert_eq!(values(&counts), "cba");
+    }
+
+    #[test]
+    fn distinct_estimate_is_close_for_large_columns() {
+        let mut profiler = ColumnProfiler::new(0, "id".to_string());
+        for value in 0..50_000 {
+            profiler.push(&format!("id-{value}"));
+        }
+        let profile = profiler.finish(1);
+        let error = (profile.distinct_estimate as f64 - 50_000.0).abs() / 50_000.0;
+        assert!(error < 0.05, "estimate {}", profile.distinct_estimate);
+    }
+}
@@ -0,0 +1,1139 @@
+use rayon::slice::ParallelSliceMut;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Default memory budget for in-memory sort keys before spilling runs to disk
+pub const DEFAULT_SORT_MEMORY_BUDGET: usize = 1024 * 1024 * 1024;
+/// Sort keys are truncated to this many bytes; ties on truncated keys are
+/// settled later by re-reading the full cells.
+pub const SORT_VALUE_MAX_LEN: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SortMode {
+    Auto,
+    Text,
+    Numeric,
+    Date,
+    Natural,
+}
+
+impl FromStr for SortMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Ok(SortMode::Auto),
+            "text" | "string" => Ok(SortMode::Text),
+            "numeric" | "number" => Ok(SortMode::Numeric),
+            "date" | "datetime" => Ok(SortMode::Date),
+            "natural" => Ok(SortMode::Natural),
+            _ => Err(format!("unknown sort mode \"{value}\"")),
+        }
+    }
+}
+
+impl SortMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortMode::Auto => "auto",
+            SortMode::Text => "text",
+            SortMode::Numeric => "numeric",
+            SortMode::Date => "date",
+            SortMode::Natural => "natural",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EmptyPlacement {
+    First,
+    Last,
+}
+
+impl FromStr for EmptyPlacement {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "first" => Ok(EmptyPlacement::First),
+            "last" => Ok(EmptyPlacement::Last),
+            _ => Err(format!("unknown empty placement \"{value}\"")),
+        }
+    }
+}
+
+impl EmptyPlacement {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmptyPlacement::First => "first",
+            EmptyPlacement::Last => "last",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortOptions {
+    pub mode: SortMode,
+    pub empty_placement: EmptyPlacement,
+}
+
+impl Default for SortOptions {
+    fn default() -> Self {
+        SortOptions {
+            mode: SortMode::Auto,
+            empty_placement: EmptyPlacement::Last,
+        }
+    }
+}
+
+/// One sort key as sent by the frontend, e.g. `{ "column": 2, "ascending": false, "mode": "numeric" }`
+#[derive(Clone, Debug, Deserialize)]
+pub struct SortKeyInput {
+    pub column: usize,
+    pub ascending: Option<bool>,
+    pub mode: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SortKey {
+    pub column: usize,
+    pub ascending: bool,
+    pub mode: SortMode,
+}
+
+impl TryFrom<&SortKeyInput> for SortKey {
+    type Error = String;
+
+    fn try_from(input: &SortKeyInput) -> Result<Self, String> {
+        Ok(SortKey {
+            column: input.column,
+            ascending: input.ascending.unwrap_or(true),
+            mode: input
+                .mode
+                .as_deref()
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or(SortMode::Auto),
+        })
+    }
+}
+
+/// Ordered list of sort keys applied lexicographically: later keys only
+/// break ties left by earlier ones, and full ties keep file order.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SortSpec {
+    pub keys: Vec<SortKey>,
+    pub empty_placement: EmptyPlacement,
+}
+
+impl SortSpec {
+    pub fn single(column: usize, ascending: bool, options: SortOptions) -> Self {
+        SortSpec {
+            keys: vec![SortKey {
+                column,
+                ascending,
+                mode: options.mode,
+            }],
+            empty_placement: options.empty_placement,
+        }
+    }
+
+    /// Hash of the spec that names and checks the on-disk order cache. It is FNV-1a
+    /// over a fixed encoding of the keys, so it stays the same across builds and
+    /// platforms, unlike the standard library hashers.
+    pub fn cache_hash(&self) -> u64 {
+        let mut bytes = Vec::new();
+        for key in &self.keys {
+            bytes.extend_from_slice(&(key.column as u64).to_le_bytes());
+            bytes.push(key.ascending as u8);
+            bytes.extend_from_slice(key.mode.as_str().as_bytes());
+            bytes.push(0);
+        }
+        bytes.extend_from_slice(self.empty_placement.as_str().as_bytes());
+        fnv1a(&bytes)
+    }
+
+    pub fn describe(&self) -> String {
+        self.keys
+            .iter()
+            .map(|key| {
+                format!(
+                    "c{}:{}:{}",
+                    key.column,
+                    if key.ascending { "asc" } else { "desc" },
+                    key.mode.as_str()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Compares two rows of sort values, one value per key in spec order
+    pub fn compare(&self, a: &[SortValue], b: &[SortValue]) -> Ordering {
+        for (idx, key) in self.keys.iter().enumerate() {
+            let (Some(left), Some(right)) = (a.get(idx), b.get(idx)) else {
+                break;
+            };
+            let options = SortOptions {
+                mode: key.mode,
+                empty_placement: self.empty_placement,
+            };
+            let ordering = compare_sort_values(left, right, key.ascending, options);
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+/// 64-bit FNV-1a
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Parsed cell value used as a sort key.
+/// Values that fail to parse in the requested mode fall back to `Text`,
+/// which always orders after numbers and dates.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortValue {
+    Empty,
+    Number(f64),
+    /// Seconds since the Unix epoch
+    Date(i64),
+    Text(Box<str>),
+}
+
+impl SortValue {
+    fn rank(&self) -> u8 {
+        match self {
+            SortValue::Empty => 0,
+            SortValue::Number(_) => 1,
+            SortValue::Date(_) => 2,
+            SortValue::Text(_) => 3,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self, SortValue::Empty)
+    }
+}
+
+pub fn parse_sort_value(value: &str, mode: SortMode) -> SortValue {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return SortValue::Empty;
+    }
+
+    match mode {
+        SortMode::Text | SortMode::Natural => SortValue::Text(value.into()),
+        SortMode::Numeric => parse_number(trimmed)
+            .map(SortValue::Number)
+            .unwrap_or_else(|| SortValue::Text(value.into())),
+        SortMode::Date => parse_date(trimmed)
+            .map(SortValue::Date)
+            .unwrap_or_else(|| SortValue::Text(value.into())),
+        SortMode::Auto => {
+            if let Some(number) = parse_number(trimmed) {
+                SortValue::Number(number)
+            } else if let Some(date) = parse_date(trimmed) {
+                SortValue::Date(date)
+            } else {
+                SortValue::Text(value.into())
+            }
+        }
+    }
+}
+
+/// Parses plain and thousands-separated numbers ("1,234.5"), with one optional
+/// sign and an optional leading currency symbol. Rejects "inf"/"nan" spellings.
+pub fn parse_number(value: &str) -> Option<f64> {
+    let mut text = value;
+    let mut negative = false;
+    if let Some(rest) = text.strip_prefix('-') {
+        negative = true;
+        text = rest;
+    } else if let Some(rest) = text.strip_prefix('+') {
+        text = rest;
+    }
+    for symbol in ['$', '€', '£', '¥'] {
+        if let Some(rest) = text.strip_prefix(symbol) {
+            text = rest;
+            break;
+        }
+    }
+    // Any further sign may only belong to an exponent
+    if !text.starts_with(|ch: char| ch.is_ascii_digit() || ch == '.')
+        || !text.bytes().any(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+    if !text.bytes().all(|byte| {
+        byte.is_ascii_digit() || matches!(byte, b'.' | b',' | b'e' | b'E' | b'-' | b'+')
+    }) {
+        return None;
+    }
+
+    let parsed = if text.contains(',') {
+        let (integer, fraction) = match text.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (text, None),
+        };
+        let mut groups = integer.split(',');
+        let first = groups.next()?;
+        if first.is_empty() || first.len() > 3 {
+            return None;
+        }
+        let mut digits = first.to_string();
+        for group in groups {
+            if group.len() != 3 {
+                return None;
+            }
+            digits.push_str(group);
+        }
+        if let Some(fraction) = fraction {
+            digits.push('.');
+            digits.push_str(fraction);
+        }
+        digits.parse::<f64>().ok()?
+    } else {
+        text.parse::<f64>().ok()?
+    };
+
+    if !parsed.is_finite() {
+        return None;
+    }
+    Some(if negative { -parsed } else { parsed })
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 => {
+            if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 {
+                29
+            } else {
+                28
+            }
+        }
+        _ => 0,
+    }
+}
+
+/// Splits a trailing UTC offset (`Z`, `+02:00`, `-0530` or `+02`) off a time and
+/// returns it in seconds east of UTC
+fn split_offset(value: &str) -> Option<(&str, Option<i64>)> {
+    if let Some(time) = value.strip_suffix('Z') {
+        return Some((time, Some(0)));
+    }
+    let Some(idx) = value.rfind(['+', '-']) else {
+        return Some((value, None));
+    };
+    let offset = &value[idx + 1..];
+    let (hours, minutes) = match offset.len() {
+        2 => (offset, "00"),
+        4 => offset.split_at(2),
+        5 if offset.as_bytes()[2] == b':' => (&offset[..2], &offset[3..]),
+        _ => return None,
+    };
+    if !hours
+        .bytes()
+        .chain(minutes.bytes())
+        .all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+    let hours = hours.parse::<i64>().ok()?;
+    let minutes = minutes.parse::<i64>().ok()?;
+    if hours > 14 || minutes > 59 {
+        return None;
+    }
+    let sign = if value.as_bytes()[idx] == b'-' { -1 } else { 1 };
+    Some((&value[..idx], Some(sign * (hours * 3600 + minutes * 60))))
+}
+
+/// Seconds since midnight and the UTC offset, if the time carries one
+fn parse_time(value: &str) -> Option<(i64, Option<i64>)> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Some((0, None));
+    }
+    let (value, offset) = split_offset(value)?;
+    // Fractional seconds do not affect ordering enough to keep
+    let value = value.trim_end();
+    let value = value.split('.').next().unwrap_or(value);
+    let mut parts = value.split(':');
+    let hour = parts.next()?.parse::<i64>().ok()?;
+    let minute = parts
+        .next()
+        .map(|m| m.parse::<i64>().ok())
+        .unwrap_or(Some(0))?;
+    let second = parts
+        .next()
+        .map(|s| s.parse::<i64>().ok())
+        .unwrap_or(Some(0))?;
+    if parts.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    Some((hour * 3600 + minute * 60 + second, offset))
+}
+
+/// Order of day and month in dates that put the year last
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DayOrder {
+    /// Month first unless the first component is above 12
+    Guess,
+    MonthFirst,
+    DayFirst,
+}
+
+/// Parses ISO (`2024-01-31`, `2024-01-31T08:15:00`), slash-separated ISO
+/// (`2024/01/31`) and US (`01/31/2024`) dates. A first component above 12
+/// is read as day-first (`31/01/2024`). Times with a UTC offset are moved to UTC.
+pub fn parse_date(value: &str) -> Option<i64> {
+    parse_timestamp(value, DayOrder::Guess).map(|(seconds, _)| seconds)
+}
+
+/// Like `parse_date` with a fixed day order for dates that put the year last. Also
+/// returns whether the value carried a UTC offset.
+pub fn parse_timestamp(value: &str, order: DayOrder) -> Option<(i64, bool)> {
+    let (date_part, time_part) = match value.find(['T', ' ']) {
+        Some(idx) => (&value[..idx], &value[idx + 1..]),
+        None => (value, ""),
+    };
+    let separator = if date_part.contains('-') {
+        '-'
+    } else if date_part.contains('/') {
+        '/'
+    } else if date_part.contains('.') {
+        '.'
+    } else {
+        return None;
+    };
+    let parts = date_part.split(separator).collect::<Vec<_>>();
+    if parts.len() != 3 || parts.iter().any(|part| part.is_empty() || part.len() > 4) {
+        return None;
+    }
+    if !parts
+        .iter()
+        .all(|part| part.bytes().all(|byte| byte.is_ascii_digit()))
+    {
+        return None;
+    }
+    let numbers = parts
+        .iter()
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    let (year, month, day) = if parts[0].len() == 4 {
+        (numbers[0] as i64, numbers[1], numbers[2])
+    } else if parts[2].len() == 4 {
+        let day_first = match order {
+            DayOrder::Guess => numbers[0] > 12,
+            DayOrder::MonthFirst => false,
+            DayOrder::DayFirst => true,
+        };
+        if day_first {
+            (numbers[2] as i64, numbers[1], numbers[0])
+        } else {
+            (numbers[2] as i64, numbers[0], numbers[1])
+        }
+    } else {
+        return None;
+    };
+
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let (seconds, offset) = parse_time(time_part)?;
+    Some((
+        days_from_civil(year, month, day) * 86_400 + seconds - offset.unwrap_or(0),
+        offset.is_some(),
+    ))
+}
+
+fn compare_digit_runs(a: &str, b: &str) -> Ordering {
+    let a_trimmed = a.trim_start_matches('0');
+    let b_trimmed = b.trim_start_matches('0');
+    a_trimmed
+        .len()
+        .cmp(&b_trimmed.len())
+        .then_with(|| a_trimmed.cmp(b_trimmed))
+        .then_with(|| a.len().cmp(&b.len()))
+}
+
+fn split_natural(value: &str) -> impl Iterator<Item = &str> {
+    let mut rest = value;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let digit = first.is_ascii_digit();
+        let end = rest
+            .char_indices()
+            .find(|(_, ch)| ch.is_ascii_digit() != digit)
+            .map(|(idx, _)| idx)
+            .unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(end);
+        rest = tail;
+        Some(run)
+    })
+}
+
+/// Compares strings so that embedded numbers order by value ("file9" < "file10").
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut left = split_natural(a);
+    let mut right = split_natural(b);
+    loop {
+        match (left.next(), right.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let x_digit = x.as_bytes()[0].is_ascii_digit();
+                let y_digit = y.as_bytes()[0].is_ascii_digit();
+                let ordering = if x_digit && y_digit {
+                    compare_digit_runs(x, y)
+                } else {
+                    x.to_lowercase().cmp(&y.to_lowercase())
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+fn compare_non_empty(a: &SortValue, b: &SortValue, mode: SortMode) -> Ordering {
+    match (a, b) {
+        (SortValue::Number(x), SortValue::Number(y)) => x.total_cmp(y),
+        (SortValue::Date(x), SortValue::Date(y)) => x.cmp(y),
+        (SortValue::Text(x), SortValue::Text(y)) => {
+            if mode == SortMode::Natural {
+                natural_cmp(x, y)
+            } else {
+                x.cmp(y)
+            }
+        }
+        _ => a.rank().cmp(&b.rank()),
+    }
+}
+
+/// Orders two sort values. Empty cells go first or last regardless of direction.
+pub fn compare_sort_values(
+    a: &SortValue,
+    b: &SortValue,
+    ascending: bool,
+    options: SortOptions,
+) -> Ordering {
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => match options.empty_placement {
+            EmptyPlacement::First => Ordering::Less,
+            EmptyPlacement::Last => Ordering::Greater,
+        },
+        (false, true) => match options.empty_placement {
+            EmptyPlacement::First => Ordering::Greater,
+            EmptyPlacement::Last => Ordering::Less,
+        },
+        (false, false) => {
+            let ordering = compare_non_empty(a, b, options.mode);
+            if ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        }
+    }
+}
+
+struct SortEntry {
+    row: u32,
+    values: Box<[SortValue]>,
+    /// Set when a key was cut to `SORT_VALUE_MAX_LEN` before parsing
+    truncated: bool,
+}
+
+/// Collects runs of equal keys in output order that involve a truncated value
+#[derive(Default)]
+struct TieTracker {
+    position: usize,
+    group_start: usize,
+    group_truncated: bool,
+    groups: Vec<std::ops::Range<usize>>,
+}
+
+impl TieTracker {
+    fn next(&mut self, tied_with_previous: bool, truncated: bool) {
+        if !tied_with_previous {
+            self.close();
+            self.group_start = self.position;
+            self.group_truncated = false;
+        }
+        self.group_truncated |= truncated;
+        self.position += 1;
+    }
+
+    fn close(&mut self) {
+        if self.group_truncated && self.position - self.group_start > 1 {
+            self.groups.push(self.group_start..self.position);
+        }
+    }
+
+    fn finish(mut self) -> Vec<std::ops::Range<usize>> {
+        self.close();
+        self.groups
+    }
+}
+
+fn entry_size(values: &[SortValue]) -> usize {
+    std::mem::size_of::<SortEntry>()
+        + values
+            .iter()
+            .map(|value| {
+                std::mem::size_of::<SortValue>()
+                    + match value {
+                        SortValue::Text(text) => text.len(),
+                        _ => 0,
+                    }
+            })
+            .sum::<usize>()
+}
+
+fn write_entry(writer: &mut impl Write, entry: &SortEntry) -> std::io::Result<()> {
+    writer.write_all(&entry.row.to_le_bytes())?;
+    writer.write_all(&[entry.truncated as u8])?;
+    for value in entry.values.iter() {
+        match value {
+            SortValue::Empty => writer.write_all(&[0])?,
+            SortValue::Number(number) => {
+                writer.write_all(&[1])?;
+                writer.write_all(&number.to_le_bytes())?;
+            }
+            SortValue::Date(date) => {
+                writer.write_all(&[2])?;
+                writer.write_all(&date.to_le_bytes())?;
+            }
+            SortValue::Text(text) => {
+                writer.write_all(&[3])?;
+                writer.write_all(&(text.len() as u32).to_le_bytes())?;
+                writer.write_all(text.as_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_entry(reader: &mut impl Read, key_count: usize) -> std::io::Result<Option<SortEntry>> {
+    let mut row = [0u8; 4];
+    match reader.read_exact(&mut row) {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let mut truncated = [0u8; 1];
+    reader.read_exact(&mut truncated)?;
+    let mut values = Vec::with_capacity(key_count);
+    for _ in 0..key_count {
+        let mut tag = [0u8; 1];
+        reader.read_exact(&mut tag)?;
+        let value = match tag[0] {
+            0 => SortValue::Empty,
+            1 | 2 => {
+                let mut buf = [0u8; 8];
+                reader.read_exact(&mut buf)?;
+                if tag[0] == 1 {
+                    SortValue::Number(f64::from_le_bytes(buf))
+                } else {
+                    SortValue::Date(i64::from_le_bytes(buf))
+                }
+            }
+            _ => {
+                let mut len = [0u8; 4];
+                reader.read_exact(&mut len)?;
+                let mut text = vec![0u8; u32::from_le_bytes(len) as usize];
+                reader.read_exact(&mut text)?;
+                let text = String::from_utf8(text)
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+                SortValue::Text(text.into_boxed_str())
+            }
+        };
+        values.push(value);
+    }
+    Ok(Some(SortEntry {
+        row: u32::from_le_bytes(row),
+        values: values.into_boxed_slice(),
+        truncated: truncated[0] != 0,
+    }))
+}
+
+/// Sorts rows by a `SortSpec` within a memory budget.
+/// Rows are buffered in memory; once the buffer exceeds the budget it is sorted
+/// and spilled to a run file, and `finish` k-way merges all runs. Rows must be
+/// pushed in file order so that ties stay stable across runs.
+pub struct ExternalSorter<'a> {
+    spec: &'a SortSpec,
+    budget: usize,
+    spill_dir: Option<tempfile::TempDir>,
+    base_dir: PathBuf,
+    buffer: Vec<SortEntry>,
+    buffer_bytes: usize,
+    runs: Vec<PathBuf>,
+}
+
+impl<'a> ExternalSorter<'a> {
+    /// `budget` is the number of bytes buffered before a run is spilled to disk
+    pub fn new(spec: &'a SortSpec, base_dir: &Path, budget: usize) -> Self {
+        ExternalSorter {
+            spec,
+            budget,
+            spill_dir: None,
+            base_dir: base_dir.to_path_buf(),
+            buffer: Vec::new(),
+            buffer_bytes: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    /// `truncated` marks rows whose keys were cut short, so that ties between
+    /// them are reported by `finish`
+    pub fn push(
+        &mut self,
+        row: u32,
+        values: Box<[SortValue]>,
+        truncated: bool,
+    ) -> Result<(), String> {
+        self.buffer_bytes += entry_size(&values);
+        self.buffer.push(SortEntry {
+            row,
+            values,
+            truncated,
+        });
+        if self.buffer_bytes >= self.budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    pub fn spilled_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    fn spill(&mut self) -> Result<(), String> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        if self.spill_dir.is_none() {
+            let dir = tempfile::Builder::new()
+                .prefix("sort_runs_")
+                .tempdir_in(&self.base_dir)
+                .map_err(|err| err.to_string())?;
+            self.spill_dir = Some(dir);
+        }
+        let dir = self.spill_dir.as_ref().unwrap().path();
+        let path = dir.join(format!("run_{:05}.bin", self.runs.len()));
+
+        let spec = self.spec;
+        self.buffer
+            .par_sort_by(|a, b| spec.compare(&a.values, &b.values));
+        let file = File::create(&path).map_err(|err| err.to_string())?;
+        let mut writer = BufWriter::new(file);
+        for entry in self.buffer.drain(..) {
+            write_entry(&mut writer, &entry).map_err(|err| err.to_string())?;
+        }
+        writer.flush().map_err(|err| err.to_string())?;
+        self.buffer_bytes = 0;
+        self.runs.push(path);
+        Ok(())
+    }
+
+    /// Emits row indices in sorted order, stopping at the first error `emit` returns.
+    /// Spill files are removed afterwards. Returns the output ranges whose keys tie
+    /// only because values were truncated; the caller settles those against the
+    /// full cell values.
+    pub fn finish(
+        self,
+        mut emit: impl FnMut(u32) -> Result<(), String>,
+    ) -> Result<Vec<std::ops::Range<usize>>, String> {
+        self.finish_with_values(|row, _| emit(row))
+    }
+
+    /// Like `finish`, also handing `emit` the sort values each row was pushed with
+    pub fn finish_with_values(
+        mut self,
+        mut emit: impl FnMut(u32, &[SortValue]) -> Result<(), String>,
+    ) -> Result<Vec<std::ops::Range<usize>>, String> {
+        let spec = self.spec;
+        let mut ties = TieTracker::default();
+        if self.runs.is_empty() {
+            self.buffer
+                .par_sort_by(|a, b| spec.compare(&a.values, &b.values));
+            for (idx, entry) in self.buffer.iter().enumerate() {
+                let tied = idx > 0
+                    && spec
+                        .compare(&self.buffer[idx - 1].values, &entry.values)
+                        .is_eq();
+                ties.next(tied, entry.truncated);
+                emit(entry.row, &entry.values)?;
+            }
+            return Ok(ties.finish());
+        }
+
+        self.spill()?;
+        let key_count = spec.keys.len();
+        let mut readers = self
+            .runs
+            .iter()
+            .map(|path| {
+                File::open(path)
+                    .map(|file| BufReader::with_capacity(256 * 1024, file))
+                    .map_err(|err| err.to_string())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let mut heads = Vec::with_capacity(readers.len());
+        for reader in readers.iter_mut() {
+            heads.push(read_entry(reader, key_count).map_err(|err| err.to_string())?);
+        }
+
+        // Runs hold consecutive row ranges, so breaking ties by run index keeps the merge stable
+        let less = |heads: &[Option<SortEntry>], a: usize, b: usize| -> bool {
+            match (&heads[a], &heads[b]) {
+                (Some(x), Some(y)) => {
+                    spec.compare(&x.values, &y.values).then(a.cmp(&b)) == Ordering::Less
+                }
+                (Some(_), None) => true,
+                _ => false,
+            }
+        };
+        let mut heap = (0..heads.len())
+            .filter(|idx| heads[*idx].is_some())
+            .collect::<Vec<_>>();
+        for idx in (0..heap.len() / 2).rev() {
+            sift_down(&mut heap, idx, |a, b| less(&heads, a, b));
+        }
+
+        let mut last: Option<SortEntry> = None;
+        while let Some(&run) = heap.first() {
+            let Some(entry) = heads[run].take() else {
+                break;
+            };
+            let tied = last
+                .as_ref()
+                .is_some_and(|previous| spec.compare(&previous.values, &entry.values).is_eq());
+            ties.next(tied, entry.truncated);
+            emit(entry.row, &entry.values)?;
+            last = Some(entry);
+            heads[run] = read_entry(&mut readers[run], key_count).map_err(|err| err.to_string())?;
+            if heads[run].is_none() {
+                let last = heap.len() - 1;
+                heap.swap(0, last);
+                heap.pop();
+            }
+            sift_down(&mut heap, 0, |a, b| less(&heads, a, b));
+        }
+        Ok(ties.finish())
+    }
+}
+
+fn sift_down(heap: &mut [usize], mut idx: usize, less: impl Fn(usize, usize) -> bool) {
+    loop {
+        let left = idx * 2 + 1;
+        let right = left + 1;
+        let mut smallest = idx;
+        if left < heap.len() && less(heap[left], heap[smallest]) {
+            smallest = left;
+        }
+        if right < heap.len() && less(heap[right], heap[smallest]) {
+            smallest = right;
+        }
+        if smallest == idx {
+            return;
+        }
+        heap.swap(idx, smallest);
+        idx = smallest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(values: &[&str], ascending: bool, options: SortOptions) -> Vec<String> {
+        let mut parsed = values
+            .iter()
+            .map(|value| (value.to_string(), parse_sort_value(value, options.mode)))
+            .collect::<Vec<_>>();
+        parsed.sort_by(|a, b| compare_sort_values(&a.1, &b.1, ascending, options));
+        parsed.into_iter().map(|(value, _)| value).collect()
+    }
+
+    #[test]
+    fn numeric_mode_orders_by_value_and_falls_back_to_text() {
+        let options = SortOptions {
+            mode: SortMode::Numeric,
+            empty_placement: EmptyPlacement::Last,
+        };
+        assert_eq!(
+            sorted(&["10", "9", "n/a", "", "1,200", "-3.5"], true, options),
+            vec!["-3.5", "9", "10", "1,200", "n/a", ""]
+        );
+        assert_eq!(
+            sorted(&["10", "9", "", "100"], false, options),
+            vec!["100", "10", "9", ""]
+        );
+    }
+
+    #[test]
+    fn empty_placement_first() {
+        let options = SortOptions {
+            mode: SortMode::Auto,
+            empty_placement: EmptyPlacement::First,
+        };
+        assert_eq!(sorted(&["b", " ", "a"], true, options), vec![" ", "a", "b"]);
+        assert_eq!(
+            sorted(&["b", " ", "a"], false, options),
+            vec![" ", "b", "a"]
+        );
+    }
+
+    #[test]
+    fn date_mode_mixes_iso_and_us_formats() {
+        let options = SortOptions {
+            mode: SortMode::Date,
+            empty_placement: EmptyPlacement::Last,
+        };
+        assert_eq!(
+            sorted(
+                &[
+                    "2024-03-01",
+                    "02/15/2024",
+                    "2024-01-31T08:00:00",
+                    "31/12/2023"
+                ],
+                true,
+                options
+            ),
+            vec![
+                "31/12/2023",
+                "2024-01-31T08:00:00",
+                "02/15/2024",
+                "2024-03-01"
+            ]
+        );
+        assert!(parse_date("2024-02-30").is_none());
+        assert_eq!(parse_date("1970-01-02"), Some(86_400));
+        assert_eq!(parse_date("1970-01-02T02:00:00+02:00"), Some(86_400));
+        assert_eq!(parse_date("1970-01-01 19:00:00 -0500"), Some(86_400));
+        assert_eq!(parse_date("1970-01-02T00:00:00Z"), Some(86_400));
+        assert!(parse_date("1970-01-02T00:00:00+2").is_none());
+        assert_eq!(
+            parse_timestamp("03/02/1970", DayOrder::DayFirst),
+            Some((33 * 86_400, false))
+        );
+        assert_eq!(
+            parse_timestamp("03/02/1970", DayOrder::MonthFirst),
+            Some((60 * 86_400, false))
+        );
+        assert!(parse_timestamp("13/02/1970", DayOrder::MonthFirst).is_none());
+    }
+
+    #[test]
+    fn natural_mode_orders_embedded_numbers() {
+        let options = SortOptions {
+            mode: SortMode::Natural,
+            empty_placement: EmptyPlacement::Last,
+        };
+        assert_eq!(
+            sorted(&["file10", "file9", "File1", "file010"], true, options),
+            vec!["File1", "file9", "file10", "file010"]
+        );
+    }
+
+    #[test]
+    fn multi_key_spec_sorts_lexicographically_and_stably() {
+        let spec = SortSpec {
+            keys: vec![
+                SortKey {
+                    column: 0,
+                    ascending: true,
+                    mode: SortMode::Text,
+                },
+                SortKey {
+                    column: 1,
+                    ascending: false,
+                    mode: SortMode::Numeric,
+                },
+            ],
+            empty_placement: EmptyPlacement::Last,
+        };
+        let rows = [
+            ("US", "10"),
+            ("FR", "5"),
+            ("US", "200"),
+            ("FR", "5"),
+            ("FR", "30"),
+        ];
+        let mut keyed = rows
+            .iter()
+            .enumerate()
+            .map(|(idx, (country, revenue))| {
+                let values = spec
+                    .keys
+                    .iter()
+                    .map(|key| {
+                        let raw = if key.column == 0 { country } else { revenue };
+                        parse_sort_value(raw, key.mode)
+                    })
+                    .collect::<Vec<_>>();
+                (idx, values)
+            })
+            .collect::<Vec<_>>();
+        keyed.sort_by(|a, b| spec.compare(&a.1, &b.1));
+        let order = keyed.iter().map(|(idx, _)| *idx).collect::<Vec<_>>();
+        assert_eq!(order, vec![4, 1, 3, 2, 0]);
+
+        let mut reversed = spec.clone();
+        reversed.keys[1].ascending = true;
+        assert_ne!(spec.cache_hash(), reversed.cache_hash());
+    }
+
+    #[test]
+    fn external_sorter_merges_spilled_runs_stably() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let spec = SortSpec::single(
+            0,
+            true,
+            SortOptions {
+                mode: SortMode::Auto,
+                empty_placement: EmptyPlacement::Last,
+            },
+        );
+        let values = (0..5_000u32)
+            .map(|idx| match idx % 4 {
+                0 => String::new(),
+                1 => format!("{}", (idx * 7919) % 1000),
+                2 => "same".to_string(),
+                _ => format!("2024-01-{:02}", idx % 28 + 1),
+            })
+            .collect::<Vec<_>>();
+
+        let mut expected = values
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| (idx as u32, vec![parse_sort_value(value, SortMode::Auto)]))
+            .collect::<Vec<_>>();
+        expected.sort_by(|a, b| spec.compare(&a.1, &b.1));
+        let expected = expected.into_iter().map(|(idx, _)| idx).collect::<Vec<_>>();
+
+        let mut sorter = ExternalSorter::new(&spec, dir.path(), 16 * 1024);
+        for (idx, value) in values.iter().enumerate() {
+            sorter
+                .push(
+                    idx as u32,
+                    vec![parse_sort_value(value, SortMode::Auto)].into(),
+                    false,
+                )
+                .expect("push");
+        }
+        assert!(sorter.spilled_runs() > 1);
+        let mut order = Vec::new();
+        let ties = sorter
+            .finish(|row| {
+                order.push(row);
+                Ok(())
+            })
+            .expect("finish");
+        assert_eq!(order, expected);
+        assert!(ties.is_empty());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn finish_reports_ties_on_truncated_values() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let spec = SortSpec::single(0, true, SortOptions::default());
+        let long = "x".repeat(SORT_VALUE_MAX_LEN + 1);
+        let exact = "x".repeat(SORT_VALUE_MAX_LEN);
+        let values = ["b", long.as_str(), "a", long.as_str(), "b", exact.as_str()];
+        // A budget of one byte spills every row, so the flag must survive run files
+        for budget in [DEFAULT_SORT_MEMORY_BUDGET, 1] {
+            let mut sorter = ExternalSorter::new(&spec, dir.path(), budget);
+            for (idx, value) in values.iter().enumerate() {
+                let key = &value[..value.len().min(SORT_VALUE_MAX_LEN)];
+                sorter
+                    .push(
+                        idx as u32,
+                        vec![parse_sort_value(key, SortMode::Text)].into(),
+                        key.len() < value.len(),
+                    )
+                    .expect("push");
+            }
+            let mut order = Vec::new();
+            let ties = sorter
+                .finish(|row| {
+                    order.push(row);
+                    Ok(())
+                })
+                .expect("finish");
+            assert_eq!(order, vec![2, 0, 4, 1, 3, 5]);
+            // "b"/"b" is a genuine tie; only the truncated rows need a full-value pass
+            assert_eq!(ties, vec![3..6]);
+        }
+    }
+
+    #[test]
+    fn parse_number_rejects_non_numbers() {
+        assert_eq!(parse_number("1,234.50"), Some(1234.5));
+        assert_eq!(parse_number("$-"), None);
+        assert_eq!(parse_number("inf"), None);
+        assert_eq!(parse_number("12,34"), None);
+        assert_eq!(parse_number("-$5"), Some(-5.0));
+        assert_eq!(parse_number("--5"), None);
+        assert_eq!(parse_number("-$-5"), None);
+        assert_eq!(parse_number("+-5"), None);
+        assert_eq!(parse_number("1.5e-3"), Some(0.0015));
+    }
+
+    #[test]
+    fn unknown_modes_and_placements_are_rejected() {
+        assert_eq!(" Number ".parse::<SortMode>(), Ok(SortMode::Numeric));
+        assert_eq!("auto".parse::<SortMode>(), Ok(SortMode::Auto));
+        assert_eq!(
+            "numric".parse::<SortMode>(),
+            Err("unknown sort mode \"numric\"".to_string())
+        );
+        assert_eq!("FIRST".parse::<EmptyPlacement>(), Ok(EmptyPlacement::First));
+        assert!("top".parse::<EmptyPlacement>().is_err());
+        let input = SortKeyInput {
+            column: 1,
+            ascending: None,
+            mode: Some("dates".to_string()),
+        };
+        assert!(SortKey::try_from(&input).is_err());
+    }
+
+    #[test]
+    fn cache_hash_is_fixed_across_builds() {
+        // Order cache files are named by this value, so it must never change
+        let spec = SortSpec::single(2, true, SortOptions::default());
+        assert_eq!(spec.cache_hash(), 0xe5ab_446b_518c_e657);
+        let descending = SortSpec::single(2, false, SortOptions::default());
+        assert_ne!(descending.cache_hash(), spec.cache_hash());
+    }
+}
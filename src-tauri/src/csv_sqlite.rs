@@ -0,0 +1,475 @@
+use crate::csv_columnar::{parse_boolean, parse_float, parse_integer, ColumnType, ColumnarSchema};
+use crate::csv_export::RowWriter;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params_from_iter, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const PROGRESS_INTERVAL: usize = 10_000;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqliteExportOptions {
+    pub table: String,
+    /// Exported column positions to index once the rows are in
+    #[serde(default)]
+    pub indexes: Vec<usize>,
+    /// Drop an existing table of the same name instead of failing
+    #[serde(default)]
+    pub replace: bool,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SqliteTable {
+    pub name: String,
+    /// "table" or "view"
+    pub kind: String,
+}
+
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn affinity(column_type: ColumnType) -> &'static str {
+    match column_type {
+        ColumnType::Integer | ColumnType::Boolean => "INTEGER",
+        ColumnType::Float => "REAL",
+        ColumnType::Timestamp(_) | ColumnType::Text => "TEXT",
+    }
+}
+
+/// Column names for the table. SQLite compares identifiers without regard to ASCII
+/// case, so names that only differ in case get a suffix like `col_2`.
+fn column_names(names: &[&str]) -> Vec<String> {
+    let mut used = HashSet::new();
+    names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let base = if name.trim().is_empty() {
+                format!("column_{}", idx + 1)
+            } else {
+                name.to_string()
+            };
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while !used.insert(name.to_ascii_lowercase()) {
+                name = format!("{base}_{suffix}");
+                suffix += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+/// Empty cells are NULL in typed columns; values that do not fit are kept as text
+fn sql_value(cell: &str, column_type: ColumnType) -> Value {
+    let trimmed = cell.trim();
+    if trimmed.is_empty() && column_type != ColumnType::Text {
+        return Value::Null;
+    }
+    let typed = match column_type {
+        ColumnType::Integer => parse_integer(trimmed).map(Value::Integer),
+        ColumnType::Float => parse_float(trimmed).map(Value::Real),
+        ColumnType::Boolean => parse_boolean(trimmed).map(|value| Value::Integer(value as i64)),
+        ColumnType::Timestamp(_) | ColumnType::Text => None,
+    };
+    typed.unwrap_or_else(|| Value::Text(cell.to_string()))
+}
+
+/// Streams rows into a new table inside one transaction, so the table only shows up
+/// in the database once every row is in. A database file the export created is
+/// removed again if the export does not finish.
+pub struct SqliteRowWriter {
+    conn: Option<Connection>,
+    created: Option<PathBuf>,
+    table: String,
+    insert: String,
+    types: Vec<ColumnType>,
+    indexes: Vec<String>,
+}
+
+impl SqliteRowWriter {
+    pub fn create(
+        path: &str,
+        options: &SqliteExportOptions,
+        schema: &ColumnarSchema,
+    ) -> Result<Self, String> {
+        let table = options.table.trim();
+        if table.is_empty() {
+            return Err("Table name is empty".to_string());
+        }
+        let names = column_names(&schema.names());
+        let types = schema.types().to_vec();
+        let mut seen = HashSet::new();
+        let indexes = options
+            .indexes
+            .iter()
+            .filter(|&&idx| seen.insert(idx))
+            .map(|&idx| {
+                names
+                    .get(idx)
+                    .cloned()
+                    .ok_or_else(|| format!("Index column {idx} out of range"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let created = (!Path::new(path).exists()).then(|| PathBuf::from(path));
+        let conn = Connection::open(path).map_err(|err| err.to_string())?;
+        let quoted = quote_identifier(table);
+        let writer = SqliteRowWriter {
+            conn: Some(conn),
+            created,
+            table: table.to_string(),
+            insert: format!(
+                "INSERT INTO {quoted} VALUES ({})",
+                vec!["?"; names.len()].join(", ")
+            ),
+            types,
+            indexes,
+        };
+        // Dropping the writer on an error below removes a file it created
+        let conn = writer.conn()?;
+        conn.execute_batch("BEGIN").map_err(|err| err.to_string())?;
+        if options.replace {
+            conn.execute_batch(&format!("DROP TABLE IF EXISTS {quoted}"))
+                .map_err(|err| err.to_string())?;
+        }
+        let columns = names
+            .iter()
+            .zip(&writer.types)
+            .map(|(name, column_type)| {
+                format!("{} {}", quote_identifier(name), affinity(*column_type))
+            })
+            .collect::<Vec<_>>();
+        conn.execute_batch(&format!("CREATE TABLE {quoted} ({})", columns.join(", ")))
+            .map_err(|err| err.to_string())?;
+        Ok(writer)
+    }
+
+    fn conn(&self) -> Result<&Connection, String> {
+        self.conn
+            .as_ref()
+            .ok_or_else(|| "The export has already finished".to_string())
+    }
+}
+
+impl Drop for SqliteRowWriter {
+    fn drop(&mut self) {
+        // Close the connection first; Windows cannot remove an open file
+        drop(self.conn.take());
+        if let Some(path) = self.created.take() {
+            let _ = fs::remove_file(&path);
+            for suffix in ["-journal", "-wal", "-shm"] {
+                let mut sidecar = path.clone().into_os_string();
+                sidecar.push(suffix);
+                let _ = fs::remove_file(sidecar);
+            }
+        }
+    }
+}
+
+impl RowWriter for SqliteRowWriter {
+    fn begin(&mut self, _headers: &[&str]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[&str]) -> Result<(), String> {
+        let values =
+            self.types.iter().enumerate().map(|(idx, column_type)| {
+                sql_value(row.get(idx).copied().unwrap_or(""), *column_type)
+            });
+        let mut statement = self
+            .conn()?
+            .prepare_cached(&self.insert)
+            .map_err(|err| err.to_string())?;
+        statement
+            .execute(params_from_iter(values))
+            .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        let conn = self.conn()?;
+        for column in &self.indexes {
+            let index = format!("idx_{}_{}", self.table, column);
+            conn.execute_batch(&format!(
+                "CREATE INDEX {} ON {} ({})",
+                quote_identifier(&index),
+                quote_identifier(&self.table),
+                quote_identifier(column)
+            ))
+            .map_err(|err| err.to_string())?;
+        }
+        conn.execute_batch("COMMIT")
+            .map_err(|err| err.to_string())?;
+        // The file holds the table now and stays when the writer is dropped
+        self.created = None;
+        self.conn = None;
+        Ok(())
+    }
+}
+
+fn open_read_only(path: &str) -> Result<Connection, String> {
+    Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Tables and views of a database, without SQLite's internal tables
+pub fn list_tables(path: &str) -> Result<Vec<SqliteTable>, String> {
+    let conn = open_read_only(path)?;
+    let mut statement = conn
+        .prepare(
+            "SELECT name, type FROM sqlite_master \
+             WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )
+        .map_err(|err| err.to_string())?;
+    let tables = statement
+        .query_map([], |row| {
+            Ok(SqliteTable {
+                name: row.get(0)?,
+                kind: row.get(1)?,
+            })
+        })
+        .map_err(|err| err.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| err.to_string())?;
+    Ok(tables)
+}
+
+/// SQL of an import: a whole table, or a query given as is
+pub fn import_query(table: Option<&str>, query: Option<&str>) -> Result<String, String> {
+    if let Some(query) = query.map(str::trim).filter(|query| !query.is_empty()) {
+        return Ok(query.trim_end_matches(';').trim_end().to_string());
+    }
+    match table.filter(|table| !table.is_empty()) {
+        Some(table) => Ok(format!("SELECT * FROM {}", quote_identifier(table))),
+        None => Err("No table or query to import".to_string()),
+    }
+}
+
+fn cell_text(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(value) => value.to_string(),
+        ValueRef::Real(value) => value.to_string(),
+        ValueRef::Text(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        ValueRef::Blob(bytes) => {
+            let hex = bytes
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>();
+            format!("0x{hex}")
+        }
+    }
+}
+
+/// Streams the result of a read-only query as CSV with a header row. NULLs become
+/// empty cells and blobs hex strings. `progress` gets the rows written so far every
+/// few thousand rows and can stop the import. Returns the number of rows written.
+pub fn write_query_csv<W: Write>(
+    path: &str,
+    sql: &str,
+    progress: &dyn Fn(usize) -> Result<(), String>,
+    out: W,
+) -> Result<usize, String> {
+    let conn = open_read_only(path)?;
+    let mut statement = conn.prepare(sql).map_err(|err| err.to_string())?;
+    if !statement.readonly() {
+        return Err("Only read-only queries can be imported".to_string());
+    }
+    let column_count = statement.column_count();
+    let mut writer = csv::Writer::from_writer(out);
+    writer
+        .write_record(statement.column_names())
+        .map_err(|err| err.to_string())?;
+
+    let mut rows = statement.query([]).map_err(|err| err.to_string())?;
+    let mut written = 0usize;
+    while let Some(row) = rows.next().map_err(|err| err.to_string())? {
+        if written.is_multiple_of(PROGRESS_INTERVAL) {
+            progress(written)?;
+        }
+        let record = (0..column_count)
+            .map(|idx| row.get_ref(idx).map(cell_text))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())?;
+        writer
+            .write_record(&record)
+            .map_err(|err| err.to_string())?;
+        written += 1;
+    }
+    writer.flush().map_err(|err| err.to_string())?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_columnar::SchemaInference;
+
+    fn schema(rows: &[&[&str]]) -> ColumnarSchema {
+        let headers = rows[0];
+        let mut inference = SchemaInference::default();
+        inference.begin(headers).unwrap();
+        for row in &rows[1..] {
+            inference.write_row(row).unwrap();
+        }
+        inference.schema(headers)
+    }
+
+    fn export(path: &str, options: &SqliteExportOptions, rows: &[&[&str]]) -> Result<(), String> {
+        let headers = rows[0];
+        let mut writer = SqliteRowWriter::create(path, options, &schema(rows))?;
+        writer.begin(headers)?;
+        for row in &rows[1..] {
+            writer.write_row(row)?;
+        }
+        writer.finish()
+    }
+
+    fn query_csv(path: &str, sql: &str) -> String {
+        let mut out = Vec::new();
+        write_query_csv(path, sql, &|_| Ok(()), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn export_infers_affinities_and_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.db");
+        let path = path.to_str().unwrap();
+        let options = SqliteExportOptions {
+            table: "people".to_string(),
+            indexes: vec![1],
+            replace: false,
+        };
+        let rows: &[&[&str]] = &[
+            &["id", "name", "score", "active", "zip"],
+            &["1", "Ann", "1.5", "true", "007"],
+            &["2", "Bob", "", "false", "12"],
+        ];
+        export(path, &options, rows).unwrap();
+
+        let conn = Connection::open(path).unwrap();
+        let types = conn
+            .prepare("SELECT type FROM pragma_table_info('people') ORDER BY cid")
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(types, ["INTEGER", "TEXT", "REAL", "INTEGER", "TEXT"]);
+        let score: Option<f64> = conn
+            .query_row("SELECT score FROM people WHERE id = 2", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(score, None);
+        let index: String = conn
+            .query_row(
+                "SELECT name FROM sqlite_master WHERE type = 'index'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(index, "idx_people_name");
+
+        assert!(export(path, &options, rows).is_err());
+        let replace = SqliteExportOptions {
+            replace: true,
+            ..options
+        };
+        export(path, &replace, rows).unwrap();
+    }
+
+    #[test]
+    fn export_renames_clashing_columns_and_removes_a_failed_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.db");
+        let path = path.to_str().unwrap();
+        let options = SqliteExportOptions {
+            table: "t".to_string(),
+            indexes: vec![1, 1],
+            replace: false,
+        };
+        let rows: &[&[&str]] = &[&["id", "ID", "", "id"], &["1", "2", "3", "4"]];
+        export(path, &options, rows).unwrap();
+
+        let conn = Connection::open(path).unwrap();
+        let names = conn
+            .prepare("SELECT name FROM pragma_table_info('t') ORDER BY cid")
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(names, ["id", "ID_2", "column_3", "id_2_2"]);
+        let indexes: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM sqlite_master WHERE type = 'index'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(indexes, 1);
+        drop(conn);
+
+        // A failing export keeps a database that was already there
+        assert!(export(path, &options, rows).is_err());
+        assert!(Path::new(path).exists());
+
+        let fresh = dir.path().join("fresh.db");
+        let fresh = fresh.to_str().unwrap();
+        let mut writer = SqliteRowWriter::create(fresh, &options, &schema(rows)).unwrap();
+        writer.write_row(rows[1]).unwrap();
+        drop(writer);
+        assert!(!Path::new(fresh).exists());
+        let bad_index = SqliteExportOptions {
+            indexes: vec![9],
+            ..options
+        };
+        assert!(export(fresh, &bad_index, rows).is_err());
+        assert!(!Path::new(fresh).exists());
+    }
+
+    #[test]
+    fn import_round_trips_tables_and_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        let path = path.to_str().unwrap();
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE \"my table\" (a INTEGER, b TEXT, c BLOB);
+             INSERT INTO \"my table\" VALUES (1, 'x,y', x'cafe'), (2, NULL, NULL);
+             CREATE VIEW evens AS SELECT a FROM \"my table\" WHERE a % 2 = 0;",
+        )
+        .unwrap();
+        drop(conn);
+
+        let tables = list_tables(path).unwrap();
+        let names = tables
+            .iter()
+            .map(|table| table.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["evens", "my table"]);
+        assert_eq!(tables[0].kind, "view");
+
+        let sql = import_query(Some("my table"), None).unwrap();
+        assert_eq!(query_csv(path, &sql), "a,b,c\n1,\"x,y\",0xcafe\n2,,\n");
+        let sql = import_query(Some("my table"), Some("SELECT a FROM evens;")).unwrap();
+        assert_eq!(query_csv(path, &sql), "a\n2\n");
+
+        let mut out = Vec::new();
+        let result = write_query_csv(path, "DELETE FROM evens", &|_| Ok(()), &mut out);
+        assert!(result.is_err());
+        assert!(import_query(None, Some("  ")).is_err());
+    }
+}
@@ -0,0 +1,544 @@
+use crate::csv_handler::{DetectedSettings, ParseOverrides};
+use calamine::{open_workbook, Cell, Data, DataRef, Ods, Range, Reader, Sheets, Xls, Xlsb, Xlsx};
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use zip::ZipArchive;
+
+const WORKBOOK_EXTENSIONS: &[&str] = &["xlsx", "xlsm", "xlsb", "xls", "ods"];
+const PROGRESS_INTERVAL: usize = 10_000;
+/// XLS and ODS sheets are only read whole, so larger files are refused
+const MAX_LOADED_WORKBOOK_SIZE: u64 = 64 * 1024 * 1024;
+const OLE_MAGIC: [u8; 8] = [0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1];
+const ODS_MIME_TYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkbookKind {
+    Xls,
+    Xlsx,
+    Xlsb,
+    Ods,
+}
+
+/// Whether the extension marks a spreadsheet workbook
+pub fn has_workbook_extension(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            WORKBOOK_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// Kind of workbook the file holds, told from its content so misnamed files open
+/// as what they are. XLSX, XLSB and ODS files are ZIP archives that are recognised
+/// by their workbook part; other archives are not workbooks.
+pub fn workbook_kind(path: &str) -> Result<Option<WorkbookKind>, String> {
+    let mut file = File::open(path).map_err(|err| err.to_string())?;
+    let mut magic = [0u8; 8];
+    let mut read = 0;
+    while read < magic.len() {
+        match file
+            .read(&mut magic[read..])
+            .map_err(|err| err.to_string())?
+        {
+            0 => break,
+            count => read += count,
+        }
+    }
+    let magic = &magic[..read];
+    if magic == OLE_MAGIC {
+        return Ok(Some(WorkbookKind::Xls));
+    }
+    if !magic.starts_with(b"PK\x03\x04") {
+        return Ok(None);
+    }
+    let Ok(mut archive) = ZipArchive::new(BufReader::new(file)) else {
+        return Ok(None);
+    };
+    if archive.index_for_name("xl/workbook.xml").is_some() {
+        return Ok(Some(WorkbookKind::Xlsx));
+    }
+    if archive.index_for_name("xl/workbook.bin").is_some() {
+        return Ok(Some(WorkbookKind::Xlsb));
+    }
+    let mut mime_type = String::new();
+    if let Ok(member) = archive.by_name("mimetype") {
+        let _ = member.take(128).read_to_string(&mut mime_type);
+    }
+    Ok((mime_type.trim() == ODS_MIME_TYPE).then_some(WorkbookKind::Ods))
+}
+
+fn open_sheets(path: &str, kind: WorkbookKind) -> Result<Sheets<BufReader<File>>, String> {
+    let error = |err: &dyn std::fmt::Display| format!("Cannot read workbook: {err}");
+    Ok(match kind {
+        WorkbookKind::Xls => {
+            Sheets::Xls(open_workbook::<Xls<_>, _>(path).map_err(|err| error(&err))?)
+        }
+        WorkbookKind::Xlsx => {
+            Sheets::Xlsx(open_workbook::<Xlsx<_>, _>(path).map_err(|err| error(&err))?)
+        }
+        WorkbookKind::Xlsb => {
+            Sheets::Xlsb(open_workbook::<Xlsb<_>, _>(path).map_err(|err| error(&err))?)
+        }
+        WorkbookKind::Ods => {
+            Sheets::Ods(open_workbook::<Ods<_>, _>(path).map_err(|err| error(&err))?)
+        }
+    })
+}
+
+/// Sheet names in workbook order
+pub fn list_sheets(path: &str, kind: WorkbookKind) -> Result<Vec<String>, String> {
+    Ok(open_sheets(path, kind)?.sheet_names())
+}
+
+/// File name of the CSV copy of a sheet: "sales.xlsx" and "Q1" give "sales-Q1.csv"
+pub fn sheet_file_name(path: &str, sheet: &str) -> String {
+    let stem = Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "workbook".to_string());
+    format!("{stem}-{sheet}.csv")
+}
+
+/// Parse settings of a sheet copy, which `write_sheet_csv` writes as comma-separated
+/// UTF-8 with LF line endings, starting at the header row
+pub fn sheet_settings() -> DetectedSettings {
+    DetectedSettings {
+        delimiter: b',',
+        quote: b'"',
+        escape: None,
+        line_ending: "lf".to_string(),
+        encoding: encoding_rs::UTF_8,
+        encoding_label: encoding_rs::UTF_8.name().to_string(),
+        has_headers: true,
+        fixed_columns: Vec::new(),
+        fixed_has_headers: true,
+        json_columns: None,
+    }
+}
+
+/// The overrides that apply to a sheet copy; its format is fixed, so only the
+/// header and malformed-row settings are kept
+pub fn sheet_overrides(overrides: Option<ParseOverrides>) -> Option<ParseOverrides> {
+    overrides.map(|overrides| ParseOverrides {
+        has_headers: overrides.has_headers,
+        malformed: overrides.malformed,
+        max_field_size: overrides.max_field_size,
+        max_record_size: overrides.max_record_size,
+        ..ParseOverrides::default()
+    })
+}
+
+/// Text of a cell as the spreadsheet shows it, without number formats. Dates become
+/// ISO 8601 and whole numbers lose their trailing ".0".
+pub fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::String(value) | Data::DateTimeIso(value) | Data::DurationIso(value) => value.clone(),
+        Data::Bool(value) => if *value { "TRUE" } else { "FALSE" }.to_string(),
+        Data::DateTime(value) if value.is_duration() => {
+            let seconds = (value.as_f64() * 86_400.0).round() as i64;
+            let sign = if seconds < 0 { "-" } else { "" };
+            let seconds = seconds.abs();
+            format!(
+                "{sign}{}:{:02}:{:02}",
+                seconds / 3600,
+                seconds % 3600 / 60,
+                seconds % 60
+            )
+        }
+        Data::DateTime(value) => {
+            let format = if value.as_f64().fract() == 0.0 {
+                "%Y-%m-%d"
+            } else {
+                "%Y-%m-%d %H:%M:%S"
+            };
+            match value.as_datetime() {
+                Some(datetime) => datetime.format(format).to_string(),
+                None => value.as_f64().to_string(),
+            }
+        }
+        other => other.to_string(),
+    }
+}
+
+/// First and last (row, column) of the non-empty cells of a sheet
+type Bounds = ((u32, u32), (u32, u32));
+
+/// Writes the used range of a sheet as CSV into `out`, starting at `header_row`
+/// (1-based, as numbered in the spreadsheet) or at the first used row. Every row has
+/// the width of the used range. `progress` gets the rows written so far and can
+/// stop the copy. Returns the number of rows written.
+///
+/// XLSX and XLSB sheets are streamed twice, once for the used range and once for
+/// the cells, so they never sit in memory whole. XLS and ODS sheets are loaded
+/// whole, up to `MAX_LOADED_WORKBOOK_SIZE`.
+pub fn write_sheet_csv<W: Write>(
+    path: &str,
+    kind: WorkbookKind,
+    sheet: &str,
+    header_row: Option<usize>,
+    progress: &dyn Fn(usize) -> Result<(), String>,
+    out: &mut W,
+) -> Result<usize, String> {
+    let sheet_error = |err: &dyn std::fmt::Display| format!("Cannot read sheet {sheet}: {err}");
+    let writer = csv::Writer::from_writer(out);
+    match kind {
+        WorkbookKind::Xlsx => {
+            let mut workbook =
+                open_workbook::<Xlsx<_>, _>(path).map_err(|err| sheet_error(&err))?;
+            let bounds = {
+                let mut reader = workbook
+                    .worksheet_cells_reader(sheet)
+                    .map_err(|err| sheet_error(&err))?;
+                used_bounds(
+                    || reader.next_cell().map_err(|err| sheet_error(&err)),
+                    progress,
+                )?
+            };
+            let mut reader = workbook
+                .worksheet_cells_reader(sheet)
+                .map_err(|err| sheet_error(&err))?;
+            write_cells(
+                bounds,
+                || reader.next_cell().map_err(|err| sheet_error(&err)),
+                header_row,
+                progress,
+                writer,
+            )
+        }
+        WorkbookKind::Xlsb => {
+            let mut workbook =
+                open_workbook::<Xlsb<_>, _>(path).map_err(|err| sheet_error(&err))?;
+            let bounds = {
+                let mut reader = workbook
+                    .worksheet_cells_reader(sheet)
+                    .map_err(|err| sheet_error(&err))?;
+                used_bounds(
+                    || reader.next_cell().map_err(|err| sheet_error(&err)),
+                    progress,
+                )?
+            };
+            let mut reader = workbook
+                .worksheet_cells_reader(sheet)
+                .map_err(|err| sheet_error(&err))?;
+            write_cells(
+                bounds,
+                || reader.next_cell().map_err(|err| sheet_error(&err)),
+                header_row,
+                progress,
+                writer,
+            )
+        }
+        WorkbookKind::Xls | WorkbookKind::Ods => {
+            let size = std::fs::metadata(path)
+                .map_err(|err| err.to_string())?
+                .len();
+            if size > MAX_LOADED_WORKBOOK_SIZE {
+                return Err(format!(
+                    "XLS and ODS workbooks over {} MB are read whole and cannot be opened; \
+                     save the sheet as XLSX or CSV instead",
+                    MAX_LOADED_WORKBOOK_SIZE / (1024 * 1024)
+                ));
+            }
+            let range = open_sheets(path, kind)?
+                .worksheet_range(sheet)
+                .map_err(|err| sheet_error(&err))?;
+            write_range(&range, header_row, progress, writer)
+        }
+    }
+}
+
+/// Used range of a streamed sheet, or None when it has no values. `progress` is
+/// told that no rows are written yet, which lets it stop the scan.
+fn used_bounds<'a>(
+    mut next_cell: impl FnMut() -> Result<Option<Cell<DataRef<'a>>>, String>,
+    progress: &dyn Fn(usize) -> Result<(), String>,
+) -> Result<Option<Bounds>, String> {
+    let mut bounds: Option<Bounds> = None;
+    let mut seen = 0usize;
+    while let Some(cell) = next_cell()? {
+        seen += 1;
+        if seen.is_multiple_of(PROGRESS_INTERVAL) {
+            progress(0)?;
+        }
+        if matches!(cell.get_value(), DataRef::Empty) {
+            continue;
+        }
+        let (row, column) = cell.get_position();
+        bounds = Some(match bounds {
+            Some(((first_row, first_column), (last_row, last_column))) => (
+                (first_row.min(row), first_column.min(column)),
+                (last_row.max(row), last_column.max(column)),
+            ),
+            None => ((row, column), (row, column)),
+        });
+    }
+    Ok(bounds)
+}
+
+/// First row written for `header_row` in a sheet whose used range starts at `first_row`
+fn start_row(first_row: u32, header_row: Option<usize>) -> u32 {
+    header_row.map_or(first_row, |row| {
+        (row.saturating_sub(1).min(u32::MAX as usize) as u32).max(first_row)
+    })
+}
+
+/// Writes the cells of a streamed sheet, which come in row order, padding gaps with
+/// empty cells and rows
+fn write_cells<'a, W: Write>(
+    bounds: Option<Bounds>,
+    mut next_cell: impl FnMut() -> Result<Option<Cell<DataRef<'a>>>, String>,
+    header_row: Option<usize>,
+    progress: &dyn Fn(usize) -> Result<(), String>,
+    mut writer: csv::Writer<W>,
+) -> Result<usize, String> {
+    let mut written = 0usize;
+    if let Some(((first_row, first_column), (last_row, last_column))) = bounds {
+        let mut record = vec![String::new(); (last_column - first_column) as usize + 1];
+        let mut row = start_row(first_row, header_row);
+        let mut write_record = |record: &mut Vec<String>, written: &mut usize| {
+            writer
+                .write_record(record.iter())
+                .map_err(|err| err.to_string())?;
+            record.iter_mut().for_each(String::clear);
+            *written += 1;
+            if written.is_multiple_of(PROGRESS_INTERVAL) {
+                progress(*written)?;
+            }
+            Ok::<(), String>(())
+        };
+        while let Some(cell) = next_cell()? {
+            let (cell_row, column) = cell.get_position();
+            if cell_row < row
+                || cell_row > last_row
+                || column < first_column
+                || column > last_column
+            {
+                continue;
+            }
+            while row < cell_row {
+                write_record(&mut record, &mut written)?;
+                row += 1;
+            }
+            record[(column - first_column) as usize] =
+                cell_text(&Data::from(cell.get_value().clone()));
+        }
+        while row <= last_row {
+            write_record(&mut record, &mut written)?;
+            row += 1;
+        }
+    }
+    writer.flush().map_err(|err| err.to_string())?;
+    progress(written)?;
+    Ok(written)
+}
+
+/// Writes the rows of a sheet loaded whole
+fn write_range<W: Write>(
+    range: &Range<Data>,
+    header_row: Option<usize>,
+    progress: &dyn Fn(usize) -> Result<(), String>,
+    mut writer: csv::Writer<W>,
+) -> Result<usize, String> {
+    let first_row = range.start().map_or(0, |(row, _)| row);
+    let skip = (start_row(first_row, header_row) - first_row) as usize;
+    let mut written = 0usize;
+    for row in range.rows().skip(skip) {
+        writer
+            .write_record(row.iter().map(cell_text))
+            .map_err(|err| err.to_string())?;
+        written += 1;
+        if written.is_multiple_of(PROGRESS_INTERVAL) {
+            progress(written)?;
+        }
+    }
+    writer.flush().map_err(|err| err.to_string())?;
+    progress(written)?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use zip::write::SimpleFileOptions;
+
+    fn sheet_xml(rows: &[(usize, &[&str])]) -> String {
+        let rows = rows
+            .iter()
+            .map(|(number, cells)| {
+                let cells = cells
+                    .iter()
+                    .enumerate()
+                    .map(|(col, value)| {
+                        let cell = format!("{}{number}", (b'A' + col as u8) as char);
+                        match value.parse::<f64>() {
+                            Ok(_) => format!(r#"<c r="{cell}"><v>{value}</v></c>"#),
+                            Err(_) => format!(
+                                r#"<c r="{cell}" t="inlineStr"><is><t>{value}</t></is></c>"#
+                            ),
+                        }
+                    })
+                    .collect::<String>();
+                format!(r#"<row r="{number}">{cells}</row>"#)
+            })
+            .collect::<String>();
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>{rows}</sheetData></worksheet>"#
+        )
+    }
+
+    fn write_xlsx(path: &Path, sheets: &[(&str, String)]) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = SimpleFileOptions::default();
+        let overrides = (1..=sheets.len())
+            .map(|idx| {
+                format!(
+                    r#"<Override PartName="/xl/worksheets/sheet{idx}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#
+                )
+            })
+            .collect::<String>();
+        let entries = sheets
+            .iter()
+            .enumerate()
+            .map(|(idx, (name, _))| {
+                format!(
+                    r#"<sheet name="{name}" sheetId="{}" r:id="rId{}"/>"#,
+                    idx + 1,
+                    idx + 1
+                )
+            })
+            .collect::<String>();
+        let rels = (1..=sheets.len())
+            .map(|idx| {
+                format!(
+                    r#"<Relationship Id="rId{idx}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{idx}.xml"/>"#
+                )
+            })
+            .collect::<String>();
+        let mut parts = vec![
+            (
+                "[Content_Types].xml".to_string(),
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>{overrides}</Types>"#
+                ),
+            ),
+            (
+                "_rels/.rels".to_string(),
+                r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#.to_string(),
+            ),
+            (
+                "xl/workbook.xml".to_string(),
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>{entries}</sheets></workbook>"#
+                ),
+            ),
+            (
+                "xl/_rels/workbook.xml.rels".to_string(),
+                format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">{rels}</Relationships>"#
+                ),
+            ),
+        ];
+        for (idx, (_, xml)) in sheets.iter().enumerate() {
+            parts.push((format!("xl/worksheets/sheet{}.xml", idx + 1), xml.clone()));
+        }
+        for (name, xml) in parts {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(xml.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn sheets_are_listed_and_written_from_the_header_row() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sales.xlsx");
+        write_xlsx(
+            &path,
+            &[
+                (
+                    "Q1",
+                    sheet_xml(&[
+                        (2, &["Quarterly report"]),
+                        (4, &["region", "amount"]),
+                        (5, &["north", "12.5"]),
+                        (6, &["south, east", "3"]),
+                    ]),
+                ),
+                ("Empty", sheet_xml(&[])),
+            ],
+        );
+        let path = path.to_str().unwrap();
+        assert!(has_workbook_extension(path));
+        assert!(!has_workbook_extension("data.csv"));
+        let kind = WorkbookKind::Xlsx;
+        assert_eq!(workbook_kind(path).unwrap(), Some(kind));
+        assert_eq!(list_sheets(path, kind).unwrap(), ["Q1", "Empty"]);
+        assert_eq!(sheet_file_name(path, "Q1"), "sales-Q1.csv");
+
+        let mut out = Vec::new();
+        let rows = write_sheet_csv(path, kind, "Q1", Some(4), &|_| Ok(()), &mut out).unwrap();
+        assert_eq!(rows, 3);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "region,amount\nnorth,12.5\n\"south, east\",3\n"
+        );
+
+        let mut out = Vec::new();
+        write_sheet_csv(path, kind, "Q1", None, &|_| Ok(()), &mut out).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .starts_with("Quarterly report,\n,\nregion,amount\n"));
+        assert!(
+            write_sheet_csv(path, kind, "Missing", None, &|_| Ok(()), &mut Vec::new()).is_err()
+        );
+    }
+
+    #[test]
+    fn cells_render_like_the_spreadsheet() {
+        use calamine::{ExcelDateTime, ExcelDateTimeType};
+
+        assert_eq!(cell_text(&Data::Float(3.0)), "3");
+        assert_eq!(cell_text(&Data::Float(0.25)), "0.25");
+        assert_eq!(cell_text(&Data::Int(-7)), "-7");
+        assert_eq!(cell_text(&Data::Bool(true)), "TRUE");
+        assert_eq!(cell_text(&Data::Empty), "");
+        let date = ExcelDateTime::new(45292.0, ExcelDateTimeType::DateTime, false);
+        assert_eq!(cell_text(&Data::DateTime(date)), "2024-01-01");
+        let datetime = ExcelDateTime::new(45292.75, ExcelDateTimeType::DateTime, false);
+        assert_eq!(cell_text(&Data::DateTime(datetime)), "2024-01-01 18:00:00");
+        let duration = ExcelDateTime::new(1.5, ExcelDateTimeType::TimeDelta, false);
+        assert_eq!(cell_text(&Data::DateTime(duration)), "36:00:00");
+        let negative = ExcelDateTime::new(-0.5, ExcelDateTimeType::TimeDelta, false);
+        assert_eq!(cell_text(&Data::DateTime(negative)), "-12:00:00");
+    }
+
+    #[test]
+    fn workbooks_are_told_apart_by_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let renamed = dir.path().join("export.csv");
+        write_xlsx(&renamed, &[("Data", sheet_xml(&[(1, &["a"])]))]);
+        assert_eq!(
+            workbook_kind(renamed.to_str().unwrap()).unwrap(),
+            Some(WorkbookKind::Xlsx)
+        );
+
+        let text = dir.path().join("report.xlsx");
+        std::fs::write(&text, "a,b\n1,2\n").unwrap();
+        assert_eq!(workbook_kind(text.to_str().unwrap()).unwrap(), None);
+
+        let archive = dir.path().join("tables.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&archive).unwrap());
+        writer
+            .start_file("data.csv", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(b"a\n1\n").unwrap();
+        writer.finish().unwrap();
+        assert_eq!(workbook_kind(archive.to_str().unwrap()).unwrap(), None);
+    }
+}
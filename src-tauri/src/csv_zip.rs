@@ -0,0 +1,191 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use zip::{CompressionMethod, ZipArchive};
+
+const COPY_BUFFER_SIZE: usize = 256 * 1024;
+const MEMBER_EXTENSIONS: &[&str] = &["csv", "tsv", "txt"];
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ZipMember {
+    pub name: String,
+    pub size: u64,
+    pub compressed_size: u64,
+}
+
+/// Whether the file starts like a ZIP archive, including an empty one
+pub fn is_zip(path: &str) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok()
+        && (magic == *b"PK\x03\x04" || magic == *b"PK\x05\x06")
+}
+
+fn open_archive(path: &str) -> Result<ZipArchive<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    ZipArchive::new(BufReader::new(file)).map_err(|err| err.to_string())
+}
+
+fn is_table_member(name: &str) -> bool {
+    // Resource forks that macOS adds next to every file
+    if name.starts_with("__MACOSX/") || name.ends_with('/') {
+        return false;
+    }
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            MEMBER_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// CSV, TSV and text members of an archive, in archive order
+pub fn list_members(path: &str) -> Result<Vec<ZipMember>, String> {
+    let mut archive = open_archive(path)?;
+    let mut members = Vec::new();
+    for idx in 0..archive.len() {
+        let file = archive.by_index_raw(idx).map_err(|err| err.to_string())?;
+        if !file.is_file() || !is_table_member(file.name()) {
+            continue;
+        }
+        members.push(ZipMember {
+            name: file.name().to_string(),
+            size: file.size(),
+            compressed_size: file.compressed_size(),
+        });
+    }
+    Ok(members)
+}
+
+/// Member to open when none was picked: the only table member of the archive
+pub fn single_member(path: &str) -> Result<String, String> {
+    let mut members = list_members(path)?;
+    match members.len() {
+        0 => Err("The archive has no CSV members".to_string()),
+        1 => Ok(members.remove(0).name),
+        count => Err(format!(
+            "The archive has {count} CSV members; pick one to open"
+        )),
+    }
+}
+
+/// File name of the extracted copy of a member, without its directories
+pub fn member_file_name(member: &str) -> String {
+    member.rsplit('/').next().unwrap_or(member).to_string()
+}
+
+/// Decodes a stored or deflated member into `out`. `progress` gets the uncompressed
+/// bytes written so far after every buffer and can stop the copy. Returns the
+/// member size.
+pub fn extract_member<W: Write>(
+    path: &str,
+    member: &str,
+    progress: &dyn Fn(u64) -> Result<(), String>,
+    out: &mut W,
+) -> Result<u64, String> {
+    let mut archive = open_archive(path)?;
+    let mut file = archive
+        .by_name(member)
+        .map_err(|err| format!("Cannot open {member}: {err}"))?;
+    match file.compression() {
+        CompressionMethod::Stored | CompressionMethod::Deflated => {}
+        method => return Err(format!("{member} uses unsupported compression {method}")),
+    }
+    let mut buf = vec![0u8; COPY_BUFFER_SIZE];
+    let mut written = 0u64;
+    loop {
+        let read = file
+            .read(&mut buf)
+            .map_err(|err| format!("Failed to extract {member}: {err}"))?;
+        if read == 0 {
+            break;
+        }
+        out.write_all(&buf[..read]).map_err(|err| err.to_string())?;
+        written += read as u64;
+        progress(written)?;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip::write::SimpleFileOptions;
+
+    fn write_archive(path: &Path, members: &[(&str, &[u8], CompressionMethod)]) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, bytes, method) in members {
+            if name.ends_with('/') {
+                writer
+                    .add_directory(*name, SimpleFileOptions::default())
+                    .unwrap();
+                continue;
+            }
+            let options = SimpleFileOptions::default().compression_method(*method);
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(bytes).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn lists_and_extracts_table_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vendor.zip");
+        write_archive(
+            &path,
+            &[
+                ("readme.md", b"notes", CompressionMethod::Deflated),
+                ("data/", b"", CompressionMethod::Stored),
+                (
+                    "data/orders.csv",
+                    b"id\n1\n2\n",
+                    CompressionMethod::Deflated,
+                ),
+                ("items.TSV", b"a\tb\n", CompressionMethod::Stored),
+                (
+                    "__MACOSX/data/._orders.csv",
+                    b"x",
+                    CompressionMethod::Stored,
+                ),
+            ],
+        );
+        let path = path.to_str().unwrap();
+        assert!(is_zip(path));
+
+        let members = list_members(path).unwrap();
+        let names = members
+            .iter()
+            .map(|member| member.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["data/orders.csv", "items.TSV"]);
+        assert_eq!(members[0].size, 7);
+        assert!(single_member(path).is_err());
+
+        for (member, expected) in [("data/orders.csv", "id\n1\n2\n"), ("items.TSV", "a\tb\n")] {
+            let mut out = Vec::new();
+            let size = extract_member(path, member, &|_| Ok(()), &mut out).unwrap();
+            assert_eq!(size as usize, expected.len());
+            assert_eq!(String::from_utf8(out).unwrap(), expected);
+        }
+        assert!(extract_member(path, "missing.csv", &|_| Ok(()), &mut Vec::new()).is_err());
+        assert_eq!(member_file_name("data/orders.csv"), "orders.csv");
+    }
+
+    #[test]
+    fn single_member_archives_open_without_picking() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("one.zip");
+        write_archive(&path, &[("only.csv", b"a\n", CompressionMethod::Deflated)]);
+        assert_eq!(single_member(path.to_str().unwrap()).unwrap(), "only.csv");
+
+        let plain = dir.path().join("plain.csv");
+        std::fs::write(&plain, b"a,b\n").unwrap();
+        assert!(!is_zip(plain.to_str().unwrap()));
+    }
+}
@@ -1,16 +1,29 @@
 use std::collections::hash_map::DefaultHasher;
 use std::fs::{self, File};
 use std::hash::{Hash, Hasher};
-use std::io::{Read, Write};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::Manager;
 
-const CACHE_VERSION: u32 = 1;
+use crate::csv_export::write_atomically;
+use crate::csv_sort::SortSpec;
+
+/// Bumped with every change to the layout or keying of cache files. Version 2 was
+/// kept across the move to multi-key sort orders, so files written under it may use
+/// either layout and are never read back.
+const CACHE_VERSION: u32 = 4;
 const CACHE_TTL: Duration = Duration::from_secs(60 * 60 * 24 * 3);
 
 const OFFSETS_MAGIC: &[u8; 4] = b"CVOF";
 const ORDER_MAGIC: &[u8; 4] = b"CVSO";
+/// Magic, version, file length and time, spec hash and row count
+const ORDER_HEADER_LEN: u64 = 4 + 4 + 8 + 8 + 8 + 8;
+
+const IMPORTS_DIR: &str = "imports";
+/// Marker next to an imported copy whose modification time records its last use.
+/// The copy itself keeps its mtime, which is part of its cache key.
+const LAST_USED_MARKER: &str = ".last-used";
 
 #[derive(Clone, Copy)]
 pub struct CacheKey {
@@ -72,18 +85,92 @@ pub fn prune_cache_dir(dir: &Path) {
             }
         }
     }
+    prune_imports(&dir.join(IMPORTS_DIR), now);
+}
+
+/// Removes imported copies not opened within the cache TTL, along with their directory
+fn prune_imports(dir: &Path, now: SystemTime) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_old = |meta: fs::Metadata| {
+            meta.modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > CACHE_TTL)
+        };
+        // Copies from before the marker existed fall back to their own times
+        let stale = match fs::metadata(path.join(LAST_USED_MARKER)) {
+            Ok(marker) => is_old(marker),
+            Err(_) => fs::read_dir(&path)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .all(|file| file.metadata().is_ok_and(is_old)),
+        };
+        if stale {
+            let _ = fs::remove_dir_all(&path);
+        }
+    }
+}
+
+/// Path of the CSV copy of an imported `source`. `variant` tells apart copies of the
+/// same source (a table, a query, an archive member); `name` is the file name shown
+/// in the window title.
+pub fn import_cache_path(
+    dir: &Path,
+    source: &str,
+    variant: &str,
+    name: &str,
+) -> Result<PathBuf, String> {
+    let key = cache_key(source, None)?;
+    let mut hasher = DefaultHasher::new();
+    key.hash.hash(&mut hasher);
+    variant.hash(&mut hasher);
+    let name = name
+        .chars()
+        .map(|ch| {
+            if ch.is_alphanumeric() || matches!(ch, '-' | '_' | '.' | ' ') {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    Ok(dir
+        .join(IMPORTS_DIR)
+        .join(format!("{:016x}", hasher.finish()))
+        .join(name))
+}
+
+/// Writes the imported copy at `path` unless an earlier import left it there. The
+/// marker next to the copy is touched either way so pruning keeps it while in use.
+pub fn materialize_import<F>(path: &Path, write: F) -> Result<(), String>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), String>,
+{
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        let _ = File::create(parent.join(LAST_USED_MARKER));
+    }
+    if path.exists() {
+        return Ok(());
+    }
+    write_atomically(path, write)
 }
 
 pub fn offsets_cache_path(dir: &Path, key: CacheKey) -> PathBuf {
     dir.join(format!("offsets_{:016x}.bin", key.hash))
 }
 
-pub fn order_cache_path(dir: &Path, key: CacheKey, column: usize, ascending: bool) -> PathBuf {
+pub fn order_cache_path(dir: &Path, key: CacheKey, spec: &SortSpec) -> PathBuf {
     dir.join(format!(
-        "order_{:016x}_c{}_{}.bin",
+        "order_{:016x}_{:016x}.bin",
         key.hash,
-        column,
-        if ascending { "asc" } else { "desc" }
+        spec.cache_hash()
     ))
 }
 
@@ -135,11 +222,10 @@ pub fn write_offsets_cache(path: &Path, key: CacheKey, offsets: &[u64]) -> Resul
 pub fn read_order_cache(
     path: &Path,
     key: CacheKey,
-    column: usize,
-    ascending: bool,
+    spec: &SortSpec,
 ) -> Result<Option<Vec<usize>>, String> {
     let mut file = match File::open(path) {
-        Ok(file) => file,
+        Ok(file) => BufReader::new(file),
         Err(_) => return Ok(None),
     };
 
@@ -159,10 +245,8 @@ pub fn read_order_cache(
         return Ok(None);
     }
 
-    let stored_column = read_u32(&mut file)? as usize;
-    let stored_direction = read_u8(&mut file)?;
-    let stored_ascending = stored_direction == 1;
-    if stored_column != column || stored_ascending != ascending {
+    let stored_spec = read_u64(&mut file)?;
+    if stored_spec != spec.cache_hash() {
         return Ok(None);
     }
 
@@ -176,36 +260,86 @@ pub fn read_order_cache(
     Ok(Some(order))
 }
 
-pub fn write_order_cache(
-    path: &Path,
-    key: CacheKey,
-    column: usize,
-    ascending: bool,
-    order: &[usize],
-) -> Result<(), String> {
-    let mut file = File::create(path).map_err(|err| err.to_string())?;
-    file.write_all(ORDER_MAGIC).map_err(|err| err.to_string())?;
-    write_u32(&mut file, CACHE_VERSION)?;
-    write_u64(&mut file, key.len)?;
-    write_u64(&mut file, key.modified)?;
-    write_u32(&mut file, column as u32)?;
-    write_u8(&mut file, if ascending { 1 } else { 0 })?;
-    write_u64(&mut file, order.len() as u64)?;
-    for value in order {
-        write_u64(&mut file, *value as u64)?;
+/// Writes an order file row by row as a sort merge produces it, so the order is not
+/// held in memory while it is written. The row count is filled in by `finish`.
+pub struct OrderCacheWriter {
+    writer: BufWriter<File>,
+    count: u64,
+}
+
+impl OrderCacheWriter {
+    pub fn new(file: File, key: CacheKey, spec: &SortSpec) -> Result<Self, String> {
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(ORDER_MAGIC)
+            .map_err(|err| err.to_string())?;
+        write_u32(&mut writer, CACHE_VERSION)?;
+        write_u64(&mut writer, key.len)?;
+        write_u64(&mut writer, key.modified)?;
+        write_u64(&mut writer, spec.cache_hash())?;
+        write_u64(&mut writer, 0)?;
+        Ok(OrderCacheWriter { writer, count: 0 })
+    }
+
+    pub fn push(&mut self, row: usize) -> Result<(), String> {
+        write_u64(&mut self.writer, row as u64)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), String> {
+        let mut file = self.writer.into_inner().map_err(|err| err.to_string())?;
+        file.seek(SeekFrom::Start(ORDER_HEADER_LEN - 8))
+            .map_err(|err| err.to_string())?;
+        write_u64(&mut file, self.count)?;
+        file.flush().map_err(|err| err.to_string())
     }
-    Ok(())
 }
 
-fn read_u8(reader: &mut impl Read) -> Result<u8, String> {
-    let mut buf = [0u8; 1];
-    reader.read_exact(&mut buf).map_err(|err| err.to_string())?;
-    Ok(buf[0])
+/// Reads `count` rows of an order file from `position` on
+pub fn read_order_range(path: &Path, position: usize, count: usize) -> Result<Vec<usize>, String> {
+    let mut file = File::open(path).map_err(|err| err.to_string())?;
+    file.seek(SeekFrom::Start(ORDER_HEADER_LEN + position as u64 * 8))
+        .map_err(|err| err.to_string())?;
+    let mut reader = BufReader::new(file);
+    (0..count)
+        .map(|_| read_u64(&mut reader).map(|row| row as usize))
+        .collect()
 }
 
-fn write_u8(writer: &mut impl Write, value: u8) -> Result<(), String> {
-    writer.write_all(&[value]).map_err(|err| err.to_string())?;
-    Ok(())
+/// Overwrites single rows of an order file in place. Positions are expected in
+/// increasing order, so the file is only seeked where they skip ahead.
+pub struct OrderCachePatcher {
+    writer: BufWriter<File>,
+    next: Option<usize>,
+}
+
+impl OrderCachePatcher {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::options()
+            .write(true)
+            .open(path)
+            .map_err(|err| err.to_string())?;
+        Ok(OrderCachePatcher {
+            writer: BufWriter::new(file),
+            next: None,
+        })
+    }
+
+    pub fn set(&mut self, position: usize, row: usize) -> Result<(), String> {
+        if self.next != Some(position) {
+            self.writer
+                .seek(SeekFrom::Start(ORDER_HEADER_LEN + position as u64 * 8))
+                .map_err(|err| err.to_string())?;
+        }
+        write_u64(&mut self.writer, row as u64)?;
+        self.next = Some(position + 1);
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        self.writer.flush().map_err(|err| err.to_string())
+    }
 }
 
 fn read_u32(reader: &mut impl Read) -> Result<u32, String> {
@@ -237,9 +371,12 @@ fn write_u64(writer: &mut impl Write, value: u64) -> Result<(), String> {
 #[cfg(test)]
 mod tests {
     use super::{
-        cache_key, offsets_cache_path, order_cache_path, read_offsets_cache, read_order_cache,
-        write_offsets_cache, write_order_cache,
+        cache_key, import_cache_path, materialize_import, offsets_cache_path, order_cache_path,
+        read_offsets_cache, read_order_cache, read_order_range, write_offsets_cache,
+        OrderCachePatcher, OrderCacheWriter, LAST_USED_MARKER,
     };
+    use crate::csv_sort::{EmptyPlacement, SortMode, SortOptions, SortSpec};
+    use std::io::Write;
 
     #[test]
     fn offsets_cache_round_trip() {
@@ -265,19 +402,77 @@ mod tests {
         std::fs::write(&file_path, b"col1,col2\n1,2\n3,4\n").expect("write csv");
 
         let key = cache_key(file_path.to_str().unwrap(), None).expect("cache key");
-        let order_path = order_cache_path(dir.path(), key, 2, true);
+        let spec = SortSpec::single(2, true, SortOptions::default());
+        let order_path = order_cache_path(dir.path(), key, &spec);
         let order = vec![2usize, 0, 1];
 
-        write_order_cache(&order_path, key, 2, true, &order).expect("write order");
-        let loaded = read_order_cache(&order_path, key, 2, true)
+        let file = std::fs::File::create(&order_path).expect("create order");
+        let mut writer = OrderCacheWriter::new(file, key, &spec).expect("order writer");
+        for row in &order {
+            writer.push(*row).expect("write order");
+        }
+        writer.finish().expect("finish order");
+        let loaded = read_order_cache(&order_path, key, &spec)
             .expect("read order")
             .expect("order");
         assert_eq!(loaded, order);
 
-        let wrong_column = read_order_cache(&order_path, key, 1, true).expect("read order");
-        assert!(wrong_column.is_none());
+        let mut patcher = OrderCachePatcher::open(&order_path).expect("patch order");
+        patcher.set(1, 1).expect("patch row");
+        patcher.set(2, 0).expect("patch row");
+        patcher.finish().expect("finish patch");
+        let loaded = read_order_cache(&order_path, key, &spec)
+            .expect("read order")
+            .expect("order");
+        assert_eq!(loaded, vec![2, 1, 0]);
+        let range = read_order_range(&order_path, 1, 2).expect("read order range");
+        assert_eq!(range, vec![1, 0]);
+
+        let wrong_column = SortSpec::single(1, true, SortOptions::default());
+        let loaded = read_order_cache(&order_path, key, &wrong_column).expect("read order");
+        assert!(loaded.is_none());
+
+        let wrong_direction = SortSpec::single(2, false, SortOptions::default());
+        let loaded = read_order_cache(&order_path, key, &wrong_direction).expect("read order");
+        assert!(loaded.is_none());
+
+        let numeric = SortSpec::single(
+            2,
+            true,
+            SortOptions {
+                mode: SortMode::Numeric,
+                empty_placement: EmptyPlacement::Last,
+            },
+        );
+        assert_ne!(order_cache_path(dir.path(), key, &numeric), order_path);
+        let loaded = read_order_cache(&order_path, key, &numeric).expect("read order");
+        assert!(loaded.is_none());
+    }
 
-        let wrong_direction = read_order_cache(&order_path, key, 2, false).expect("read order");
-        assert!(wrong_direction.is_none());
+    #[test]
+    fn imports_are_written_once_per_variant() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let source = dir.path().join("data.db");
+        std::fs::write(&source, b"db").expect("write source");
+        let source = source.to_str().unwrap();
+
+        let table = import_cache_path(dir.path(), source, "table", "data-a/b.csv").unwrap();
+        let query = import_cache_path(dir.path(), source, "query", "data-a/b.csv").unwrap();
+        assert_ne!(table.parent(), query.parent());
+        assert_eq!(table.file_name().unwrap(), "data-a_b.csv");
+
+        materialize_import(&table, |out| {
+            out.write_all(b"a\n1\n").map_err(|e| e.to_string())
+        })
+        .expect("write import");
+        let modified = std::fs::metadata(&table).unwrap().modified().unwrap();
+        materialize_import(&table, |_| Err("not rewritten".to_string())).expect("reuse import");
+        assert_eq!(std::fs::read(&table).unwrap(), b"a\n1\n");
+        // Reuse must not change the copy's cache key
+        assert_eq!(
+            std::fs::metadata(&table).unwrap().modified().unwrap(),
+            modified
+        );
+        assert!(table.parent().unwrap().join(LAST_USED_MARKER).exists());
     }
 }
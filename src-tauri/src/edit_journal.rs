@@ -0,0 +1,627 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// Stable identity of a column, so edits keep pointing at the same column when
+/// columns are added or removed in front of it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum ColumnId {
+    File(usize),
+    Added(usize),
+}
+
+#[derive(Clone, Debug)]
+struct Column {
+    id: ColumnId,
+    /// Header of an added column; file columns take theirs from the file
+    name: Option<String>,
+}
+
+/// An edit requested by the frontend. Rows are file rows followed by inserted rows,
+/// and columns are positions in the edited layout.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Edit {
+    SetCell {
+        row: usize,
+        column: usize,
+        value: String,
+    },
+    DeleteRows {
+        rows: Vec<usize>,
+    },
+    RestoreRows {
+        rows: Vec<usize>,
+    },
+    InsertRow {
+        values: Option<Vec<String>>,
+    },
+    AddColumn {
+        name: String,
+        position: Option<usize>,
+    },
+    RemoveColumn {
+        column: usize,
+    },
+}
+
+/// A journal entry, holding what is needed to both redo and undo it
+#[derive(Clone, Debug)]
+enum Entry {
+    SetCell {
+        row: usize,
+        column: ColumnId,
+        value: Option<String>,
+        previous: Option<String>,
+    },
+    DeleteRows(Vec<usize>),
+    RestoreRows(Vec<usize>),
+    InsertRow {
+        row: usize,
+        cells: HashMap<ColumnId, String>,
+    },
+    AddColumn {
+        position: usize,
+        column: Column,
+    },
+    RemoveColumn {
+        position: usize,
+        column: Column,
+    },
+}
+
+/// What an applied, undone or redone edit touched, so views derived from row
+/// contents can be brought up to date without rescanning every row
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Rows(Vec<usize>),
+    /// Columns were added or removed, moving every row's cells
+    Layout,
+}
+
+impl Entry {
+    fn change(&self) -> Change {
+        match self {
+            Entry::SetCell { row, .. } | Entry::InsertRow { row, .. } => Change::Rows(vec![*row]),
+            Entry::DeleteRows(rows) | Entry::RestoreRows(rows) => Change::Rows(rows.clone()),
+            Entry::AddColumn { .. } | Entry::RemoveColumn { .. } => Change::Layout,
+        }
+    }
+}
+
+/// Edit state reported to the frontend after every change
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditState {
+    pub headers: Vec<String>,
+    /// File rows plus inserted rows
+    pub rows: usize,
+    pub deleted_rows: Vec<usize>,
+    /// Whether saving would write anything different from the file
+    pub edited: bool,
+    pub can_undo: bool,
+    pub can_redo: bool,
+    /// Rows of the active filter, which follows the edits
+    pub filtered_rows: Option<usize>,
+}
+
+/// Pending edits of one document, applied on top of the rows read from the file.
+/// Inserted rows get ids after the last file row, so file row ids never shift.
+#[derive(Clone, Debug)]
+pub struct EditJournal {
+    file_rows: usize,
+    file_columns: usize,
+    columns: Vec<Column>,
+    /// Whether `columns` is still the file's own column order
+    identity_layout: bool,
+    next_added: usize,
+    inserted: usize,
+    cells: HashMap<usize, HashMap<ColumnId, String>>,
+    deleted: BTreeSet<usize>,
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+}
+
+impl EditJournal {
+    pub fn new(file_rows: usize, file_columns: usize) -> Self {
+        EditJournal {
+            file_rows,
+            file_columns,
+            columns: (0..file_columns)
+                .map(|idx| Column {
+                    id: ColumnId::File(idx),
+                    name: None,
+                })
+                .collect(),
+            identity_layout: true,
+            next_added: 0,
+            inserted: 0,
+            cells: HashMap::new(),
+            deleted: BTreeSet::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    pub fn file_rows(&self) -> usize {
+        self.file_rows
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.file_rows + self.inserted
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.identity_layout
+            || self.inserted > 0
+            || !self.cells.is_empty()
+            || !self.deleted.is_empty()
+    }
+
+    pub fn is_deleted(&self, row: usize) -> bool {
+        self.deleted.contains(&row)
+    }
+
+    /// Headers of the edited layout
+    pub fn headers(&self, file_headers: &[String]) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|column| match (&column.name, column.id) {
+                (Some(name), _) => name.clone(),
+                (None, ColumnId::File(idx)) => file_headers.get(idx).cloned().unwrap_or_default(),
+                (None, ColumnId::Added(_)) => String::new(),
+            })
+            .collect()
+    }
+
+    pub fn state(&self, file_headers: &[String]) -> EditState {
+        EditState {
+            headers: self.headers(file_headers),
+            rows: self.row_count(),
+            deleted_rows: self.deleted.iter().copied().collect(),
+            edited: self.has_changes(),
+            can_undo: !self.undo.is_empty(),
+            can_redo: !self.redo.is_empty(),
+            filtered_rows: None,
+        }
+    }
+
+    /// File column shown at `column` of the edited layout, None for added columns
+    pub fn file_column(&self, column: usize) -> Option<usize> {
+        match self.columns.get(column)?.id {
+            ColumnId::File(idx) => Some(idx),
+            ColumnId::Added(_) => None,
+        }
+    }
+
+    /// Records an edit, dropping anything that could have been redone. `original` is
+    /// the unedited text of the cell a `SetCell` targets (empty for inserted rows and
+    /// added columns); setting a cell back to it removes the edit of that cell.
+    /// Returns None when the edit changed nothing.
+    pub fn apply(&mut self, edit: Edit, original: &str) -> Result<Option<Change>, String> {
+        let entry = match edit {
+            Edit::SetCell { row, column, value } => {
+                self.check_row(row)?;
+                let column = self.column_id(column)?;
+                let previous = self.cell(row, column).cloned();
+                let value = (value != original).then_some(value);
+                if previous == value {
+                    return Ok(None);
+                }
+                Entry::SetCell {
+                    row,
+                    column,
+                    value,
+                    previous,
+                }
+            }
+            Edit::DeleteRows { rows } => {
+                let rows = self.unique_rows(rows, |row| !self.is_deleted(row));
+                if rows.is_empty() {
+                    return Ok(None);
+                }
+                Entry::DeleteRows(rows)
+            }
+            Edit::RestoreRows { rows } => {
+                let rows = self.unique_rows(rows, |row| self.is_deleted(row));
+                if rows.is_empty() {
+                    return Ok(None);
+                }
+                Entry::RestoreRows(rows)
+            }
+            Edit::InsertRow { values } => {
+                let cells = values
+                    .unwrap_or_default()
+                    .into_iter()
+                    .zip(self.columns.iter())
+                    .filter(|(value, _)| !value.is_empty())
+                    .map(|(value, column)| (column.id, value))
+                    .collect();
+                Entry::InsertRow {
+                    row: self.row_count(),
+                    cells,
+                }
+            }
+            Edit::AddColumn { name, position } => {
+                let position = position.unwrap_or(self.columns.len());
+                if position > self.columns.len() {
+                    return Err(format!("Column position {position} is out of range"));
+                }
+                let id = ColumnId::Added(self.next_added);
+                self.next_added += 1;
+                Entry::AddColumn {
+                    position,
+                    column: Column {
+                        id,
+                        name: Some(name),
+                    },
+                }
+            }
+            Edit::RemoveColumn { column } => {
+                self.column_id(column)?;
+                if self.columns.len() == 1 {
+                    return Err("Cannot remove the last column".to_string());
+                }
+                Entry::RemoveColumn {
+                    position: column,
+                    column: self.columns[column].clone(),
+                }
+            }
+        };
+        self.perform(&entry);
+        let change = entry.change();
+        self.undo.push(entry);
+        self.redo.clear();
+        Ok(Some(change))
+    }
+
+    /// Reverts the most recent edit. Returns None when there was nothing to undo.
+    pub fn undo(&mut self) -> Option<Change> {
+        let entry = self.undo.pop()?;
+        self.revert(&entry);
+        let change = entry.change();
+        self.redo.push(entry);
+        Some(change)
+    }
+
+    /// Re-applies the most recently undone edit. Returns None when there was nothing to redo.
+    pub fn redo(&mut self) -> Option<Change> {
+        let entry = self.redo.pop()?;
+        self.perform(&entry);
+        let change = entry.change();
+        self.undo.push(entry);
+        Some(change)
+    }
+
+    /// Applies edits to one row. `file_row` is the row as read from the file,
+    /// or empty for inserted rows.
+    pub fn apply_row(&self, row: usize, file_row: Vec<String>) -> Vec<String> {
+        let edits = self.cells.get(&row);
+        if self.identity_layout {
+            let Some(edits) = edits else {
+                return file_row;
+            };
+            let mut file_row = file_row;
+            if file_row.len() < self.file_columns {
+                file_row.resize(self.file_columns, String::new());
+            }
+            for (column, value) in edits {
+                if let ColumnId::File(idx) = column {
+                    file_row[*idx] = value.clone();
+                }
+            }
+            return file_row;
+        }
+        self.columns
+            .iter()
+            .map(|column| {
+                edits
+                    .and_then(|edits| edits.get(&column.id))
+                    .or(match column.id {
+                        ColumnId::File(idx) => file_row.get(idx),
+                        ColumnId::Added(_) => None,
+                    })
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Applies edits to consecutive file rows starting at row `start`
+    pub fn apply_rows(&self, start: usize, file_rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+        file_rows
+            .into_iter()
+            .enumerate()
+            .map(|(idx, row)| self.apply_row(start + idx, row))
+            .collect()
+    }
+
+    /// Applies edits to the rows `rows`, given their file data in the same order.
+    /// Inserted rows are read from the file as empty rows.
+    pub fn apply_indexed(&self, rows: &[usize], file_rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+        rows.iter()
+            .zip(file_rows)
+            .map(|(&row, file_row)| self.apply_row(row, file_row))
+            .collect()
+    }
+
+    /// Inserted rows whose ids fall in `start..end`
+    pub fn inserted_rows(&self, start: usize, end: usize) -> Vec<Vec<String>> {
+        (start.max(self.file_rows)..end.min(self.row_count()))
+            .map(|row| self.apply_row(row, Vec::new()))
+            .collect()
+    }
+
+    fn check_row(&self, row: usize) -> Result<(), String> {
+        if row < self.row_count() {
+            Ok(())
+        } else {
+            Err(format!("Row {row} is out of range"))
+        }
+    }
+
+    fn column_id(&self, column: usize) -> Result<ColumnId, String> {
+        self.columns
+            .get(column)
+            .map(|column| column.id)
+            .ok_or_else(|| format!("Column {column} is out of range"))
+    }
+
+    fn cell(&self, row: usize, column: ColumnId) -> Option<&String> {
+        self.cells.get(&row).and_then(|cells| cells.get(&column))
+    }
+
+    /// Sorted, de-duplicated in-range rows passing `keep`
+    fn unique_rows(&self, mut rows: Vec<usize>, keep: impl Fn(usize) -> bool) -> Vec<usize> {
+        rows.sort_unstable();
+        rows.dedup();
+        rows.retain(|&row| row < self.row_count() && keep(row));
+        rows
+    }
+
+    fn set_cell(&mut self, row: usize, column: ColumnId, value: Option<&String>) {
+        match value {
+            Some(value) => {
+                self.cells
+                    .entry(row)
+                    .or_default()
+                    .insert(column, value.clone());
+            }
+            None => {
+                if let Some(cells) = self.cells.get_mut(&row) {
+                    cells.remove(&column);
+                    if cells.is_empty() {
+                        self.cells.remove(&row);
+                    }
+                }
+            }
+        }
+    }
+
+    fn perform(&mut self, entry: &Entry) {
+        match entry {
+            Entry::SetCell {
+                row, column, value, ..
+            } => self.set_cell(*row, *column, value.as_ref()),
+            Entry::DeleteRows(rows) => self.deleted.extend(rows.iter().copied()),
+            Entry::RestoreRows(rows) => {
+                for row in rows {
+                    self.deleted.remove(row);
+                }
+            }
+            Entry::InsertRow { row, cells } => {
+                self.inserted += 1;
+                if !cells.is_empty() {
+                    self.cells.insert(*row, cells.clone());
+                }
+            }
+            Entry::AddColumn { position, column } => {
+                self.columns.insert(*position, column.clone());
+                self.refresh_layout();
+            }
+            Entry::RemoveColumn { position, .. } => {
+                self.columns.remove(*position);
+                self.refresh_layout();
+            }
+        }
+    }
+
+    fn revert(&mut self, entry: &Entry) {
+        match entry {
+            Entry::SetCell {
+                row,
+                column,
+                previous,
+                ..
+            } => self.set_cell(*row, *column, previous.as_ref()),
+            Entry::DeleteRows(rows) => {
+                for row in rows {
+                    self.deleted.remove(row);
+                }
+            }
+            Entry::RestoreRows(rows) => self.deleted.extend(rows.iter().copied()),
+            Entry::InsertRow { row, .. } => {
+                // Later edits to the row were undone before this entry
+                self.inserted -= 1;
+                self.cells.remove(row);
+            }
+            Entry::AddColumn { position, .. } => {
+                self.columns.remove(*position);
+                self.refresh_layout();
+            }
+            Entry::RemoveColumn { position, column } => {
+                self.columns.insert(*position, column.clone());
+                self.refresh_layout();
+            }
+        }
+    }
+
+    fn refresh_layout(&mut self) {
+        self.identity_layout = self.columns.len() == self.file_columns
+            && self
+                .columns
+                .iter()
+                .enumerate()
+                .all(|(idx, column)| column.id == ColumnId::File(idx));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn set_cell(row: usize, column: usize, value: &str) -> Edit {
+        Edit::SetCell {
+            row,
+            column,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn set_cell_undo_redo() {
+        let mut journal = EditJournal::new(3, 2);
+        assert!(journal.apply(set_cell(1, 0, "x"), "a").unwrap().is_some());
+        assert!(journal.apply(set_cell(1, 0, "y"), "a").unwrap().is_some());
+        assert!(journal.apply(set_cell(1, 0, "y"), "a").unwrap().is_none());
+        assert_eq!(journal.apply_row(1, row(&["a", "b"])), row(&["y", "b"]));
+        assert_eq!(journal.apply_row(0, row(&["a", "b"])), row(&["a", "b"]));
+
+        assert!(journal.undo().is_some());
+        assert_eq!(journal.apply_row(1, row(&["a", "b"])), row(&["x", "b"]));
+        assert!(journal.undo().is_some());
+        assert_eq!(journal.apply_row(1, row(&["a", "b"])), row(&["a", "b"]));
+        assert!(!journal.has_changes());
+        assert!(journal.undo().is_none());
+
+        assert!(journal.redo().is_some());
+        assert_eq!(journal.apply_row(1, row(&["a", "b"])), row(&["x", "b"]));
+        journal.apply(set_cell(2, 1, "z"), "").unwrap();
+        assert!(journal.redo().is_none());
+        assert!(journal.apply(set_cell(3, 0, "x"), "").is_err());
+        assert!(journal.apply(set_cell(0, 2, "x"), "").is_err());
+    }
+
+    #[test]
+    fn setting_a_cell_back_removes_its_edit() {
+        let mut journal = EditJournal::new(2, 2);
+        assert_eq!(
+            journal.apply(set_cell(1, 0, "x"), "a").unwrap(),
+            Some(Change::Rows(vec![1]))
+        );
+        assert!(journal.apply(set_cell(1, 0, "a"), "a").unwrap().is_some());
+        assert!(!journal.has_changes());
+        assert!(journal.apply(set_cell(1, 0, "a"), "a").unwrap().is_none());
+        assert!(journal.undo().is_some());
+        assert_eq!(journal.apply_row(1, row(&["a", "b"])), row(&["x", "b"]));
+
+        journal.apply(Edit::InsertRow { values: None }, "").unwrap();
+        assert!(journal.apply(set_cell(2, 1, ""), "").unwrap().is_none());
+        assert_eq!(
+            journal
+                .apply(
+                    Edit::AddColumn {
+                        name: "new".to_string(),
+                        position: None,
+                    },
+                    "",
+                )
+                .unwrap(),
+            Some(Change::Layout)
+        );
+    }
+
+    #[test]
+    fn delete_and_restore_rows() {
+        let mut journal = EditJournal::new(5, 1);
+        journal
+            .apply(
+                Edit::DeleteRows {
+                    rows: vec![3, 1, 3, 9],
+                },
+                "",
+            )
+            .unwrap();
+        assert_eq!(journal.state(&[]).deleted_rows, vec![1, 3]);
+        assert!(journal
+            .apply(Edit::DeleteRows { rows: vec![1] }, "")
+            .unwrap()
+            .is_none());
+        journal
+            .apply(Edit::RestoreRows { rows: vec![1, 2] }, "")
+            .unwrap();
+        assert_eq!(journal.state(&[]).deleted_rows, vec![3]);
+        journal.undo();
+        assert_eq!(journal.state(&[]).deleted_rows, vec![1, 3]);
+        journal.undo();
+        assert!(journal.state(&[]).deleted_rows.is_empty());
+    }
+
+    #[test]
+    fn inserted_rows_follow_file_rows() {
+        let mut journal = EditJournal::new(2, 2);
+        journal
+            .apply(
+                Edit::InsertRow {
+                    values: Some(row(&["p", "q"])),
+                },
+                "",
+            )
+            .unwrap();
+        journal.apply(Edit::InsertRow { values: None }, "").unwrap();
+        journal.apply(set_cell(3, 1, "r"), "").unwrap();
+        assert_eq!(journal.row_count(), 4);
+        assert_eq!(
+            journal.inserted_rows(1, 10),
+            vec![row(&["p", "q"]), row(&["", "r"])]
+        );
+        assert_eq!(
+            journal.apply_indexed(&[3, 0], vec![Vec::new(), row(&["a", "b"])]),
+            vec![row(&["", "r"]), row(&["a", "b"])]
+        );
+
+        journal.undo();
+        journal.undo();
+        assert_eq!(journal.row_count(), 3);
+        assert_eq!(journal.inserted_rows(0, 10), vec![row(&["p", "q"])]);
+    }
+
+    #[test]
+    fn columns_keep_their_edits_across_layout_changes() {
+        let headers = row(&["a", "b", "c"]);
+        let mut journal = EditJournal::new(1, 3);
+        journal.apply(set_cell(0, 2, "C"), "").unwrap();
+        journal
+            .apply(
+                Edit::AddColumn {
+                    name: "new".to_string(),
+                    position: Some(0),
+                },
+                "",
+            )
+            .unwrap();
+        journal.apply(set_cell(0, 0, "N"), "").unwrap();
+        journal.apply(Edit::RemoveColumn { column: 2 }, "").unwrap();
+        assert_eq!(journal.headers(&headers), row(&["new", "a", "c"]));
+        assert_eq!(
+            journal.apply_row(0, row(&["1", "2", "3"])),
+            row(&["N", "1", "C"])
+        );
+
+        journal.undo();
+        assert_eq!(
+            journal.apply_row(0, row(&["1", "2", "3"])),
+            row(&["N", "1", "2", "C"])
+        );
+        journal.undo();
+        journal.undo();
+        assert_eq!(journal.headers(&headers), headers);
+        assert!(journal.has_changes());
+        journal.undo();
+        assert!(!journal.has_changes());
+        assert!(journal.apply(Edit::RemoveColumn { column: 3 }, "").is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use crate::csv_handler::CANCELLED_MESSAGE;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::async_runtime::JoinHandle;
+
+/// Finished jobs kept around so the UI can show how they ended
+const MAX_FINISHED_JOBS: usize = 20;
+const PROGRESS_EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    OffsetBuild,
+    IndexBuild,
+    Sort,
+    Duplicates,
+    Profile,
+    ValueCounts,
+    Filter,
+    Aggregate,
+    Export,
+    Import,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: u64,
+    pub kind: JobKind,
+    pub doc_id: String,
+    pub label: String,
+    pub status: JobStatus,
+    pub processed: usize,
+    pub total: Option<usize>,
+    pub error: Option<String>,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+/// Called with the latest job state whenever a job is announced or updated
+pub type JobNotifier = Arc<dyn Fn(&JobInfo) + Send + Sync>;
+
+struct JobEntry {
+    info: JobInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+pub struct JobRegistry {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, JobEntry>>,
+    notifier: Mutex<Option<JobNotifier>>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            jobs: Mutex::new(HashMap::new()),
+            notifier: Mutex::new(None),
+        }
+    }
+
+    pub fn set_notifier(&self, notifier: JobNotifier) {
+        *self.notifier.lock().unwrap() = Some(notifier);
+    }
+
+    fn notify(&self, info: &JobInfo) {
+        let notifier = self.notifier.lock().unwrap().clone();
+        if let Some(notifier) = notifier {
+            notifier(info);
+        }
+    }
+
+    /// Registers a running job and announces it to the notifier
+    pub fn start(
+        self: &Arc<Self>,
+        kind: JobKind,
+        doc_id: &str,
+        label: impl Into<String>,
+    ) -> JobHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let info = JobInfo {
+            id,
+            kind,
+            doc_id: doc_id.to_string(),
+            label: label.into(),
+            status: JobStatus::Running,
+            processed: 0,
+            total: None,
+            error: None,
+            started_at: now_millis(),
+            finished_at: None,
+        };
+        self.notify(&info);
+        self.jobs.lock().unwrap().insert(
+            id,
+            JobEntry {
+                info,
+                cancel: cancel.clone(),
+            },
+        );
+        JobHandle {
+            registry: self.clone(),
+            id,
+            cancel,
+            last_emit: Mutex::new(Instant::now()),
+            finished: AtomicBool::new(false),
+            pass: Mutex::new((0, 1)),
+        }
+    }
+
+    /// Runs `work` as a job on the blocking thread pool and resolves to its result
+    pub fn run<T, F>(
+        self: &Arc<Self>,
+        kind: JobKind,
+        doc_id: &str,
+        label: impl Into<String>,
+        work: F,
+    ) -> impl Future<Output = Result<T, String>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&JobHandle) -> Result<T, String> + Send + 'static,
+    {
+        let handle = self.launch(kind, doc_id, label, work);
+        async move { handle.await.map_err(|err| err.to_string())? }
+    }
+
+    /// Like `run`, for background work nobody waits on
+    pub fn spawn<F>(
+        self: &Arc<Self>,
+        kind: JobKind,
+        doc_id: &str,
+        label: impl Into<String>,
+        work: F,
+    ) where
+        F: FnOnce(&JobHandle) -> Result<(), String> + Send + 'static,
+    {
+        drop(self.launch(kind, doc_id, label, work));
+    }
+
+    fn launch<T, F>(
+        self: &Arc<Self>,
+        kind: JobKind,
+        doc_id: &str,
+        label: impl Into<String>,
+        work: F,
+    ) -> JoinHandle<Result<T, String>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&JobHandle) -> Result<T, String> + Send + 'static,
+    {
+        let job = self.start(kind, doc_id, label);
+        tauri::async_runtime::spawn_blocking(move || {
+            let result = work(&job);
+            job.finish(&result);
+            result
+        })
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        let jobs = self.jobs.lock().unwrap();
+        let mut list: Vec<JobInfo> = jobs.values().map(|entry| entry.info.clone()).collect();
+        list.sort_by_key(|info| info.id);
+        list
+    }
+
+    /// Flags a running job for cancellation; returns false if it is unknown or already done
+    pub fn cancel(&self, id: u64) -> bool {
+        let jobs = self.jobs.lock().unwrap();
+        match jobs.get(&id) {
+            Some(entry) if entry.info.status == JobStatus::Running => {
+                entry.cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Cancels every running job working on the given document
+    pub fn cancel_document(&self, doc_id: &str) {
+        let jobs = self.jobs.lock().unwrap();
+        for entry in jobs.values() {
+            if entry.info.doc_id == doc_id && entry.info.status == JobStatus::Running {
+                entry.cancel.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn update(&self, id: u64, apply: impl FnOnce(&mut JobInfo)) -> Option<JobInfo> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let info = jobs.get_mut(&id).map(|entry| {
+            apply(&mut entry.info);
+            entry.info.clone()
+        });
+        self.prune(&mut jobs);
+        info
+    }
+
+    fn prune(&self, jobs: &mut HashMap<u64, JobEntry>) {
+        let mut finished: Vec<u64> = jobs
+            .values()
+            .filter(|entry| entry.info.status != JobStatus::Running)
+            .map(|entry| entry.info.id)
+            .collect();
+        if finished.len() <= MAX_FINISHED_JOBS {
+            return;
+        }
+        finished.sort_unstable();
+        for id in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+            jobs.remove(id);
+        }
+    }
+}
+
+/// Handle held by the code doing the work; marks the job failed if dropped unfinished
+pub struct JobHandle {
+    registry: Arc<JobRegistry>,
+    id: u64,
+    cancel: Arc<AtomicBool>,
+    last_emit: Mutex<Instant>,
+    finished: AtomicBool,
+    /// Current pass and number of passes sharing the progress range
+    pass: Mutex<(usize, usize)>,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn cancel_flag(&self) -> &Arc<AtomicBool> {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED_MESSAGE.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Starts pass `index` of `count` passes over the same work, which split the
+    /// progress range evenly so it does not restart at zero for each pass
+    pub fn begin_pass(&self, index: usize, count: usize) {
+        *self.pass.lock().unwrap() = (index, count.max(1));
+    }
+
+    /// Records progress within the current pass; notifications are throttled
+    pub fn set_progress(&self, processed: usize, total: Option<usize>) {
+        let (pass, passes) = *self.pass.lock().unwrap();
+        let (processed, total) = match total {
+            Some(total) => (pass * total + processed.min(total), Some(passes * total)),
+            None => (processed, None),
+        };
+        let info = self.registry.update(self.id, |info| {
+            info.processed = processed;
+            info.total = total;
+        });
+        let mut last_emit = self.last_emit.lock().unwrap();
+        if last_emit.elapsed() < PROGRESS_EMIT_INTERVAL {
+            return;
+        }
+        *last_emit = Instant::now();
+        if let Some(info) = info {
+            self.registry.notify(&info);
+        }
+    }
+
+    /// Records the outcome of the job from its result
+    pub fn finish<T>(&self, result: &Result<T, String>) {
+        match result {
+            Ok(_) => self.close(JobStatus::Completed, None),
+            Err(err) if self.is_cancelled() || err == CANCELLED_MESSAGE => {
+                self.close(JobStatus::Cancelled, None)
+            }
+            Err(err) => self.close(JobStatus::Failed, Some(err.clone())),
+        }
+    }
+
+    fn close(&self, status: JobStatus, error: Option<String>) {
+        if self.finished.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let info = self.registry.update(self.id, |info| {
+            info.status = status;
+            info.error = error;
+            info.finished_at = Some(now_millis());
+        });
+        if let Some(info) = info {
+            self.registry.notify(&info);
+        }
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        if self.is_cancelled() {
+            self.close(JobStatus::Cancelled, None);
+        } else {
+            self.close(
+                JobStatus::Failed,
+                Some("Job ended without a result".to_string()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_lifecycle_and_cancel() {
+        let registry = Arc::new(JobRegistry::new());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        registry.set_notifier(Arc::new(move |info: &JobInfo| {
+            sink.lock().unwrap().push(info.status);
+        }));
+
+        let done = registry.start(JobKind::Sort, "a", "sort");
+        done.set_progress(5, Some(10));
+        done.finish(&Ok::<(), String>(()));
+
+        let running = registry.start(JobKind::IndexBuild, "b", "index");
+        assert!(registry.cancel(running.id()));
+        assert!(!registry.cancel(done.id()));
+        assert!(running.check().is_err());
+        drop(running);
+
+        let list = registry.list();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].status, JobStatus::Completed);
+        assert_eq!(list[0].processed, 5);
+        assert_eq!(list[1].status, JobStatus::Cancelled);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                JobStatus::Running,
+                JobStatus::Completed,
+                JobStatus::Running,
+                JobStatus::Cancelled
+            ]
+        );
+
+        let other = registry.start(JobKind::Duplicates, "b", "dupes");
+        registry.cancel_document("a");
+        assert!(other.check().is_ok());
+        registry.cancel_document("b");
+        assert!(other.check().is_err());
+        drop(other);
+    }
+
+    #[test]
+    fn passes_share_one_progress_range() {
+        let registry = Arc::new(JobRegistry::new());
+        let job = registry.start(JobKind::Export, "a", "export");
+        job.begin_pass(0, 2);
+        job.set_progress(10, Some(10));
+        assert_eq!(registry.list()[0].processed, 10);
+        assert_eq!(registry.list()[0].total, Some(20));
+        job.begin_pass(1, 2);
+        job.set_progress(5, Some(10));
+        assert_eq!(registry.list()[0].processed, 15);
+        job.finish(&Ok::<(), String>(()));
+    }
+
+    #[test]
+    fn finished_jobs_are_pruned() {
+        let registry = Arc::new(JobRegistry::new());
+        for _ in 0..MAX_FINISHED_JOBS + 5 {
+            let job = registry.start(JobKind::Duplicates, "a", "dupes");
+            job.finish(&Ok::<(), String>(()));
+        }
+        let list = registry.list();
+        assert_eq!(list.len(), MAX_FINISHED_JOBS);
+        assert_eq!(list[0].id, 6);
+    }
+}
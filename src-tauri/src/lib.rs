@@ -1,36 +1,95 @@
+mod csv_aggregate;
 mod csv_cache;
+mod csv_columnar;
+mod csv_compression;
+mod csv_export;
+mod csv_filter;
 mod csv_handler;
+mod csv_jsonl;
 mod csv_mmap;
+mod csv_profile;
+mod csv_sort;
+mod csv_sqlite;
+mod csv_workbook;
+mod csv_zip;
 mod disk_cache;
+mod edit_journal;
+mod jobs;
+use csv_aggregate::{
+    Aggregate, AggregatePlan, AggregateTable, GroupTable, PivotPlan, PivotTable,
+    DEFAULT_PIVOT_COLUMNS,
+};
 use csv_cache::CsvCache;
+use csv_columnar::{columnar_writer, SchemaInference};
+use csv_compression::{
+    decompress, decompressed_name, has_compressed_extension, sniff_compression, Compression,
+};
+use csv_export::{
+    project_row, row_writer, write_atomically, CsvExportOptions, ExportFormat, ExportOptions,
+    RowSource, RowWriter,
+};
+use csv_filter::{equals_expression, parse_filter};
 use csv_handler::{
-    apply_parse_overrides, build_reader, build_row_offsets, build_row_offsets_mmap, decode_record,
-    default_parse_settings, detect_parse_settings, get_headers, parse_info_from_settings,
-    read_chunk, read_chunk_mmap, read_chunk_with_offsets, read_chunk_with_offsets_mmap,
-    read_rows_by_index, read_rows_by_index_mmap, search_range_with_offsets,
-    search_range_with_offsets_mmap, settings_cache_hash, ParseInfo, ParseOverrides, ParseSettings,
-    ParseWarning, MAX_WARNING_COUNT,
+    apply_parse_overrides, build_reader, build_row_offsets, build_row_offsets_from,
+    build_row_offsets_mmap, check_fixed_width_encoding, decode_record, default_parse_settings,
+    detect_parse_settings, filter_range_with_offsets, filter_range_with_offsets_mmap, get_headers,
+    parse_info_from_settings, read_chunk, read_chunk_mmap, read_chunk_with_offsets,
+    read_chunk_with_offsets_mmap, read_rows_by_index, read_rows_by_index_mmap,
+    search_range_with_offsets, search_range_with_offsets_mmap, settings_cache_hash, CellMatcher,
+    ParseInfo, ParseOverrides, ParseSettings, ParseWarning, CANCELLED_MESSAGE, MAX_WARNING_COUNT,
 };
 use csv_mmap::open_mmap_if_large;
+use csv_profile::{
+    sort_value_counts, ColumnProfile, ColumnProfiler, ValueCount, ValueCountOrder,
+    DEFAULT_TOP_VALUES,
+};
+use csv_sort::{
+    parse_sort_value, EmptyPlacement, ExternalSorter, SortKey, SortKeyInput, SortMode, SortOptions,
+    SortSpec, SortValue, DEFAULT_SORT_MEMORY_BUDGET, SORT_VALUE_MAX_LEN,
+};
+use csv_sqlite::{
+    import_query, list_tables, write_query_csv, SqliteExportOptions, SqliteRowWriter, SqliteTable,
+};
+use csv_workbook::{
+    has_workbook_extension, list_sheets, sheet_file_name, sheet_overrides, sheet_settings,
+    workbook_kind, write_sheet_csv, WorkbookKind,
+};
+use csv_zip::{extract_member, is_zip, list_members, member_file_name, single_member, ZipMember};
 use disk_cache::{
-    cache_key, ensure_cache_dir, offsets_cache_path, order_cache_path, prune_cache_dir,
-    read_offsets_cache, read_order_cache, write_offsets_cache, write_order_cache,
+    cache_key, ensure_cache_dir, import_cache_path, materialize_import, offsets_cache_path,
+    order_cache_path, prune_cache_dir, read_offsets_cache, read_order_cache, read_order_range,
+    write_offsets_cache, OrderCachePatcher, OrderCacheWriter,
 };
+use edit_journal::{Change, Edit, EditJournal, EditState};
+use jobs::{JobHandle, JobInfo, JobKind, JobRegistry};
 use memmap2::Mmap;
 use rayon::prelude::*;
 // use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fs::OpenOptions;
-use std::io::Write;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::{Emitter, Manager, State, WebviewWindowBuilder};
+use tauri::{
+    Emitter, EventTarget, Manager, State, WebviewWindow, WebviewWindowBuilder, WindowEvent,
+};
 
 #[cfg(desktop)]
 use tauri::menu::{
     CheckMenuItemBuilder, MenuBuilder, MenuItemBuilder, MenuItemKind, SubmenuBuilder,
 };
 
+/// File open in a document, along with the archive member or sheet read from it
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentSource {
+    path: String,
+    member: Option<String>,
+}
+
 #[derive(Clone, serde::Serialize)]
 struct SortedRow {
     index: usize,
@@ -44,6 +103,16 @@ pub struct CsvMetadata {
     pub effective: ParseInfo,
     pub warnings: Vec<ParseWarning>,
     pub estimated_count: Option<usize>,
+    /// Compression of the opened file, whose rows are read from a decompressed copy
+    pub compression: Option<Compression>,
+    /// Archive member or workbook sheet the rows come from
+    pub member: Option<String>,
+    /// Sheets of the opened file, when it is a spreadsheet workbook
+    pub sheets: Option<Vec<String>>,
+    /// Sheet row (1-based) the rows start at; None starts at the first used row
+    pub header_row: Option<usize>,
+    /// Rows cannot be edited, as they come from a workbook sheet
+    pub read_only: bool,
 }
 
 /// Inverted index for fast search: maps lowercase value → row indices
@@ -59,6 +128,11 @@ struct SearchIndex {
     ready: bool,
     /// Columns skipped due to high cardinality (>500K unique values)
     skipped_columns: Vec<usize>,
+    /// Number of leading rows covered by the index
+    rows: usize,
+    /// Column and key of each cell of the last indexed row, so it can be taken out
+    /// again when it is re-read
+    last_row: Vec<(usize, Box<str>)>,
 }
 
 impl SearchIndex {
@@ -67,6 +141,8 @@ impl SearchIndex {
             columns: Vec::new(),
             ready: false,
             skipped_columns: Vec::new(),
+            rows: 0,
+            last_row: Vec::new(),
         }
     }
 
@@ -74,36 +150,387 @@ impl SearchIndex {
         self.columns.clear();
         self.ready = false;
         self.skipped_columns.clear();
+        self.rows = 0;
+        self.last_row.clear();
+    }
+
+    /// Forgets rows from `start` on, e.g. a last row that was only partly written
+    /// when it was indexed. Row ids are added in order, so they sit at the end of
+    /// each posting list.
+    fn truncate(&mut self, start: usize) {
+        if start >= self.rows {
+            return;
+        }
+        let drop_tail = |rows: &mut Vec<u32>| {
+            while rows.last().is_some_and(|&row| row as usize >= start) {
+                rows.pop();
+            }
+        };
+        if start + 1 == self.rows {
+            for (column, key) in self.last_row.drain(..) {
+                let Some(Some(col_index)) = self.columns.get_mut(column) else {
+                    continue;
+                };
+                if let Some(rows) = col_index.get_mut(&key) {
+                    drop_tail(rows);
+                    if rows.is_empty() {
+                        col_index.remove(&key);
+                    }
+                }
+            }
+        } else {
+            for col_index in self.columns.iter_mut().flatten() {
+                col_index.retain(|_, rows| {
+                    drop_tail(rows);
+                    !rows.is_empty()
+                });
+            }
+            self.last_row.clear();
+        }
+        self.rows = start;
+    }
+
+    /// Adds rows starting at `start` to the per-column indexes
+    fn add_rows(&mut self, start: usize, rows: &[Vec<String>]) {
+        for (idx, row) in rows.iter().enumerate() {
+            let row_index = (start + idx) as u32;
+            let is_last = idx + 1 == rows.len();
+            if is_last {
+                self.last_row.clear();
+            }
+
+            for (col_idx, cell) in row.iter().enumerate() {
+                if col_idx >= self.columns.len() {
+                    continue;
+                }
+
+                // Skip columns already marked as too high cardinality
+                let Some(col_index) = self.columns[col_idx].as_mut() else {
+                    continue;
+                };
+
+                // Create lowercase key, truncated for memory efficiency
+                let key: Box<str> = if cell.len() > INDEX_VALUE_MAX_LEN {
+                    truncate_utf8(cell, INDEX_VALUE_MAX_LEN)
+                        .to_lowercase()
+                        .into()
+                } else {
+                    cell.to_lowercase().into()
+                };
+                if is_last {
+                    self.last_row.push((col_idx, key.clone()));
+                }
+
+                col_index
+                    .entry(key)
+                    .or_insert_with(Vec::new)
+                    .push(row_index);
+
+                // Check cardinality limit
+                if col_index.len() > INDEX_MAX_CARDINALITY {
+                    println!(
+                        "[INDEX] Skipping column {} (too many unique values: {})",
+                        col_idx,
+                        col_index.len()
+                    );
+                    self.skipped_columns.push(col_idx);
+                    self.columns[col_idx] = None;
+                }
+            }
+        }
+        self.rows = self.rows.max(start + rows.len());
     }
 }
 
-struct AppState {
-    file_path: Mutex<Option<String>>,
+/// Rows matching the active filter expression, in file order
+struct FilterView {
+    expression: String,
+    rows: Vec<usize>,
+}
+
+/// Value counts of the most recently counted column, kept for paging
+struct ValueCountsCache {
+    column: usize,
+    counts: Vec<ValueCount>,
+    untracked_rows: usize,
+}
+
+/// Cancellation flag for a streaming request, unregistered when dropped
+struct RequestToken<'a> {
+    tokens: &'a Mutex<HashMap<u32, Arc<AtomicBool>>>,
+    request_id: u32,
+    flag: Arc<AtomicBool>,
+}
+
+impl<'a> RequestToken<'a> {
+    fn register(tokens: &'a Mutex<HashMap<u32, Arc<AtomicBool>>>, request_id: u32) -> Self {
+        Self::with_flag(tokens, request_id, Arc::new(AtomicBool::new(false)))
+    }
+
+    fn with_flag(
+        tokens: &'a Mutex<HashMap<u32, Arc<AtomicBool>>>,
+        request_id: u32,
+        flag: Arc<AtomicBool>,
+    ) -> Self {
+        tokens.lock().unwrap().insert(request_id, flag.clone());
+        Self {
+            tokens,
+            request_id,
+            flag,
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.flag.load(Ordering::Relaxed) {
+            Err(CANCELLED_MESSAGE.to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for RequestToken<'_> {
+    fn drop(&mut self) {
+        let mut tokens = self.tokens.lock().unwrap();
+        if tokens
+            .get(&self.request_id)
+            .is_some_and(|flag| Arc::ptr_eq(flag, &self.flag))
+        {
+            tokens.remove(&self.request_id);
+        }
+    }
+}
+
+/// One open file. Loading a file into a document id replaces its session wholesale,
+/// so background workers holding the old session can tell they are stale.
+struct DocumentState {
+    /// File the rows are read from
+    file_path: String,
+    /// File the user opened; a decoded copy in the cache dir is read in its place
+    /// when it is compressed or an archive
+    source_path: String,
+    member: Option<String>,
     total_rows: Mutex<usize>,
     headers: Mutex<Vec<String>>,
     cache: CsvCache,
-    sorted_order: Mutex<Option<Vec<usize>>>,
-    pending_open: Mutex<Option<String>>,
+    sorted_order: Mutex<Option<Arc<Vec<usize>>>>,
+    filter_view: Mutex<Option<FilterView>>,
+    value_counts: Mutex<Option<ValueCountsCache>>,
+    /// Result of the last aggregation, kept so it can be exported
+    aggregate_result: Mutex<Option<AggregateTable>>,
+    /// Row ids of the last completed search and duplicate check, kept for export
+    search_results: Mutex<Option<Vec<usize>>>,
+    duplicate_results: Mutex<Option<Vec<usize>>>,
     row_offsets: Mutex<Option<Vec<u64>>>,
     mmap: Mutex<Option<Arc<Mmap>>>,
     parse_settings: Mutex<ParseSettings>,
-    parse_info_detected: Mutex<ParseInfo>,
-    parse_info_effective: Mutex<ParseInfo>,
     parse_warnings: Mutex<Vec<ParseWarning>>,
     search_index: Mutex<SearchIndex>,
+    request_tokens: Mutex<HashMap<u32, Arc<AtomicBool>>>,
+    /// Stop flag of the thread following appends to the file, if any
+    follow: Mutex<Option<Arc<AtomicBool>>>,
+    follow_cursor: Mutex<FollowCursor>,
+    /// Pending edits, created on the first edit once row offsets are known
+    edits: Mutex<Option<Arc<EditJournal>>>,
+    /// Modification time of the file when the session was opened
+    modified: Option<SystemTime>,
+    /// Workbook sheets are shown as they are in the workbook and cannot be edited
+    read_only: bool,
+}
+
+impl DocumentState {
+    fn new(
+        source_path: String,
+        member: Option<String>,
+        file_path: String,
+        headers: Vec<String>,
+        settings: ParseSettings,
+        warnings: Vec<ParseWarning>,
+        read_only: bool,
+    ) -> Self {
+        let metadata = std::fs::metadata(&file_path).ok();
+        DocumentState {
+            total_rows: Mutex::new(0),
+            headers: Mutex::new(headers),
+            cache: CsvCache::new(64),
+            sorted_order: Mutex::new(None),
+            filter_view: Mutex::new(None),
+            value_counts: Mutex::new(None),
+            aggregate_result: Mutex::new(None),
+            search_results: Mutex::new(None),
+            duplicate_results: Mutex::new(None),
+            row_offsets: Mutex::new(None),
+            mmap: Mutex::new(None),
+            parse_settings: Mutex::new(settings),
+            parse_warnings: Mutex::new(warnings),
+            search_index: Mutex::new(SearchIndex::new()),
+            request_tokens: Mutex::new(HashMap::new()),
+            follow: Mutex::new(None),
+            // Offsets are built after the file is opened, so they cover at least
+            // the bytes it had then
+            follow_cursor: Mutex::new(FollowCursor {
+                len: metadata.as_ref().map_or(0, |meta| meta.len()),
+                anchor: None,
+            }),
+            edits: Mutex::new(None),
+            modified: metadata.and_then(|meta| meta.modified().ok()),
+            file_path,
+            source_path,
+            member,
+            read_only,
+        }
+    }
+
+    fn stop_follow(&self) {
+        if let Some(stop) = self.follow.lock().unwrap().take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn is_following(&self) -> bool {
+        self.follow
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|stop| !stop.load(Ordering::Relaxed))
+    }
+
+    /// Fails when the rows cannot be edited: for workbook sheets, and while the
+    /// file is followed
+    fn check_editable(&self) -> Result<(), String> {
+        if self.read_only {
+            return Err("Workbook sheets are read-only; export the rows to edit them".to_string());
+        }
+        if self.is_following() {
+            return Err("Stop following the file before editing it".to_string());
+        }
+        Ok(())
+    }
+
+    /// Snapshot of the pending edits, if there are any
+    fn edited(&self) -> Option<Arc<EditJournal>> {
+        self.edits
+            .lock()
+            .unwrap()
+            .clone()
+            .filter(|journal| journal.has_changes())
+    }
+
+    /// Headers with added and removed columns taken into account
+    fn display_headers(&self) -> Vec<String> {
+        let headers = self.headers.lock().unwrap().clone();
+        match self.edits.lock().unwrap().as_ref() {
+            Some(journal) => journal.headers(&headers),
+            None => headers,
+        }
+    }
+
+    /// File rows plus rows inserted by edits
+    fn row_count(&self) -> usize {
+        match self.edits.lock().unwrap().as_ref() {
+            Some(journal) => journal.row_count(),
+            None => *self.total_rows.lock().unwrap(),
+        }
+    }
+
+    fn edit_state(&self) -> EditState {
+        let headers = self.headers.lock().unwrap().clone();
+        let state = match self.edits.lock().unwrap().as_ref() {
+            Some(journal) => journal.state(&headers),
+            None => EditState {
+                headers,
+                rows: *self.total_rows.lock().unwrap(),
+                ..EditState::default()
+            },
+        };
+        EditState {
+            filtered_rows: self
+                .filter_view
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|view| view.rows.len()),
+            ..state
+        }
+    }
+}
+
+struct AppState {
+    documents: Mutex<HashMap<String, Arc<DocumentState>>>,
+    pending_open: Mutex<Option<String>>,
+    parse_info_detected: Mutex<ParseInfo>,
+    parse_info_effective: Mutex<ParseInfo>,
     enable_indexing: Mutex<bool>,
+    sort_memory_budget: Mutex<usize>,
+    jobs: Arc<JobRegistry>,
     debug_logging: Mutex<bool>,
 }
 
+impl AppState {
+    fn document(&self, doc_id: &str) -> Result<Arc<DocumentState>, String> {
+        self.documents
+            .lock()
+            .unwrap()
+            .get(doc_id)
+            .cloned()
+            .ok_or_else(|| "No file loaded".to_string())
+    }
+
+    /// Drops the session open under `doc_id`, stopping its jobs and follow thread
+    fn close_document(&self, doc_id: &str) {
+        self.jobs.cancel_document(doc_id);
+        if let Some(doc) = self.documents.lock().unwrap().remove(doc_id) {
+            doc.stop_follow();
+        }
+    }
+
+    /// Whether `doc` is still the session open under `doc_id`
+    fn is_current(&self, doc_id: &str, doc: &Arc<DocumentState>) -> bool {
+        self.documents
+            .lock()
+            .unwrap()
+            .get(doc_id)
+            .is_some_and(|current| Arc::ptr_eq(current, doc))
+    }
+}
+
 const SEARCH_CHUNK_SIZE: usize = 25_000;
 const BULK_CHUNK_SIZE: usize = 10_000;
 const INDEX_VALUE_MAX_LEN: usize = 256;
 const INDEX_MAX_CARDINALITY: usize = 2_000_000; // Skip column if >2M unique values
+const VALUE_COUNTS_MAX_CARDINALITY: usize = 1_000_000; // Stop adding values past 1M
 const RESULT_CHUNK_SIZE: usize = 5_000;
+const FOLLOW_DEFAULT_INTERVAL_MS: u64 = 1_000;
+const FOLLOW_MIN_INTERVAL_MS: u64 = 100;
+const FOLLOW_FINGERPRINT_BYTES: u64 = 4 * 1024;
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DocPayload {
+    doc_id: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RowCountPayload {
+    doc_id: String,
+    rows: usize,
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ValueCountsPage {
+    /// Number of distinct values counted in the column
+    total_values: usize,
+    values: Vec<ValueCount>,
+    /// Rows holding values first seen after the cardinality cap was reached
+    untracked_rows: usize,
+}
 
 #[derive(Clone, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 struct MatchesChunkPayload {
+    doc_id: String,
     request_id: u32,
     matches: Vec<usize>,
 }
@@ -111,13 +538,32 @@ struct MatchesChunkPayload {
 #[derive(Clone, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 struct MatchesCompletePayload {
+    doc_id: String,
+    request_id: u32,
+    total: usize,
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchProgressPayload {
+    doc_id: String,
     request_id: u32,
+    scanned: usize,
     total: usize,
 }
 
+fn emit_row_count(app: &tauri::AppHandle, event: &str, doc_id: &str, rows: usize) {
+    let payload = RowCountPayload {
+        doc_id: doc_id.to_string(),
+        rows,
+    };
+    let _ = app.emit(event, payload);
+}
+
 fn emit_matches_chunk(
     app: &tauri::AppHandle,
     event: &str,
+    doc_id: &str,
     request_id: u32,
     matches: &[usize],
 ) -> Result<(), String> {
@@ -125,6 +571,7 @@ fn emit_matches_chunk(
         return Ok(());
     }
     let payload = MatchesChunkPayload {
+        doc_id: doc_id.to_string(),
         request_id,
         matches: matches.to_vec(),
     };
@@ -135,14 +582,35 @@ fn emit_matches_chunk(
 fn emit_matches_complete(
     app: &tauri::AppHandle,
     event: &str,
+    doc_id: &str,
     request_id: u32,
     total: usize,
 ) -> Result<(), String> {
-    let payload = MatchesCompletePayload { request_id, total };
+    let payload = MatchesCompletePayload {
+        doc_id: doc_id.to_string(),
+        request_id,
+        total,
+    };
     app.emit(event, payload).map_err(|err| err.to_string())?;
     Ok(())
 }
 
+fn emit_search_progress(
+    app: &tauri::AppHandle,
+    doc_id: &str,
+    request_id: u32,
+    scanned: usize,
+    total: usize,
+) {
+    let payload = SearchProgressPayload {
+        doc_id: doc_id.to_string(),
+        request_id,
+        scanned,
+        total,
+    };
+    let _ = app.emit("search-progress", payload);
+}
+
 const DEBUG_LOG_FILE: &str = "quickrows.log";
 const CRASH_LOG_FILE: &str = "quickrows-crash.log";
 const LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
@@ -204,6 +672,13 @@ fn truncate_utf8(s: &str, max_bytes: usize) -> &str {
     &s[..end]
 }
 
+fn file_label(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
 fn truncate_string_utf8(s: &mut String, max_bytes: usize) {
     if s.len() <= max_bytes {
         return;
@@ -233,10 +708,11 @@ fn build_search_index(
     offsets: &[u64],
     mmap: Option<&Mmap>,
     num_columns: usize,
-) -> SearchIndex {
+    job: &JobHandle,
+) -> Result<SearchIndex, String> {
     let mut index = SearchIndex::new();
     if num_columns == 0 {
-        return index;
+        return Ok(index);
     }
 
     // Initialize column indexes
@@ -248,6 +724,7 @@ fn build_search_index(
     let mut warnings = Vec::new();
 
     loop {
+        job.check()?;
         let chunk: Vec<Vec<String>> = if let Some(mmap) = mmap {
             match read_chunk_with_offsets_mmap(
                 mmap,
@@ -280,113 +757,465 @@ fn build_search_index(
             break;
         }
 
-        // Index each row
-        for (idx, row) in chunk.iter().enumerate() {
-            let row_index = (start + idx) as u32;
-
-            for (col_idx, cell) in row.iter().enumerate() {
-                if col_idx >= index.columns.len() {
-                    continue;
-                }
-
-                // Skip columns already marked as too high cardinality
-                if index.columns[col_idx].is_none() {
-                    continue;
-                }
-
-                let col_index = index.columns[col_idx].as_mut().unwrap();
-
-                // Create lowercase key, truncated for memory efficiency
-                let key: Box<str> = if cell.len() > INDEX_VALUE_MAX_LEN {
-                    truncate_utf8(cell, INDEX_VALUE_MAX_LEN)
-                        .to_lowercase()
-                        .into()
-                } else {
-                    cell.to_lowercase().into()
-                };
-
-                col_index
-                    .entry(key)
-                    .or_insert_with(Vec::new)
-                    .push(row_index);
-
-                // Check cardinality limit
-                if col_index.len() > INDEX_MAX_CARDINALITY {
-                    println!(
-                        "[INDEX] Skipping column {} (too many unique values: {})",
-                        col_idx,
-                        col_index.len()
-                    );
-                    index.skipped_columns.push(col_idx);
-                    index.columns[col_idx] = None;
-                }
-            }
-        }
+        index.add_rows(start, &chunk);
 
         if chunk.len() < BULK_CHUNK_SIZE {
             break;
         }
         start += chunk.len();
+        job.set_progress(start, Some(offsets.len()));
     }
 
     index.ready = true;
-    index
+    Ok(index)
 }
 
-fn initial_open_path() -> Option<String> {
-    std::env::args_os().skip(1).find_map(|arg| {
-        let path = std::path::PathBuf::from(arg);
-        match path.extension().and_then(|ext| ext.to_str()) {
-            Some(ext) if ext.eq_ignore_ascii_case("csv") => {
-                Some(path.to_string_lossy().to_string())
-            }
-            _ => None,
-        }
-    })
+/// What the follow thread remembers about the file between polls. It lives with the
+/// document, so restarting the thread does not reread rows it already picked up.
+struct FollowCursor {
+    len: u64,
+    /// Start of the last known row and a hash of the bytes just before it
+    anchor: Option<(u64, u64)>,
 }
 
-#[tauri::command]
-async fn load_csv_metadata(
-    path: String,
-    overrides: Option<ParseOverrides>,
-    state: State<'_, AppState>,
-    app: tauri::AppHandle,
-) -> Result<CsvMetadata, String> {
-    let detected = detect_parse_settings(&path).map_err(|err| err.to_string())?;
-    let settings = apply_parse_overrides(&detected, overrides);
-    let detected_settings = apply_parse_overrides(&detected, None);
-    let detected_info = parse_info_from_settings(&detected_settings);
-    let effective_info = parse_info_from_settings(&settings);
+enum FollowPoll {
+    Unchanged,
+    Grew {
+        rows: usize,
+        /// Matching rows of the active filter, which was extended with the new rows
+        filtered: Option<usize>,
+        /// Whether a sort order was dropped because it does not place the new rows
+        sort_dropped: bool,
+    },
+    Rewritten,
+}
 
-    let mut warnings = Vec::new();
-    let headers = get_headers(&path, &settings, &mut warnings).map_err(|err| err.to_string())?;
+fn fingerprint_before(path: &str, end: u64) -> Result<u64, String> {
+    let start = end.saturating_sub(FOLLOW_FINGERPRINT_BYTES);
+    let mut file = std::fs::File::open(path).map_err(|err| err.to_string())?;
+    file.seek(SeekFrom::Start(start))
+        .map_err(|err| err.to_string())?;
+    let mut bytes = vec![0u8; (end - start) as usize];
+    file.read_exact(&mut bytes).map_err(|err| err.to_string())?;
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Ok(hasher.finish())
+}
 
-    *state.file_path.lock().unwrap() = Some(path.clone());
-    *state.total_rows.lock().unwrap() = 0;
-    *state.headers.lock().unwrap() = headers.clone();
-    *state.sorted_order.lock().unwrap() = None;
-    *state.row_offsets.lock().unwrap() = None;
-    *state.mmap.lock().unwrap() = None;
-    *state.parse_settings.lock().unwrap() = settings.clone();
-    *state.parse_info_detected.lock().unwrap() = detected_info.clone();
-    *state.parse_info_effective.lock().unwrap() = effective_info.clone();
-    *state.parse_warnings.lock().unwrap() = warnings.clone();
-    state.search_index.lock().unwrap().clear();
-    state.cache.clear();
+/// Picks up rows appended since the last poll. The last known row is parsed again
+/// because it may have been only partly written when it was first seen.
+fn poll_follow(doc: &DocumentState, cursor: &mut FollowCursor) -> Result<FollowPoll, String> {
+    let path = doc.file_path.as_str();
+    let len = std::fs::metadata(path)
+        .map_err(|err| err.to_string())?
+        .len();
+    if len < cursor.len {
+        return Ok(FollowPoll::Rewritten);
+    }
+    if let Some((anchor, fingerprint)) = cursor.anchor {
+        if len < anchor || fingerprint_before(path, anchor)? != fingerprint {
+            return Ok(FollowPoll::Rewritten);
+        }
+    }
+    if len == cursor.len {
+        return Ok(FollowPoll::Unchanged);
+    }
 
-    let expected_columns = if headers.is_empty() {
-        None
-    } else {
-        Some(headers.len())
+    let (resume, known_rows) = match doc.row_offsets.lock().unwrap().as_ref() {
+        Some(offsets) => (offsets.last().copied(), offsets.len()),
+        // Offsets are still being built by the initial load
+        None => return Ok(FollowPoll::Unchanged),
+    };
+    let settings = doc.parse_settings.lock().unwrap().clone();
+    let expected_columns = match doc.headers.lock().unwrap().len() {
+        0 => None,
+        len => Some(len),
+    };
+    let mut warnings = Vec::new();
+    let appended: Vec<u64> = match resume {
+        Some(resume) => build_row_offsets_from(
+            path,
+            &settings,
+            resume,
+            (known_rows - 1) as u64,
+            expected_columns,
+            &mut warnings,
+        )
+        .map_err(|err| err.to_string())?
+        .into_iter()
+        .filter(|&offset| offset > resume)
+        .collect(),
+        None => build_row_offsets(path, &settings, expected_columns, &mut warnings, None, None)
+            .map_err(|err| err.to_string())?,
     };
+    let mmap = open_mmap_if_large(path).map_err(|err| err.to_string())?;
 
-    let mut estimated_count = None;
-    if let Ok(file) = std::fs::File::open(&path) {
-        if let Ok(meta) = file.metadata() {
-            let len = meta.len();
-            if len > 0 {
-                use std::io::Read;
-                let mut sample = vec![0u8; 64 * 1024];
+    let rows = {
+        let mut offsets_guard = doc.row_offsets.lock().unwrap();
+        if offsets_guard.as_ref().map(Vec::len) != Some(known_rows) {
+            // Offsets were replaced while this poll was reading the file
+            return Ok(FollowPoll::Unchanged);
+        }
+        let offsets = offsets_guard.get_or_insert_with(Vec::new);
+        offsets.extend(appended);
+        *doc.total_rows.lock().unwrap() = offsets.len();
+        *doc.mmap.lock().unwrap() = mmap;
+        doc.cache.clear();
+        *doc.value_counts.lock().unwrap() = None;
+        if let Some(&last) = offsets.last() {
+            cursor.anchor = Some((last, fingerprint_before(path, last)?));
+        }
+        offsets.len()
+    };
+    if !warnings.is_empty() {
+        let mut doc_warnings = doc.parse_warnings.lock().unwrap();
+        doc_warnings.extend(warnings);
+        doc_warnings.truncate(MAX_WARNING_COUNT);
+    }
+    cursor.len = len;
+
+    // The last known row was parsed again, so views built from it drop it and take
+    // it up again along with the appended rows
+    let reparsed = known_rows.saturating_sub(1);
+    doc.search_index.lock().unwrap().truncate(reparsed);
+    let filtered = extend_filter_view(doc, reparsed)?;
+    let sort_dropped = doc.sorted_order.lock().unwrap().take().is_some();
+    Ok(FollowPoll::Grew {
+        rows,
+        filtered,
+        sort_dropped,
+    })
+}
+
+/// Evaluates the active filter again from row `start` on, after the rows from there
+/// changed or were appended. Returns the new number of matching rows.
+fn extend_filter_view(doc: &DocumentState, start: usize) -> Result<Option<usize>, String> {
+    let Some(expression) = doc
+        .filter_view
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|view| view.expression.clone())
+    else {
+        return Ok(None);
+    };
+    let settings = doc.parse_settings.lock().unwrap().clone();
+    let headers = doc.headers.lock().unwrap().clone();
+    let filter = parse_filter(&expression, &headers)?;
+    let predicate = |row: &[String]| filter.matches(row);
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let found = {
+        let offsets = doc.row_offsets.lock().unwrap();
+        let offsets = offsets.as_deref().unwrap_or_default();
+        match mmap.as_ref() {
+            Some(mmap) => filter_range_with_offsets_mmap(
+                &mmap[..],
+                offsets,
+                start,
+                offsets.len(),
+                &settings,
+                &predicate,
+            ),
+            None => filter_range_with_offsets(
+                &doc.file_path,
+                offsets,
+                start,
+                offsets.len(),
+                &settings,
+                &predicate,
+            ),
+        }
+        .map_err(|err| err.to_string())?
+    };
+
+    let mut view = doc.filter_view.lock().unwrap();
+    let Some(view) = view.as_mut().filter(|view| view.expression == expression) else {
+        return Ok(None);
+    };
+    while view.rows.last().is_some_and(|&row| row >= start) {
+        view.rows.pop();
+    }
+    view.rows.extend(found);
+    Ok(Some(view.rows.len()))
+}
+
+/// Indexes rows the follow thread appended after the search index was built
+fn extend_search_index(doc: &DocumentState) -> Result<(), String> {
+    let indexed = {
+        let index = doc.search_index.lock().unwrap();
+        if !index.ready || index.columns.is_empty() {
+            return Ok(());
+        }
+        index.rows
+    };
+    let total = *doc.total_rows.lock().unwrap();
+    if indexed >= total {
+        return Ok(());
+    }
+
+    let offsets = doc.row_offsets.lock().unwrap().clone().unwrap_or_default();
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let settings = doc.parse_settings.lock().unwrap().clone();
+    let num_columns = doc.headers.lock().unwrap().len();
+    let mut warnings = Vec::new();
+    let mut start = indexed;
+    while start < offsets.len() {
+        let count = BULK_CHUNK_SIZE.min(offsets.len() - start);
+        let chunk = match mmap.as_deref() {
+            Some(mmap) => read_chunk_with_offsets_mmap(
+                mmap,
+                &offsets,
+                start,
+                count,
+                &settings,
+                Some(num_columns),
+                &mut warnings,
+            ),
+            None => read_chunk_with_offsets(
+                &doc.file_path,
+                &offsets,
+                start,
+                count,
+                &settings,
+                Some(num_columns),
+                &mut warnings,
+            ),
+        }
+        .map_err(|err| err.to_string())?;
+        if chunk.is_empty() {
+            break;
+        }
+        doc.search_index.lock().unwrap().add_rows(start, &chunk);
+        start += chunk.len();
+    }
+    Ok(())
+}
+
+/// Decompresses `source_path` into the cache dir, once per version of the file, and
+/// returns the path of the copy
+async fn decompressed_copy(
+    doc_id: &str,
+    source_path: &str,
+    compression: Compression,
+    state: &AppState,
+    app: &tauri::AppHandle,
+) -> Result<String, String> {
+    let name = decompressed_name(source_path, compression);
+    let target = import_cache_path(&ensure_cache_dir(app)?, source_path, "decompressed", &name)?;
+    let total = std::fs::metadata(source_path)
+        .map(|meta| meta.len() as usize)
+        .ok();
+
+    let label = format!("Decompress {}", file_label(source_path));
+    let source_path = source_path.to_string();
+    state
+        .jobs
+        .run(JobKind::Import, doc_id, label, move |job| {
+            materialize_import(&target, |out| {
+                let progress = |read: u64| {
+                    job.check()?;
+                    job.set_progress(read as usize, total);
+                    Ok(())
+                };
+                decompress(&source_path, compression, &progress, out).map(|_| ())
+            })?;
+            Ok(target.to_string_lossy().into_owned())
+        })
+        .await
+}
+
+/// Extracts an archive member into the cache dir, once per version of the archive,
+/// and returns the path of the copy
+async fn extracted_member(
+    doc_id: &str,
+    archive_path: &str,
+    member: &str,
+    state: &AppState,
+    app: &tauri::AppHandle,
+) -> Result<String, String> {
+    let name = member_file_name(member);
+    let target = import_cache_path(&ensure_cache_dir(app)?, archive_path, member, &name)?;
+
+    let label = format!("Extract {name}");
+    let (archive_path, member) = (archive_path.to_string(), member.to_string());
+    state
+        .jobs
+        .run(JobKind::Import, doc_id, label, move |job| {
+            materialize_import(&target, |out| {
+                let progress = |written: u64| {
+                    job.check()?;
+                    job.set_progress(written as usize, None);
+                    Ok(())
+                };
+                extract_member(&archive_path, &member, &progress, out).map(|_| ())
+            })?;
+            Ok(target.to_string_lossy().into_owned())
+        })
+        .await
+}
+
+/// Writes a workbook sheet as CSV into the cache dir, once per version of the
+/// workbook and header row, and returns the path of the copy
+async fn sheet_copy(
+    doc_id: &str,
+    workbook_path: &str,
+    kind: WorkbookKind,
+    sheet: &str,
+    header_row: Option<usize>,
+    state: &AppState,
+    app: &tauri::AppHandle,
+) -> Result<String, String> {
+    let name = sheet_file_name(workbook_path, sheet);
+    let variant = format!("sheet:{sheet}:{}", header_row.unwrap_or(0));
+    let target = import_cache_path(&ensure_cache_dir(app)?, workbook_path, &variant, &name)?;
+
+    let label = format!("Read sheet {sheet}");
+    let (workbook_path, sheet) = (workbook_path.to_string(), sheet.to_string());
+    state
+        .jobs
+        .run(JobKind::Import, doc_id, label, move |job| {
+            materialize_import(&target, |out| {
+                let progress = |rows: usize| {
+                    job.check()?;
+                    job.set_progress(rows, None);
+                    Ok(())
+                };
+                write_sheet_csv(&workbook_path, kind, &sheet, header_row, &progress, out)
+                    .map(|_| ())
+            })?;
+            Ok(target.to_string_lossy().into_owned())
+        })
+        .await
+}
+
+/// Table members of a ZIP archive with their uncompressed sizes
+#[tauri::command]
+async fn list_zip_members(path: String) -> Result<Vec<ZipMember>, String> {
+    list_members(&path)
+}
+
+fn initial_open_path() -> Option<String> {
+    std::env::args_os().skip(1).find_map(|arg| {
+        let path = std::path::PathBuf::from(arg);
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext)
+                if ["csv", "jsonl", "ndjson", "zip"]
+                    .iter()
+                    .any(|known| ext.eq_ignore_ascii_case(known))
+                    || has_compressed_extension(&path)
+                    || has_workbook_extension(&path.to_string_lossy()) =>
+            {
+                Some(path.to_string_lossy().to_string())
+            }
+            _ => None,
+        }
+    })
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn load_csv_metadata(
+    doc_id: String,
+    path: String,
+    member: Option<String>,
+    header_row: Option<usize>,
+    overrides: Option<ParseOverrides>,
+    keep_edits: Option<bool>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<CsvMetadata, String> {
+    let source_path = path;
+    // Workbook sheets, archive members and compressed files are read from plain
+    // copies in the cache dir. XLSX and ODS files are archives too; they are told
+    // apart by their workbook part, whatever the file is named.
+    let workbook = workbook_kind(&source_path)?;
+    let sheets = match workbook {
+        Some(kind) => Some(list_sheets(&source_path, kind)?),
+        None => None,
+    };
+    let member = match (member, sheets.as_ref()) {
+        (Some(member), _) => Some(member),
+        (None, Some(sheets)) => Some(
+            sheets
+                .first()
+                .cloned()
+                .ok_or_else(|| "The workbook has no sheets".to_string())?,
+        ),
+        (None, None) if is_zip(&source_path) => Some(single_member(&source_path)?),
+        (None, None) => None,
+    };
+    let header_row = header_row.filter(|_| sheets.is_some());
+    let mut path = match (member.as_deref(), workbook) {
+        (Some(sheet), Some(kind)) => {
+            sheet_copy(&doc_id, &source_path, kind, sheet, header_row, &state, &app).await?
+        }
+        (Some(member), None) => {
+            extracted_member(&doc_id, &source_path, member, &state, &app).await?
+        }
+        (None, _) => source_path.clone(),
+    };
+    let compression = sniff_compression(&path)?;
+    if let Some(compression) = compression {
+        path = decompressed_copy(&doc_id, &path, compression, &state, &app).await?;
+    }
+    // Sheet copies are written in one known format, so there is nothing to detect
+    let (detected, overrides) = if sheets.is_some() {
+        (sheet_settings(), sheet_overrides(overrides))
+    } else {
+        let detected = detect_parse_settings(&path).map_err(|err| err.to_string())?;
+        (detected, overrides)
+    };
+    let settings = apply_parse_overrides(&detected, overrides);
+    check_fixed_width_encoding(&settings)?;
+    let detected_settings = apply_parse_overrides(&detected, None);
+    let detected_info = parse_info_from_settings(&detected_settings);
+    let effective_info = parse_info_from_settings(&settings);
+    *state.parse_info_detected.lock().unwrap() = detected_info.clone();
+    *state.parse_info_effective.lock().unwrap() = effective_info.clone();
+
+    let mut warnings = Vec::new();
+    let headers = get_headers(&path, &settings, &mut warnings).map_err(|err| err.to_string())?;
+
+    // Work for the file previously open in this document is no longer useful
+    state.jobs.cancel_document(&doc_id);
+    let doc = Arc::new(DocumentState::new(
+        source_path,
+        member.clone(),
+        path.clone(),
+        headers.clone(),
+        settings.clone(),
+        warnings.clone(),
+        sheets.is_some(),
+    ));
+    let previous = state
+        .documents
+        .lock()
+        .unwrap()
+        .insert(doc_id.clone(), doc.clone());
+    // Pending edits carry over when the same, unchanged file is reopened with them
+    if let Some(previous) = previous.filter(|previous| {
+        keep_edits.unwrap_or(false)
+            && previous.file_path == path
+            && previous.modified == doc.modified
+            && settings_cache_hash(&previous.parse_settings.lock().unwrap())
+                == settings_cache_hash(&settings)
+    }) {
+        *doc.edits.lock().unwrap() = previous.edits.lock().unwrap().clone();
+    }
+
+    let expected_columns = if headers.is_empty() {
+        None
+    } else {
+        Some(headers.len())
+    };
+
+    let mut estimated_count = None;
+    if let Ok(file) = std::fs::File::open(&path) {
+        if let Ok(meta) = file.metadata() {
+            let len = meta.len();
+            if len > 0 {
+                use std::io::Read;
+                let mut sample = vec![0u8; 64 * 1024];
                 let mut take = file.take(sample.len() as u64);
                 if let Ok(read) = take.read(&mut sample) {
                     if read > 0 {
@@ -404,134 +1233,137 @@ async fn load_csv_metadata(
         }
     }
 
-    tauri::async_runtime::spawn_blocking(move || {
-        let cache_dir = match ensure_cache_dir(&app) {
-            Ok(dir) => dir,
-            Err(_) => return,
-        };
-        prune_cache_dir(&cache_dir);
+    let jobs = state.jobs.clone();
+    let job_doc_id = doc_id.clone();
+    let label = format!("Index rows of {}", file_label(&path));
+    state
+        .jobs
+        .spawn(JobKind::OffsetBuild, &job_doc_id, label, move |job| {
+            let cache_dir = ensure_cache_dir(&app)?;
+            prune_cache_dir(&cache_dir);
 
-        let settings_hash = settings_cache_hash(&settings);
-        let key = match cache_key(&path, Some(settings_hash)) {
-            Ok(key) => key,
-            Err(_) => return,
-        };
-        let cache_path = offsets_cache_path(&cache_dir, key);
+            let settings_hash = settings_cache_hash(&settings);
+            let key = cache_key(&path, Some(settings_hash))?;
+            let cache_path = offsets_cache_path(&cache_dir, key);
 
-        let mmap = match open_mmap_if_large(&path) {
-            Ok(mmap) => mmap,
-            Err(_) => None,
-        };
+            let mmap = match open_mmap_if_large(&path) {
+                Ok(mmap) => mmap,
+                Err(_) => None,
+            };
 
-        let mut offset_warnings = Vec::new();
-        let app_for_cb = app.clone();
-        let progress_cb = move |rows: usize| {
-            let _ = app_for_cb.emit("parse-progress", rows);
-        };
+            let mut offset_warnings = Vec::new();
+            let app_for_cb = app.clone();
+            let job_for_cb = job;
+            let doc_id_for_cb = doc_id.clone();
+            let progress_cb = move |rows: usize| {
+                emit_row_count(&app_for_cb, "parse-progress", &doc_id_for_cb, rows);
+                job_for_cb.set_progress(rows, estimated_count);
+            };
+
+            let offsets = match read_offsets_cache(&cache_path, key) {
+                Ok(Some(offsets)) => {
+                    emit_row_count(&app, "parse-progress", &doc_id, offsets.len());
+                    offsets
+                }
+                _ => {
+                    let offsets = match mmap.as_deref() {
+                        Some(mmap) => build_row_offsets_mmap(
+                            &mmap[..],
+                            &settings,
+                            expected_columns,
+                            &mut offset_warnings,
+                            Some(&progress_cb),
+                            Some(job.cancel_flag()),
+                        ),
+                        None => build_row_offsets(
+                            &path,
+                            &settings,
+                            expected_columns,
+                            &mut offset_warnings,
+                            Some(&progress_cb),
+                            Some(job.cancel_flag()),
+                        ),
+                    }
+                    .map_err(|err| err.to_string())?;
+                    let _ = write_offsets_cache(&cache_path, key, &offsets);
+                    offsets
+                }
+            };
 
-        let offsets = match read_offsets_cache(&cache_path, key) {
-            Ok(Some(offsets)) => {
-                let _ = app.emit("parse-progress", offsets.len());
-                offsets
+            let count = offsets.len();
+            let state = app.state::<AppState>();
+            if !state.is_current(&doc_id, &doc) {
+                return Err(CANCELLED_MESSAGE.to_string());
             }
-            _ => {
-                let offsets = match mmap.as_deref() {
-                    Some(mmap) => match build_row_offsets_mmap(
-                        &mmap[..],
-                        &settings,
-                        expected_columns,
-                        &mut offset_warnings,
-                        Some(&progress_cb),
-                    ) {
-                        Ok(offsets) => offsets,
-                        Err(_) => return,
-                    },
-                    None => match build_row_offsets(
-                        &path,
-                        &settings,
-                        expected_columns,
-                        &mut offset_warnings,
-                        Some(&progress_cb),
-                    ) {
-                        Ok(offsets) => offsets,
-                        Err(_) => return,
-                    },
-                };
-                let _ = write_offsets_cache(&cache_path, key, &offsets);
-                offsets
+            job.set_progress(count, Some(count));
+            if !offset_warnings.is_empty() {
+                let mut warnings = doc.parse_warnings.lock().unwrap();
+                warnings.extend(offset_warnings);
+                warnings.truncate(MAX_WARNING_COUNT);
             }
-        };
+            *doc.row_offsets.lock().unwrap() = Some(offsets.clone());
+            *doc.total_rows.lock().unwrap() = count;
+            *doc.mmap.lock().unwrap() = mmap.clone();
+            emit_row_count(&app, "row-count", &doc_id, doc.row_count());
 
-        let count = offsets.len();
-        let state = app.state::<AppState>();
-        let current_path = state.file_path.lock().unwrap().clone();
-        if current_path.as_deref() != Some(path.as_str()) {
-            return;
-        }
-        if !offset_warnings.is_empty() {
-            let mut warnings = state.parse_warnings.lock().unwrap();
-            warnings.extend(offset_warnings);
-            warnings.truncate(MAX_WARNING_COUNT);
-        }
-        *state.row_offsets.lock().unwrap() = Some(offsets.clone());
-        *state.total_rows.lock().unwrap() = count;
-        *state.mmap.lock().unwrap() = mmap.clone();
-        let _ = app.emit("row-count", count);
-
-        // Build search index in background
-        let enable_indexing = *state.enable_indexing.lock().unwrap();
-        if enable_indexing {
-            let num_columns = state.headers.lock().unwrap().len();
-            println!(
-                "[INDEX] Starting index build: path={}, num_columns={}, offsets_len={}",
-                path,
-                num_columns,
-                offsets.len()
-            );
-            let index_path = path.clone();
-            let index_settings = settings.clone();
-            let index_offsets = offsets;
-            let index_mmap = mmap;
-            let app_for_index = app.clone();
-
-            std::thread::spawn(move || {
-                println!("[INDEX] Thread started, building index...");
-                let index = if let Some(ref mmap) = index_mmap {
-                    build_search_index(
-                        &index_path,
-                        &index_settings,
-                        &index_offsets,
-                        Some(mmap.as_ref()),
-                        num_columns,
-                    )
-                } else {
-                    build_search_index(
-                        &index_path,
-                        &index_settings,
-                        &index_offsets,
-                        None,
-                        num_columns,
-                    )
-                };
+            // Build search index in background
+            let enable_indexing = *state.enable_indexing.lock().unwrap();
+            if enable_indexing {
+                let num_columns = doc.headers.lock().unwrap().len();
                 println!(
-                    "[INDEX] Index built: ready={}, columns={}",
-                    index.ready,
-                    index.columns.len()
+                    "[INDEX] Starting index build: path={}, num_columns={}, offsets_len={}",
+                    path,
+                    num_columns,
+                    offsets.len()
                 );
+                let index_path = path.clone();
+                let index_settings = settings.clone();
+                let index_offsets = offsets;
+                let index_mmap = mmap;
+                let app_for_index = app.clone();
+                let index_doc_id = doc_id.clone();
+                let label = format!("Build search index for {}", file_label(&index_path));
+                jobs.spawn(
+                    JobKind::IndexBuild,
+                    &index_doc_id,
+                    label,
+                    move |index_job| {
+                        println!("[INDEX] Building index (job {})...", index_job.id());
+                        let index = build_search_index(
+                            &index_path,
+                            &index_settings,
+                            &index_offsets,
+                            index_mmap.as_deref(),
+                            num_columns,
+                            index_job,
+                        )
+                        .inspect_err(|err| println!("[INDEX] Index build stopped: {err}"))?;
+                        println!(
+                            "[INDEX] Index built: ready={}, columns={}",
+                            index.ready,
+                            index.columns.len()
+                        );
 
-                let state = app_for_index.state::<AppState>();
-                // Check if same file is still loaded
-                let current_path = state.file_path.lock().unwrap().clone();
-                if current_path.as_deref() == Some(index_path.as_str()) {
-                    *state.search_index.lock().unwrap() = index;
-                    println!("[INDEX] Index stored in state");
-                    let _ = app_for_index.emit("index-ready", true);
-                } else {
-                    println!("[INDEX] File changed, discarding index");
-                }
-            });
-        }
-    });
+                        let state = app_for_index.state::<AppState>();
+                        // Check if this session is still the one open under its document id
+                        if state.is_current(&doc_id, &doc) {
+                            *doc.search_index.lock().unwrap() = index;
+                            println!("[INDEX] Index stored in state");
+                            let _ = app_for_index.emit(
+                                "index-ready",
+                                DocPayload {
+                                    doc_id: doc_id.clone(),
+                                },
+                            );
+                        } else {
+                            println!("[INDEX] File changed, discarding index");
+                        }
+                        Ok(())
+                    },
+                );
+            }
+            Ok(())
+        });
 
     Ok(CsvMetadata {
         headers,
@@ -539,28 +1371,57 @@ async fn load_csv_metadata(
         effective: effective_info,
         warnings,
         estimated_count,
+        compression,
+        member,
+        read_only: sheets.is_some(),
+        sheets,
+        header_row,
     })
 }
 
 #[tauri::command]
 async fn get_csv_chunk(
+    doc_id: String,
     start: usize,
     count: usize,
     state: State<'_, AppState>,
 ) -> Result<Vec<Vec<String>>, String> {
-    if let Some(cached) = state.cache.get(start, count) {
+    let doc = state.document(&doc_id)?;
+    let data = read_view_chunk(&doc, start, count)?;
+    let Some(journal) = doc.edited() else {
+        return Ok(data);
+    };
+
+    let view_guard = doc.filter_view.lock().unwrap();
+    match view_guard.as_ref() {
+        Some(view) => {
+            let end = usize::min(start.saturating_add(count), view.rows.len());
+            let indices = view.rows.get(start..end).unwrap_or(&[]);
+            Ok(journal.apply_indexed(indices, data))
+        }
+        None => {
+            let mut rows = journal.apply_rows(start, data);
+            rows.extend(journal.inserted_rows(start, start.saturating_add(count)));
+            Ok(rows)
+        }
+    }
+}
+
+/// Rows `start..start + count` of the file, or of the active filter, as read from
+/// disk. The chunk cache holds rows without edits applied.
+fn read_view_chunk(
+    doc: &DocumentState,
+    start: usize,
+    count: usize,
+) -> Result<Vec<Vec<String>>, String> {
+    if let Some(cached) = doc.cache.get(start, count) {
         return Ok(cached);
     }
 
-    let path = state
-        .file_path
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No file loaded")?;
-    let settings = state.parse_settings.lock().unwrap().clone();
+    let path = doc.file_path.clone();
+    let settings = doc.parse_settings.lock().unwrap().clone();
     let expected_columns = {
-        let len = state.headers.lock().unwrap().len();
+        let len = doc.headers.lock().unwrap().len();
         if len == 0 {
             None
         } else {
@@ -569,10 +1430,37 @@ async fn get_csv_chunk(
     };
     let mut warnings = Vec::new();
 
-    let mmap = state.mmap.lock().unwrap().clone();
-    let offsets_guard = state.row_offsets.lock().unwrap();
-    let data = match offsets_guard.as_ref() {
-        Some(offsets) => {
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let offsets_guard = doc.row_offsets.lock().unwrap();
+    let view_guard = doc.filter_view.lock().unwrap();
+    let data = match (offsets_guard.as_ref(), view_guard.as_ref()) {
+        (Some(offsets), Some(view)) => {
+            // Page through the filtered rows instead of the whole file
+            let end = usize::min(start.saturating_add(count), view.rows.len());
+            let indices = view.rows.get(start..end).unwrap_or(&[]);
+            if let Some(mmap) = mmap.as_ref() {
+                read_rows_by_index_mmap(
+                    &mmap[..],
+                    offsets,
+                    indices,
+                    &settings,
+                    expected_columns,
+                    &mut warnings,
+                )
+                .map_err(|err| err.to_string())?
+            } else {
+                read_rows_by_index(
+                    &path,
+                    offsets,
+                    indices,
+                    &settings,
+                    expected_columns,
+                    &mut warnings,
+                )
+                .map_err(|err| err.to_string())?
+            }
+        }
+        (Some(offsets), None) => {
             if let Some(mmap) = mmap.as_ref() {
                 read_chunk_with_offsets_mmap(
                     &mmap[..],
@@ -597,7 +1485,7 @@ async fn get_csv_chunk(
                 .map_err(|err| err.to_string())?
             }
         }
-        None => {
+        (None, _) => {
             if let Some(mmap) = mmap.as_ref() {
                 read_chunk_mmap(
                     &mmap[..],
@@ -621,44 +1509,66 @@ async fn get_csv_chunk(
             }
         }
     };
+    drop(view_guard);
     if !warnings.is_empty() {
-        let mut stored = state.parse_warnings.lock().unwrap();
+        let mut stored = doc.parse_warnings.lock().unwrap();
         stored.extend(warnings);
         stored.truncate(MAX_WARNING_COUNT);
     }
-    state.cache.put(start, count, data.clone());
+    doc.cache.put(start, count, data.clone());
 
     Ok(data)
 }
 
 #[tauri::command]
 async fn search_csv(
+    doc_id: String,
     column_idx: Option<usize>,
     query: String,
     match_case: Option<bool>,
     whole_word: Option<bool>,
+    regex: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<Vec<usize>, String> {
-    let path = state
-        .file_path
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No file loaded")?;
+    let doc = state.document(&doc_id)?;
+    let matches = search_rows(
+        &doc,
+        column_idx,
+        &query,
+        match_case.unwrap_or(false),
+        whole_word.unwrap_or(false),
+        regex.unwrap_or(false),
+    )?;
+    *doc.search_results.lock().unwrap() = Some(matches.clone());
+    Ok(matches)
+}
 
-    let match_case = match_case.unwrap_or(false);
-    let whole_word = whole_word.unwrap_or(false);
+fn search_rows(
+    doc: &DocumentState,
+    column_idx: Option<usize>,
+    query: &str,
+    match_case: bool,
+    whole_word: bool,
+    regex: bool,
+) -> Result<Vec<usize>, String> {
+    let path = doc.file_path.clone();
 
     let query_processed = if match_case {
-        query.clone()
+        query.to_string()
     } else {
         query.to_lowercase()
     };
+    let matcher = CellMatcher::new(query, match_case, whole_word, regex)?;
+
+    // Neither the index nor the raw file know about pending edits
+    if doc.edited().is_some() {
+        return search_edited_rows(doc, column_idx, &matcher, &|| Ok(()), &|_, _| {});
+    }
 
-    let settings = state.parse_settings.lock().unwrap().clone();
+    let settings = doc.parse_settings.lock().unwrap().clone();
 
     // Try index-based search first (for exact or contains matches)
-    let search_index = state.search_index.lock().unwrap();
+    let search_index = doc.search_index.lock().unwrap();
     println!(
         "[SEARCH] index.ready={} match_case={} column_idx={:?} columns_len={}",
         search_index.ready,
@@ -666,7 +1576,8 @@ async fn search_csv(
         column_idx,
         search_index.columns.len()
     );
-    if search_index.ready && !match_case {
+    // The index stores lowercased literal values, so it cannot answer regex queries
+    if search_index.ready && !match_case && !regex {
         if let Some(col_idx) = column_idx {
             // Search specific column using index
             if col_idx < search_index.columns.len() {
@@ -710,8 +1621,8 @@ async fn search_csv(
     }
     drop(search_index); // Release lock before sequential scan
 
-    let mmap = state.mmap.lock().unwrap().clone();
-    let offsets_guard = state.row_offsets.lock().unwrap();
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let offsets_guard = doc.row_offsets.lock().unwrap();
     if let Some(offsets) = offsets_guard.as_ref() {
         let total = offsets.len();
         let ranges = (0..total)
@@ -729,22 +1640,12 @@ async fn search_csv(
                         *start,
                         *end,
                         column_idx,
-                        &query_processed,
-                        match_case,
-                        whole_word,
+                        &matcher,
                         &settings,
                     )
                 } else {
                     search_range_with_offsets(
-                        &path,
-                        offsets,
-                        *start,
-                        *end,
-                        column_idx,
-                        &query_processed,
-                        match_case,
-                        whole_word,
-                        &settings,
+                        &path, offsets, *start, *end, column_idx, &matcher, &settings,
                     )
                 }
                 .map_err(|err| err.to_string())?;
@@ -765,23 +1666,7 @@ async fn search_csv(
     let mut record = csv::ByteRecord::new();
     let mut idx: usize = 0;
 
-    // Helper closure for matching logic
-    let check_match = |cell: &str| -> bool {
-        if !match_case {
-            let val_lower = cell.to_lowercase();
-            if whole_word {
-                val_lower == query_processed
-            } else {
-                val_lower.contains(&query_processed)
-            }
-        } else {
-            if whole_word {
-                cell == query_processed
-            } else {
-                cell.contains(&query_processed)
-            }
-        }
-    };
+    let check_match = |cell: &str| -> bool { matcher.is_match(cell) };
 
     if let Some(mmap) = mmap.as_ref() {
         let mut rdr = build_reader(&mmap[..], &settings, settings.has_headers);
@@ -831,35 +1716,56 @@ async fn search_csv(
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn search_csv_stream(
+    doc_id: String,
     column_idx: Option<usize>,
     query: String,
     match_case: Option<bool>,
     whole_word: Option<bool>,
+    regex: Option<bool>,
     request_id: u32,
     state: State<'_, AppState>,
     app: tauri::AppHandle,
 ) -> Result<(), String> {
-    let path = state
-        .file_path
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No file loaded")?;
+    let doc = state.document(&doc_id)?;
+    let path = doc.file_path.clone();
 
     let match_case = match_case.unwrap_or(false);
     let whole_word = whole_word.unwrap_or(false);
+    let regex = regex.unwrap_or(false);
     let query_processed = if match_case {
         query.clone()
     } else {
         query.to_lowercase()
     };
+    let matcher = CellMatcher::new(&query, match_case, whole_word, regex)?;
+    let token = RequestToken::register(&doc.request_tokens, request_id);
+
+    // Neither the index nor the raw file know about pending edits
+    if doc.edited().is_some() {
+        let matches = search_edited_rows(
+            &doc,
+            column_idx,
+            &matcher,
+            &|| token.check(),
+            &|done, total| emit_search_progress(&app, &doc_id, request_id, done, total),
+        )?;
+        let total = matches.len();
+        for chunk in matches.chunks(RESULT_CHUNK_SIZE) {
+            token.check()?;
+            emit_matches_chunk(&app, "search-chunk", &doc_id, request_id, chunk)?;
+        }
+        *doc.search_results.lock().unwrap() = Some(matches.clone());
+        emit_matches_complete(&app, "search-complete", &doc_id, request_id, total)?;
+        return Ok(());
+    }
 
-    let settings = state.parse_settings.lock().unwrap().clone();
+    let settings = doc.parse_settings.lock().unwrap().clone();
 
     // Try index-based search first (for exact or contains matches)
-    let search_index = state.search_index.lock().unwrap();
-    if search_index.ready && !match_case {
+    let search_index = doc.search_index.lock().unwrap();
+    if search_index.ready && !match_case && !regex {
         if let Some(col_idx) = column_idx {
             if col_idx < search_index.columns.len() {
                 if let Some(ref col_index) = search_index.columns[col_idx] {
@@ -874,12 +1780,27 @@ async fn search_csv_stream(
                             let matches: Vec<usize> = rows.iter().map(|&r| r as usize).collect();
                             let total = matches.len();
                             for chunk in matches.chunks(RESULT_CHUNK_SIZE) {
-                                emit_matches_chunk(&app, "search-chunk", request_id, chunk)?;
+                                token.check()?;
+                                emit_matches_chunk(
+                                    &app,
+                                    "search-chunk",
+                                    &doc_id,
+                                    request_id,
+                                    chunk,
+                                )?;
                             }
-                            emit_matches_complete(&app, "search-complete", request_id, total)?;
+                            *doc.search_results.lock().unwrap() = Some(matches.clone());
+                            emit_matches_complete(
+                                &app,
+                                "search-complete",
+                                &doc_id,
+                                request_id,
+                                total,
+                            )?;
                             return Ok(());
                         } else {
-                            emit_matches_complete(&app, "search-complete", request_id, 0)?;
+                            *doc.search_results.lock().unwrap() = Some(Vec::new());
+                            emit_matches_complete(&app, "search-complete", &doc_id, request_id, 0)?;
                             return Ok(());
                         }
                     } else {
@@ -893,9 +1814,11 @@ async fn search_csv_stream(
                         matches.dedup();
                         let total = matches.len();
                         for chunk in matches.chunks(RESULT_CHUNK_SIZE) {
-                            emit_matches_chunk(&app, "search-chunk", request_id, chunk)?;
+                            token.check()?;
+                            emit_matches_chunk(&app, "search-chunk", &doc_id, request_id, chunk)?;
                         }
-                        emit_matches_complete(&app, "search-complete", request_id, total)?;
+                        *doc.search_results.lock().unwrap() = Some(matches.clone());
+                        emit_matches_complete(&app, "search-complete", &doc_id, request_id, total)?;
                         return Ok(());
                     }
                 }
@@ -904,18 +1827,21 @@ async fn search_csv_stream(
     }
     drop(search_index); // Release lock before sequential scan
 
-    let mmap = state.mmap.lock().unwrap().clone();
-    let offsets_guard = state.row_offsets.lock().unwrap();
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let offsets_guard = doc.row_offsets.lock().unwrap();
     if let Some(offsets) = offsets_guard.as_ref() {
         let total = offsets.len();
         let ranges = (0..total)
             .step_by(SEARCH_CHUNK_SIZE)
             .map(|start| (start, usize::min(start + SEARCH_CHUNK_SIZE, total)))
             .collect::<Vec<_>>();
+        let scanned = AtomicUsize::new(0);
+        emit_search_progress(&app, &doc_id, request_id, 0, total);
 
         let mut matches = ranges
             .par_iter()
             .try_fold(Vec::new, |mut acc, (start, end)| {
+                token.check()?;
                 let mut found = if let Some(mmap) = mmap.as_ref() {
                     search_range_with_offsets_mmap(
                         &mmap[..],
@@ -923,26 +1849,18 @@ async fn search_csv_stream(
                         *start,
                         *end,
                         column_idx,
-                        &query_processed,
-                        match_case,
-                        whole_word,
+                        &matcher,
                         &settings,
                     )
                 } else {
                     search_range_with_offsets(
-                        &path,
-                        offsets,
-                        *start,
-                        *end,
-                        column_idx,
-                        &query_processed,
-                        match_case,
-                        whole_word,
-                        &settings,
+                        &path, offsets, *start, *end, column_idx, &matcher, &settings,
                     )
                 }
                 .map_err(|err| err.to_string())?;
                 acc.append(&mut found);
+                let done = scanned.fetch_add(end - start, Ordering::Relaxed) + (end - start);
+                emit_search_progress(&app, &doc_id, request_id, done, total);
                 Ok::<Vec<usize>, String>(acc)
             })
             .try_reduce(Vec::new, |mut left, mut right| {
@@ -953,34 +1871,23 @@ async fn search_csv_stream(
         matches.sort_unstable();
         let total = matches.len();
         for chunk in matches.chunks(RESULT_CHUNK_SIZE) {
-            emit_matches_chunk(&app, "search-chunk", request_id, chunk)?;
+            token.check()?;
+            emit_matches_chunk(&app, "search-chunk", &doc_id, request_id, chunk)?;
         }
-        emit_matches_complete(&app, "search-complete", request_id, total)?;
+        *doc.search_results.lock().unwrap() = Some(matches.clone());
+        emit_matches_complete(&app, "search-complete", &doc_id, request_id, total)?;
         return Ok(());
     }
     drop(offsets_guard);
 
+    // Row count is only an estimate until offsets have been built
+    let expected_rows = *doc.total_rows.lock().unwrap();
     let mut matches = Vec::new();
-    let mut total = 0usize;
+    let mut emitted = 0usize;
     let mut record = csv::ByteRecord::new();
     let mut idx: usize = 0;
 
-    let check_match = |cell: &str| -> bool {
-        if !match_case {
-            let val_lower = cell.to_lowercase();
-            if whole_word {
-                val_lower == query_processed
-            } else {
-                val_lower.contains(&query_processed)
-            }
-        } else {
-            if whole_word {
-                cell == query_processed
-            } else {
-                cell.contains(&query_processed)
-            }
-        }
-    };
+    let check_match = |cell: &str| -> bool { matcher.is_match(cell) };
 
     if let Some(mmap) = mmap.as_ref() {
         let mut rdr = build_reader(&mmap[..], &settings, settings.has_headers);
@@ -999,13 +1906,22 @@ async fn search_csv_stream(
             };
             if is_match {
                 matches.push(idx);
-                if matches.len() >= RESULT_CHUNK_SIZE {
-                    total += matches.len();
-                    emit_matches_chunk(&app, "search-chunk", request_id, &matches)?;
-                    matches.clear();
+                if matches.len() - emitted >= RESULT_CHUNK_SIZE {
+                    emit_matches_chunk(
+                        &app,
+                        "search-chunk",
+                        &doc_id,
+                        request_id,
+                        &matches[emitted..],
+                    )?;
+                    emitted = matches.len();
                 }
             }
             idx += 1;
+            if idx.is_multiple_of(SEARCH_CHUNK_SIZE) {
+                token.check()?;
+                emit_search_progress(&app, &doc_id, request_id, idx, expected_rows.max(idx));
+            }
         }
     } else {
         let file = std::fs::File::open(&path).map_err(|err| err.to_string())?;
@@ -1026,122 +1942,408 @@ async fn search_csv_stream(
             };
             if is_match {
                 matches.push(idx);
-                if matches.len() >= RESULT_CHUNK_SIZE {
-                    total += matches.len();
-                    emit_matches_chunk(&app, "search-chunk", request_id, &matches)?;
-                    matches.clear();
+                if matches.len() - emitted >= RESULT_CHUNK_SIZE {
+                    emit_matches_chunk(
+                        &app,
+                        "search-chunk",
+                        &doc_id,
+                        request_id,
+                        &matches[emitted..],
+                    )?;
+                    emitted = matches.len();
                 }
             }
             idx += 1;
+            if idx.is_multiple_of(SEARCH_CHUNK_SIZE) {
+                token.check()?;
+                emit_search_progress(&app, &doc_id, request_id, idx, expected_rows.max(idx));
+            }
         }
     }
 
-    total += matches.len();
-    emit_matches_chunk(&app, "search-chunk", request_id, &matches)?;
-    emit_matches_complete(&app, "search-complete", request_id, total)?;
+    emit_matches_chunk(
+        &app,
+        "search-chunk",
+        &doc_id,
+        request_id,
+        &matches[emitted..],
+    )?;
+    let total = matches.len();
+    *doc.search_results.lock().unwrap() = Some(matches);
+    emit_matches_complete(&app, "search-complete", &doc_id, request_id, total)?;
     Ok(())
 }
 
-#[tauri::command]
-async fn find_duplicates(
+/// Rows matching `matcher`, scanned with pending edits applied and deleted rows skipped
+fn search_edited_rows(
+    doc: &DocumentState,
     column_idx: Option<usize>,
-    state: State<'_, AppState>,
+    matcher: &CellMatcher,
+    check: &(dyn Fn() -> Result<(), String> + Sync),
+    progress: &(dyn Fn(usize, usize) + Sync),
 ) -> Result<Vec<usize>, String> {
-    let path = state
-        .file_path
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No file loaded")?;
-    let settings = state.parse_settings.lock().unwrap().clone();
+    let mut matches = fold_rows(
+        doc,
+        check,
+        progress,
+        Vec::new,
+        |mut acc, ids, rows| {
+            for (&id, row) in ids.iter().zip(rows) {
+                let is_match = match column_idx {
+                    Some(index) => row.get(index).is_some_and(|cell| matcher.is_match(cell)),
+                    None => row.iter().any(|cell| matcher.is_match(cell)),
+                };
+                if is_match {
+                    acc.push(id);
+                }
+            }
+            Ok(acc)
+        },
+        |mut left, mut right| {
+            left.append(&mut right);
+            Ok(left)
+        },
+    )?;
+    matches.sort_unstable();
+    Ok(matches)
+}
+
+/// Duplicate rows (or duplicate values of one column) with pending edits applied,
+/// leaving deleted rows out.
+/// Like the file-based search, rows are grouped by hash and then verified by content.
+fn find_edited_duplicates(
+    doc: &DocumentState,
+    journal: &EditJournal,
+    column_idx: Option<usize>,
+    check: &(dyn Fn() -> Result<(), String> + Sync),
+) -> Result<Vec<usize>, String> {
+    let mut hashes = fold_rows(
+        doc,
+        check,
+        &|_, _| {},
+        Vec::new,
+        |mut acc, ids, rows| {
+            for (&id, row) in ids.iter().zip(rows) {
+                let mut hasher = DefaultHasher::new();
+                match column_idx {
+                    Some(index) => row
+                        .get(index)
+                        .map(String::as_str)
+                        .unwrap_or("")
+                        .hash(&mut hasher),
+                    None => row.hash(&mut hasher),
+                }
+                acc.push((hasher.finish(), id));
+            }
+            Ok(acc)
+        },
+        |mut left, mut right| {
+            left.append(&mut right);
+            Ok(left)
+        },
+    )?;
+    hashes.par_sort_unstable();
+
+    let mut duplicates = Vec::new();
+    for group in hashes.chunk_by(|a, b| a.0 == b.0) {
+        if group.len() < 2 {
+            continue;
+        }
+        check()?;
+        let candidates = group.iter().map(|&(_, row)| row).collect::<Vec<_>>();
+        let rows = read_edited_rows(doc, journal, &candidates)?;
+        let mut content_map: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+        for (row_idx, row) in candidates.into_iter().zip(rows) {
+            let key = match column_idx {
+                Some(index) => vec![row.get(index).cloned().unwrap_or_default()],
+                None => row,
+            };
+            content_map.entry(key).or_default().push(row_idx);
+        }
+        duplicates.extend(
+            content_map
+                .into_values()
+                .filter(|indices| indices.len() > 1)
+                .flatten(),
+        );
+    }
+    duplicates.sort_unstable();
+    Ok(duplicates)
+}
+
+/// Reads rows by id with pending edits applied
+fn read_edited_rows(
+    doc: &DocumentState,
+    journal: &EditJournal,
+    indices: &[usize],
+) -> Result<Vec<Vec<String>>, String> {
+    let rows = read_file_rows(doc, indices)?;
+    Ok(journal.apply_indexed(indices, rows))
+}
+
+/// Reads rows by id as they are on disk, one (possibly empty) row per id
+fn read_file_rows(doc: &DocumentState, indices: &[usize]) -> Result<Vec<Vec<String>>, String> {
+    let settings = doc.parse_settings.lock().unwrap().clone();
+    let expected_columns = Some(doc.headers.lock().unwrap().len());
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let offsets_guard = doc.row_offsets.lock().unwrap();
+    let offsets = offsets_guard
+        .as_ref()
+        .ok_or("Row offsets are still being built")?;
+    let mut warnings = Vec::new();
+    if let Some(mmap) = mmap.as_ref() {
+        read_rows_by_index_mmap(
+            &mmap[..],
+            offsets,
+            indices,
+            &settings,
+            expected_columns,
+            &mut warnings,
+        )
+    } else {
+        read_rows_by_index(
+            &doc.file_path,
+            offsets,
+            indices,
+            &settings,
+            expected_columns,
+            &mut warnings,
+        )
+    }
+    .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn find_duplicates(
+    doc_id: String,
+    column_idx: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<usize>, String> {
+    let doc = state.document(&doc_id)?;
+    let path = doc.file_path.clone();
+    let settings = doc.parse_settings.lock().unwrap().clone();
 
-    let mmap = state.mmap.lock().unwrap().clone();
-    let offsets = state.row_offsets.lock().unwrap().clone();
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let offsets = doc.row_offsets.lock().unwrap().clone();
     // Offsets are required for random access verification
     let offsets = offsets.ok_or("File not fully indexed yet")?;
 
     // Use hashed approach for memory efficiency
-    let duplicates = if let Some(mmap) = mmap.as_ref() {
-        csv_handler::find_duplicates_hashed_mmap(&mmap[..], &offsets, &settings, column_idx)
-            .map_err(|err| err.to_string())?
-    } else {
-        csv_handler::find_duplicates_hashed(&path, &offsets, &settings, column_idx)
-            .map_err(|err| err.to_string())?
-    };
-
-    Ok(duplicates)
+    state
+        .jobs
+        .run(
+            JobKind::Duplicates,
+            &doc_id,
+            "Find duplicates",
+            move |job| {
+                let duplicates = if let Some(journal) = doc.edited() {
+                    find_edited_duplicates(&doc, &journal, column_idx, &|| job.check())
+                } else if let Some(mmap) = mmap.as_ref() {
+                    csv_handler::find_duplicates_hashed_mmap(
+                        &mmap[..],
+                        &offsets,
+                        &settings,
+                        column_idx,
+                        job.cancel_flag(),
+                    )
+                    .map_err(|err| err.to_string())
+                } else {
+                    csv_handler::find_duplicates_hashed(
+                        &path,
+                        &offsets,
+                        &settings,
+                        column_idx,
+                        job.cancel_flag(),
+                    )
+                    .map_err(|err| err.to_string())
+                }?;
+                *doc.duplicate_results.lock().unwrap() = Some(duplicates.clone());
+                Ok(duplicates)
+            },
+        )
+        .await
 }
 
 #[tauri::command]
 async fn find_duplicates_stream(
+    doc_id: String,
     column_idx: Option<usize>,
     request_id: u32,
     state: State<'_, AppState>,
     app: tauri::AppHandle,
 ) -> Result<(), String> {
-    let path = state
-        .file_path
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No file loaded")?;
-    let settings = state.parse_settings.lock().unwrap().clone();
+    let doc = state.document(&doc_id)?;
+    let path = doc.file_path.clone();
+    let settings = doc.parse_settings.lock().unwrap().clone();
 
-    let mmap = state.mmap.lock().unwrap().clone();
-    let offsets = state.row_offsets.lock().unwrap().clone();
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let offsets = doc.row_offsets.lock().unwrap().clone();
     let offsets = offsets.ok_or("File not fully indexed yet")?;
+    let job_doc_id = doc_id.clone();
+    state
+        .jobs
+        .run(
+            JobKind::Duplicates,
+            &job_doc_id,
+            "Find duplicates",
+            move |job| {
+                let token = RequestToken::with_flag(
+                    &doc.request_tokens,
+                    request_id,
+                    job.cancel_flag().clone(),
+                );
+                let duplicates = if let Some(journal) = doc.edited() {
+                    find_edited_duplicates(&doc, &journal, column_idx, &|| token.check())
+                } else if let Some(mmap) = mmap.as_ref() {
+                    csv_handler::find_duplicates_hashed_mmap(
+                        &mmap[..],
+                        &offsets,
+                        &settings,
+                        column_idx,
+                        &token.flag,
+                    )
+                    .map_err(|err| err.to_string())
+                } else {
+                    csv_handler::find_duplicates_hashed(
+                        &path,
+                        &offsets,
+                        &settings,
+                        column_idx,
+                        &token.flag,
+                    )
+                    .map_err(|err| err.to_string())
+                }?;
+                *doc.duplicate_results.lock().unwrap() = Some(duplicates.clone());
 
-    let duplicates = if let Some(mmap) = mmap.as_ref() {
-        csv_handler::find_duplicates_hashed_mmap(&mmap[..], &offsets, &settings, column_idx)
-            .map_err(|err| err.to_string())?
-    } else {
-        csv_handler::find_duplicates_hashed(&path, &offsets, &settings, column_idx)
-            .map_err(|err| err.to_string())?
-    };
+                let total = duplicates.len();
+                for chunk in duplicates.chunks(RESULT_CHUNK_SIZE) {
+                    token.check()?;
+                    emit_matches_chunk(&app, "duplicates-chunk", &doc_id, request_id, chunk)?;
+                }
+                emit_matches_complete(&app, "duplicates-complete", &doc_id, request_id, total)
+            },
+        )
+        .await
+}
 
-    let total = duplicates.len();
-    for chunk in duplicates.chunks(RESULT_CHUNK_SIZE) {
-        emit_matches_chunk(&app, "duplicates-chunk", request_id, chunk)?;
+#[tauri::command]
+async fn cancel_request(
+    doc_id: String,
+    request_id: u32,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let doc = state.document(&doc_id)?;
+    let tokens = doc.request_tokens.lock().unwrap();
+    match tokens.get(&request_id) {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            Ok(true)
+        }
+        None => Ok(false),
     }
-    emit_matches_complete(&app, "duplicates-complete", request_id, total)?;
-    Ok(())
 }
 
 #[tauri::command]
+async fn list_jobs(state: State<'_, AppState>) -> Result<Vec<JobInfo>, String> {
+    Ok(state.jobs.list())
+}
+
+#[tauri::command]
+async fn cancel_job(job_id: u64, state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.jobs.cancel(job_id))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn sort_csv(
-    column_idx: usize,
-    ascending: bool,
+    doc_id: String,
+    column_idx: Option<usize>,
+    ascending: Option<bool>,
+    mode: Option<String>,
+    empty_placement: Option<String>,
+    keys: Option<Vec<SortKeyInput>>,
     state: State<'_, AppState>,
     app: tauri::AppHandle,
-) -> Result<Vec<usize>, String> {
-    let path = state
-        .file_path
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No file loaded")?;
-    let settings = state.parse_settings.lock().unwrap().clone();
+) -> Result<Arc<Vec<usize>>, String> {
+    let doc = state.document(&doc_id)?;
+    let empty_placement = empty_placement
+        .as_deref()
+        .map(str::parse)
+        .transpose()?
+        .unwrap_or(EmptyPlacement::Last);
+    let spec = match keys {
+        Some(keys) => SortSpec {
+            keys: keys
+                .iter()
+                .map(SortKey::try_from)
+                .collect::<Result<_, _>>()?,
+            empty_placement,
+        },
+        None => SortSpec::single(
+            column_idx.ok_or("No sort column given")?,
+            ascending.unwrap_or(true),
+            SortOptions {
+                mode: mode
+                    .as_deref()
+                    .map(str::parse)
+                    .transpose()?
+                    .unwrap_or(SortMode::Auto),
+                empty_placement,
+            },
+        ),
+    };
+    if spec.keys.is_empty() {
+        return Err("Sort spec has no keys".to_string());
+    }
+
+    let label = format!("Sort by {}", spec.describe());
+    state
+        .jobs
+        .run(JobKind::Sort, &doc_id, label, move |job| {
+            sort_rows(&doc, &spec, &app.state::<AppState>(), &app, job)
+        })
+        .await
+}
+
+fn sort_rows(
+    doc: &DocumentState,
+    spec: &SortSpec,
+    state: &AppState,
+    app: &tauri::AppHandle,
+    job: &JobHandle,
+) -> Result<Arc<Vec<usize>>, String> {
+    let path = doc.file_path.as_str();
+    let settings = doc.parse_settings.lock().unwrap().clone();
     let started = std::time::Instant::now();
     let debug_enabled = *state.debug_logging.lock().unwrap();
     if debug_enabled {
         let _ = append_debug_line(
-            &app,
+            app,
             &format!(
-                "[{}] INFO sort_csv start column_idx={column_idx} ascending={ascending} path={path}",
-                now_timestamp()
+                "[{}] INFO sort_csv start spec={} empty={} path={path}",
+                now_timestamp(),
+                spec.describe(),
+                spec.empty_placement.as_str()
             ),
         );
     }
 
-    let cache_dir = ensure_cache_dir(&app)?;
+    let cache_dir = ensure_cache_dir(app)?;
     let settings_hash = settings_cache_hash(&settings);
-    let key = cache_key(&path, Some(settings_hash))?;
-    let order_path = order_cache_path(&cache_dir, key, column_idx, ascending);
-    if let Ok(Some(order)) = read_order_cache(&order_path, key, column_idx, ascending) {
-        *state.sorted_order.lock().unwrap() = Some(order.clone());
+    let key = cache_key(path, Some(settings_hash))?;
+    let order_path = order_cache_path(&cache_dir, key, spec);
+    // Cached orders describe the file on disk, not its pending edits
+    let journal = doc.edited();
+    let cached = match journal {
+        Some(_) => None,
+        None => read_order_cache(&order_path, key, spec).ok().flatten(),
+    };
+    if let Some(order) = cached {
+        let order = Arc::new(order);
+        *doc.sorted_order.lock().unwrap() = Some(order.clone());
         if debug_enabled {
             let _ = append_debug_line(
-                &app,
+                app,
                 &format!(
                     "[{}] INFO sort_csv cache_hit len={} ms={}",
                     now_timestamp(),
@@ -1152,256 +2354,1669 @@ async fn sort_csv(
         }
         return Ok(order);
     }
-    if !ascending {
-        let asc_path = order_cache_path(&cache_dir, key, column_idx, true);
-        if let Ok(Some(mut order)) = read_order_cache(&asc_path, key, column_idx, true) {
-            order.reverse();
-            *state.sorted_order.lock().unwrap() = Some(order.clone());
-            if debug_enabled {
-                let _ = append_debug_line(
-                    &app,
-                    &format!(
-                        "[{}] INFO sort_csv cache_hit(reversed) len={} ms={}",
-                        now_timestamp(),
-                        order.len(),
-                        started.elapsed().as_millis()
-                    ),
-                );
+
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let offsets = doc.row_offsets.lock().unwrap().clone();
+    let expected_columns = {
+        let len = doc.headers.lock().unwrap().len();
+        if len == 0 {
+            None
+        } else {
+            Some(len)
+        }
+    };
+    // Rows are read in file order, before pending edits are applied
+    let read_file_chunk = |start: usize, warnings: &mut Vec<ParseWarning>| {
+        match offsets.as_ref() {
+            Some(offsets) => {
+                if let Some(mmap) = mmap.as_ref() {
+                    read_chunk_with_offsets_mmap(
+                        &mmap[..],
+                        offsets,
+                        start,
+                        BULK_CHUNK_SIZE,
+                        &settings,
+                        expected_columns,
+                        warnings,
+                    )
+                } else {
+                    read_chunk_with_offsets(
+                        path,
+                        offsets,
+                        start,
+                        BULK_CHUNK_SIZE,
+                        &settings,
+                        expected_columns,
+                        warnings,
+                    )
+                }
+            }
+            None => {
+                if let Some(mmap) = mmap.as_ref() {
+                    read_chunk_mmap(
+                        &mmap[..],
+                        start,
+                        BULK_CHUNK_SIZE,
+                        &settings,
+                        expected_columns,
+                        warnings,
+                    )
+                } else {
+                    read_chunk(
+                        path,
+                        start,
+                        BULK_CHUNK_SIZE,
+                        &settings,
+                        expected_columns,
+                        warnings,
+                    )
+                }
+            }
+        }
+        .map_err(|err| err.to_string())
+    };
+    // Memory optimization: truncate values to 256 chars max
+    // Most sort comparisons differ in first few chars anyway
+    let mut warnings = Vec::new();
+    let memory_budget = *state.sort_memory_budget.lock().unwrap();
+    let mut sorter = ExternalSorter::new(spec, &cache_dir, memory_budget);
+    let expected_rows = offsets
+        .as_ref()
+        .map(|o| o.len())
+        .unwrap_or_else(|| *doc.total_rows.lock().unwrap());
+    let mut start = 0usize;
+
+    loop {
+        job.check()?;
+        let chunk = read_file_chunk(start, &mut warnings)?;
+        if chunk.is_empty() {
+            break;
+        }
+        let read = chunk.len();
+        let chunk = match journal.as_ref() {
+            Some(journal) => journal.apply_rows(start, chunk),
+            None => chunk,
+        };
+        for (idx, row) in chunk.iter().enumerate() {
+            if journal
+                .as_ref()
+                .is_some_and(|journal| journal.is_deleted(start + idx))
+            {
+                continue;
+            }
+            let (values, truncated) = truncated_sort_values(spec, row);
+            sorter.push((start + idx) as u32, values, truncated)?;
+        }
+
+        if read < BULK_CHUNK_SIZE {
+            break;
+        }
+        start += read;
+        job.set_progress(start, Some(expected_rows.max(start)));
+    }
+    if let Some(journal) = journal.as_ref() {
+        let first = journal.file_rows();
+        for (idx, row) in journal
+            .inserted_rows(first, journal.row_count())
+            .iter()
+            .enumerate()
+        {
+            if journal.is_deleted(first + idx) {
+                continue;
+            }
+            let (values, truncated) = truncated_sort_values(spec, row);
+            sorter.push((first + idx) as u32, values, truncated)?;
+        }
+    }
+
+    // Stable sort: rows that tie on every key keep their file order.
+    // Runs beyond the memory budget were spilled to the cache dir and are merged
+    // straight into an order file, which is read back once its ties are settled.
+    let spilled_runs = sorter.spilled_runs();
+    let order_file = tempfile::Builder::new()
+        .prefix("order_")
+        .suffix(".part")
+        .tempfile_in(&cache_dir)
+        .map_err(|err| err.to_string())?;
+    let mut writer = OrderCacheWriter::new(
+        order_file.reopen().map_err(|err| err.to_string())?,
+        key,
+        spec,
+    )?;
+    let mut merged = 0usize;
+    let truncated_ties = sorter.finish(|idx| {
+        merged += 1;
+        if merged.is_multiple_of(BULK_CHUNK_SIZE) {
+            job.check()?;
+            job.set_progress(merged, Some(expected_rows.max(merged)));
+        }
+        writer.push(idx as usize)
+    })?;
+    writer.finish()?;
+    job.check()?;
+
+    // Rows whose truncated keys tie are re-sorted by their full cell values and written
+    // over their ranges of the order file. The tied rows go through an external sort by
+    // row number first, so they are read in file order, and then through a second one
+    // keyed by tie group, so neither the list nor the full values outgrow the budget.
+    if !truncated_ties.is_empty() {
+        let row_spec = SortSpec::single(
+            0,
+            true,
+            SortOptions {
+                mode: SortMode::Numeric,
+                ..SortOptions::default()
+            },
+        );
+        // Each entry carries its tie group where a row index would normally go
+        let mut tied_rows = ExternalSorter::new(&row_spec, &cache_dir, memory_budget);
+        for (group, range) in truncated_ties.iter().enumerate() {
+            for start in range.clone().step_by(BULK_CHUNK_SIZE) {
+                job.check()?;
+                let count = BULK_CHUNK_SIZE.min(range.end - start);
+                for row in read_order_range(order_file.path(), start, count)? {
+                    let values = Box::new([SortValue::Number(row as f64)]);
+                    tied_rows.push(group as u32, values, false)?;
+                }
             }
-            return Ok(order);
         }
+
+        let mut tie_spec = spec.clone();
+        // The leading key is the tie group rather than a column
+        tie_spec.keys.insert(
+            0,
+            SortKey {
+                column: 0,
+                ascending: true,
+                mode: SortMode::Numeric,
+            },
+        );
+        let mut tie_sorter = ExternalSorter::new(&tie_spec, &cache_dir, memory_budget);
+        let mut push_tied = |row: usize, group: usize, cells: &[String]| {
+            let values = std::iter::once(SortValue::Number(group as f64))
+                .chain(spec.keys.iter().map(|sort_key| {
+                    cells
+                        .get(sort_key.column)
+                        .map(|s| parse_sort_value(s, sort_key.mode))
+                        .unwrap_or(SortValue::Empty)
+                }))
+                .collect();
+            tie_sorter.push(row as u32, values, false)
+        };
+
+        // Without row offsets the file is scanned again, picking out the tied rows
+        let mut scan_start = 0usize;
+        let mut scan_chunk = Vec::new();
+        let mut scan_done = false;
+        let mut read_tied = |indices: &[usize], warnings: &mut Vec<ParseWarning>| {
+            if let Some(offsets) = offsets.as_ref() {
+                let full_rows = if let Some(mmap) = mmap.as_ref() {
+                    read_rows_by_index_mmap(
+                        &mmap[..],
+                        offsets,
+                        indices,
+                        &settings,
+                        expected_columns,
+                        warnings,
+                    )
+                } else {
+                    read_rows_by_index(
+                        path,
+                        offsets,
+                        indices,
+                        &settings,
+                        expected_columns,
+                        warnings,
+                    )
+                }
+                .map_err(|err| err.to_string())?;
+                return Ok(match journal.as_ref() {
+                    Some(journal) => journal.apply_indexed(indices, full_rows),
+                    None => full_rows,
+                });
+            }
+            let mut full_rows = Vec::with_capacity(indices.len());
+            for &row in indices {
+                while !scan_done && row >= scan_start + scan_chunk.len() {
+                    scan_start += scan_chunk.len();
+                    let chunk = read_file_chunk(scan_start, warnings)?;
+                    scan_done = chunk.len() < BULK_CHUNK_SIZE;
+                    scan_chunk = match journal.as_ref() {
+                        Some(journal) => journal.apply_rows(scan_start, chunk),
+                        None => chunk,
+                    };
+                }
+                let cells = match scan_chunk.get(row.wrapping_sub(scan_start)) {
+                    Some(cells) => cells.clone(),
+                    // Rows past the end of the file were inserted by edits
+                    None => journal
+                        .as_ref()
+                        .and_then(|journal| journal.inserted_rows(row, row + 1).pop())
+                        .unwrap_or_default(),
+                };
+                full_rows.push(cells);
+            }
+            Ok::<_, String>(full_rows)
+        };
+
+        let mut batch = Vec::with_capacity(BULK_CHUNK_SIZE);
+        let mut settle_batch = |batch: &mut Vec<(usize, usize)>,
+                                warnings: &mut Vec<ParseWarning>| {
+            job.check()?;
+            let indices = batch.iter().map(|(row, _)| *row).collect::<Vec<_>>();
+            let full_rows = read_tied(&indices, warnings)?;
+            for ((row, group), cells) in batch.drain(..).zip(full_rows.iter()) {
+                push_tied(row, group, cells)?;
+            }
+            Ok::<_, String>(())
+        };
+        tied_rows.finish_with_values(|group, values| {
+            let Some(SortValue::Number(row)) = values.first() else {
+                return Err("Sort tie list is unreadable".to_string());
+            };
+            batch.push((*row as usize, group as usize));
+            if batch.len() == BULK_CHUNK_SIZE {
+                settle_batch(&mut batch, &mut warnings)?;
+            }
+            Ok(())
+        })?;
+        settle_batch(&mut batch, &mut warnings)?;
+
+        let mut patcher = OrderCachePatcher::open(order_file.path())?;
+        let mut positions = truncated_ties.iter().flat_map(|range| range.clone());
+        let mut settled = 0usize;
+        tie_sorter.finish(|row| {
+            settled += 1;
+            if settled.is_multiple_of(BULK_CHUNK_SIZE) {
+                job.check()?;
+            }
+            match positions.next() {
+                Some(position) => patcher.set(position, row as usize),
+                None => Ok(()),
+            }
+        })?;
+        patcher.finish()?;
+    }
+    let order =
+        read_order_cache(order_file.path(), key, spec)?.ok_or("Sort order file is unreadable")?;
+
+    if !warnings.is_empty() {
+        let mut stored = doc.parse_warnings.lock().unwrap();
+        stored.extend(warnings);
+        stored.truncate(MAX_WARNING_COUNT);
+    }
+
+    // Orders of edited documents are dropped with the temp file
+    if journal.is_none() {
+        let _ = order_file.persist(&order_path);
+    }
+    let order = Arc::new(order);
+    *doc.sorted_order.lock().unwrap() = Some(order.clone());
+    if debug_enabled {
+        let _ = append_debug_line(
+            app,
+            &format!(
+                "[{}] INFO sort_csv done len={} runs={} ms={}",
+                now_timestamp(),
+                order.len(),
+                spilled_runs,
+                started.elapsed().as_millis()
+            ),
+        );
+    }
+
+    Ok(order)
+}
+
+/// Sort key values of a row, truncated to `SORT_VALUE_MAX_LEN` to bound memory use,
+/// and whether any of them was cut short
+fn truncated_sort_values(spec: &SortSpec, row: &[String]) -> (Box<[SortValue]>, bool) {
+    let mut truncated = false;
+    let values = spec
+        .keys
+        .iter()
+        .map(|sort_key| {
+            row.get(sort_key.column)
+                .map(|s| {
+                    let key = truncate_utf8(s, SORT_VALUE_MAX_LEN);
+                    truncated |= key.len() < s.len();
+                    parse_sort_value(key, sort_key.mode)
+                })
+                .unwrap_or(SortValue::Empty)
+        })
+        .collect();
+    (values, truncated)
+}
+
+#[tauri::command]
+async fn get_sorted_chunk(
+    doc_id: String,
+    start: usize,
+    count: usize,
+    state: State<'_, AppState>,
+) -> Result<Vec<SortedRow>, String> {
+    let doc = state.document(&doc_id)?;
+    let sorted = doc.sorted_order.lock().unwrap();
+    let order = sorted.as_ref().ok_or("No sorted data")?;
+    if start >= order.len() {
+        return Ok(Vec::new());
+    }
+    let end = usize::min(start + count, order.len());
+    let slice = &order[start..end];
+    let path = doc.file_path.clone();
+    let settings = doc.parse_settings.lock().unwrap().clone();
+    let expected_columns = {
+        let len = doc.headers.lock().unwrap().len();
+        if len == 0 {
+            None
+        } else {
+            Some(len)
+        }
+    };
+    let mut warnings = Vec::new();
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let offsets_guard = doc.row_offsets.lock().unwrap();
+    let offsets = offsets_guard.as_ref().ok_or("Row index not ready")?;
+    let rows = if let Some(mmap) = mmap.as_ref() {
+        read_rows_by_index_mmap(
+            &mmap[..],
+            offsets,
+            slice,
+            &settings,
+            expected_columns,
+            &mut warnings,
+        )
+        .map_err(|err| err.to_string())?
+    } else {
+        read_rows_by_index(
+            &path,
+            offsets,
+            slice,
+            &settings,
+            expected_columns,
+            &mut warnings,
+        )
+        .map_err(|err| err.to_string())?
+    };
+    if !warnings.is_empty() {
+        let mut stored = doc.parse_warnings.lock().unwrap();
+        stored.extend(warnings);
+        stored.truncate(MAX_WARNING_COUNT);
+    }
+    let rows = match doc.edited() {
+        Some(journal) => journal.apply_indexed(slice, rows),
+        None => rows,
+    };
+    let sorted_rows = slice
+        .iter()
+        .zip(rows.into_iter())
+        .map(|(idx, row)| SortedRow { index: *idx, row })
+        .collect::<Vec<_>>();
+    Ok(sorted_rows)
+}
+
+#[tauri::command]
+async fn get_sorted_indices(
+    doc_id: String,
+    start: usize,
+    count: usize,
+    state: State<'_, AppState>,
+) -> Result<Vec<usize>, String> {
+    let doc = state.document(&doc_id)?;
+    let sorted = doc.sorted_order.lock().unwrap();
+    let order = sorted.as_ref().ok_or("No sorted data")?;
+    if start >= order.len() {
+        return Ok(Vec::new());
+    }
+    let end = usize::min(start + count, order.len());
+    Ok(order[start..end].to_vec())
+}
+
+#[tauri::command]
+async fn clear_sort(doc_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let doc = state.document(&doc_id)?;
+    *doc.sorted_order.lock().unwrap() = None;
+    Ok(())
+}
+
+#[tauri::command]
+async fn apply_filter(
+    doc_id: String,
+    expression: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    filter_job(&doc_id, expression, &state).await
+}
+
+/// Filters to rows whose cell equals a value, e.g. one picked from `value_counts`
+#[tauri::command]
+async fn filter_by_value(
+    doc_id: String,
+    column: usize,
+    value: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    filter_job(&doc_id, equals_expression(column, &value), &state).await
+}
+
+/// Runs a filter over the document as a cancellable job and returns the match count
+async fn filter_job(doc_id: &str, expression: String, state: &AppState) -> Result<usize, String> {
+    let doc = state.document(doc_id)?;
+    let label = format!("Filter {}", file_label(&doc.source_path));
+    state
+        .jobs
+        .run(JobKind::Filter, doc_id, label, move |job| {
+            run_filter(&doc, expression, &|| job.check(), &|done, total| {
+                job.set_progress(done, Some(total))
+            })
+        })
+        .await
+}
+
+/// Rows matching the expression, over the edited rows when there are pending edits.
+/// `check` can stop the scan between chunks and `progress` gets the rows scanned.
+fn run_filter(
+    doc: &DocumentState,
+    expression: String,
+    check: &(dyn Fn() -> Result<(), String> + Sync),
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> Result<usize, String> {
+    let filter = parse_filter(&expression, &doc.display_headers())?;
+    let mut rows = if doc.edited().is_some() {
+        fold_rows(
+            doc,
+            check,
+            progress,
+            Vec::new,
+            |mut acc, ids, rows| {
+                acc.extend(
+                    ids.iter()
+                        .zip(rows)
+                        .filter(|(_, row)| filter.matches(row))
+                        .map(|(&id, _)| id),
+                );
+                Ok(acc)
+            },
+            |mut left, mut right| {
+                left.append(&mut right);
+                Ok(left)
+            },
+        )?
+    } else {
+        filter_file_rows(doc, &|row: &[String]| filter.matches(row), check, progress)?
+    };
+    rows.sort_unstable();
+
+    let count = rows.len();
+    *doc.filter_view.lock().unwrap() = Some(FilterView { expression, rows });
+    doc.cache.clear();
+    Ok(count)
+}
+
+/// Rows of the file on disk passing `predicate`, in no particular order
+fn filter_file_rows(
+    doc: &DocumentState,
+    predicate: &(dyn Fn(&[String]) -> bool + Sync),
+    check: &(dyn Fn() -> Result<(), String> + Sync),
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> Result<Vec<usize>, String> {
+    let path = doc.file_path.clone();
+    let settings = doc.parse_settings.lock().unwrap().clone();
+    let mmap = doc.mmap.lock().unwrap().clone();
+    let offsets = doc.row_offsets.lock().unwrap().clone();
+    let offsets = offsets.ok_or("File not fully indexed yet")?;
+    let total = offsets.len();
+    let ranges = (0..total)
+        .step_by(SEARCH_CHUNK_SIZE)
+        .map(|start| (start, usize::min(start + SEARCH_CHUNK_SIZE, total)))
+        .collect::<Vec<_>>();
+    let scanned = AtomicUsize::new(0);
+
+    ranges
+        .par_iter()
+        .try_fold(Vec::new, |mut acc, (start, end)| {
+            check()?;
+            let mut found = if let Some(mmap) = mmap.as_ref() {
+                filter_range_with_offsets_mmap(
+                    &mmap[..],
+                    &offsets,
+                    *start,
+                    *end,
+                    &settings,
+                    predicate,
+                )
+            } else {
+                filter_range_with_offsets(&path, &offsets, *start, *end, &settings, predicate)
+            }
+            .map_err(|err| err.to_string())?;
+            acc.append(&mut found);
+            let done = scanned.fetch_add(end - start, Ordering::Relaxed) + (end - start);
+            progress(done, total);
+            Ok::<Vec<usize>, String>(acc)
+        })
+        .try_reduce(Vec::new, |mut left, mut right| {
+            left.append(&mut right);
+            Ok::<Vec<usize>, String>(left)
+        })
+}
+
+/// Brings views computed from row contents in line with the journal after an edit,
+/// undo or redo. The active filter is re-checked on just the rows the change
+/// touched, or re-run when columns moved, and dropped once it no longer parses.
+fn refresh_edited_views(doc: &DocumentState, change: &Change) -> Result<(), String> {
+    *doc.value_counts.lock().unwrap() = None;
+    let Some(expression) = doc
+        .filter_view
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|view| view.expression.clone())
+    else {
+        return Ok(());
+    };
+    let rows = match change {
+        Change::Rows(rows) => rows,
+        Change::Layout => {
+            // Edits are applied synchronously, so the filter is re-run in place
+            if run_filter(doc, expression, &|| Ok(()), &|_, _| {}).is_err() {
+                *doc.filter_view.lock().unwrap() = None;
+                doc.cache.clear();
+            }
+            return Ok(());
+        }
+    };
+
+    let filter = parse_filter(&expression, &doc.display_headers())?;
+    let journal = doc.edits.lock().unwrap().clone();
+    let contents = match journal.as_ref() {
+        Some(journal) => read_edited_rows(doc, journal, rows)?,
+        None => read_file_rows(doc, rows)?,
+    };
+    let row_count = doc.row_count();
+    let mut view = doc.filter_view.lock().unwrap();
+    let Some(view) = view.as_mut() else {
+        return Ok(());
+    };
+    for (&row, cells) in rows.iter().zip(&contents) {
+        let keep = row < row_count
+            && !journal
+                .as_ref()
+                .is_some_and(|journal| journal.is_deleted(row))
+            && filter.matches(cells);
+        match (view.rows.binary_search(&row), keep) {
+            (Ok(position), false) => {
+                view.rows.remove(position);
+            }
+            (Err(position), true) => view.rows.insert(position, row),
+            _ => {}
+        }
+    }
+    doc.cache.clear();
+    Ok(())
+}
+
+/// Unedited text of a cell: the file's own cell for file rows and columns, and
+/// empty for inserted rows and added columns
+fn original_cell(
+    doc: &DocumentState,
+    file_rows: usize,
+    row: usize,
+    column: usize,
+) -> Result<String, String> {
+    let file_column = match doc.edits.lock().unwrap().as_ref() {
+        Some(journal) => journal.file_column(column),
+        None => Some(column),
+    };
+    match file_column {
+        Some(file_column) if row < file_rows => Ok(read_file_rows(doc, &[row])?
+            .into_iter()
+            .next()
+            .and_then(|cells| cells.into_iter().nth(file_column))
+            .unwrap_or_default()),
+        _ => Ok(String::new()),
+    }
+}
+
+#[tauri::command]
+async fn clear_filter(doc_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let doc = state.document(&doc_id)?;
+    *doc.filter_view.lock().unwrap() = None;
+    doc.cache.clear();
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_active_filter(
+    doc_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let doc = state.document(&doc_id)?;
+    let expression = doc
+        .filter_view
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|view| view.expression.clone());
+    Ok(expression)
+}
+
+#[tauri::command]
+async fn get_filter_indices(
+    doc_id: String,
+    start: usize,
+    count: usize,
+    state: State<'_, AppState>,
+) -> Result<Vec<usize>, String> {
+    let doc = state.document(&doc_id)?;
+    let view = doc.filter_view.lock().unwrap();
+    let view = view.as_ref().ok_or("No active filter")?;
+    if start >= view.rows.len() {
+        return Ok(Vec::new());
+    }
+    let end = usize::min(start + count, view.rows.len());
+    Ok(view.rows[start..end].to_vec())
+}
+
+/// Rows of the active filter with their row ids, paged like `get_sorted_chunk`
+#[tauri::command]
+async fn get_filtered_chunk(
+    doc_id: String,
+    start: usize,
+    count: usize,
+    state: State<'_, AppState>,
+) -> Result<Vec<SortedRow>, String> {
+    let doc = state.document(&doc_id)?;
+    let indices = {
+        let view = doc.filter_view.lock().unwrap();
+        let view = view.as_ref().ok_or("No active filter")?;
+        let end = usize::min(start.saturating_add(count), view.rows.len());
+        view.rows.get(start..end).unwrap_or(&[]).to_vec()
+    };
+    let rows = match doc.edited() {
+        Some(journal) => read_edited_rows(&doc, &journal, &indices)?,
+        None => read_file_rows(&doc, &indices)?,
+    };
+    Ok(indices
+        .into_iter()
+        .zip(rows)
+        .map(|(index, row)| SortedRow { index, row })
+        .collect())
+}
+
+/// Records an edit in the document's journal and returns the resulting edit state
+#[tauri::command]
+async fn edit_csv(
+    doc_id: String,
+    edit: Edit,
+    state: State<'_, AppState>,
+) -> Result<EditState, String> {
+    let doc = state.document(&doc_id)?;
+    doc.check_editable()?;
+    // Edits address rows by id, so the row count has to be final
+    let rows = doc
+        .row_offsets
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(Vec::len)
+        .ok_or("Row index not ready")?;
+    let columns = doc.headers.lock().unwrap().len();
+    let original = match &edit {
+        Edit::SetCell { row, column, .. } => original_cell(&doc, rows, *row, *column)?,
+        _ => String::new(),
+    };
+    let change = Arc::make_mut(
+        doc.edits
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| Arc::new(EditJournal::new(rows, columns))),
+    )
+    .apply(edit, &original)?;
+    if let Some(change) = change {
+        refresh_edited_views(&doc, &change)?;
+    }
+    Ok(doc.edit_state())
+}
+
+#[tauri::command]
+async fn undo_edit(doc_id: String, state: State<'_, AppState>) -> Result<EditState, String> {
+    let doc = state.document(&doc_id)?;
+    doc.check_editable()?;
+    let change = doc
+        .edits
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|journal| Arc::make_mut(journal).undo());
+    if let Some(change) = change {
+        refresh_edited_views(&doc, &change)?;
+    }
+    Ok(doc.edit_state())
+}
+
+#[tauri::command]
+async fn redo_edit(doc_id: String, state: State<'_, AppState>) -> Result<EditState, String> {
+    let doc = state.document(&doc_id)?;
+    doc.check_editable()?;
+    let change = doc
+        .edits
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|journal| Arc::make_mut(journal).redo());
+    if let Some(change) = change {
+        refresh_edited_views(&doc, &change)?;
+    }
+    Ok(doc.edit_state())
+}
+
+#[tauri::command]
+async fn get_edit_state(doc_id: String, state: State<'_, AppState>) -> Result<EditState, String> {
+    Ok(state.document(&doc_id)?.edit_state())
+}
+
+/// File open under `doc_id`, so a reloaded webview can reattach to it
+#[tauri::command]
+async fn get_document_path(
+    doc_id: String,
+    state: State<'_, AppState>,
+) -> Result<Option<DocumentSource>, String> {
+    Ok(state
+        .documents
+        .lock()
+        .unwrap()
+        .get(&doc_id)
+        .map(|doc| DocumentSource {
+            path: doc.source_path.clone(),
+            member: doc.member.clone(),
+        }))
+}
+
+#[tauri::command]
+async fn close_document(doc_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    state.close_document(&doc_id);
+    Ok(())
+}
+
+#[tauri::command]
+async fn start_follow(
+    doc_id: String,
+    interval_ms: Option<u64>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let doc = state.document(&doc_id)?;
+    if doc.source_path != doc.file_path {
+        return Err("Compressed files and archive members cannot be followed".to_string());
+    }
+    if doc.edited().is_some() {
+        return Err("Save pending edits before following the file".to_string());
+    }
+    // Appended rows would take the ids of inserted rows left in the redo history
+    *doc.edits.lock().unwrap() = None;
+    let stop = {
+        let mut follow = doc.follow.lock().unwrap();
+        if follow
+            .as_ref()
+            .is_some_and(|stop| !stop.load(Ordering::Relaxed))
+        {
+            return Ok(());
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        *follow = Some(stop.clone());
+        stop
+    };
+    let interval = std::time::Duration::from_millis(
+        interval_ms
+            .unwrap_or(FOLLOW_DEFAULT_INTERVAL_MS)
+            .max(FOLLOW_MIN_INTERVAL_MS),
+    );
+
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(interval);
+            let state = app.state::<AppState>();
+            if stop.load(Ordering::Relaxed) || !state.is_current(&doc_id, &doc) {
+                break;
+            }
+            let poll = poll_follow(&doc, &mut doc.follow_cursor.lock().unwrap());
+            match poll {
+                Ok(FollowPoll::Unchanged) => {}
+                Ok(FollowPoll::Grew {
+                    rows,
+                    filtered,
+                    sort_dropped,
+                }) => {
+                    if sort_dropped {
+                        let _ = app.emit(
+                            "sort-reset",
+                            DocPayload {
+                                doc_id: doc_id.clone(),
+                            },
+                        );
+                    }
+                    if let Some(filtered) = filtered {
+                        emit_row_count(&app, "filter-updated", &doc_id, filtered);
+                    }
+                    emit_row_count(&app, "row-count", &doc_id, rows);
+                }
+                Ok(FollowPoll::Rewritten) => {
+                    println!("[FOLLOW] {} was rewritten, reloading", doc.file_path);
+                    let _ = app.emit(
+                        "follow-reset",
+                        DocPayload {
+                            doc_id: doc_id.clone(),
+                        },
+                    );
+                    break;
+                }
+                Err(err) => println!("[FOLLOW] Poll failed: {err}"),
+            }
+            if let Err(err) = extend_search_index(&doc) {
+                println!("[FOLLOW] Index update failed: {err}");
+            }
+        }
+        stop.store(true, Ordering::Relaxed);
+    });
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_follow(doc_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    state.document(&doc_id)?.stop_follow();
+    Ok(())
+}
+
+#[tauri::command]
+async fn take_pending_open(state: State<'_, AppState>) -> Result<Option<String>, String> {
+    Ok(state.pending_open.lock().unwrap().take())
+}
+
+#[tauri::command]
+async fn profile_column(
+    doc_id: String,
+    column: usize,
+    top_n: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<ColumnProfile, String> {
+    let doc = state.document(&doc_id)?;
+    let name = doc
+        .display_headers()
+        .get(column)
+        .cloned()
+        .ok_or("Column out of range")?;
+    let label = format!("Profile column {name}");
+    state
+        .jobs
+        .run(JobKind::Profile, &doc_id, label, move |job| {
+            profile_rows(
+                &doc,
+                vec![ColumnProfiler::new(column, name)],
+                top_n.unwrap_or(DEFAULT_TOP_VALUES),
+                job,
+            )
+            .map(|mut profiles| profiles.remove(0))
+        })
+        .await
+}
+
+#[tauri::command]
+async fn profile_columns(
+    doc_id: String,
+    top_n: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ColumnProfile>, String> {
+    let doc = state.document(&doc_id)?;
+    let profilers = doc
+        .display_headers()
+        .iter()
+        .enumerate()
+        .map(|(column, name)| ColumnProfiler::new(column, name.clone()))
+        .collect();
+    let label = format!("Profile {}", file_label(&doc.file_path));
+    state
+        .jobs
+        .run(JobKind::Profile, &doc_id, label, move |job| {
+            profile_rows(&doc, profilers, top_n.unwrap_or(DEFAULT_TOP_VALUES), job)
+        })
+        .await
+}
+
+/// Streams every row through the profilers, one column per rayon task
+fn profile_rows(
+    doc: &DocumentState,
+    mut profilers: Vec<ColumnProfiler>,
+    top_n: usize,
+    job: &JobHandle,
+) -> Result<Vec<ColumnProfile>, String> {
+    for_each_row_chunk(doc, job, |_, rows| {
+        profilers.par_iter_mut().for_each(|profiler| {
+            let column = profiler.column();
+            for row in rows {
+                profiler.push(row.get(column).map(String::as_str).unwrap_or(""));
+            }
+        });
+        Ok(())
+    })?;
+
+    Ok(profilers
+        .into_iter()
+        .map(|profiler| profiler.finish(top_n))
+        .collect())
+}
+
+/// Paged (value, count) pairs for a column, keyed by the exact cell text so each
+/// value can be handed back to `filter_by_value` unchanged.
+#[tauri::command]
+async fn value_counts(
+    doc_id: String,
+    column: usize,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    sort: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ValueCountsPage, String> {
+    let doc = state.document(&doc_id)?;
+    if column >= doc.display_headers().len() {
+        return Err("Column out of range".to_string());
+    }
+    let order = sort
+        .as_deref()
+        .map(ValueCountOrder::from_str)
+        .unwrap_or(ValueCountOrder::CountDesc);
+
+    let mut cached = doc.value_counts.lock().unwrap().take();
+    if cached.as_ref().is_none_or(|cache| cache.column != column) {
+        let label = format!("Count values in {}", file_label(&doc.file_path));
+        let scan_doc = doc.clone();
+        let (counts, untracked_rows) = state
+            .jobs
+            .run(JobKind::ValueCounts, &doc_id, label, move |job| {
+                scan_value_counts(&scan_doc, column, job)
+            })
+            .await?;
+        cached = Some(ValueCountsCache {
+            column,
+            counts,
+            untracked_rows,
+        });
+    }
+    let mut cache = cached.unwrap();
+    sort_value_counts(&mut cache.counts, order);
+
+    let offset = offset.unwrap_or(0).min(cache.counts.len());
+    let end = limit.map_or(cache.counts.len(), |limit| {
+        usize::min(offset.saturating_add(limit), cache.counts.len())
+    });
+    let page = ValueCountsPage {
+        total_values: cache.counts.len(),
+        values: cache.counts[offset..end].to_vec(),
+        untracked_rows: cache.untracked_rows,
+    };
+    *doc.value_counts.lock().unwrap() = Some(cache);
+    Ok(page)
+}
+
+/// Streaming hash aggregation over the column. Once `VALUE_COUNTS_MAX_CARDINALITY`
+/// values are tracked, rows with unseen values are only tallied as untracked.
+fn scan_value_counts(
+    doc: &DocumentState,
+    column: usize,
+    job: &JobHandle,
+) -> Result<(Vec<ValueCount>, usize), String> {
+    let mut counts: HashMap<Box<str>, usize> = HashMap::new();
+    let mut untracked_rows = 0usize;
+    for_each_row_chunk(doc, job, |_, rows| {
+        for row in rows {
+            let Some(cell) = row.get(column) else {
+                continue;
+            };
+            if let Some(count) = counts.get_mut(cell.as_str()) {
+                *count += 1;
+            } else if counts.len() < VALUE_COUNTS_MAX_CARDINALITY {
+                counts.insert(cell.as_str().into(), 1);
+            } else {
+                untracked_rows += 1;
+            }
+        }
+        Ok(())
+    })?;
+
+    let counts = counts
+        .into_iter()
+        .map(|(value, count)| ValueCount {
+            value: value.into_string(),
+            count,
+        })
+        .collect();
+    Ok((counts, untracked_rows))
+}
+
+#[tauri::command]
+async fn aggregate_csv(
+    doc_id: String,
+    group_by: Vec<usize>,
+    aggregates: Vec<Aggregate>,
+    state: State<'_, AppState>,
+) -> Result<AggregateTable, String> {
+    let doc = state.document(&doc_id)?;
+    let headers = doc.display_headers();
+    let plan = AggregatePlan::new(group_by, aggregates, headers.len())?;
+    let label = format!("Summarize {}", file_label(&doc.file_path));
+    let table = state
+        .jobs
+        .run(JobKind::Aggregate, &doc_id, label, {
+            let doc = doc.clone();
+            move |job| {
+                aggregate_rows(&doc, &plan, job).map(|rows| AggregateTable {
+                    headers: plan.headers(&headers),
+                    rows,
+                    totals: None,
+                })
+            }
+        })
+        .await?;
+    *doc.aggregate_result.lock().unwrap() = Some(table.clone());
+    Ok(table)
+}
+
+fn aggregate_rows(
+    doc: &DocumentState,
+    plan: &AggregatePlan,
+    job: &JobHandle,
+) -> Result<Vec<Vec<String>>, String> {
+    let table = fold_row_ranges(
+        doc,
+        job,
+        || GroupTable::new(plan),
+        |mut table, _, rows| {
+            for row in rows {
+                table.push(row)?;
+            }
+            Ok(table)
+        },
+        |left, right| left.merge(right),
+    )?;
+    Ok(table.finish())
+}
+
+#[tauri::command]
+async fn pivot_csv(
+    doc_id: String,
+    row_key: usize,
+    column_key: usize,
+    aggregate: Aggregate,
+    max_columns: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<AggregateTable, String> {
+    let doc = state.document(&doc_id)?;
+    let headers = doc.display_headers();
+    let plan = PivotPlan::new(
+        row_key,
+        column_key,
+        aggregate,
+        max_columns.unwrap_or(DEFAULT_PIVOT_COLUMNS),
+        headers.len(),
+    )?;
+    let label = format!("Pivot {}", file_label(&doc.file_path));
+    let table = state
+        .jobs
+        .run(JobKind::Aggregate, &doc_id, label, {
+            let doc = doc.clone();
+            move |job| {
+                fold_row_ranges(
+                    &doc,
+                    job,
+                    || PivotTable::new(&plan),
+                    |mut table, _, rows| {
+                        for row in rows {
+                            table.push(row)?;
+                        }
+                        Ok(table)
+                    },
+                    |left, right| left.merge(right),
+                )
+                .map(|table| table.finish(&headers))
+            }
+        })
+        .await?;
+    *doc.aggregate_result.lock().unwrap() = Some(table.clone());
+    Ok(table)
+}
+
+/// Folds every row of the document in parallel over `SEARCH_CHUNK_SIZE` ranges of
+/// the row offsets, reporting progress and stopping early when the job is cancelled
+fn fold_row_ranges<T, Init, Fold, Reduce>(
+    doc: &DocumentState,
+    job: &JobHandle,
+    init: Init,
+    fold: Fold,
+    reduce: Reduce,
+) -> Result<T, String>
+where
+    T: Send,
+    Init: Fn() -> T + Sync + Send,
+    Fold: Fn(T, &[usize], &[Vec<String>]) -> Result<T, String> + Sync + Send,
+    Reduce: Fn(T, T) -> Result<T, String> + Sync + Send,
+{
+    fold_rows(
+        doc,
+        &|| job.check(),
+        &|done, total| job.set_progress(done, Some(total)),
+        init,
+        fold,
+        reduce,
+    )
+}
+
+/// Like `fold_row_ranges`, with cancellation and progress supplied by the caller.
+/// `fold` gets each chunk's row ids alongside its rows, as read by `RowScan`.
+fn fold_rows<T, Init, Fold, Reduce>(
+    doc: &DocumentState,
+    check: &(dyn Fn() -> Result<(), String> + Sync),
+    progress: &(dyn Fn(usize, usize) + Sync),
+    init: Init,
+    fold: Fold,
+    reduce: Reduce,
+) -> Result<T, String>
+where
+    T: Send,
+    Init: Fn() -> T + Sync + Send,
+    Fold: Fn(T, &[usize], &[Vec<String>]) -> Result<T, String> + Sync + Send,
+    Reduce: Fn(T, T) -> Result<T, String> + Sync + Send,
+{
+    let scan = RowScan::new(doc)?;
+    let total = scan.total();
+    let scanned = AtomicUsize::new(0);
+
+    scan.ranges(SEARCH_CHUNK_SIZE)
+        .par_iter()
+        .try_fold(&init, |acc, &(start, end)| {
+            check()?;
+            let (ids, rows) = scan.read(start, end)?;
+            let acc = fold(acc, &ids, &rows)?;
+            let done = scanned.fetch_add(end - start, Ordering::Relaxed) + (end - start);
+            progress(done, total);
+            Ok::<T, String>(acc)
+        })
+        .try_reduce(&init, &reduce)
+}
+
+/// Sequential counterpart of `fold_rows` for scans that keep one state across all
+/// chunks, visiting `BULK_CHUNK_SIZE` rows at a time in row id order
+fn for_each_row_chunk(
+    doc: &DocumentState,
+    job: &JobHandle,
+    mut visit: impl FnMut(&[usize], &[Vec<String>]) -> Result<(), String>,
+) -> Result<(), String> {
+    let scan = RowScan::new(doc)?;
+    let total = scan.total();
+    for (start, end) in scan.ranges(BULK_CHUNK_SIZE) {
+        job.check()?;
+        let (ids, rows) = scan.read(start, end)?;
+        visit(&ids, &rows)?;
+        job.set_progress(end, Some(total));
+    }
+    Ok(())
+}
+
+/// What every full scan of a document reads: file rows through the row offsets with
+/// pending edits applied, then inserted rows, leaving deleted rows out
+struct RowScan {
+    path: String,
+    settings: ParseSettings,
+    mmap: Option<Arc<Mmap>>,
+    offsets: Vec<u64>,
+    expected_columns: Option<usize>,
+    journal: Option<Arc<EditJournal>>,
+}
+
+impl RowScan {
+    fn new(doc: &DocumentState) -> Result<Self, String> {
+        let offsets = doc
+            .row_offsets
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("Row offsets are still being built")?;
+        let columns = doc.headers.lock().unwrap().len();
+        Ok(RowScan {
+            path: doc.file_path.clone(),
+            settings: doc.parse_settings.lock().unwrap().clone(),
+            mmap: doc.mmap.lock().unwrap().clone(),
+            offsets,
+            expected_columns: (columns > 0).then_some(columns),
+            journal: doc.edited(),
+        })
     }
 
-    let mmap = state.mmap.lock().unwrap().clone();
-    let offsets = state.row_offsets.lock().unwrap().clone();
-    let expected_columns = {
-        let len = state.headers.lock().unwrap().len();
-        if len == 0 {
-            None
-        } else {
-            Some(len)
+    /// File rows plus inserted rows, deleted ones included
+    fn total(&self) -> usize {
+        self.journal
+            .as_ref()
+            .map_or(self.offsets.len(), |journal| journal.row_count())
+    }
+
+    /// Row id ranges of at most `size` file rows, then one range of inserted rows
+    fn ranges(&self, size: usize) -> Vec<(usize, usize)> {
+        let file_rows = self.offsets.len();
+        let mut ranges = (0..file_rows)
+            .step_by(size)
+            .map(|start| (start, usize::min(start + size, file_rows)))
+            .collect::<Vec<_>>();
+        if self.total() > file_rows {
+            ranges.push((file_rows, self.total()));
         }
-    };
-    // Memory optimization: truncate values to 256 chars max
-    // Most sort comparisons differ in first few chars anyway
-    const SORT_VALUE_MAX_LEN: usize = 256;
-    let mut warnings = Vec::new();
-    let mut rows: Vec<(u32, Box<str>)> =
-        Vec::with_capacity(offsets.as_ref().map(|o| o.len()).unwrap_or(100_000));
-    let mut start = 0usize;
+        ranges
+    }
 
-    loop {
-        let chunk = match offsets.as_ref() {
-            Some(offsets) => {
-                if let Some(mmap) = mmap.as_ref() {
+    /// Ids and edited contents of the rows in `start..end` that aren't deleted
+    fn read(&self, start: usize, end: usize) -> Result<(Vec<usize>, Vec<Vec<String>>), String> {
+        let rows = match self.journal.as_ref() {
+            Some(journal) if start >= self.offsets.len() => journal.inserted_rows(start, end),
+            journal => {
+                let mut warnings = Vec::new();
+                let chunk = if let Some(mmap) = self.mmap.as_ref() {
                     read_chunk_with_offsets_mmap(
                         &mmap[..],
-                        offsets,
+                        &self.offsets,
                         start,
-                        BULK_CHUNK_SIZE,
-                        &settings,
-                        expected_columns,
+                        end - start,
+                        &self.settings,
+                        self.expected_columns,
                         &mut warnings,
                     )
-                    .map_err(|err| err.to_string())?
                 } else {
                     read_chunk_with_offsets(
-                        &path,
-                        offsets,
+                        &self.path,
+                        &self.offsets,
                         start,
-                        BULK_CHUNK_SIZE,
-                        &settings,
-                        expected_columns,
+                        end - start,
+                        &self.settings,
+                        self.expected_columns,
                         &mut warnings,
                     )
-                    .map_err(|err| err.to_string())?
                 }
-            }
-            None => {
-                if let Some(mmap) = mmap.as_ref() {
-                    read_chunk_mmap(
-                        &mmap[..],
-                        start,
-                        BULK_CHUNK_SIZE,
-                        &settings,
-                        expected_columns,
-                        &mut warnings,
-                    )
-                    .map_err(|err| err.to_string())?
-                } else {
-                    read_chunk(
-                        &path,
-                        start,
-                        BULK_CHUNK_SIZE,
-                        &settings,
-                        expected_columns,
-                        &mut warnings,
-                    )
-                    .map_err(|err| err.to_string())?
+                .map_err(|err| err.to_string())?;
+                match journal {
+                    Some(journal) => journal.apply_rows(start, chunk),
+                    None => chunk,
                 }
             }
         };
+        Ok((start..)
+            .zip(rows)
+            .filter(|(row, _)| {
+                self.journal
+                    .as_ref()
+                    .is_none_or(|journal| !journal.is_deleted(*row))
+            })
+            .unzip())
+    }
+}
 
-        if chunk.is_empty() {
-            break;
-        }
+/// Writes the last aggregation result of the document as a CSV file
+#[tauri::command]
+async fn export_aggregate_csv(
+    doc_id: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let doc = state.document(&doc_id)?;
+    let result = doc.aggregate_result.lock().unwrap();
+    let table = result.as_ref().ok_or("No aggregation to export")?;
+    let mut writer = csv::Writer::from_path(&path).map_err(|err| err.to_string())?;
+    for record in table.records() {
+        writer
+            .write_record(&record)
+            .map_err(|err| err.to_string())?;
+    }
+    writer.flush().map_err(|err| err.to_string())
+}
 
-        for (idx, row) in chunk.iter().enumerate() {
-            let row_index = (start + idx) as u32;
-            let value = row
-                .get(column_idx)
-                .map(|s| {
-                    // Truncate to reduce memory usage
-                    if s.len() > SORT_VALUE_MAX_LEN {
-                        truncate_utf8(s, SORT_VALUE_MAX_LEN).into()
-                    } else {
-                        s.as_str().into()
-                    }
-                })
-                .unwrap_or_else(|| "".into());
-            rows.push((row_index, value));
-        }
+/// Streams rows of the document to a CSV file, with pending edits applied and deleted
+/// rows left out. Unset options keep the format the file was parsed with; `columns`
+/// picks and orders the written columns. Writing over the open file is only allowed
+/// for every row and column, after which the document has to be reopened; compressed,
+/// archived and imported originals are never replaced. Returns the number of rows
+/// written.
+#[tauri::command]
+async fn export_csv(
+    doc_id: String,
+    path: String,
+    options: Option<CsvExportOptions>,
+    source: Option<RowSource>,
+    columns: Option<Vec<usize>>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let options = ExportOptions {
+        csv: options.unwrap_or_default(),
+        ..ExportOptions::default()
+    };
+    export_document(doc_id, path, options, source, columns, &state).await
+}
 
-        if chunk.len() < BULK_CHUNK_SIZE {
-            break;
+/// Like `export_csv`, in any of the CSV, JSON, NDJSON, Markdown, HTML, Parquet or
+/// Arrow IPC formats. Only a CSV may replace the open file.
+#[tauri::command]
+async fn export_rows(
+    doc_id: String,
+    path: String,
+    options: Option<ExportOptions>,
+    source: Option<RowSource>,
+    columns: Option<Vec<usize>>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    export_document(
+        doc_id,
+        path,
+        options.unwrap_or_default(),
+        source,
+        columns,
+        &state,
+    )
+    .await
+}
+
+async fn export_document(
+    doc_id: String,
+    path: String,
+    options: ExportOptions,
+    source: Option<RowSource>,
+    columns: Option<Vec<usize>>,
+    state: &AppState,
+) -> Result<usize, String> {
+    let doc = state.document(&doc_id)?;
+    let settings = doc.parse_settings.lock().unwrap().clone();
+    let format = ExportFormat::resolve(&options)?;
+    let headers = doc.display_headers();
+    check_export_columns(columns.as_deref(), headers.len())?;
+    let source = source.unwrap_or_default();
+    // A compressed, archived or imported original is never replaced by plain CSV; only
+    // the file the rows are read from may be rewritten in place
+    if doc.source_path != doc.file_path && is_same_file(&doc.source_path, &path) {
+        return Err(
+            "The open file is read through a converted copy and cannot be replaced; \
+             choose another file"
+                .to_string(),
+        );
+    }
+    if is_same_file(&doc.file_path, &path) {
+        let whole = matches!(source, RowSource::All)
+            && columns
+                .as_deref()
+                .is_none_or(|columns| columns.iter().copied().eq(0..headers.len()));
+        if format != ExportFormat::Csv || !whole {
+            return Err(
+                "Only a CSV of every row and column can replace the open file; choose another file"
+                    .to_string(),
+            );
         }
-        start += chunk.len();
+        // A mapped file cannot be replaced on Windows; read through the file instead
+        *doc.mmap.lock().unwrap() = None;
     }
+    let indices = source_rows(&doc, source)?;
 
-    if !warnings.is_empty() {
-        let mut stored = state.parse_warnings.lock().unwrap();
-        stored.extend(warnings);
-        stored.truncate(MAX_WARNING_COUNT);
-    }
+    let label = format!("Export {}", file_label(&path));
+    let job_doc_id = doc_id.clone();
+    state
+        .jobs
+        .run(JobKind::Export, &job_doc_id, label, move |job| {
+            write_atomically(Path::new(&path), |out| {
+                let columns = columns.as_deref();
+                let headers = project_row(&headers, columns);
+                let write_rows = |writer: &mut dyn RowWriter| {
+                    write_source_rows(&doc, indices.as_deref(), columns, writer, job)
+                };
+                let mut writer = if format.is_columnar() {
+                    // Column types come from a first pass over the same rows
+                    let mut inference = SchemaInference::default();
+                    inference.begin(&headers)?;
+                    job.begin_pass(0, 2);
+                    write_rows(&mut inference)?;
+                    job.begin_pass(1, 2);
+                    columnar_writer(
+                        format,
+                        options.compression.as_deref(),
+                        &inference.schema(&headers),
+                        BULK_CHUNK_SIZE,
+                        out,
+                    )?
+                } else {
+                    row_writer(&options, &settings, out)?
+                };
+                writer.begin(&headers)?;
+                let written = write_rows(writer.as_mut())?;
+                writer.finish()?;
+                Ok(written)
+            })
+        })
+        .await
+}
 
-    if ascending {
-        rows.par_sort_unstable_by(|a, b| a.1.cmp(&b.1));
-    } else {
-        rows.par_sort_unstable_by(|a, b| b.1.cmp(&a.1));
+/// Streams rows of the document into a new table of a SQLite database, creating the
+/// file if needed and removing it again if the export fails. Column affinities come
+/// from a first pass over the rows. Returns the number of rows written.
+#[tauri::command]
+async fn export_sqlite(
+    doc_id: String,
+    path: String,
+    options: SqliteExportOptions,
+    source: Option<RowSource>,
+    columns: Option<Vec<usize>>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let doc = state.document(&doc_id)?;
+    if is_open_file(&doc, &path) {
+        return Err("Cannot write a SQLite database over the open file".to_string());
     }
+    let headers = doc.display_headers();
+    check_export_columns(columns.as_deref(), headers.len())?;
+    let indices = source_rows(&doc, source.unwrap_or_default())?;
 
-    // Convert u32 indices back to usize
-    let order: Vec<usize> = rows.iter().map(|(idx, _)| *idx as usize).collect();
-    *state.sorted_order.lock().unwrap() = Some(order.clone());
-    let _ = write_order_cache(&order_path, key, column_idx, ascending, &order);
-    if debug_enabled {
-        let _ = append_debug_line(
-            &app,
-            &format!(
-                "[{}] INFO sort_csv done len={} ms={}",
-                now_timestamp(),
-                order.len(),
-                started.elapsed().as_millis()
-            ),
-        );
-    }
+    let label = format!("Export {}", file_label(&path));
+    state
+        .jobs
+        .run(JobKind::Export, &doc_id, label, move |job| {
+            let columns = columns.as_deref();
+            let headers = project_row(&headers, columns);
+            let mut inference = SchemaInference::default();
+            inference.begin(&headers)?;
+            job.begin_pass(0, 2);
+            write_source_rows(&doc, indices.as_deref(), columns, &mut inference, job)?;
+            job.begin_pass(1, 2);
+            let schema = inference.schema(&headers);
+            let mut writer = SqliteRowWriter::create(&path, &options, &schema)?;
+            writer.begin(&headers)?;
+            let written = write_source_rows(&doc, indices.as_deref(), columns, &mut writer, job)?;
+            writer.finish()?;
+            Ok(written)
+        })
+        .await
+}
 
-    Ok(order)
+#[tauri::command]
+async fn list_sqlite_tables(path: String) -> Result<Vec<SqliteTable>, String> {
+    list_tables(&path)
 }
 
+/// Copies a table or read-only query result of a SQLite database to a cached CSV and
+/// returns its path, which then opens like any other file
 #[tauri::command]
-async fn get_sorted_chunk(
-    start: usize,
-    count: usize,
+async fn import_sqlite(
+    doc_id: String,
+    path: String,
+    table: Option<String>,
+    query: Option<String>,
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<Vec<SortedRow>, String> {
-    let sorted = state.sorted_order.lock().unwrap();
-    let order = sorted.as_ref().ok_or("No sorted data")?;
-    if start >= order.len() {
-        return Ok(Vec::new());
-    }
-    let end = usize::min(start + count, order.len());
-    let slice = &order[start..end];
-    let path = state
-        .file_path
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No file loaded")?;
-    let settings = state.parse_settings.lock().unwrap().clone();
-    let expected_columns = {
-        let len = state.headers.lock().unwrap().len();
-        if len == 0 {
-            None
-        } else {
-            Some(len)
-        }
-    };
-    let mut warnings = Vec::new();
-    let mmap = state.mmap.lock().unwrap().clone();
-    let offsets_guard = state.row_offsets.lock().unwrap();
-    let offsets = offsets_guard.as_ref().ok_or("Row index not ready")?;
-    let rows = if let Some(mmap) = mmap.as_ref() {
-        read_rows_by_index_mmap(
-            &mmap[..],
-            offsets,
-            slice,
-            &settings,
-            expected_columns,
-            &mut warnings,
-        )
-        .map_err(|err| err.to_string())?
-    } else {
-        read_rows_by_index(
-            &path,
-            offsets,
-            slice,
-            &settings,
-            expected_columns,
-            &mut warnings,
-        )
-        .map_err(|err| err.to_string())?
+) -> Result<String, String> {
+    let sql = import_query(table.as_deref(), query.as_deref())?;
+    let stem = Path::new(&path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "database".to_string());
+    let from_query = query
+        .as_deref()
+        .is_some_and(|query| !query.trim().is_empty());
+    let name = match table.as_deref().filter(|_| !from_query) {
+        Some(table) => format!("{stem}-{table}.csv"),
+        None => format!("{stem}-query.csv"),
     };
-    if !warnings.is_empty() {
-        let mut stored = state.parse_warnings.lock().unwrap();
-        stored.extend(warnings);
-        stored.truncate(MAX_WARNING_COUNT);
+    let target = import_cache_path(&ensure_cache_dir(&app)?, &path, &sql, &name)?;
+
+    let label = format!("Import {name}");
+    state
+        .jobs
+        .run(JobKind::Import, &doc_id, label, move |job| {
+            materialize_import(&target, |out| {
+                let progress = |rows| {
+                    job.check()?;
+                    job.set_progress(rows, None);
+                    Ok(())
+                };
+                write_query_csv(&path, &sql, &progress, out).map(|_| ())
+            })?;
+            Ok(target.to_string_lossy().into_owned())
+        })
+        .await
+}
+
+/// Whether `path` names the file the document was opened from or reads its rows from
+fn is_open_file(doc: &DocumentState, path: &str) -> bool {
+    is_same_file(&doc.file_path, path) || is_same_file(&doc.source_path, path)
+}
+
+fn is_same_file(open: &str, path: &str) -> bool {
+    let target = std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    std::fs::canonicalize(open).is_ok_and(|open| open == target) || Path::new(open) == target
+}
+
+fn check_export_columns(columns: Option<&[usize]>, header_count: usize) -> Result<(), String> {
+    if let Some(columns) = columns {
+        if columns.is_empty() {
+            return Err("No columns to export".to_string());
+        }
+        if let Some(column) = columns.iter().find(|&&column| column >= header_count) {
+            return Err(format!("Column {column} out of range"));
+        }
     }
-    let sorted_rows = slice
-        .iter()
-        .zip(rows.into_iter())
-        .map(|(idx, row)| SortedRow { index: *idx, row })
-        .collect::<Vec<_>>();
-    Ok(sorted_rows)
+    Ok(())
 }
 
-#[tauri::command]
-async fn get_sorted_indices(
-    start: usize,
-    count: usize,
-    state: State<'_, AppState>,
-) -> Result<Vec<usize>, String> {
-    let sorted = state.sorted_order.lock().unwrap();
-    let order = sorted.as_ref().ok_or("No sorted data")?;
-    if start >= order.len() {
-        return Ok(Vec::new());
+/// Row ids of an export source, or None for every row in file order
+fn source_rows(doc: &DocumentState, source: RowSource) -> Result<Option<Vec<usize>>, String> {
+    let indices = match source {
+        RowSource::All => None,
+        RowSource::Sorted => Some(
+            doc.sorted_order
+                .lock()
+                .unwrap()
+                .as_deref()
+                .cloned()
+                .ok_or("No sort order to export")?,
+        ),
+        RowSource::Search => Some(
+            doc.search_results
+                .lock()
+                .unwrap()
+                .clone()
+                .ok_or("No search results to export")?,
+        ),
+        RowSource::Duplicates => Some(
+            doc.duplicate_results
+                .lock()
+                .unwrap()
+                .clone()
+                .ok_or("No duplicates to export")?,
+        ),
+        RowSource::Rows { rows } => Some(rows),
+    };
+    Ok(indices)
+}
+
+fn write_source_rows(
+    doc: &DocumentState,
+    indices: Option<&[usize]>,
+    columns: Option<&[usize]>,
+    writer: &mut dyn RowWriter,
+    job: &JobHandle,
+) -> Result<usize, String> {
+    match indices {
+        Some(indices) => write_indexed_rows(doc, indices, columns, writer, job),
+        None => write_file_rows(doc, columns, writer, job),
     }
-    let end = usize::min(start + count, order.len());
-    Ok(order[start..end].to_vec())
 }
 
-#[tauri::command]
-async fn clear_sort(state: State<'_, AppState>) -> Result<(), String> {
-    *state.sorted_order.lock().unwrap() = None;
-    Ok(())
+/// Writes every row in file order, followed by inserted rows, skipping deleted rows
+fn write_file_rows(
+    doc: &DocumentState,
+    columns: Option<&[usize]>,
+    writer: &mut dyn RowWriter,
+    job: &JobHandle,
+) -> Result<usize, String> {
+    let mut written = 0usize;
+    for_each_row_chunk(doc, job, |_, rows| {
+        for row in rows {
+            writer.write_row(&project_row(row, columns))?;
+            written += 1;
+        }
+        Ok(())
+    })?;
+    Ok(written)
 }
 
-#[tauri::command]
-async fn take_pending_open(state: State<'_, AppState>) -> Result<Option<String>, String> {
-    Ok(state.pending_open.lock().unwrap().take())
+/// Writes the rows with the given ids in the given order, skipping deleted rows
+fn write_indexed_rows(
+    doc: &DocumentState,
+    indices: &[usize],
+    columns: Option<&[usize]>,
+    writer: &mut dyn RowWriter,
+    job: &JobHandle,
+) -> Result<usize, String> {
+    let journal = doc.edited();
+    let row_count = doc.row_count();
+    let mut written = 0usize;
+    let mut processed = 0usize;
+
+    for batch in indices.chunks(BULK_CHUNK_SIZE) {
+        job.check()?;
+        let rows = match journal.as_ref() {
+            Some(journal) => read_edited_rows(doc, journal, batch)?,
+            None => read_file_rows(doc, batch)?,
+        };
+        for (&row_id, row) in batch.iter().zip(&rows) {
+            if row_id >= row_count
+                || journal
+                    .as_ref()
+                    .is_some_and(|journal| journal.is_deleted(row_id))
+            {
+                continue;
+            }
+            writer.write_row(&project_row(row, columns))?;
+            written += 1;
+        }
+        processed += batch.len();
+        job.set_progress(processed, Some(indices.len()));
+    }
+
+    Ok(written)
 }
 
 #[tauri::command]
-async fn get_row_count(state: State<'_, AppState>) -> Result<usize, String> {
-    Ok(*state.total_rows.lock().unwrap())
+async fn get_row_count(doc_id: String, state: State<'_, AppState>) -> Result<usize, String> {
+    let doc = state.document(&doc_id)?;
+    Ok(doc.row_count())
 }
 
 #[tauri::command]
 async fn get_parse_warnings(
+    doc_id: String,
     clear: bool,
     state: State<'_, AppState>,
 ) -> Result<Vec<ParseWarning>, String> {
-    let mut warnings = state.parse_warnings.lock().unwrap();
+    let doc = state.document(&doc_id)?;
+    let mut warnings = doc.parse_warnings.lock().unwrap();
     let output = warnings.clone();
     if clear {
         warnings.clear();
@@ -1463,11 +4078,6 @@ async fn append_debug_log(
     append_debug_line(&app, &format!("[{}] WEB {msg}", now_timestamp()))
 }
 
-#[tauri::command]
-async fn write_csv_file(path: String, contents: String) -> Result<(), String> {
-    std::fs::write(path, contents).map_err(|err| err.to_string())
-}
-
 #[tauri::command]
 async fn set_show_index_checked(checked: bool, app: tauri::AppHandle) -> Result<(), String> {
     if let Some(menu) = app.menu() {
@@ -1488,11 +4098,56 @@ async fn set_show_index_checked(checked: bool, app: tauri::AppHandle) -> Result<
 async fn set_enable_indexing(enabled: bool, state: State<'_, AppState>) -> Result<(), String> {
     *state.enable_indexing.lock().unwrap() = enabled;
     if !enabled {
-        state.search_index.lock().unwrap().clear();
+        for doc in state.documents.lock().unwrap().values() {
+            doc.search_index.lock().unwrap().clear();
+        }
     }
     Ok(())
 }
 
+#[tauri::command]
+async fn set_sort_memory_budget(
+    megabytes: usize,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    *state.sort_memory_budget.lock().unwrap() = megabytes.max(1).saturating_mul(1024 * 1024);
+    Ok(())
+}
+
+/// Window menu actions apply to: the focused one, or any open window when none is
+/// focused
+fn menu_target_window(app: &tauri::AppHandle) -> Option<WebviewWindow> {
+    let windows = app.webview_windows();
+    windows
+        .values()
+        .find(|window| window.is_focused().unwrap_or(false))
+        .or_else(|| windows.get("main"))
+        .or_else(|| windows.values().next())
+        .cloned()
+}
+
+/// Sends a menu action to the window it applies to, so each window acts on its own
+/// document
+fn emit_menu_event<S: serde::Serialize + Clone>(app: &tauri::AppHandle, event: &str, payload: S) {
+    if let Some(window) = menu_target_window(app) {
+        let _ = app.emit_to(EventTarget::labeled(window.label()), event, payload);
+    }
+}
+
+/// Opens another window like the main one. Its label is the id of the document it
+/// shows, so files open side by side without replacing each other.
+fn open_document_window(app: &tauri::AppHandle) -> tauri::Result<()> {
+    static NEXT_WINDOW: AtomicUsize = AtomicUsize::new(1);
+    let Some(mut config) = app.config().app.windows.first().cloned() else {
+        return Ok(());
+    };
+    config.label = format!("document-{}", NEXT_WINDOW.fetch_add(1, Ordering::Relaxed));
+    WebviewWindowBuilder::from_config(app, &config)?
+        .enable_clipboard_access()
+        .build()?;
+    Ok(())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let default_settings = default_parse_settings();
@@ -1502,6 +4157,12 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {
             install_panic_hook(app.handle().clone());
+            let job_events = app.handle().clone();
+            app.state::<AppState>()
+                .jobs
+                .set_notifier(Arc::new(move |info: &JobInfo| {
+                    let _ = job_events.emit("job-updated", info.clone());
+                }));
             for window_config in app.config().app.windows.iter().filter(|w| !w.create) {
                 WebviewWindowBuilder::from_config(app.handle(), window_config)?
                     .enable_clipboard_access()
@@ -1510,20 +4171,13 @@ pub fn run() {
             Ok(())
         })
         .manage(AppState {
-            file_path: Mutex::new(None),
-            total_rows: Mutex::new(0),
-            headers: Mutex::new(Vec::new()),
-            cache: CsvCache::new(64),
-            sorted_order: Mutex::new(None),
+            documents: Mutex::new(HashMap::new()),
             pending_open: Mutex::new(initial_open_path()),
-            row_offsets: Mutex::new(None),
-            mmap: Mutex::new(None),
-            parse_settings: Mutex::new(default_settings),
             parse_info_detected: Mutex::new(default_info.clone()),
             parse_info_effective: Mutex::new(default_info),
-            parse_warnings: Mutex::new(Vec::new()),
-            search_index: Mutex::new(SearchIndex::new()),
             enable_indexing: Mutex::new(true),
+            sort_memory_budget: Mutex::new(DEFAULT_SORT_MEMORY_BUDGET),
+            jobs: Arc::new(JobRegistry::new()),
             debug_logging: Mutex::new(false),
         })
         .invoke_handler(tauri::generate_handler![
@@ -1533,10 +4187,39 @@ pub fn run() {
             search_csv_stream,
             find_duplicates,
             find_duplicates_stream,
+            cancel_request,
+            list_jobs,
+            cancel_job,
+            edit_csv,
+            undo_edit,
+            redo_edit,
+            get_edit_state,
+            get_document_path,
+            close_document,
+            start_follow,
+            stop_follow,
+            profile_column,
+            profile_columns,
+            value_counts,
+            filter_by_value,
+            aggregate_csv,
+            pivot_csv,
+            export_aggregate_csv,
+            export_csv,
+            export_rows,
+            export_sqlite,
+            list_sqlite_tables,
+            import_sqlite,
+            list_zip_members,
             sort_csv,
             get_sorted_chunk,
             get_sorted_indices,
             clear_sort,
+            apply_filter,
+            clear_filter,
+            get_active_filter,
+            get_filter_indices,
+            get_filtered_chunk,
             take_pending_open,
             get_row_count,
             get_parse_warnings,
@@ -1545,53 +4228,77 @@ pub fn run() {
             set_debug_logging,
             append_debug_log,
             clear_debug_log,
-            write_csv_file,
             set_show_index_checked,
-            set_enable_indexing
+            set_enable_indexing,
+            set_sort_memory_budget
         ]);
 
     #[cfg(desktop)]
     let builder = builder
         .menu(build_menu)
+        .on_window_event(|window, event| {
+            // Each window holds the document keyed by its label
+            if let WindowEvent::Destroyed = event {
+                window.state::<AppState>().close_document(window.label());
+            }
+        })
         .on_menu_event(|app, event| match event.id().as_ref() {
             "open" => {
-                let _ = app.emit("menu-open", ());
+                emit_menu_event(app, "menu-open", ());
             }
             "save" => {
-                let _ = app.emit("menu-save", ());
+                emit_menu_event(app, "menu-save", ());
             }
             "save-as" => {
-                let _ = app.emit("menu-save-as", ());
+                emit_menu_event(app, "menu-save-as", ());
+            }
+            "export" => {
+                emit_menu_event(app, "menu-export", ());
+            }
+            "import-sqlite" => {
+                emit_menu_event(app, "menu-import-sqlite", ());
             }
             "clear" => {
-                let _ = app.emit("menu-clear", ());
+                emit_menu_event(app, "menu-clear", ());
             }
             "open-settings" => {
-                let _ = app.emit("open-settings", ());
+                emit_menu_event(app, "open-settings", ());
             }
             "find" => {
-                let _ = app.emit("menu-find", ());
+                emit_menu_event(app, "menu-find", ());
             }
             "clear-search" => {
-                let _ = app.emit("menu-clear-search", ());
+                emit_menu_event(app, "menu-clear-search", ());
             }
             "check-duplicates" => {
-                let _ = app.emit("menu-check-duplicates", ());
+                emit_menu_event(app, "menu-check-duplicates", ());
+            }
+            "filter-rows" => {
+                emit_menu_event(app, "menu-filter", ());
+            }
+            "profile-columns" => {
+                emit_menu_event(app, "menu-profile", ());
+            }
+            "summarize" => {
+                emit_menu_event(app, "menu-summarize", ());
+            }
+            "show-jobs" => {
+                emit_menu_event(app, "menu-jobs", ());
             }
             "next-match" => {
-                let _ = app.emit("menu-next-match", ());
+                emit_menu_event(app, "menu-next-match", ());
             }
             "prev-match" => {
-                let _ = app.emit("menu-prev-match", ());
+                emit_menu_event(app, "menu-prev-match", ());
             }
             "close-find" => {
-                let _ = app.emit("menu-close-find", ());
+                emit_menu_event(app, "menu-close-find", ());
             }
             "toggle-theme" => {
-                let _ = app.emit("menu-toggle-theme", ());
+                emit_menu_event(app, "menu-toggle-theme", ());
             }
             "parse-settings" => {
-                let _ = app.emit("menu-parse-settings", ());
+                emit_menu_event(app, "menu-parse-settings", ());
             }
             "show-index" => {
                 if let Some(menu) = app.menu() {
@@ -1600,24 +4307,39 @@ pub fn run() {
                             if let Some(item) = submenu.get("show-index") {
                                 if let MenuItemKind::Check(check) = item {
                                     let checked = check.is_checked().unwrap_or(false);
-                                    let _ = app.emit("menu-show-index", checked);
+                                    emit_menu_event(app, "menu-show-index", checked);
                                 }
                             }
                         }
                     }
                 }
             }
+            "follow-file" => {
+                if let Some(MenuItemKind::Submenu(submenu)) =
+                    app.menu().and_then(|menu| menu.get("view"))
+                {
+                    if let Some(MenuItemKind::Check(check)) = submenu.get("follow-file") {
+                        let checked = check.is_checked().unwrap_or(false);
+                        emit_menu_event(app, "menu-follow", checked);
+                    }
+                }
+            }
             "row-compact" => {
-                let _ = app.emit("menu-row-height", 28);
+                emit_menu_event(app, "menu-row-height", 28);
             }
             "row-default" => {
-                let _ = app.emit("menu-row-height", 36);
+                emit_menu_event(app, "menu-row-height", 36);
             }
             "row-spacious" => {
-                let _ = app.emit("menu-row-height", 44);
+                emit_menu_event(app, "menu-row-height", 44);
+            }
+            "new-window" => {
+                if let Err(err) = open_document_window(app) {
+                    eprintln!("Failed to open a window: {err}");
+                }
             }
             "reload" => {
-                if let Some(window) = app.get_webview_window("main") {
+                if let Some(window) = menu_target_window(app) {
                     let _ = window.eval("window.location.reload()");
                 }
             }
@@ -1634,12 +4356,20 @@ fn build_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<tau
     let open_item = MenuItemBuilder::with_id("open", "Open...")
         .accelerator("CmdOrCtrl+O")
         .build(app)?;
+    let new_window_item = MenuItemBuilder::with_id("new-window", "New Window")
+        .accelerator("CmdOrCtrl+Shift+N")
+        .build(app)?;
+    let import_sqlite_item =
+        MenuItemBuilder::with_id("import-sqlite", "Import SQLite...").build(app)?;
     let save_item = MenuItemBuilder::with_id("save", "Save")
         .accelerator("CmdOrCtrl+S")
         .build(app)?;
     let save_as_item = MenuItemBuilder::with_id("save-as", "Save As...")
         .accelerator("CmdOrCtrl+Shift+S")
         .build(app)?;
+    let export_item = MenuItemBuilder::with_id("export", "Export...")
+        .accelerator("CmdOrCtrl+E")
+        .build(app)?;
     let clear_item = MenuItemBuilder::with_id("clear", "Clear")
         .accelerator("CmdOrCtrl+Shift+K")
         .build(app)?;
@@ -1655,6 +4385,9 @@ fn build_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<tau
     let prev_match_item = MenuItemBuilder::with_id("prev-match", "Previous Match")
         .accelerator("Shift+F3")
         .build(app)?;
+    let filter_rows_item = MenuItemBuilder::with_id("filter-rows", "Filter Rows...")
+        .accelerator("CmdOrCtrl+Shift+L")
+        .build(app)?;
     let close_find_item = MenuItemBuilder::with_id("close-find", "Close Find")
         .accelerator("Esc")
         .build(app)?;
@@ -1668,6 +4401,9 @@ fn build_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<tau
         .accelerator("CmdOrCtrl+I")
         .checked(false)
         .build(app)?;
+    let follow_item = CheckMenuItemBuilder::with_id("follow-file", "Follow File Changes")
+        .checked(false)
+        .build(app)?;
     let row_compact_item = MenuItemBuilder::with_id("row-compact", "Compact")
         .accelerator("CmdOrCtrl+Alt+1")
         .build(app)?;
@@ -1685,14 +4421,21 @@ fn build_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<tau
     let check_duplicates_item = MenuItemBuilder::with_id("check-duplicates", "Check Duplicates...")
         .accelerator("CmdOrCtrl+Shift+D")
         .build(app)?;
+    let profile_columns_item =
+        MenuItemBuilder::with_id("profile-columns", "Profile Columns...").build(app)?;
+    let summarize_item = MenuItemBuilder::with_id("summarize", "Summarize...").build(app)?;
+    let show_jobs_item = MenuItemBuilder::with_id("show-jobs", "Background Jobs...").build(app)?;
     let parse_settings_item = MenuItemBuilder::with_id("parse-settings", "Parse Settings...")
         .accelerator("CmdOrCtrl+Shift+P")
         .build(app)?;
 
     let file_menu = SubmenuBuilder::new(app, "File")
+        .item(&new_window_item)
         .item(&open_item)
+        .item(&import_sqlite_item)
         .item(&save_item)
         .item(&save_as_item)
+        .item(&export_item)
         .separator()
         .item(&clear_item)
         .separator()
@@ -1712,6 +4455,8 @@ fn build_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<tau
         .separator()
         .item(&show_index_item)
         .item(&row_height_menu)
+        .separator()
+        .item(&follow_item)
         .build()?;
     let search_menu = SubmenuBuilder::new(app, "Search")
         .item(&find_item)
@@ -1720,15 +4465,25 @@ fn build_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<tau
         .item(&next_match_item)
         .item(&prev_match_item)
         .item(&close_find_item)
+        .separator()
+        .item(&filter_rows_item)
         .build()?;
     let tools_menu = SubmenuBuilder::new(app, "Tools")
         .item(&check_duplicates_item)
+        .item(&profile_columns_item)
+        .item(&summarize_item)
         .item(&parse_settings_item)
+        .separator()
+        .item(&show_jobs_item)
         .build()?;
     let shortcuts_open = MenuItemBuilder::new("Open File")
         .accelerator("CmdOrCtrl+O")
         .enabled(false)
         .build(app)?;
+    let shortcuts_new_window = MenuItemBuilder::new("New Window")
+        .accelerator("CmdOrCtrl+Shift+N")
+        .enabled(false)
+        .build(app)?;
     let shortcuts_clear = MenuItemBuilder::new("Clear File")
         .accelerator("CmdOrCtrl+Shift+K")
         .enabled(false)
@@ -1769,6 +4524,10 @@ fn build_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<tau
         .accelerator("CmdOrCtrl+Shift+D")
         .enabled(false)
         .build(app)?;
+    let shortcuts_filter_rows = MenuItemBuilder::new("Filter Rows")
+        .accelerator("CmdOrCtrl+Shift+L")
+        .enabled(false)
+        .build(app)?;
     let shortcuts_parse_settings = MenuItemBuilder::new("Parse Settings")
         .accelerator("CmdOrCtrl+Shift+P")
         .enabled(false)
@@ -1795,6 +4554,7 @@ fn build_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<tau
         .enabled(false)
         .build(app)?;
     let shortcuts_menu = SubmenuBuilder::new(app, "Shortcuts")
+        .item(&shortcuts_new_window)
         .item(&shortcuts_open)
         .item(&shortcuts_clear)
         .separator()
@@ -1815,6 +4575,7 @@ fn build_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<tau
         .item(&shortcuts_row_spacious)
         .separator()
         .item(&shortcuts_check_duplicates)
+        .item(&shortcuts_filter_rows)
         .item(&shortcuts_parse_settings)
         .separator()
         .item(&shortcuts_settings)
@@ -5,27 +5,51 @@ import { invoke } from "@tauri-apps/api/core";
 import { open as openDialog, save } from "@tauri-apps/plugin-dialog";
 import { openPath } from "@tauri-apps/plugin-opener";
 import { appDataDir, join } from "@tauri-apps/api/path";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import { useVirtualizer } from "@tanstack/react-virtual";
 import { useDebounce } from "./hooks/useDebounce";
 import "./App.css";
 
 const BASE_TITLE = "QuickRows";
+// Each window holds its own document session in the backend
+const DOC_ID = getCurrentWindow().label;
 const DEFAULT_ROW_HEIGHT = 36;
 const PREFETCH = 12;
 const CHUNK_SIZE = 800;
 const MAX_CACHED_ROWS = CHUNK_SIZE * 12;
+const MAX_SUMMARY_PREVIEW_ROWS = 500;
 const ROW_COUNT_POLL_INTERVAL = 250;
 const ROW_COUNT_POLL_MAX = 60;
 const COLUMN_WIDTH_MIN = 120;
 const SETTINGS_KEY = "csv-viewer.settings";
 const MAX_RECENT_FILES = 6;
 const ROW_HEIGHT_OPTIONS = new Set([28, 36, 44]);
+const DEFAULT_SORT_MEMORY_MB = 1024;
+const SORT_MEMORY_OPTIONS = [256, 512, 1024, 2048, 4096];
 const MAX_PARSE_WARNINGS = 200;
+// Compressed files are sniffed by their leading bytes and opened decompressed;
+// ZIP archives open one of their CSV members
+const OPEN_EXTENSIONS = [
+  "csv",
+  "jsonl",
+  "ndjson",
+  "gz",
+  "zst",
+  "bz2",
+  "xz",
+  "zip",
+  "xlsx",
+  "xlsm",
+  "xlsb",
+  "xls",
+  "ods",
+];
 
 type SortDirection = "asc" | "desc";
 type SortState = { column: number; direction: SortDirection };
 type SortedRow = { index: number; row: string[] };
+type FilterState = { expression: string; rows: number };
 type SortLookup = Uint32Array | number[];
 type SortWorkerRequest = {
   type: "BUILD_SORT_LOOKUP";
@@ -37,12 +61,120 @@ type SortWorkerResponse = {
   requestId: number;
   lookup: Uint32Array;
 };
+type RowCountPayload = {
+  docId: string;
+  rows: number;
+};
+type DocPayload = {
+  docId: string;
+};
 type MatchesChunkPayload = {
+  docId: string;
   requestId: number;
   matches: number[];
 };
 type MatchesCompletePayload = {
+  docId: string;
+  requestId: number;
+  total: number;
+};
+type JobInfo = {
+  id: number;
+  kind:
+    | "offset_build"
+    | "index_build"
+    | "sort"
+    | "duplicates"
+    | "profile"
+    | "value_counts"
+    | "filter"
+    | "aggregate"
+    | "export"
+    | "import";
+  docId: string;
+  label: string;
+  status: "running" | "completed" | "failed" | "cancelled";
+  processed: number;
+  total: number | null;
+  error: string | null;
+};
+type ColumnProfile = {
+  column: number;
+  name: string;
+  inferredType: "empty" | "integer" | "float" | "boolean" | "date" | "text";
+  rows: number;
+  nullCount: number;
+  min: string | null;
+  max: string | null;
+  mean: number | null;
+  stddev: number | null;
+  minLength: number | null;
+  maxLength: number | null;
+  distinctEstimate: number;
+  topValues: { value: string; count: number }[];
+  topValuesExact: boolean;
+};
+type ValueCountsPage = {
+  totalValues: number;
+  values: { value: string; count: number }[];
+  untrackedRows: number;
+};
+type AggregateFn = "count" | "count_distinct" | "sum" | "avg" | "min" | "max";
+type AggregateInput = {
+  func: AggregateFn;
+  column: number | null;
+};
+type AggregateTable = {
+  headers: string[];
+  rows: string[][];
+  // Pivot totals, kept out of the keyed cells
+  totals: { rows: string[]; columns: string[]; grand: string } | null;
+};
+type ExportSourceKind = "all" | "sorted" | "search" | "duplicates" | "rows";
+type ExportFormat =
+  | "csv"
+  | "json"
+  | "ndjson"
+  | "markdown"
+  | "html"
+  | "parquet"
+  | "arrow"
+  | "sqlite";
+type ParquetCompression =
+  | "snappy"
+  | "zstd"
+  | "gzip"
+  | "lz4"
+  | "brotli"
+  | "none";
+const EXPORT_FORMATS: Record<
+  ExportFormat,
+  { label: string; extension: string }
+> = {
+  csv: { label: "CSV", extension: "csv" },
+  json: { label: "JSON", extension: "json" },
+  ndjson: { label: "JSON Lines", extension: "jsonl" },
+  markdown: { label: "Markdown", extension: "md" },
+  html: { label: "HTML", extension: "html" },
+  parquet: { label: "Parquet", extension: "parquet" },
+  arrow: { label: "Arrow IPC", extension: "arrow" },
+  sqlite: { label: "SQLite", extension: "sqlite" },
+};
+type ExportColumn = { column: number; included: boolean; indexed: boolean };
+type SqliteTable = { name: string; kind: "table" | "view" };
+type SqliteImport = { path: string; tables: SqliteTable[] };
+const AGGREGATE_LABELS: Record<AggregateFn, string> = {
+  count: "Count",
+  count_distinct: "Count distinct",
+  sum: "Sum",
+  avg: "Average",
+  min: "Min",
+  max: "Max",
+};
+type SearchProgressPayload = {
+  docId: string;
   requestId: number;
+  scanned: number;
   total: number;
 };
 type ThemeMode = "light" | "dark";
@@ -65,6 +197,8 @@ type ParseInfo = {
   malformed: string;
   max_field_size: number;
   max_record_size: number;
+  fixed_width?: string | null;
+  json_lines?: boolean;
 };
 type ParseWarning = {
   record?: number;
@@ -76,21 +210,46 @@ type ParseWarning = {
   expected_len?: number;
   len?: number;
 };
+type FileCompression = "gzip" | "zstd" | "bzip2" | "xz";
 type CsvMetadata = {
   headers: string[];
   detected: ParseInfo;
   effective: ParseInfo;
   warnings: ParseWarning[];
   estimated_count?: number;
+  compression?: FileCompression | null;
+  member?: string | null;
+  sheets?: string[] | null;
+  header_row?: number | null;
+  read_only?: boolean;
 };
+type ZipMember = { name: string; size: number; compressedSize: number };
+type ZipPicker = { path: string; members: ZipMember[] };
+type DocumentSource = { path: string; member: string | null };
 type EditingCell = {
   displayRow: number;
   column: number;
   originalRow: number;
   value: string;
   initialValue: string;
-  originalValue: string;
 };
+type EditState = {
+  headers: string[];
+  rows: number;
+  deletedRows: number[];
+  edited: boolean;
+  canUndo: boolean;
+  canRedo: boolean;
+  // Rows of the active filter, which the backend keeps in step with edits
+  filteredRows: number | null;
+};
+type JournalEdit =
+  | { kind: "setCell"; row: number; column: number; value: string }
+  | { kind: "deleteRows"; rows: number[] }
+  | { kind: "restoreRows"; rows: number[] }
+  | { kind: "insertRow"; values?: string[] }
+  | { kind: "addColumn"; name: string; position?: number }
+  | { kind: "removeColumn"; column: number };
 type ParseOverridesState = {
   delimiter:
     | "auto"
@@ -115,6 +274,8 @@ type ParseOverridesState = {
   malformed: "strict" | "skip" | "repair";
   maxFieldSize: number;
   maxRecordSize: number;
+  fixedWidth: "off" | "auto" | "custom";
+  fixedWidthCustom: string;
 };
 
 type SelectionRange = { start: number; end: number };
@@ -229,6 +390,23 @@ const getFileNameFromPath = (path: string) => {
   }
   return path.slice(lastIndex + 1);
 };
+// Matches the number of finished jobs the backend keeps
+const MAX_JOB_HISTORY = 20;
+const VALUE_COUNTS_PAGE_SIZE = 200;
+const formatJobPercent = (job: JobInfo) =>
+  job.total ? Math.min(100, Math.floor((job.processed / job.total) * 100)) : 0;
+
+const formatFileSize = (bytes: number) => {
+  const units = ["B", "KB", "MB", "GB", "TB"];
+  let size = bytes;
+  let unit = 0;
+  while (size >= 1024 && unit < units.length - 1) {
+    size /= 1024;
+    unit += 1;
+  }
+  return `${unit ? size.toFixed(1) : size} ${units[unit]}`;
+};
+
 const setWindowTitle = (path: string | null, dirty: boolean = false) => {
   const name = path ? getFileNameFromPath(path) : "";
   const suffix = dirty ? " *" : "";
@@ -295,10 +473,20 @@ const DEFAULT_PARSE_OVERRIDES: ParseOverridesState = {
   malformed: "skip",
   maxFieldSize: 256 * 1024,
   maxRecordSize: 2 * 1024 * 1024,
+  fixedWidth: "off",
+  fixedWidthCustom: "",
 };
 
 function App() {
   const [filePath, setFilePath] = useState<string | null>(null);
+  const [fileCompression, setFileCompression] =
+    useState<FileCompression | null>(null);
+  const [fileMember, setFileMember] = useState<string | null>(null);
+  const [fileSheets, setFileSheets] = useState<string[] | null>(null);
+  const [fileHeaderRow, setFileHeaderRow] = useState<number | null>(null);
+  // Workbook sheets cannot be edited
+  const [fileReadOnly, setFileReadOnly] = useState(false);
+  const [zipPicker, setZipPicker] = useState<ZipPicker | null>(null);
   const [checkingInitialOpen, setCheckingInitialOpen] = useState(true);
   const [headers, setHeaders] = useState<string[]>([]);
   const [totalRows, setTotalRows] = useState(0);
@@ -310,8 +498,10 @@ function App() {
   const [searchColumn, setSearchColumn] = useState<number | null>(null);
   const [searchMatchCase, setSearchMatchCase] = useState(false);
   const [searchWholeWord, setSearchWholeWord] = useState(false);
+  const [searchRegex, setSearchRegex] = useState(false);
   const [searchResults, setSearchResults] = useState<number[] | null>(null);
   const [searching, setSearching] = useState(false);
+  const [searchProgress, setSearchProgress] = useState<number | null>(null);
   const [showFind, setShowFind] = useState(false);
   const [currentMatch, setCurrentMatch] = useState(0);
   const [showDuplicates, setShowDuplicates] = useState(false);
@@ -325,8 +515,58 @@ function App() {
     "search" | "duplicates" | null
   >(null);
   const [showIndex, setShowIndex] = useState(false);
+  const [followFile, setFollowFile] = useState(false);
+  const [showProfile, setShowProfile] = useState(false);
+  const [profiles, setProfiles] = useState<ColumnProfile[] | null>(null);
+  const [profileError, setProfileError] = useState<string | null>(null);
+  const [valueCountsColumn, setValueCountsColumn] = useState<number | null>(
+    null,
+  );
+  const [valueCounts, setValueCounts] = useState<ValueCountsPage | null>(null);
+  const [valueCountsError, setValueCountsError] = useState<string | null>(
+    null,
+  );
+  const [showSummary, setShowSummary] = useState(false);
+  const [summaryGroupBy, setSummaryGroupBy] = useState<number[]>([]);
+  const [summaryAggregates, setSummaryAggregates] = useState<AggregateInput[]>(
+    [{ func: "count", column: null }],
+  );
+  const [summaryResult, setSummaryResult] = useState<AggregateTable | null>(
+    null,
+  );
+  const [summaryRunning, setSummaryRunning] = useState(false);
+  const [summaryMode, setSummaryMode] = useState<"group" | "pivot">("group");
+  const [pivotRowKey, setPivotRowKey] = useState(0);
+  const [pivotColumnKey, setPivotColumnKey] = useState(0);
+  const [summaryError, setSummaryError] = useState<string | null>(null);
+  const [showExport, setShowExport] = useState(false);
+  const [exportSource, setExportSource] = useState<ExportSourceKind>("all");
+  const [exportColumns, setExportColumns] = useState<ExportColumn[]>([]);
+  const [exportFormat, setExportFormat] = useState<ExportFormat>("csv");
+  const [exportInferTypes, setExportInferTypes] = useState(false);
+  const [exportCompression, setExportCompression] =
+    useState<ParquetCompression>("snappy");
+  const [exportTable, setExportTable] = useState("data");
+  const [exportReplaceTable, setExportReplaceTable] = useState(false);
+  const [exportRunning, setExportRunning] = useState(false);
+  const [exportError, setExportError] = useState<string | null>(null);
+  const [sqliteImport, setSqliteImport] = useState<SqliteImport | null>(null);
+  const [sqliteImportTable, setSqliteImportTable] = useState("");
+  const [sqliteImportQuery, setSqliteImportQuery] = useState("");
+  const [sqliteImportRunning, setSqliteImportRunning] = useState(false);
+  const [sqliteImportError, setSqliteImportError] = useState<string | null>(
+    null,
+  );
   const [sortState, setSortState] = useState<SortState | null>(null);
+  const [filterState, setFilterState] = useState<FilterState | null>(null);
+  const [showFilter, setShowFilter] = useState(false);
+  const [filterInput, setFilterInput] = useState("");
+  const [filterError, setFilterError] = useState<string | null>(null);
+  const [filterApplying, setFilterApplying] = useState(false);
   const [sortLoading, setSortLoading] = useState(false);
+  // Whether the row count shown is that of a sort order, which leaves out
+  // deleted rows
+  const sortedRowsRef = useRef(false);
   const [sortedIndexLookup, setSortedIndexLookup] = useState<SortLookup | null>(
     null,
   );
@@ -350,14 +590,20 @@ function App() {
   const [contextMenu, setContextMenu] = useState<ContextMenuState | null>(null);
   const [editingCell, setEditingCell] = useState<EditingCell | null>(null);
   const [hasEdits, setHasEdits] = useState(false);
+  const [canUndo, setCanUndo] = useState(false);
+  const [canRedo, setCanRedo] = useState(false);
   const [saving, setSaving] = useState(false);
   const [searchStale, setSearchStale] = useState(false);
   const [duplicateStale, setDuplicateStale] = useState(false);
   const [savePath, setSavePath] = useState<string | null>(null);
   const [deletedRowsVersion, setDeletedRowsVersion] = useState(0);
   const [loadingProgress, setLoadingProgress] = useState<number | null>(null);
+  const [runningJobs, setRunningJobs] = useState<JobInfo[]>([]);
+  const [jobHistory, setJobHistory] = useState<JobInfo[]>([]);
+  const [showJobs, setShowJobs] = useState(false);
   const [showSettings, setShowSettings] = useState(false);
   const [debugLogging, setDebugLogging] = useState(false);
+  const [sortMemoryMb, setSortMemoryMb] = useState(DEFAULT_SORT_MEMORY_MB);
   const [debugLogPath, setDebugLogPath] = useState<string | null>(null);
   const [crashLogPath, setCrashLogPath] = useState<string | null>(null);
   const [selectedRanges, setSelectedRanges] = useState<SelectionRange[]>([]);
@@ -369,10 +615,15 @@ function App() {
   });
   const debugLoggingRef = useRef(debugLogging);
   debugLoggingRef.current = debugLogging;
+  const filterStateRef = useRef(filterState);
+  filterStateRef.current = filterState;
   const dataRef = useRef<Map<number, string[]>>(new Map());
   const rowIndexMapRef = useRef<Map<number, number>>(new Map());
   const searchRequestIdRef = useRef(0);
   const duplicateRequestIdRef = useRef(0);
+  // Search and duplicate requests share one id space so cancel_request is unambiguous
+  const streamRequestCounterRef = useRef(0);
+  const followRowsRef = useRef(0);
   const searchResultsCountRef = useRef(0);
   const duplicateResultsCountRef = useRef(0);
   const sortWorkerRef = useRef<Worker | null>(null);
@@ -382,7 +633,7 @@ function App() {
     order: number[];
   } | null>(null);
   const sortWorkerTimeoutRef = useRef<number | null>(null);
-  const editsRef = useRef<Map<number, Map<number, string>>>(new Map());
+  // Mirrors the deleted rows of the backend edit journal
   const deletedRowsRef = useRef<Set<number>>(new Set());
   const [, setDataVersion] = useState(0);
   const [, setRowIndexVersion] = useState(0);
@@ -410,7 +661,7 @@ function App() {
     return total;
   }, [selectedRanges]);
 
-  const isDirty = editsRef.current.size > 0 || deletedRowsRef.current.size > 0;
+  const isDirty = hasEdits;
 
   const clearSelection = useCallback(() => {
     setSelectedRanges([]);
@@ -418,6 +669,52 @@ function App() {
     setFocusedRow(null);
   }, []);
 
+  // Shows only the rows a filter command matched; a filter replaces any sort
+  const runFilter = useCallback(
+    async (command: string, args: Record<string, unknown>) => {
+      setFilterApplying(true);
+      setFilterError(null);
+      try {
+        const rows = await invoke<number>(command, { docId: DOC_ID, ...args });
+        const expression = await invoke<string | null>("get_active_filter", {
+          docId: DOC_ID,
+        });
+        setSortState(null);
+        clearSelection();
+        rowIndexMapRef.current = new Map();
+        setRowIndexVersion((prev) => prev + 1);
+        dataRef.current = new Map();
+        setDataVersion((prev) => prev + 1);
+        setFilterState({ expression: expression ?? "", rows });
+        setFilterInput(expression ?? "");
+        setTotalRows(rows);
+        setShowFilter(true);
+      } catch (err) {
+        setFilterError(typeof err === "string" ? err : "Filter failed.");
+      } finally {
+        setFilterApplying(false);
+      }
+    },
+    [clearSelection],
+  );
+
+  const clearFilter = useCallback(async () => {
+    setFilterError(null);
+    try {
+      await invoke("clear_filter", { docId: DOC_ID });
+      const rows = await invoke<number>("get_row_count", { docId: DOC_ID });
+      clearSelection();
+      rowIndexMapRef.current = new Map();
+      setRowIndexVersion((prev) => prev + 1);
+      dataRef.current = new Map();
+      setDataVersion((prev) => prev + 1);
+      setFilterState(null);
+      setTotalRows(rows);
+    } catch (err) {
+      setError(typeof err === "string" ? err : "Failed to clear the filter.");
+    }
+  }, [clearSelection]);
+
   const selectDisplayRow = useCallback(
     (displayIndex: number, opts: { shiftKey: boolean; toggleKey: boolean }) => {
       if (!Number.isFinite(displayIndex) || displayIndex < 0) {
@@ -571,7 +868,12 @@ function App() {
         sortWorkerTimeoutRef.current = null;
       }
 
-      const lookup = new Uint32Array(order.length);
+      // Deleted rows are left out of the order, so ids can exceed its length
+      let size = 0;
+      for (const row of order) {
+        size = Math.max(size, row + 1);
+      }
+      const lookup = new Uint32Array(size);
       let i = 0;
       const chunkSize = 250_000;
 
@@ -701,6 +1003,17 @@ function App() {
     () => debouncedSearch.trim().toLowerCase(),
     [debouncedSearch],
   );
+  const searchPattern = useMemo(() => {
+    if (!searchRegex || !debouncedSearch) return null;
+    const source = searchWholeWord
+      ? `^(?:${debouncedSearch})$`
+      : debouncedSearch;
+    try {
+      return new RegExp(source, searchMatchCase ? "" : "i");
+    } catch {
+      return null;
+    }
+  }, [debouncedSearch, searchMatchCase, searchRegex, searchWholeWord]);
   const searchHighlightActive =
     activeHighlight === "search" && !searchStale && searchQueryLower.length > 0;
   const activeCurrentMatch =
@@ -725,6 +1038,7 @@ function App() {
         lastOpenDir?: string;
         recentFiles?: string[];
         debugLogging?: boolean;
+        sortMemoryMb?: number;
         parseOverrides?: Partial<ParseOverridesState>;
       };
       if (typeof parsed.showIndex === "boolean") {
@@ -776,6 +1090,12 @@ function App() {
       if (typeof parsed.debugLogging === "boolean") {
         setDebugLogging(parsed.debugLogging);
       }
+      if (
+        typeof parsed.sortMemoryMb === "number" &&
+        SORT_MEMORY_OPTIONS.includes(parsed.sortMemoryMb)
+      ) {
+        setSortMemoryMb(parsed.sortMemoryMb);
+      }
       if (parsed.parseOverrides) {
         setParseOverrides((prev) => ({
           ...prev,
@@ -797,6 +1117,7 @@ function App() {
       lastOpenDir,
       recentFiles,
       debugLogging,
+      sortMemoryMb,
       parseOverrides,
     };
     try {
@@ -813,6 +1134,7 @@ function App() {
     lastOpenDir,
     recentFiles,
     debugLogging,
+    sortMemoryMb,
     parseOverrides,
   ]);
 
@@ -835,6 +1157,12 @@ function App() {
       .catch(() => {});
   }, [debugLogging]);
 
+  useEffect(() => {
+    invoke("set_sort_memory_budget", { megabytes: sortMemoryMb }).catch(
+      () => {},
+    );
+  }, [sortMemoryMb]);
+
   useEffect(() => {
     if (!debugLogging) {
       return;
@@ -934,8 +1262,12 @@ function App() {
   }, [filePath, parseDetected, parseOverrides.hasHeaders]);
 
   const resetForNewFile = useCallback(() => {
-    searchRequestIdRef.current += 1;
-    duplicateRequestIdRef.current += 1;
+    invoke("cancel_request", {
+      docId: DOC_ID,
+      requestId: duplicateRequestIdRef.current,
+    }).catch(() => {});
+    searchRequestIdRef.current = ++streamRequestCounterRef.current;
+    duplicateRequestIdRef.current = ++streamRequestCounterRef.current;
     setError(null);
     setSelectedRanges([]);
     setSelectionAnchor(null);
@@ -943,6 +1275,9 @@ function App() {
     setSortState(null);
     setSortLoading(false);
     setSortedIndexLookup(null);
+    sortedRowsRef.current = false;
+    setFilterState(null);
+    setFilterError(null);
     rowIndexMapRef.current = new Map();
     setRowIndexVersion((prev) => prev + 1);
     setShowFind(false);
@@ -956,6 +1291,12 @@ function App() {
     setCurrentDuplicateMatch(0);
     setDuplicateColumn(null);
     setActiveHighlight(null);
+    setSummaryGroupBy([]);
+    setPivotRowKey(0);
+    setPivotColumnKey(0);
+    setSummaryAggregates([{ func: "count", column: null }]);
+    setSummaryResult(null);
+    setSummaryError(null);
     setParseDetected(null);
     setParseEffective(null);
     setParseWarnings([]);
@@ -963,17 +1304,18 @@ function App() {
     setContextMenu(null);
     setEditingCell(null);
     setHasEdits(false);
+    setCanUndo(false);
+    setCanRedo(false);
     setSaving(false);
     setSearchStale(false);
     setDuplicateStale(false);
     setSavePath(null);
-    editsRef.current = new Map();
     deletedRowsRef.current = new Set();
     setDeletedRowsVersion((prev) => prev + 1);
     dataRef.current = new Map();
     setDataVersion((prev) => prev + 1);
     setRowCountReady(false);
-    invoke("clear_sort").catch(() => {});
+    invoke("clear_sort", { docId: DOC_ID }).catch(() => {});
   }, []);
 
   const toggleTheme = useCallback(() => {
@@ -995,6 +1337,7 @@ function App() {
         malformed?: "strict" | "skip" | "repair";
         max_field_size?: number;
         max_record_size?: number;
+        fixed_width?: string;
       } = {
         malformed: overridesState.malformed,
         max_field_size: overridesState.maxFieldSize,
@@ -1025,6 +1368,14 @@ function App() {
       if (overridesState.hasHeaders !== "auto") {
         overrides.has_headers = overridesState.hasHeaders === "yes";
       }
+      if (overridesState.fixedWidth === "auto") {
+        overrides.fixed_width = "auto";
+      } else if (
+        overridesState.fixedWidth === "custom" &&
+        overridesState.fixedWidthCustom.trim().length
+      ) {
+        overrides.fixed_width = overridesState.fixedWidthCustom;
+      }
 
       return overrides;
     },
@@ -1045,7 +1396,7 @@ function App() {
   }, []);
 
   const refreshParseWarnings = useCallback(() => {
-    invoke<ParseWarning[]>("get_parse_warnings", { clear: true })
+    invoke<ParseWarning[]>("get_parse_warnings", { docId: DOC_ID, clear: true })
       .then((next) => {
         appendParseWarnings(next);
       })
@@ -1070,32 +1421,115 @@ function App() {
     };
   }, [parseEffective]);
 
-  const applyEditsToRow = useCallback(
-    (row: string[], originalIndex: number | undefined) => {
-      if (originalIndex === undefined) {
-        return row;
+  const applyEditState = useCallback(
+    (state: EditState) => {
+      deletedRowsRef.current = new Set(state.deletedRows);
+      setDeletedRowsVersion((prev) => prev + 1);
+      setHasEdits(state.edited);
+      setCanUndo(state.canUndo);
+      setCanRedo(state.canRedo);
+      setHeaders(state.headers);
+      // Filtered views follow the edits; sorted views only when rebuilt
+      const filter = filterStateRef.current;
+      if (filter) {
+        if (state.filteredRows === null) {
+          setFilterState(null);
+          setTotalRows(state.rows);
+        } else {
+          setFilterState({ ...filter, rows: state.filteredRows });
+          setTotalRows(state.filteredRows);
+        }
+        rowIndexMapRef.current = new Map();
+        setRowIndexVersion((prev) => prev + 1);
+      } else if (rowCountReady && !sortState) {
+        setTotalRows(state.rows);
       }
-      const rowEdits = editsRef.current.get(originalIndex);
-      if (!rowEdits || rowEdits.size === 0) {
-        return row;
+      setEditingCell((prev) =>
+        prev && deletedRowsRef.current.has(prev.originalRow) ? null : prev,
+      );
+      // Chunks come back from the backend with edits applied
+      dataRef.current = new Map();
+      setDataVersion((prev) => prev + 1);
+      setSearchStale(true);
+      setDuplicateStale(true);
+    },
+    [rowCountReady, sortState],
+  );
+
+  const runEdit = useCallback(
+    async (command: string, edit?: JournalEdit) => {
+      if (fileReadOnly) {
+        setError(
+          "Workbook sheets are read-only; export the rows to edit them.",
+        );
+        return;
+      }
+      try {
+        const state = await invoke<EditState>(command, {
+          docId: DOC_ID,
+          ...(edit ? { edit } : {}),
+        });
+        applyEditState(state);
+      } catch (err) {
+        setError(typeof err === "string" ? err : "Failed to apply edit.");
       }
-      const next = row.slice();
-      rowEdits.forEach((value, colIndex) => {
-        next[colIndex] = value;
-      });
-      return next;
     },
-    [],
+    [applyEditState, fileReadOnly],
+  );
+
+  const undoEdit = useCallback(() => {
+    if (!filePath || !canUndo) {
+      return;
+    }
+    setEditingCell(null);
+    void runEdit("undo_edit");
+  }, [canUndo, filePath, runEdit]);
+
+  const redoEdit = useCallback(() => {
+    if (!filePath || !canRedo) {
+      return;
+    }
+    setEditingCell(null);
+    void runEdit("redo_edit");
+  }, [canRedo, filePath, runEdit]);
+
+  const insertRow = useCallback(() => {
+    if (!filePath || !rowCountReady) {
+      return;
+    }
+    void runEdit("edit_csv", { kind: "insertRow" });
+  }, [filePath, rowCountReady, runEdit]);
+
+  const addColumn = useCallback(
+    (position: number) => {
+      const name = window.prompt("Name of the new column", "");
+      if (name === null) {
+        return;
+      }
+      void runEdit("edit_csv", { kind: "addColumn", name, position });
+    },
+    [runEdit],
+  );
+
+  const removeColumn = useCallback(
+    (column: number) => {
+      const label = headers[column]?.trim() || `Column ${column + 1}`;
+      if (!window.confirm(`Remove column "${label}"?`)) {
+        return;
+      }
+      void runEdit("edit_csv", { kind: "removeColumn", column });
+    },
+    [headers, runEdit],
   );
 
   const getOriginalRowIndex = useCallback(
     (displayRow: number) => {
-      if (!sortState) {
+      if (!sortState && !filterState) {
         return displayRow;
       }
       return rowIndexMapRef.current.get(displayRow);
     },
-    [sortState],
+    [filterState, sortState],
   );
 
   const isRowDeleted = useCallback(
@@ -1113,9 +1547,8 @@ function App() {
       displayRow: number,
       column: number,
       value: string,
-      originalValue: string,
     ) => {
-      if (!filePath) {
+      if (!filePath || fileReadOnly) {
         return;
       }
       const originalRow = getOriginalRowIndex(displayRow);
@@ -1131,10 +1564,9 @@ function App() {
         originalRow,
         value,
         initialValue: value,
-        originalValue,
       });
     },
-    [filePath, getOriginalRowIndex, isRowDeleted],
+    [fileReadOnly, filePath, getOriginalRowIndex, isRowDeleted],
   );
 
   const deleteRow = useCallback(
@@ -1146,20 +1578,63 @@ function App() {
       if (deletedRowsRef.current.has(originalRow)) {
         return;
       }
-      deletedRowsRef.current.add(originalRow);
-      setEditingCell((prev) =>
-        prev && prev.originalRow === originalRow ? null : prev,
-      );
-      setDeletedRowsVersion((prev) => prev + 1);
-      setHasEdits(true);
-      setSearchStale(true);
-      setDuplicateStale(true);
+      void runEdit("edit_csv", { kind: "deleteRows", rows: [originalRow] });
     },
-    [getOriginalRowIndex],
+    [getOriginalRowIndex, runEdit],
   );
 
+  // Original row ids of the selection in display order, without deleted rows
+  const collectSelectedRows = useCallback(async () => {
+    const rows: number[] = [];
+    const normalized = normalizeSelectionRanges(selectedRanges);
+    if (!sortState && !filterState) {
+      for (const range of normalized) {
+        for (
+          let displayRow = range.start;
+          displayRow <= range.end;
+          displayRow += 1
+        ) {
+          if (displayRow < 0 || displayRow >= totalRows) {
+            continue;
+          }
+          if (deletedRowsRef.current.has(displayRow)) {
+            continue;
+          }
+          rows.push(displayRow);
+        }
+      }
+    } else {
+      for (const range of normalized) {
+        let start = Math.max(0, range.start);
+        const end = Math.min(range.end, totalRows - 1);
+        while (start <= end) {
+          const count = Math.min(CHUNK_SIZE, end - start + 1);
+          const originalIndices = await invoke<number[]>(
+            sortState ? "get_sorted_indices" : "get_filter_indices",
+            {
+              docId: DOC_ID,
+              start,
+              count,
+            },
+          );
+          originalIndices.forEach((originalRow) => {
+            if (deletedRowsRef.current.has(originalRow)) {
+              return;
+            }
+            rows.push(originalRow);
+          });
+          start += count;
+          if (originalIndices.length < count) {
+            break;
+          }
+        }
+      }
+    }
+    return rows;
+  }, [filterState, selectedRanges, sortState, totalRows]);
+
   const deleteSelectedRows = useCallback(async () => {
-    if (!filePath) {
+    if (!filePath || fileReadOnly) {
       return;
     }
     const normalized = normalizeSelectionRanges(selectedRanges);
@@ -1178,69 +1653,25 @@ function App() {
     }
 
     try {
-      let changed = false;
-      if (!sortState) {
-        for (const range of normalized) {
-          for (
-            let displayRow = range.start;
-            displayRow <= range.end;
-            displayRow += 1
-          ) {
-            if (displayRow < 0 || displayRow >= totalRows) {
-              continue;
-            }
-            if (deletedRowsRef.current.has(displayRow)) {
-              continue;
-            }
-            deletedRowsRef.current.add(displayRow);
-            changed = true;
-          }
-        }
-      } else {
-        for (const range of normalized) {
-          let start = Math.max(0, range.start);
-          const end = Math.min(range.end, totalRows - 1);
-          while (start <= end) {
-            const count = Math.min(CHUNK_SIZE, end - start + 1);
-            const originalIndices = await invoke<number[]>(
-              "get_sorted_indices",
-              {
-                start,
-                count,
-              },
-            );
-            originalIndices.forEach((originalRow) => {
-              if (deletedRowsRef.current.has(originalRow)) {
-                return;
-              }
-              deletedRowsRef.current.add(originalRow);
-              changed = true;
-            });
-            start += count;
-            if (originalIndices.length < count) {
-              break;
-            }
-          }
-        }
-      }
-
-      if (!changed) {
+      const rows = await collectSelectedRows();
+      if (!rows.length) {
         return;
       }
 
-      setEditingCell((prev) =>
-        prev && deletedRowsRef.current.has(prev.originalRow) ? null : prev,
-      );
-      setDeletedRowsVersion((prev) => prev + 1);
-      setHasEdits(true);
-      setSearchStale(true);
-      setDuplicateStale(true);
+      await runEdit("edit_csv", { kind: "deleteRows", rows });
     } catch (err) {
       setError(
         typeof err === "string" ? err : "Failed to delete selected rows.",
       );
     }
-  }, [filePath, selectedCount, selectedRanges, sortState, totalRows]);
+  }, [
+    collectSelectedRows,
+    fileReadOnly,
+    filePath,
+    runEdit,
+    selectedCount,
+    selectedRanges,
+  ]);
 
   const copySelectedRows = useCallback(async () => {
     if (!filePath) {
@@ -1267,13 +1698,14 @@ function App() {
 
     try {
       const lines: string[] = [];
-      if (!sortState) {
+      if (!sortState && !filterState) {
         for (const range of normalized) {
           let start = Math.max(0, range.start);
           const end = Math.min(range.end, totalRows - 1);
           while (start <= end) {
             const count = Math.min(CHUNK_SIZE, end - start + 1);
             const chunk = await invoke<string[][]>("get_csv_chunk", {
+              docId: DOC_ID,
               start,
               count,
             });
@@ -1282,9 +1714,8 @@ function App() {
               if (deletedRowsRef.current.has(originalIndex)) {
                 return;
               }
-              const nextRow = applyEditsToRow(row, originalIndex);
               lines.push(
-                formatCsvRow(nextRow, csvFormat.delimiter, csvFormat.quote),
+                formatCsvRow(row, csvFormat.delimiter, csvFormat.quote),
               );
             });
             start += chunk.length;
@@ -1299,17 +1730,20 @@ function App() {
           const end = Math.min(range.end, totalRows - 1);
           while (start <= end) {
             const count = Math.min(CHUNK_SIZE, end - start + 1);
-            const chunk = await invoke<SortedRow[]>("get_sorted_chunk", {
-              start,
-              count,
-            });
+            const chunk = await invoke<SortedRow[]>(
+              sortState ? "get_sorted_chunk" : "get_filtered_chunk",
+              {
+                docId: DOC_ID,
+                start,
+                count,
+              },
+            );
             chunk.forEach((item) => {
               if (deletedRowsRef.current.has(item.index)) {
                 return;
               }
-              const nextRow = applyEditsToRow(item.row, item.index);
               lines.push(
-                formatCsvRow(nextRow, csvFormat.delimiter, csvFormat.quote),
+                formatCsvRow(item.row, csvFormat.delimiter, csvFormat.quote),
               );
             });
             start += chunk.length;
@@ -1334,11 +1768,11 @@ function App() {
       setError(typeof err === "string" ? err : "Failed to copy selected rows.");
     }
   }, [
-    applyEditsToRow,
     csvFormat.delimiter,
     csvFormat.lineEnding,
     csvFormat.quote,
     filePath,
+    filterState,
     selectedCount,
     selectedRanges,
     sortLoading,
@@ -1355,17 +1789,13 @@ function App() {
       if (!deletedRowsRef.current.has(originalRow)) {
         return;
       }
-      deletedRowsRef.current.delete(originalRow);
-      setDeletedRowsVersion((prev) => prev + 1);
-      setHasEdits(editsRef.current.size > 0 || deletedRowsRef.current.size > 0);
-      setSearchStale(true);
-      setDuplicateStale(true);
+      void runEdit("edit_csv", { kind: "restoreRows", rows: [originalRow] });
     },
-    [getOriginalRowIndex],
+    [getOriginalRowIndex, runEdit],
   );
 
   const restoreSelectedRows = useCallback(async () => {
-    if (!filePath) {
+    if (!filePath || fileReadOnly) {
       return;
     }
     const normalized = normalizeSelectionRanges(selectedRanges);
@@ -1374,8 +1804,8 @@ function App() {
     }
 
     try {
-      let changed = false;
-      if (!sortState) {
+      const rows: number[] = [];
+      if (!sortState && !filterState) {
         for (const range of normalized) {
           for (
             let displayRow = range.start;
@@ -1388,8 +1818,7 @@ function App() {
             if (!deletedRowsRef.current.has(displayRow)) {
               continue;
             }
-            deletedRowsRef.current.delete(displayRow);
-            changed = true;
+            rows.push(displayRow);
           }
         }
       } else {
@@ -1399,8 +1828,9 @@ function App() {
           while (start <= end) {
             const count = Math.min(CHUNK_SIZE, end - start + 1);
             const originalIndices = await invoke<number[]>(
-              "get_sorted_indices",
+              sortState ? "get_sorted_indices" : "get_filter_indices",
               {
+                docId: DOC_ID,
                 start,
                 count,
               },
@@ -1409,8 +1839,7 @@ function App() {
               if (!deletedRowsRef.current.has(originalRow)) {
                 return;
               }
-              deletedRowsRef.current.delete(originalRow);
-              changed = true;
+              rows.push(originalRow);
             });
             start += count;
             if (originalIndices.length < count) {
@@ -1420,63 +1849,65 @@ function App() {
         }
       }
 
-      if (!changed) {
+      if (!rows.length) {
         return;
       }
 
-      setDeletedRowsVersion((prev) => prev + 1);
-      setHasEdits(editsRef.current.size > 0 || deletedRowsRef.current.size > 0);
-      setSearchStale(true);
-      setDuplicateStale(true);
+      await runEdit("edit_csv", { kind: "restoreRows", rows });
     } catch (err) {
       setError(
         typeof err === "string" ? err : "Failed to restore selected rows.",
       );
     }
-  }, [filePath, selectedRanges, sortState, totalRows]);
+  }, [
+    fileReadOnly,
+    filePath,
+    filterState,
+    runEdit,
+    selectedRanges,
+    sortState,
+    totalRows,
+  ]);
 
   const commitEdit = useCallback(() => {
     if (!editingCell) {
       return;
     }
-    const { column, originalRow, value, initialValue, originalValue } =
-      editingCell;
+    const { column, originalRow, value, initialValue } = editingCell;
     setEditingCell(null);
 
     if (value === initialValue) {
       return;
     }
 
-    const edits = editsRef.current;
-    const rowEdits = edits.get(originalRow);
-    if (value === originalValue) {
-      if (rowEdits) {
-        rowEdits.delete(column);
-        if (rowEdits.size === 0) {
-          edits.delete(originalRow);
-        }
-      }
-    } else {
-      const nextRowEdits = rowEdits ?? new Map<number, string>();
-      nextRowEdits.set(column, value);
-      edits.set(originalRow, nextRowEdits);
-    }
-
-    setHasEdits(editsRef.current.size > 0 || deletedRowsRef.current.size > 0);
-    setSearchStale(true);
-    setDuplicateStale(true);
-  }, [editingCell]);
+    void runEdit("edit_csv", {
+      kind: "setCell",
+      row: originalRow,
+      column,
+      value,
+    });
+  }, [editingCell, runEdit]);
 
   const cancelEdit = useCallback(() => {
     setEditingCell(null);
   }, []);
 
   const handleOpenPath = useCallback(
-    async (path: string, overrides?: Record<string, unknown>) => {
+    async (
+      path: string,
+      overrides?: Record<string, unknown>,
+      keepEdits?: boolean,
+      member?: string,
+      headerRow?: number,
+    ) => {
       try {
         const csvMetadata = await invoke<CsvMetadata>("load_csv_metadata", {
+          docId: DOC_ID,
           path,
+          member: member ?? null,
+          headerRow: headerRow ?? null,
           overrides: overrides ?? buildParseOverrides(),
+          keepEdits: keepEdits ?? false,
         });
         resetForNewFile();
         setFilePath(path);
@@ -1485,7 +1916,12 @@ function App() {
         setParseDetected(csvMetadata.detected);
         setParseEffective(csvMetadata.effective);
         setParseWarnings(csvMetadata.warnings ?? []);
-        invoke("get_parse_warnings", { clear: true }).catch(() => {});
+        setFileCompression(csvMetadata.compression ?? null);
+        setFileMember(csvMetadata.member ?? null);
+        setFileSheets(csvMetadata.sheets ?? null);
+        setFileHeaderRow(csvMetadata.header_row ?? null);
+        setFileReadOnly(csvMetadata.read_only ?? false);
+        invoke("get_parse_warnings", { docId: DOC_ID, clear: true }).catch(() => {});
         setLoadingProgress(0);
         setTotalRows(csvMetadata.estimated_count ?? CHUNK_SIZE);
         setRowCountReady(false);
@@ -1498,6 +1934,14 @@ function App() {
           const next = [path, ...prev.filter((item) => item !== path)];
           return next.slice(0, MAX_RECENT_FILES);
         });
+        if (keepEdits) {
+          const editState = await invoke<EditState>("get_edit_state", {
+            docId: DOC_ID,
+          });
+          if (editState.edited || editState.canUndo || editState.canRedo) {
+            applyEditState(editState);
+          }
+        }
       } catch (err) {
         setError(
           typeof err === "string" ? err : "Unable to load CSV metadata.",
@@ -1506,7 +1950,7 @@ function App() {
         // effectively keeping user on previous screen or file with an error message.
       }
     },
-    [buildParseOverrides, resetForNewFile],
+    [applyEditState, buildParseOverrides, resetForNewFile],
   );
 
   const removeRecent = useCallback((path: string) => {
@@ -1517,18 +1961,72 @@ function App() {
     (nextOverrides: ParseOverridesState) => {
       setParseOverrides(nextOverrides);
       if (filePath) {
-        handleOpenPath(filePath, buildParseOverrides(nextOverrides));
+        handleOpenPath(
+          filePath,
+          buildParseOverrides(nextOverrides),
+          false,
+          fileMember ?? undefined,
+          fileHeaderRow ?? undefined,
+        );
       }
     },
-    [buildParseOverrides, filePath, handleOpenPath],
+    [buildParseOverrides, fileHeaderRow, fileMember, filePath, handleOpenPath],
   );
 
   const handleApplyParse = useCallback(() => {
     if (!filePath) {
       return;
     }
-    handleOpenPath(filePath, buildParseOverrides());
-  }, [buildParseOverrides, filePath, handleOpenPath]);
+    handleOpenPath(
+      filePath,
+      buildParseOverrides(),
+      false,
+      fileMember ?? undefined,
+      fileHeaderRow ?? undefined,
+    );
+  }, [
+    buildParseOverrides,
+    fileHeaderRow,
+    fileMember,
+    filePath,
+    handleOpenPath,
+  ]);
+
+  // Switching sheets starts at the first used row again
+  const handleSelectSheet = useCallback(
+    (sheet: string, headerRow?: number) => {
+      if (!filePath) {
+        return;
+      }
+      handleOpenPath(filePath, undefined, false, sheet, headerRow);
+    },
+    [filePath, handleOpenPath],
+  );
+
+  // Archives with several CSV members ask which one to open
+  const handleOpenSource = useCallback(
+    async (path: string) => {
+      if (!/\.zip$/i.test(path)) {
+        return handleOpenPath(path);
+      }
+      try {
+        const members = await invoke<ZipMember[]>("list_zip_members", {
+          path,
+        });
+        if (members.length === 1) {
+          return handleOpenPath(path, undefined, false, members[0].name);
+        }
+        if (members.length === 0) {
+          setError("The archive has no CSV members.");
+          return;
+        }
+        setZipPicker({ path, members });
+      } catch (err) {
+        setError(typeof err === "string" ? err : "Unable to read the archive.");
+      }
+    },
+    [handleOpenPath],
+  );
 
   const handleHeaderPromptChoice = useCallback(
     (useHeaders: boolean) => {
@@ -1547,7 +2045,7 @@ function App() {
   const handlePickFile = useCallback(async () => {
     const selected = await openDialog({
       multiple: false,
-      filters: [{ name: "CSV", extensions: ["csv"] }],
+      filters: [{ name: "CSV", extensions: OPEN_EXTENSIONS }],
       defaultPath: lastOpenDir ?? undefined,
     });
 
@@ -1555,13 +2053,44 @@ function App() {
       return;
     }
 
-    handleOpenPath(selected);
-  }, [handleOpenPath, lastOpenDir]);
+    handleOpenSource(selected);
+  }, [handleOpenSource, lastOpenDir]);
+
+  const handlePickSqlite = useCallback(async () => {
+    const selected = await openDialog({
+      multiple: false,
+      filters: [{ name: "SQLite", extensions: ["sqlite", "sqlite3", "db"] }],
+      defaultPath: lastOpenDir ?? undefined,
+    });
+    if (!selected || Array.isArray(selected)) {
+      return;
+    }
+    try {
+      const tables = await invoke<SqliteTable[]>("list_sqlite_tables", {
+        path: selected,
+      });
+      setSqliteImport({ path: selected, tables });
+      setSqliteImportTable(tables[0]?.name ?? "");
+      setSqliteImportQuery("");
+      setSqliteImportError(null);
+    } catch (err) {
+      setError(typeof err === "string" ? err : "Unable to read the database.");
+    }
+  }, [lastOpenDir]);
 
   const handleClearFile = useCallback(() => {
-    searchRequestIdRef.current += 1;
-    duplicateRequestIdRef.current += 1;
+    invoke("cancel_request", {
+      docId: DOC_ID,
+      requestId: duplicateRequestIdRef.current,
+    }).catch(() => {});
+    searchRequestIdRef.current = ++streamRequestCounterRef.current;
+    duplicateRequestIdRef.current = ++streamRequestCounterRef.current;
     setFilePath(null);
+    setFileCompression(null);
+    setFileMember(null);
+    setFileSheets(null);
+    setFileHeaderRow(null);
+    setFileReadOnly(false);
     setWindowTitle(null);
     setHeaders([]);
     setTotalRows(0);
@@ -1579,6 +2108,10 @@ function App() {
     setSortState(null);
     setSortLoading(false);
     setSortedIndexLookup(null);
+    setFilterState(null);
+    setShowFilter(false);
+    setFilterInput("");
+    setFilterError(null);
     rowIndexMapRef.current = new Map();
     setRowIndexVersion((prev) => prev + 1);
     setRowCountReady(false);
@@ -1587,6 +2120,12 @@ function App() {
     setCurrentDuplicateMatch(0);
     setDuplicateColumn(null);
     setActiveHighlight(null);
+    setSummaryGroupBy([]);
+    setPivotRowKey(0);
+    setPivotColumnKey(0);
+    setSummaryAggregates([{ func: "count", column: null }]);
+    setSummaryResult(null);
+    setSummaryError(null);
     setParseDetected(null);
     setParseEffective(null);
     setParseWarnings([]);
@@ -1594,14 +2133,15 @@ function App() {
     setContextMenu(null);
     setEditingCell(null);
     setHasEdits(false);
+    setCanUndo(false);
+    setCanRedo(false);
     setSaving(false);
     setSearchStale(false);
     setDuplicateStale(false);
     setSavePath(null);
-    editsRef.current = new Map();
     deletedRowsRef.current = new Set();
     setDeletedRowsVersion((prev) => prev + 1);
-    invoke("clear_sort").catch(() => {});
+    invoke("close_document", { docId: DOC_ID }).catch(() => {});
   }, []);
 
   useEffect(() => {
@@ -1612,7 +2152,11 @@ function App() {
     if (!filePath) {
       return;
     }
-    const requestId = duplicateRequestIdRef.current + 1;
+    invoke("cancel_request", {
+      docId: DOC_ID,
+      requestId: duplicateRequestIdRef.current,
+    }).catch(() => {});
+    const requestId = ++streamRequestCounterRef.current;
     duplicateRequestIdRef.current = requestId;
     setError(null);
     setDuplicateChecking(true);
@@ -1620,6 +2164,7 @@ function App() {
     setActiveHighlight("duplicates");
     setDuplicateStale(false);
     invoke("find_duplicates_stream", {
+      docId: DOC_ID,
       columnIdx: duplicateColumn,
       requestId,
     }).catch((err) => {
@@ -1672,27 +2217,37 @@ function App() {
     }
 
     setLoadingRows(true);
-    const sortKey = sortState
-      ? `${sortState.column}:${sortState.direction}`
-      : null;
-    const command = sortState ? "get_sorted_chunk" : "get_csv_chunk";
+    const viewKey = (
+      sort: SortState | null,
+      filter: FilterState | null,
+    ): string | null =>
+      sort
+        ? `${sort.column}:${sort.direction}`
+        : filter
+          ? `filter:${filter.expression}`
+          : null;
+    const sortKey = viewKey(sortState, filterState);
+    const command = sortState
+      ? "get_sorted_chunk"
+      : filterState
+        ? "get_filtered_chunk"
+        : "get_csv_chunk";
     const requestedCount = Math.min(
       Math.max(rangeCount, CHUNK_SIZE),
       totalRows - startIndex,
     );
     invoke<string[][] | SortedRow[]>(command, {
+      docId: DOC_ID,
       start: startIndex,
       count: requestedCount,
     })
       .then((chunk) => {
-        const currentSortKey = sortState
-          ? `${sortState.column}:${sortState.direction}`
-          : null;
+        const currentSortKey = viewKey(sortState, filterStateRef.current);
         if (sortKey !== currentSortKey) {
           return;
         }
         setError(null);
-        if (sortState) {
+        if (sortState || filterState) {
           const sortedChunk = chunk as SortedRow[];
           const dataMap = dataRef.current;
           const indexMap = rowIndexMapRef.current;
@@ -1722,9 +2277,7 @@ function App() {
         }
       })
       .catch((err) => {
-        const currentSortKey = sortState
-          ? `${sortState.column}:${sortState.direction}`
-          : null;
+        const currentSortKey = viewKey(sortState, filterStateRef.current);
         if (sortKey !== currentSortKey) {
           return;
         }
@@ -1733,6 +2286,7 @@ function App() {
       .finally(() => setLoadingRows(false));
   }, [
     filePath,
+    filterState,
     loadingRows,
     rowCountReady,
     sortLoading,
@@ -1777,7 +2331,7 @@ function App() {
       }
     }
 
-    if (sortState) {
+    if (sortState || filterState) {
       const indexMap = rowIndexMapRef.current;
       for (const key of indexMap.keys()) {
         if (key < windowStart || key >= windowEnd) {
@@ -1789,11 +2343,11 @@ function App() {
 
     if (removed) {
       setDataVersion((prev) => prev + 1);
-      if (sortState) {
+      if (sortState || filterState) {
         setRowIndexVersion((prev) => prev + 1);
       }
     }
-  }, [rowCountReady, sortState, totalRows, virtualItems]);
+  }, [filterState, rowCountReady, sortState, totalRows, virtualItems]);
 
   useEffect(() => {
     if (showFind) {
@@ -1952,30 +2506,33 @@ function App() {
 
   useEffect(() => {
     if (!filePath || !rowCountReady) {
-      searchRequestIdRef.current += 1;
+      searchRequestIdRef.current = ++streamRequestCounterRef.current;
       setSearching(false);
       setSearchStale(false);
       return;
     }
     if (!debouncedSearch) {
-      searchRequestIdRef.current += 1;
+      searchRequestIdRef.current = ++streamRequestCounterRef.current;
       setSearchResults(null);
       setSearching(false);
       setSearchStale(false);
       return;
     }
 
-    const requestId = searchRequestIdRef.current + 1;
+    const requestId = ++streamRequestCounterRef.current;
     searchRequestIdRef.current = requestId;
     setError(null);
     setSearching(true);
+    setSearchProgress(null);
     setSearchResults([]);
     setSearchStale(false);
     invoke("search_csv_stream", {
+      docId: DOC_ID,
       columnIdx: searchColumn,
       query: debouncedSearch,
       matchCase: searchMatchCase,
       wholeWord: searchWholeWord,
+      regex: searchRegex,
       requestId,
     }).catch((err) => {
       if (requestId !== searchRequestIdRef.current) {
@@ -1985,6 +2542,9 @@ function App() {
       setSearching(false);
       setSearchResults(null);
     });
+    return () => {
+      invoke("cancel_request", { docId: DOC_ID, requestId }).catch(() => {});
+    };
   }, [
     debouncedSearch,
     filePath,
@@ -1992,17 +2552,22 @@ function App() {
     searchColumn,
     searchMatchCase,
     searchWholeWord,
+    searchRegex,
     searchRefreshToken,
   ]);
 
   const getDisplayIndex = useCallback(
     (originalIndex: number) => {
+      if (filterState) {
+        // Filtered rows are not indexed by row id on this side
+        return undefined;
+      }
       if (!sortState) {
         return originalIndex;
       }
       return sortedIndexLookup?.[originalIndex];
     },
-    [sortState, sortedIndexLookup],
+    [filterState, sortState, sortedIndexLookup],
   );
 
   const scrollToMatch = useCallback(
@@ -2129,7 +2694,14 @@ function App() {
       setSaving(true);
 
       try {
-        let targetPath = savePath ?? filePath;
+        // Compressed files, archives, fixed-width and JSON Lines files are
+        // not overwritten with plain CSV without asking
+        const convertsSource =
+          fileCompression ||
+          fileMember ||
+          parseEffective?.fixed_width ||
+          parseEffective?.json_lines;
+        let targetPath = savePath ?? (convertsSource ? null : filePath);
         if (forceSaveAs || !targetPath) {
           const selected = await save({
             filters: [{ name: "CSV", extensions: ["csv"] }],
@@ -2147,47 +2719,15 @@ function App() {
           }
         }
 
-        const rows: string[] = [];
-        if (csvFormat.hasHeaders && headers.length) {
-          rows.push(
-            formatCsvRow(headers, csvFormat.delimiter, csvFormat.quote),
-          );
-        }
-
-        let start = 0;
-        while (true) {
-          const chunk = await invoke<string[][]>("get_csv_chunk", {
-            start,
-            count: CHUNK_SIZE,
-          });
-          if (chunk.length === 0) {
-            break;
-          }
-          chunk.forEach((row, idx) => {
-            const originalIndex = start + idx;
-            if (deletedRowsRef.current.has(originalIndex)) {
-              return;
-            }
-            const nextRow = applyEditsToRow(row, originalIndex);
-            rows.push(
-              formatCsvRow(nextRow, csvFormat.delimiter, csvFormat.quote),
-            );
-          });
-          if (chunk.length < CHUNK_SIZE) {
-            break;
-          }
-          start += chunk.length;
-        }
-
-        const contents = rows.join(csvFormat.lineEnding);
-        await invoke("write_csv_file", {
+        // The backend streams the rows with pending edits applied, in the
+        // delimiter, quote and line ending the file was parsed with
+        await invoke<number>("export_csv", {
+          docId: DOC_ID,
           path: targetPath,
-          contents,
         });
 
         setHasEdits(false);
         if (targetPath === filePath) {
-          editsRef.current = new Map();
           await handleOpenPath(filePath);
         } else {
           setSavePath(targetPath);
@@ -2199,13 +2739,13 @@ function App() {
       }
     },
     [
-      applyEditsToRow,
-      csvFormat,
+      fileCompression,
+      fileMember,
       filePath,
       handleOpenPath,
       hasEdits,
-      headers,
       lastOpenDir,
+      parseEffective,
       savePath,
       saving,
     ],
@@ -2224,8 +2764,17 @@ function App() {
       setRowIndexVersion((prev) => prev + 1);
       dataRef.current = new Map();
       setDataVersion((prev) => prev + 1);
-      invoke("clear_sort").catch(() => {});
+      invoke("clear_sort", { docId: DOC_ID }).catch(() => {});
       setSortLoading(false);
+      if (sortedRowsRef.current) {
+        sortedRowsRef.current = false;
+        // A filter that replaced the sort has set its own row count
+        if (!filterStateRef.current) {
+          invoke<number>("get_row_count", { docId: DOC_ID })
+            .then((rows) => setTotalRows(rows))
+            .catch(() => {});
+        }
+      }
       return;
     }
 
@@ -2238,6 +2787,7 @@ function App() {
     dataRef.current = new Map();
     setDataVersion((prev) => prev + 1);
     invoke<number[]>("sort_csv", {
+      docId: DOC_ID,
       columnIdx: sortState.column,
       ascending: sortState.direction === "asc",
     })
@@ -2245,6 +2795,8 @@ function App() {
         if (requestId !== sortWorkerRequestIdRef.current) {
           return;
         }
+        sortedRowsRef.current = true;
+        setTotalRows(order.length);
         const worker = sortWorkerRef.current;
         if (worker) {
           const message: SortWorkerRequest = {
@@ -2284,7 +2836,9 @@ function App() {
           return;
         }
         appendDebugLog(`sort_csv invoke failed: ${String(err)}`);
-        setError(typeof err === "string" ? err : "Failed to sort CSV.");
+        if (err !== "Request cancelled") {
+          setError(typeof err === "string" ? err : "Failed to sort CSV.");
+        }
         setSortState(null);
         setSortLoading(false);
       });
@@ -2301,29 +2855,31 @@ function App() {
     let unlistenFns: Array<() => void> = [];
 
     const setupMenuListeners = async () => {
+      // Menu actions are sent to the focused window only
+      const currentWindow = getCurrentWebviewWindow();
       const fns = await Promise.all([
-        listen("menu-open", () => {
+        currentWindow.listen("menu-open", () => {
           handlePickFile();
         }),
-        listen("menu-save", () => {
+        currentWindow.listen("menu-save", () => {
           handleSave(false);
         }),
-        listen("menu-save-as", () => {
+        currentWindow.listen("menu-save-as", () => {
           handleSave(true);
         }),
-        listen("menu-clear", () => {
+        currentWindow.listen("menu-clear", () => {
           handleClearFile();
         }),
-        listen("open-settings", () => {
+        currentWindow.listen("open-settings", () => {
           setShowSettings(true);
         }),
-        listen("menu-find", () => {
+        currentWindow.listen("menu-find", () => {
           setShowFind(true);
           setShowDuplicates(false);
           setActiveHighlight("search");
         }),
-        listen("menu-clear-search", () => {
-          searchRequestIdRef.current += 1;
+        currentWindow.listen("menu-clear-search", () => {
+          searchRequestIdRef.current = ++streamRequestCounterRef.current;
           setSearchTerm("");
           setSearchResults(null);
           setSearching(false);
@@ -2331,39 +2887,83 @@ function App() {
           setActiveHighlight((prev) => (prev === "search" ? null : prev));
           setSearchStale(false);
         }),
-        listen("menu-check-duplicates", () => {
+        currentWindow.listen("menu-profile", () => {
+          setShowProfile(true);
+        }),
+        currentWindow.listen("menu-summarize", () => {
+          setShowSummary(true);
+        }),
+        currentWindow.listen("menu-jobs", () => {
+          setShowJobs(true);
+        }),
+        currentWindow.listen("menu-export", () => {
+          setShowExport(true);
+        }),
+        currentWindow.listen("menu-import-sqlite", () => {
+          handlePickSqlite();
+        }),
+        currentWindow.listen("menu-check-duplicates", () => {
           setShowDuplicates(true);
           setShowFind(false);
           setActiveHighlight("duplicates");
         }),
-        listen<number>("menu-row-height", (event) => {
+        currentWindow.listen("menu-filter", () => {
+          setShowFilter(true);
+        }),
+        currentWindow.listen<number>("menu-row-height", (event) => {
           setRowHeight(event.payload);
         }),
-        listen<boolean>("menu-show-index", (event) => {
+        currentWindow.listen<boolean>("menu-show-index", (event) => {
           setShowIndex(event.payload);
         }),
-        listen("menu-next-match", () => {
+        currentWindow.listen<boolean>("menu-follow", (event) => {
+          setFollowFile(event.payload);
+        }),
+        currentWindow.listen("menu-next-match", () => {
           goToNextMatch();
         }),
-        listen("menu-prev-match", () => {
+        currentWindow.listen("menu-prev-match", () => {
           goToPrevMatch();
         }),
-        listen("menu-close-find", () => {
+        currentWindow.listen("menu-close-find", () => {
           setShowFind(false);
         }),
-        listen("menu-toggle-theme", () => {
+        currentWindow.listen("menu-toggle-theme", () => {
           toggleTheme();
         }),
-        listen("menu-parse-settings", () => {
+        currentWindow.listen("menu-parse-settings", () => {
           setShowSettings(true);
         }),
-        listen<number>("row-count", (event) => {
-          setTotalRows(event.payload);
+        listen<RowCountPayload>("row-count", (event) => {
+          if (event.payload.docId !== DOC_ID) {
+            return;
+          }
+          if (!filterStateRef.current) {
+            setTotalRows(event.payload.rows);
+          }
           setRowCountReady(true);
           setLoadingProgress(null);
         }),
-        listen<number>("parse-progress", (event) => {
-          setLoadingProgress(event.payload);
+        listen<RowCountPayload>("parse-progress", (event) => {
+          if (event.payload.docId !== DOC_ID) {
+            return;
+          }
+          setLoadingProgress(event.payload.rows);
+        }),
+        listen<JobInfo>("job-updated", (event) => {
+          const job = event.payload;
+          if (job.docId !== DOC_ID) {
+            return;
+          }
+          setRunningJobs((prev) => {
+            const others = prev.filter((item) => item.id !== job.id);
+            return job.status === "running" ? [...others, job] : others;
+          });
+          setJobHistory((prev) =>
+            [...prev.filter((item) => item.id !== job.id), job]
+              .sort((a, b) => a.id - b.id)
+              .slice(-MAX_JOB_HISTORY),
+          );
         }),
       ]);
 
@@ -2386,10 +2986,168 @@ function App() {
     goToPrevMatch,
     handleClearFile,
     handlePickFile,
+    handlePickSqlite,
     handleSave,
     toggleTheme,
   ]);
 
+  useEffect(() => {
+    if (!showProfile || !filePath || !rowCountReady) {
+      return;
+    }
+    let active = true;
+    setProfiles(null);
+    setProfileError(null);
+    invoke<ColumnProfile[]>("profile_columns", { docId: DOC_ID })
+      .then((result) => {
+        if (active) {
+          setProfiles(result);
+        }
+      })
+      .catch((err) => {
+        if (active && err !== "Request cancelled") {
+          setProfileError(
+            typeof err === "string" ? err : "Failed to profile columns.",
+          );
+        }
+      });
+    return () => {
+      active = false;
+    };
+  }, [filePath, rowCountReady, showProfile]);
+
+  useEffect(() => {
+    if (valueCountsColumn === null || !filePath || !rowCountReady) {
+      return;
+    }
+    let active = true;
+    setValueCounts(null);
+    setValueCountsError(null);
+    invoke<ValueCountsPage>("value_counts", {
+      docId: DOC_ID,
+      column: valueCountsColumn,
+      limit: VALUE_COUNTS_PAGE_SIZE,
+      offset: 0,
+    })
+      .then((page) => {
+        if (active) {
+          setValueCounts(page);
+        }
+      })
+      .catch((err) => {
+        if (active && err !== "Request cancelled") {
+          setValueCountsError(
+            typeof err === "string" ? err : "Failed to count values.",
+          );
+        }
+      });
+    return () => {
+      active = false;
+    };
+  }, [filePath, rowCountReady, valueCountsColumn]);
+
+  const loadMoreValueCounts = useCallback(async () => {
+    if (valueCountsColumn === null || !valueCounts) {
+      return;
+    }
+    try {
+      const page = await invoke<ValueCountsPage>("value_counts", {
+        docId: DOC_ID,
+        column: valueCountsColumn,
+        limit: VALUE_COUNTS_PAGE_SIZE,
+        offset: valueCounts.values.length,
+      });
+      setValueCounts({
+        ...page,
+        values: [...valueCounts.values, ...page.values],
+      });
+    } catch (err) {
+      setValueCountsError(
+        typeof err === "string" ? err : "Failed to count values.",
+      );
+    }
+  }, [valueCounts, valueCountsColumn]);
+
+  useEffect(() => {
+    if (!showJobs) {
+      return;
+    }
+    invoke<JobInfo[]>("list_jobs")
+      .then((jobs) => setJobHistory(jobs))
+      .catch(() => {});
+  }, [showJobs]);
+
+  useEffect(() => {
+    if (!followFile || !filePath || !rowCountReady) {
+      return;
+    }
+    let active = true;
+    let unlistenFns: Array<() => void> = [];
+    // Rows of the file already shown; the last one is read again once it grows
+    followRowsRef.current = filterStateRef.current ? 0 : totalRows;
+
+    const setupFollow = async () => {
+      const fns = await Promise.all([
+        listen<RowCountPayload>("row-count", (event) => {
+          if (event.payload.docId !== DOC_ID) {
+            return;
+          }
+          // The previous last row may have been partly written when it was loaded
+          const previous = followRowsRef.current;
+          followRowsRef.current = event.payload.rows;
+          if (sortState || filterStateRef.current) {
+            return;
+          }
+          if (dataRef.current.delete(previous - 1)) {
+            setDataVersion((prev) => prev + 1);
+          }
+        }),
+        listen<RowCountPayload>("filter-updated", (event) => {
+          if (event.payload.docId !== DOC_ID) {
+            return;
+          }
+          // The last matching row may have been read again or stopped matching
+          const previous = filterStateRef.current?.rows ?? 0;
+          rowIndexMapRef.current.delete(previous - 1);
+          setRowIndexVersion((prev) => prev + 1);
+          dataRef.current.delete(previous - 1);
+          setDataVersion((prev) => prev + 1);
+          setFilterState((prev) =>
+            prev ? { ...prev, rows: event.payload.rows } : prev,
+          );
+          setTotalRows(event.payload.rows);
+        }),
+        listen<DocPayload>("sort-reset", (event) => {
+          if (event.payload.docId !== DOC_ID) {
+            return;
+          }
+          // Appended rows have no place in the old order
+          setSortState(null);
+        }),
+        listen<DocPayload>("follow-reset", (event) => {
+          if (event.payload.docId !== DOC_ID) {
+            return;
+          }
+          handleOpenPath(filePath);
+        }),
+      ]);
+      if (!active) {
+        fns.forEach((fn) => fn());
+        return;
+      }
+      unlistenFns = fns;
+      invoke("start_follow", { docId: DOC_ID }).catch(() => {});
+    };
+
+    setupFollow();
+
+    return () => {
+      active = false;
+      unlistenFns.forEach((fn) => fn());
+      invoke("stop_follow", { docId: DOC_ID }).catch(() => {});
+    };
+  }, [filePath, followFile, handleOpenPath, rowCountReady, sortState]);
+
   useEffect(() => {
     let active = true;
     let unlistenFns: Array<() => void> = [];
@@ -2398,7 +3156,10 @@ function App() {
       const fns = await Promise.all([
         listen<MatchesChunkPayload>("search-chunk", (event) => {
           const payload = event.payload;
-          if (payload.requestId !== searchRequestIdRef.current) {
+          if (
+            payload.docId !== DOC_ID ||
+            payload.requestId !== searchRequestIdRef.current
+          ) {
             return;
           }
           setSearchResults((prev) =>
@@ -2406,14 +3167,35 @@ function App() {
           );
         }),
         listen<MatchesCompletePayload>("search-complete", (event) => {
-          if (event.payload.requestId !== searchRequestIdRef.current) {
+          if (
+            event.payload.docId !== DOC_ID ||
+            event.payload.requestId !== searchRequestIdRef.current
+          ) {
             return;
           }
           setSearching(false);
+          setSearchProgress(null);
+        }),
+        listen<SearchProgressPayload>("search-progress", (event) => {
+          const payload = event.payload;
+          if (
+            payload.docId !== DOC_ID ||
+            payload.requestId !== searchRequestIdRef.current
+          ) {
+            return;
+          }
+          setSearchProgress(
+            payload.total > 0
+              ? Math.min(100, Math.floor((payload.scanned / payload.total) * 100))
+              : null,
+          );
         }),
         listen<MatchesChunkPayload>("duplicates-chunk", (event) => {
           const payload = event.payload;
-          if (payload.requestId !== duplicateRequestIdRef.current) {
+          if (
+            payload.docId !== DOC_ID ||
+            payload.requestId !== duplicateRequestIdRef.current
+          ) {
             return;
           }
           setDuplicateResults((prev) =>
@@ -2421,7 +3203,10 @@ function App() {
           );
         }),
         listen<MatchesCompletePayload>("duplicates-complete", (event) => {
-          if (event.payload.requestId !== duplicateRequestIdRef.current) {
+          if (
+            event.payload.docId !== DOC_ID ||
+            event.payload.requestId !== duplicateRequestIdRef.current
+          ) {
             return;
           }
           setDuplicateChecking(false);
@@ -2448,12 +3233,25 @@ function App() {
     let active = true;
 
     invoke<string | null>("take_pending_open")
-      .then((path) => {
+      .then(async (path) => {
         if (!active) {
           return;
         }
         if (path) {
-          return handleOpenPath(path);
+          return handleOpenSource(path);
+        }
+        // A reloaded webview reattaches to the file the backend still has open
+        const source = await invoke<DocumentSource | null>(
+          "get_document_path",
+          { docId: DOC_ID },
+        );
+        if (active && source) {
+          return handleOpenPath(
+            source.path,
+            undefined,
+            true,
+            source.member ?? undefined,
+          );
         }
       })
       .catch(() => {})
@@ -2466,7 +3264,7 @@ function App() {
     return () => {
       active = false;
     };
-  }, [handleOpenPath]);
+  }, [handleOpenPath, handleOpenSource]);
 
   useEffect(() => {
     if (!filePath || rowCountReady) {
@@ -2479,7 +3277,7 @@ function App() {
 
     const poll = async () => {
       try {
-        const count = await invoke<number>("get_row_count");
+        const count = await invoke<number>("get_row_count", { docId: DOC_ID });
         if (!active) {
           return;
         }
@@ -2567,6 +3365,19 @@ function App() {
       }
       if (key === "o" || key === "r") {
         event.preventDefault();
+        return;
+      }
+      if (isTextInputTarget(event.target)) {
+        return;
+      }
+      if ((key === "z" && event.shiftKey) || key === "y") {
+        event.preventDefault();
+        redoEdit();
+        return;
+      }
+      if (key === "z") {
+        event.preventDefault();
+        undoEdit();
       }
     };
 
@@ -2574,7 +3385,7 @@ function App() {
     return () => {
       window.removeEventListener("keydown", handleKeydown);
     };
-  }, []);
+  }, [redoEdit, undoEdit]);
 
   const defaultColumnStyle = useMemo(() => {
     const width = `${columnWidth}px`;
@@ -2610,8 +3421,222 @@ function App() {
   // const searchSelectionValue =
   //   searchColumn === null ? "row" : String(searchColumn);
   const parseWarningCount = parseWarnings.length;
+  const offsetJob = runningJobs.find((job) => job.kind === "offset_build");
+  const backgroundJobs = runningJobs.filter(
+    (job) => job.kind !== "offset_build",
+  );
+  const profileJob = runningJobs.find((job) => job.kind === "profile");
+  const documentJobs = jobHistory
+    .filter((job) => job.docId === DOC_ID)
+    .reverse();
+  const summaryJob = runningJobs.find((job) => job.kind === "aggregate");
+  const valueCountsJob = runningJobs.find((job) => job.kind === "value_counts");
+  const filterJob = runningJobs.find((job) => job.kind === "filter");
+  const closeValueCounts = () => {
+    if (valueCountsJob) {
+      invoke("cancel_job", { jobId: valueCountsJob.id }).catch(() => {});
+    }
+    setValueCountsColumn(null);
+  };
+  const closeProfile = () => {
+    if (profileJob) {
+      invoke("cancel_job", { jobId: profileJob.id }).catch(() => {});
+    }
+    setShowProfile(false);
+  };
+  const closeSummary = () => {
+    if (summaryJob) {
+      invoke("cancel_job", { jobId: summaryJob.id }).catch(() => {});
+    }
+    setShowSummary(false);
+  };
+  const runSummary = () => {
+    setSummaryRunning(true);
+    setSummaryError(null);
+    const request =
+      summaryMode === "pivot"
+        ? invoke<AggregateTable>("pivot_csv", {
+            docId: DOC_ID,
+            rowKey: pivotRowKey,
+            columnKey: pivotColumnKey,
+            aggregate: summaryAggregates[0],
+          })
+        : invoke<AggregateTable>("aggregate_csv", {
+            docId: DOC_ID,
+            groupBy: summaryGroupBy,
+            aggregates: summaryAggregates,
+          });
+    request
+      .then((result) => setSummaryResult(result))
+      .catch((err) => {
+        setSummaryResult(null);
+        if (err !== "Request cancelled") {
+          setSummaryError(
+            typeof err === "string" ? err : "Failed to summarize the file.",
+          );
+        }
+      })
+      .finally(() => setSummaryRunning(false));
+  };
+  const exportSummary = async () => {
+    const selected = await save({
+      filters: [{ name: "CSV", extensions: ["csv"] }],
+      defaultPath: lastOpenDir ?? undefined,
+    });
+    if (!selected) {
+      return;
+    }
+    try {
+      await invoke("export_aggregate_csv", { docId: DOC_ID, path: selected });
+      setNotice(`Saved summary to ${selected}.`);
+    } catch (err) {
+      setSummaryError(
+        typeof err === "string" ? err : "Failed to export the summary.",
+      );
+    }
+  };
+  useEffect(() => {
+    if (!showExport) {
+      return;
+    }
+    setExportColumns(
+      headers.map((_, column) => ({ column, included: true, indexed: false })),
+    );
+    setExportError(null);
+  }, [headers, showExport]);
+  const exportJob = runningJobs.find((job) => job.kind === "export");
+  const closeExport = () => {
+    if (exportJob) {
+      invoke("cancel_job", { jobId: exportJob.id }).catch(() => {});
+    }
+    setShowExport(false);
+  };
+  const moveExportColumn = (position: number, offset: number) => {
+    setExportColumns((prev) => {
+      const target = position + offset;
+      if (target < 0 || target >= prev.length) {
+        return prev;
+      }
+      const next = [...prev];
+      [next[position], next[target]] = [next[target], next[position]];
+      return next;
+    });
+  };
+  const runExport = async () => {
+    const { label, extension } = EXPORT_FORMATS[exportFormat];
+    const selected = await save({
+      filters: [{ name: label, extensions: [extension] }],
+      defaultPath: lastOpenDir ?? undefined,
+    });
+    if (!selected) {
+      return;
+    }
+    setExportRunning(true);
+    setExportError(null);
+    try {
+      const source =
+        exportSource === "rows"
+          ? { kind: "rows", rows: await collectSelectedRows() }
+          : { kind: exportSource };
+      const included = exportColumns
+        .filter((item) => item.included)
+        .map((item) => item.column);
+      const projected =
+        included.length !== headers.length ||
+        included.some((column, index) => column !== index);
+      const columns = projected ? included : null;
+      const written =
+        exportFormat === "sqlite"
+          ? await invoke<number>("export_sqlite", {
+              docId: DOC_ID,
+              path: selected,
+              options: {
+                table: exportTable,
+                indexes: exportColumns
+                  .filter((item) => item.included)
+                  .flatMap((item, position) =>
+                    item.indexed ? [position] : [],
+                  ),
+                replace: exportReplaceTable,
+              },
+              source,
+              columns,
+            })
+          : await invoke<number>("export_rows", {
+              docId: DOC_ID,
+              path: selected,
+              options: {
+                format: exportFormat,
+                inferTypes: exportInferTypes,
+                compression: exportCompression,
+              },
+              source,
+              columns,
+            });
+      setNotice(`Exported ${written.toLocaleString()} rows to ${selected}.`);
+      setShowExport(false);
+      // The backend only lets a full CSV export replace the open file, which
+      // then has to be read again like after a save
+      if (selected === filePath) {
+        setHasEdits(false);
+        await handleOpenPath(filePath);
+      }
+    } catch (err) {
+      if (err !== "Request cancelled") {
+        setExportError(
+          typeof err === "string" ? err : "Failed to export rows.",
+        );
+      }
+    } finally {
+      setExportRunning(false);
+    }
+  };
+  const importJob = runningJobs.find((job) => job.kind === "import");
+  const importedRows = importJob?.processed ?? 0;
+  const closeSqliteImport = () => {
+    if (importJob) {
+      invoke("cancel_job", { jobId: importJob.id }).catch(() => {});
+    }
+    setSqliteImport(null);
+  };
+  const runSqliteImport = async () => {
+    if (!sqliteImport) {
+      return;
+    }
+    setSqliteImportRunning(true);
+    setSqliteImportError(null);
+    try {
+      const query = sqliteImportQuery.trim();
+      const csvPath = await invoke<string>("import_sqlite", {
+        docId: DOC_ID,
+        path: sqliteImport.path,
+        table: query ? null : sqliteImportTable,
+        query: query || null,
+      });
+      setSqliteImport(null);
+      await handleOpenPath(csvPath);
+      // Keep later dialogs next to the database, not the import cache
+      const dir = getDirFromPath(sqliteImport.path);
+      if (dir) {
+        setLastOpenDir(dir);
+      }
+    } catch (err) {
+      if (err !== "Request cancelled") {
+        setSqliteImportError(
+          typeof err === "string" ? err : "Failed to import from SQLite.",
+        );
+      }
+    } finally {
+      setSqliteImportRunning(false);
+    }
+  };
+  const hasEditHistory = canUndo || canRedo;
   const showFloatingToolbar =
-    !!filePath && (parseWarningCount > 0 || isDirty || selectedCount > 1);
+    !!filePath &&
+    (parseWarningCount > 0 ||
+      isDirty ||
+      hasEditHistory ||
+      selectedCount > 1);
 
   const handleResizeStart = useCallback(
     (event: React.MouseEvent<HTMLDivElement>, columnIndex: number) => {
@@ -2689,6 +3714,10 @@ function App() {
     if (sortLoading || !rowCountReady) {
       return;
     }
+    if (filterState) {
+      // Sorting replaces the filtered view
+      void clearFilter();
+    }
     setSortState((prev) => {
       if (!prev || prev.column !== columnIndex) {
         return { column: columnIndex, direction: "asc" };
@@ -2788,9 +3817,38 @@ function App() {
               Loading {getFileNameFromPath(filePath || "")} (
               {loadingProgress.toLocaleString()} rows)...
             </div>
-            {/* Optional: Cancel button if supported */}
+            {offsetJob ? (
+              <button
+                className="loading-banner-action"
+                onClick={() => {
+                  invoke("cancel_job", { jobId: offsetJob.id }).catch(() => {});
+                  handleClearFile();
+                }}
+              >
+                Cancel
+              </button>
+            ) : null}
           </div>
         ) : null}
+        {backgroundJobs.map((job) => (
+          <div className="loading-banner" key={job.id}>
+            <div className="loading-banner-icon">
+              <div className="spinner-ring" />
+            </div>
+            <div className="loading-banner-text">
+              {job.label}
+              {job.total ? ` (${formatJobPercent(job)}%)` : ""}...
+            </div>
+            <button
+              className="loading-banner-action"
+              onClick={() => {
+                invoke("cancel_job", { jobId: job.id }).catch(() => {});
+              }}
+            >
+              Cancel
+            </button>
+          </div>
+        ))}
         {showHeaderPrompt && filePath ? (
           <div className={`parse-banner${showIndex ? " with-index" : ""}`}>
             <div className="parse-banner-text">
@@ -2819,6 +3877,52 @@ function App() {
           </div>
         ) : null}
 
+        {fileSheets && fileMember && filePath ? (
+          <div className={`parse-banner${showIndex ? " with-index" : ""}`}>
+            <div className="parse-banner-text">Sheet</div>
+            <div className="parse-banner-actions">
+              <select
+                className="setting-select"
+                value={fileMember}
+                onChange={(event) => handleSelectSheet(event.target.value)}
+              >
+                {fileSheets.map((sheet) => (
+                  <option key={sheet} value={sheet}>
+                    {sheet}
+                  </option>
+                ))}
+              </select>
+              <label className="parse-banner-text" htmlFor="sheet-header-row">
+                Header row
+              </label>
+              <input
+                id="sheet-header-row"
+                key={`${fileMember}:${fileHeaderRow ?? ""}`}
+                type="number"
+                min={1}
+                className="setting-select"
+                style={{ width: 80 }}
+                defaultValue={fileHeaderRow ?? ""}
+                placeholder="First"
+                title="Sheet row holding the headers; rows above it are skipped"
+                onKeyDown={(event) => {
+                  if (event.key === "Enter") {
+                    event.currentTarget.blur();
+                  }
+                }}
+                onBlur={(event) => {
+                  const value = Number(event.currentTarget.value);
+                  const headerRow =
+                    Number.isInteger(value) && value > 0 ? value : undefined;
+                  if (headerRow !== (fileHeaderRow ?? undefined)) {
+                    handleSelectSheet(fileMember, headerRow);
+                  }
+                }}
+              />
+            </div>
+          </div>
+        ) : null}
+
         {showFind && filePath ? (
           <div className="find-widget">
             <div className="find-scope-select-wrapper">
@@ -2942,13 +4046,22 @@ function App() {
                 >
                   ab
                 </button>
+                <button
+                  className={`find-toggle-btn${searchRegex ? " active" : ""}`}
+                  onClick={() => setSearchRegex((prev) => !prev)}
+                  title="Use Regular Expression"
+                >
+                  .*
+                </button>
               </div>
             </div>
 
             <span className="find-results-count">
-              {searchResults?.length
-                ? `${currentMatch + 1} of ${searchResults.length}`
-                : "No results"}
+              {searching && searchProgress !== null
+                ? `Searching ${searchProgress}%`
+                : searchResults?.length
+                  ? `${currentMatch + 1} of ${searchResults.length}`
+                  : "No results"}
             </span>
             {searchStale ? (
               <div className="find-stale">
@@ -3127,19 +4240,103 @@ function App() {
             </button>
           </div>
         ) : null}
-        {!filePath ? (
-          checkingInitialOpen ? (
-            <div className="empty-state loading">
-              <h2>Opening file...</h2>
-              <p>Preparing the CSV viewer.</p>
-            </div>
-          ) : (
-            <div className="empty-state">
-              <button
-                className="btn primary empty-cta"
-                onClick={handlePickFile}
-              >
-                Open CSV File
+        {(showFilter || filterState) && filePath ? (
+          <div className="find-widget">
+            <div className="find-input-container">
+              <input
+                type="text"
+                value={filterInput}
+                onChange={(event) => setFilterInput(event.target.value)}
+                placeholder='Filter, e.g. status = "open" AND amount > 100'
+                disabled={!rowCountReady || filterApplying}
+                autoFocus
+                onKeyDown={(event) => {
+                  if (event.key === "Escape" && !filterState) {
+                    setShowFilter(false);
+                  }
+                  if (event.key === "Enter" && filterInput.trim()) {
+                    event.preventDefault();
+                    void runFilter("apply_filter", {
+                      expression: filterInput,
+                    });
+                  }
+                }}
+              />
+            </div>
+            <button
+              className="find-toggle-btn"
+              style={{
+                marginLeft: 6,
+                height: 26,
+                alignSelf: "center",
+                border: "1px solid var(--border)",
+                borderRadius: 4,
+                padding: "0 8px",
+              }}
+              onClick={() =>
+                void runFilter("apply_filter", { expression: filterInput })
+              }
+              disabled={!rowCountReady || filterApplying || !filterInput.trim()}
+            >
+              {filterApplying
+                ? filterJob && filterJob.total
+                  ? `Filtering ${formatJobPercent(filterJob)}%...`
+                  : "Filtering..."
+                : "Apply"}
+            </button>
+            <button
+              className="find-toggle-btn"
+              style={{
+                marginLeft: 4,
+                height: 26,
+                alignSelf: "center",
+                border: "1px solid var(--border)",
+                borderRadius: 4,
+                padding: "0 8px",
+              }}
+              onClick={() => void clearFilter()}
+              disabled={filterState === null}
+            >
+              Clear
+            </button>
+            <span
+              className="find-results-count"
+              style={{ flexGrow: 1, justifyContent: "flex-end" }}
+              title={filterError ?? undefined}
+            >
+              {filterError
+                ? filterError
+                : filterState
+                  ? `${filterState.rows.toLocaleString()} matching rows`
+                  : "No filter"}
+            </span>
+            <button
+              className="find-icon-btn"
+              onClick={() => {
+                if (filterState) {
+                  void clearFilter();
+                }
+                setShowFilter(false);
+              }}
+              title={filterState ? "Clear Filter and Close" : "Close"}
+            >
+              ✕
+            </button>
+          </div>
+        ) : null}
+        {!filePath ? (
+          checkingInitialOpen ? (
+            <div className="empty-state loading">
+              <h2>Opening file...</h2>
+              <p>Preparing the CSV viewer.</p>
+            </div>
+          ) : (
+            <div className="empty-state">
+              <button
+                className="btn primary empty-cta"
+                onClick={handlePickFile}
+              >
+                Open CSV File
               </button>
               {recentFiles.length ? (
                 <div className="empty-recent">
@@ -3153,7 +4350,7 @@ function App() {
                           <button
                             type="button"
                             className="recent-item"
-                            onClick={() => handleOpenPath(path)}
+                            onClick={() => handleOpenSource(path)}
                             title={path}
                           >
                             <span className="recent-name">{name}</span>
@@ -3257,17 +4454,16 @@ function App() {
                 </div>
                 {virtualItems.map((virtualRow) => {
                   const rowData = dataRef.current.get(virtualRow.index);
-                  const originalIndex = sortState
-                    ? rowIndexMapRef.current.get(virtualRow.index)
-                    : virtualRow.index;
+                  const originalIndex =
+                    sortState || filterState
+                      ? rowIndexMapRef.current.get(virtualRow.index)
+                      : virtualRow.index;
                   const isDeleted = isRowDeleted(originalIndex);
                   const isSelected = isIndexInSelectionRanges(
                     selectedRanges,
                     virtualRow.index,
                   );
-                  const displayRow = rowData
-                    ? applyEditsToRow(rowData, originalIndex)
-                    : null;
+                  const displayRow = rowData ?? null;
                   const rowNumber = (originalIndex ?? virtualRow.index) + 1;
                   const rowText = displayRow
                     ? formatCsvRow(
@@ -3332,7 +4528,9 @@ function App() {
                             searchHighlightActive &&
                             (searchColumn === null || cellIdx === searchColumn)
                           ) {
-                            if (!searchMatchCase) {
+                            if (searchPattern) {
+                              isCellMatch = searchPattern.test(cellValue);
+                            } else if (!searchMatchCase) {
                               const scLower = cellValue.toLowerCase();
                               if (searchWholeWord) {
                                 isCellMatch = scLower === searchQueryLower;
@@ -3379,7 +4577,6 @@ function App() {
                                   virtualRow.index,
                                   cellIdx,
                                   cellValue,
-                                  rowData?.[cellIdx] ?? "",
                                 )
                               }
                               onKeyDown={(event) => {
@@ -3389,7 +4586,6 @@ function App() {
                                     virtualRow.index,
                                     cellIdx,
                                     cellValue,
-                                    rowData?.[cellIdx] ?? "",
                                   );
                                 }
                               }}
@@ -3459,7 +4655,7 @@ function App() {
                 ) : (
                   <span />
                 )}
-                {selectedCount > 1 || isDirty ? (
+                {selectedCount > 1 || isDirty || hasEditHistory ? (
                   <div className="floating-toolbar-surface floating-toolbar-actions">
                     {selectedCount > 1 ? (
                       <>
@@ -3473,6 +4669,7 @@ function App() {
                         <button
                           className="btn subtle"
                           onClick={() => void deleteSelectedRows()}
+                          disabled={fileReadOnly}
                           title="Delete selected rows (Delete)"
                         >
                           Delete selected
@@ -3480,12 +4677,33 @@ function App() {
                         <button
                           className="btn subtle"
                           onClick={() => void restoreSelectedRows()}
+                          disabled={fileReadOnly}
                           title="Restore selected rows"
                         >
                           Restore selected
                         </button>
                       </>
                     ) : null}
+                    {hasEditHistory ? (
+                      <>
+                        <button
+                          className="btn subtle"
+                          onClick={undoEdit}
+                          disabled={!canUndo}
+                          title="Undo (Ctrl/Cmd+Z)"
+                        >
+                          Undo
+                        </button>
+                        <button
+                          className="btn subtle"
+                          onClick={redoEdit}
+                          disabled={!canRedo}
+                          title="Redo (Ctrl/Cmd+Shift+Z)"
+                        >
+                          Redo
+                        </button>
+                      </>
+                    ) : null}
                     {isDirty ? (
                       <>
                         <span className="table-toolbar-meta">
@@ -3666,6 +4884,56 @@ function App() {
                     </div>
                   ) : null}
                 </div>
+                <div className="setting-item">
+                  <div className="setting-item-row">
+                    <span className="setting-label">Fixed Width</span>
+                    <select
+                      className="setting-select"
+                      value={parseOverrides.fixedWidth}
+                      onChange={(event) => {
+                        const value = event.target
+                          .value as ParseOverridesState["fixedWidth"];
+                        setParseOverrides((prev) => ({
+                          ...prev,
+                          fixedWidth: value,
+                          fixedWidthCustom:
+                            value === "custom" && !prev.fixedWidthCustom
+                              ? (parseEffective?.fixed_width ?? "")
+                              : prev.fixedWidthCustom,
+                        }));
+                      }}
+                    >
+                      <option value="off">Off (delimited)</option>
+                      <option value="auto">Infer columns</option>
+                      <option value="custom">Custom columns</option>
+                    </select>
+                  </div>
+                  {parseOverrides.fixedWidth === "custom" ? (
+                    <div
+                      className="setting-item-row"
+                      style={{ marginTop: 8, justifyContent: "flex-end" }}
+                    >
+                      <input
+                        type="text"
+                        className="setting-select"
+                        style={{ width: 200 }}
+                        value={parseOverrides.fixedWidthCustom}
+                        onChange={(event) =>
+                          setParseOverrides((prev) => ({
+                            ...prev,
+                            fixedWidthCustom: event.target.value,
+                          }))
+                        }
+                        placeholder="1-10,11-25,26-"
+                      />
+                    </div>
+                  ) : null}
+                  {parseEffective?.fixed_width ? (
+                    <p className="setting-description">
+                      Columns: {parseEffective.fixed_width}
+                    </p>
+                  ) : null}
+                </div>
                 <div className="setting-item">
                   <div className="setting-item-row">
                     <span className="setting-label">Quote Char</span>
@@ -3766,6 +5034,31 @@ function App() {
                   </button>
                 </div>
               </div>
+              <div className="setting-group">
+                <h3>Performance</h3>
+                <div className="setting-item">
+                  <div className="setting-item-row">
+                    <span className="setting-label">Sort Memory</span>
+                    <select
+                      className="setting-select"
+                      value={sortMemoryMb}
+                      onChange={(e) => setSortMemoryMb(Number(e.target.value))}
+                    >
+                      {SORT_MEMORY_OPTIONS.map((megabytes) => (
+                        <option key={megabytes} value={megabytes}>
+                          {megabytes >= 1024
+                            ? `${megabytes / 1024} GB`
+                            : `${megabytes} MB`}
+                        </option>
+                      ))}
+                    </select>
+                  </div>
+                  <p className="setting-description">
+                    Memory a sort may use before spilling sorted runs to disk.
+                    Lower it on machines with little free RAM.
+                  </p>
+                </div>
+              </div>
               <div className="setting-group">
                 <h3>Diagnostics</h3>
                 <div className="setting-item">
@@ -3835,6 +5128,780 @@ function App() {
           </div>
         </div>
       ) : null}
+      {showProfile ? (
+        <div className="modal-overlay" onClick={closeProfile}>
+          <div
+            className="modal-content wide"
+            onClick={(e) => e.stopPropagation()}
+          >
+            <div className="modal-header">
+              <h2>Column Profile</h2>
+              <button className="close-button" onClick={closeProfile}>
+                &times;
+              </button>
+            </div>
+            <div className="modal-body">
+              {!filePath ? (
+                <p className="profile-status">Open a file to profile it.</p>
+              ) : profileError ? (
+                <p className="profile-status">{profileError}</p>
+              ) : !profiles ? (
+                <p className="profile-status">
+                  {profileJob && profileJob.total
+                    ? `Profiling ${Math.floor(
+                        (profileJob.processed / profileJob.total) * 100,
+                      )}%...`
+                    : "Profiling..."}
+                </p>
+              ) : (
+                <table className="profile-table">
+                  <thead>
+                    <tr>
+                      <th>Column</th>
+                      <th>Type</th>
+                      <th>Nulls</th>
+                      <th>Distinct</th>
+                      <th>Min</th>
+                      <th>Max</th>
+                      <th>Mean</th>
+                      <th>Std dev</th>
+                      <th>Length</th>
+                      <th>Top values</th>
+                    </tr>
+                  </thead>
+                  <tbody>
+                    {profiles.map((profile) => (
+                      <tr key={profile.column}>
+                        <td>{profile.name || `Column ${profile.column + 1}`}</td>
+                        <td>{profile.inferredType}</td>
+                        <td>{profile.nullCount.toLocaleString()}</td>
+                        <td>~{profile.distinctEstimate.toLocaleString()}</td>
+                        <td>{profile.min ?? ""}</td>
+                        <td>{profile.max ?? ""}</td>
+                        <td>{profile.mean?.toLocaleString() ?? ""}</td>
+                        <td>{profile.stddev?.toLocaleString() ?? ""}</td>
+                        <td>
+                          {profile.minLength !== null
+                            ? `${profile.minLength}-${profile.maxLength}`
+                            : ""}
+                        </td>
+                        <td
+                          title={
+                            profile.topValuesExact
+                              ? undefined
+                              : "Too many distinct values; counts are approximate"
+                          }
+                        >
+                          {profile.topValues
+                            .map((item) => `${item.value} (${item.count})`)
+                            .join(", ")}
+                        </td>
+                      </tr>
+                    ))}
+                  </tbody>
+                </table>
+              )}
+            </div>
+          </div>
+        </div>
+      ) : null}
+      {valueCountsColumn !== null ? (
+        <div className="modal-overlay" onClick={closeValueCounts}>
+          <div
+            className="modal-content wide"
+            onClick={(e) => e.stopPropagation()}
+          >
+            <div className="modal-header">
+              <h2>
+                Value Counts:{" "}
+                {headers[valueCountsColumn] ||
+                  `Column ${valueCountsColumn + 1}`}
+              </h2>
+              <button className="close-button" onClick={closeValueCounts}>
+                &times;
+              </button>
+            </div>
+            <div className="modal-body">
+              {valueCountsError ? (
+                <p className="profile-status">{valueCountsError}</p>
+              ) : !valueCounts ? (
+                <p className="profile-status">
+                  {valueCountsJob && valueCountsJob.total
+                    ? `Counting ${formatJobPercent(valueCountsJob)}%...`
+                    : "Counting..."}
+                </p>
+              ) : (
+                <>
+                  <p className="profile-status">
+                    {valueCounts.totalValues.toLocaleString()} distinct values
+                    {valueCounts.untrackedRows > 0
+                      ? `; ${valueCounts.untrackedRows.toLocaleString()} rows with further values were not counted`
+                      : ""}
+                    . Click a value to filter to it.
+                  </p>
+                  <table className="profile-table">
+                    <thead>
+                      <tr>
+                        <th>Value</th>
+                        <th>Count</th>
+                      </tr>
+                    </thead>
+                    <tbody>
+                      {valueCounts.values.map((item, index) => (
+                        <tr
+                          key={index}
+                          className="clickable-row"
+                          onClick={() => {
+                            const column = valueCountsColumn;
+                            setValueCountsColumn(null);
+                            void runFilter("filter_by_value", {
+                              column,
+                              value: item.value,
+                            });
+                          }}
+                        >
+                          <td>{item.value === "" ? "(empty)" : item.value}</td>
+                          <td>{item.count.toLocaleString()}</td>
+                        </tr>
+                      ))}
+                    </tbody>
+                  </table>
+                  {valueCounts.values.length < valueCounts.totalValues ? (
+                    <button
+                      className="btn subtle"
+                      onClick={() => void loadMoreValueCounts()}
+                    >
+                      Show more
+                    </button>
+                  ) : null}
+                </>
+              )}
+            </div>
+          </div>
+        </div>
+      ) : null}
+      {showJobs ? (
+        <div className="modal-overlay" onClick={() => setShowJobs(false)}>
+          <div
+            className="modal-content wide"
+            onClick={(e) => e.stopPropagation()}
+          >
+            <div className="modal-header">
+              <h2>Background Jobs</h2>
+              <button
+                className="close-button"
+                onClick={() => setShowJobs(false)}
+              >
+                &times;
+              </button>
+            </div>
+            <div className="modal-body">
+              {documentJobs.length === 0 ? (
+                <p className="profile-status">No jobs have run yet.</p>
+              ) : (
+                <table className="profile-table">
+                  <thead>
+                    <tr>
+                      <th>Job</th>
+                      <th>Status</th>
+                      <th>Progress</th>
+                      <th />
+                    </tr>
+                  </thead>
+                  <tbody>
+                    {documentJobs.map((job) => (
+                      <tr key={job.id}>
+                        <td>{job.label}</td>
+                        <td title={job.error ?? undefined}>
+                          {job.error
+                            ? `${job.status}: ${job.error}`
+                            : job.status}
+                        </td>
+                        <td>
+                          {job.total
+                            ? `${formatJobPercent(job)}%`
+                            : job.processed.toLocaleString()}
+                        </td>
+                        <td>
+                          {job.status === "running" ? (
+                            <button
+                              className="btn subtle"
+                              onClick={() => {
+                                invoke("cancel_job", { jobId: job.id }).catch(
+                                  () => {},
+                                );
+                              }}
+                            >
+                              Cancel
+                            </button>
+                          ) : null}
+                        </td>
+                      </tr>
+                    ))}
+                  </tbody>
+                </table>
+              )}
+            </div>
+          </div>
+        </div>
+      ) : null}
+      {showSummary ? (
+        <div className="modal-overlay" onClick={closeSummary}>
+          <div
+            className="modal-content wide"
+            onClick={(e) => e.stopPropagation()}
+          >
+            <div className="modal-header">
+              <h2>Summarize</h2>
+              <button className="close-button" onClick={closeSummary}>
+                &times;
+              </button>
+            </div>
+            <div className="modal-body">
+              <div className="setting-group">
+                <div className="setting-item-row">
+                  <span className="setting-label">Mode</span>
+                  <select
+                    className="setting-select"
+                    value={summaryMode}
+                    onChange={(e) => {
+                      setSummaryMode(e.target.value as "group" | "pivot");
+                      setSummaryResult(null);
+                    }}
+                  >
+                    <option value="group">Group by</option>
+                    <option value="pivot">Pivot</option>
+                  </select>
+                </div>
+              </div>
+              {summaryMode === "pivot" ? (
+                <div className="setting-group">
+                  <h3>Pivot</h3>
+                  <div className="setting-item-row">
+                    <span className="setting-label">Rows</span>
+                    <select
+                      className="setting-select"
+                      value={pivotRowKey}
+                      onChange={(e) => setPivotRowKey(Number(e.target.value))}
+                    >
+                      {headers.map((header, index) => (
+                        <option key={index} value={index}>
+                          {header || `Column ${index + 1}`}
+                        </option>
+                      ))}
+                    </select>
+                  </div>
+                  <div className="setting-item-row">
+                    <span className="setting-label">Columns</span>
+                    <select
+                      className="setting-select"
+                      value={pivotColumnKey}
+                      onChange={(e) =>
+                        setPivotColumnKey(Number(e.target.value))
+                      }
+                    >
+                      {headers.map((header, index) => (
+                        <option key={index} value={index}>
+                          {header || `Column ${index + 1}`}
+                        </option>
+                      ))}
+                    </select>
+                  </div>
+                </div>
+              ) : (
+                <div className="setting-group">
+                  <h3>Group by</h3>
+                  <div className="summary-columns">
+                    {headers.map((header, index) => (
+                      <label key={index} className="summary-column">
+                        <input
+                          type="checkbox"
+                          checked={summaryGroupBy.includes(index)}
+                          onChange={(e) => {
+                            const checked = e.target.checked;
+                            setSummaryGroupBy((prev) =>
+                              checked
+                                ? [...prev, index]
+                                : prev.filter((item) => item !== index),
+                            );
+                          }}
+                        />
+                        {header || `Column ${index + 1}`}
+                      </label>
+                    ))}
+                  </div>
+                </div>
+              )}
+              <div className="setting-group">
+                <h3>{summaryMode === "pivot" ? "Value" : "Aggregates"}</h3>
+                {(summaryMode === "pivot"
+                  ? summaryAggregates.slice(0, 1)
+                  : summaryAggregates
+                ).map((aggregate, position) => (
+                  <div key={position} className="summary-aggregate">
+                    <select
+                      className="setting-select"
+                      value={aggregate.func}
+                      onChange={(e) => {
+                        const func = e.target.value as AggregateFn;
+                        setSummaryAggregates((prev) =>
+                          prev.map((item, idx) =>
+                            idx === position
+                              ? {
+                                  func,
+                                  column:
+                                    func === "count"
+                                      ? item.column
+                                      : (item.column ?? 0),
+                                }
+                              : item,
+                          ),
+                        );
+                      }}
+                    >
+                      {(Object.keys(AGGREGATE_LABELS) as AggregateFn[]).map(
+                        (func) => (
+                          <option key={func} value={func}>
+                            {AGGREGATE_LABELS[func]}
+                          </option>
+                        ),
+                      )}
+                    </select>
+                    <select
+                      className="setting-select"
+                      value={aggregate.column ?? ""}
+                      onChange={(e) => {
+                        const column =
+                          e.target.value === "" ? null : Number(e.target.value);
+                        setSummaryAggregates((prev) =>
+                          prev.map((item, idx) =>
+                            idx === position ? { ...item, column } : item,
+                          ),
+                        );
+                      }}
+                    >
+                      {aggregate.func === "count" ? (
+                        <option value="">Rows</option>
+                      ) : null}
+                      {headers.map((header, index) => (
+                        <option key={index} value={index}>
+                          {header || `Column ${index + 1}`}
+                        </option>
+                      ))}
+                    </select>
+                    <button
+                      className="btn secondary small"
+                      disabled={
+                        summaryMode === "pivot" || summaryAggregates.length === 1
+                      }
+                      onClick={() =>
+                        setSummaryAggregates((prev) =>
+                          prev.filter((_, idx) => idx !== position),
+                        )
+                      }
+                    >
+                      Remove
+                    </button>
+                  </div>
+                ))}
+                {summaryMode === "group" ? (
+                  <div className="setting-actions">
+                    <button
+                      className="btn secondary small"
+                      onClick={() =>
+                        setSummaryAggregates((prev) => [
+                          ...prev,
+                          { func: "sum", column: 0 },
+                        ])
+                      }
+                    >
+                      Add aggregate
+                    </button>
+                  </div>
+                ) : null}
+              </div>
+              {summaryError ? (
+                <p className="profile-status">{summaryError}</p>
+              ) : summaryRunning ? (
+                <p className="profile-status">
+                  {summaryJob && summaryJob.total
+                    ? `Summarizing ${Math.floor(
+                        (summaryJob.processed / summaryJob.total) * 100,
+                      )}%...`
+                    : "Summarizing..."}
+                </p>
+              ) : summaryResult ? (
+                <table className="profile-table">
+                  <thead>
+                    <tr>
+                      {summaryResult.headers.map((header, index) => (
+                        <th key={index}>{header}</th>
+                      ))}
+                      {summaryResult.totals ? (
+                        <th className="pivot-total">Total</th>
+                      ) : null}
+                    </tr>
+                  </thead>
+                  <tbody>
+                    {summaryResult.rows
+                      .slice(0, MAX_SUMMARY_PREVIEW_ROWS)
+                      .map((row, rowIndex) => (
+                        <tr key={rowIndex}>
+                          {row.map((cell, index) => (
+                            <td key={index}>{cell}</td>
+                          ))}
+                          {summaryResult.totals ? (
+                            <td className="pivot-total">
+                              {summaryResult.totals.rows[rowIndex]}
+                            </td>
+                          ) : null}
+                        </tr>
+                      ))}
+                  </tbody>
+                  {summaryResult.totals ? (
+                    <tfoot>
+                      <tr className="pivot-total">
+                        <td>Total</td>
+                        {summaryResult.totals.columns.map((cell, index) => (
+                          <td key={index}>{cell}</td>
+                        ))}
+                        <td>{summaryResult.totals.grand}</td>
+                      </tr>
+                    </tfoot>
+                  ) : null}
+                </table>
+              ) : null}
+              {summaryResult &&
+              summaryResult.rows.length > MAX_SUMMARY_PREVIEW_ROWS ? (
+                <p className="profile-status">
+                  Showing {MAX_SUMMARY_PREVIEW_ROWS.toLocaleString()} of{" "}
+                  {summaryResult.rows.length.toLocaleString()} groups. Export to
+                  see all of them.
+                </p>
+              ) : null}
+            </div>
+            <div className="modal-footer summary-footer">
+              <button
+                className="btn secondary"
+                disabled={!summaryResult || summaryRunning}
+                onClick={() => void exportSummary()}
+              >
+                Export CSV
+              </button>
+              <button
+                className="btn primary"
+                disabled={!filePath || !rowCountReady || summaryRunning}
+                onClick={runSummary}
+              >
+                Run
+              </button>
+            </div>
+          </div>
+        </div>
+      ) : null}
+      {showExport ? (
+        <div className="modal-overlay" onClick={closeExport}>
+          <div className="modal-content" onClick={(e) => e.stopPropagation()}>
+            <div className="modal-header">
+              <h2>Export</h2>
+              <button className="close-button" onClick={closeExport}>
+                &times;
+              </button>
+            </div>
+            <div className="modal-body">
+              <div className="setting-group">
+                <div className="setting-item-row">
+                  <span className="setting-label">Format</span>
+                  <select
+                    className="setting-select"
+                    value={exportFormat}
+                    onChange={(e) =>
+                      setExportFormat(e.target.value as ExportFormat)
+                    }
+                  >
+                    {(Object.keys(EXPORT_FORMATS) as ExportFormat[]).map(
+                      (format) => (
+                        <option key={format} value={format}>
+                          {EXPORT_FORMATS[format].label}
+                        </option>
+                      ),
+                    )}
+                  </select>
+                </div>
+                {exportFormat === "json" || exportFormat === "ndjson" ? (
+                  <label className="summary-column">
+                    <input
+                      type="checkbox"
+                      checked={exportInferTypes}
+                      onChange={(e) => setExportInferTypes(e.target.checked)}
+                    />
+                    Write numbers, booleans and empty cells as JSON values
+                  </label>
+                ) : null}
+                {exportFormat === "parquet" ? (
+                  <div className="setting-item-row">
+                    <span className="setting-label">Compression</span>
+                    <select
+                      className="setting-select"
+                      value={exportCompression}
+                      onChange={(e) =>
+                        setExportCompression(
+                          e.target.value as ParquetCompression,
+                        )
+                      }
+                    >
+                      <option value="snappy">Snappy</option>
+                      <option value="zstd">Zstandard</option>
+                      <option value="gzip">Gzip</option>
+                      <option value="lz4">LZ4</option>
+                      <option value="brotli">Brotli</option>
+                      <option value="none">None</option>
+                    </select>
+                  </div>
+                ) : null}
+                {exportFormat === "sqlite" ? (
+                  <>
+                    <div className="setting-item-row">
+                      <span className="setting-label">Table</span>
+                      <input
+                        type="text"
+                        className="setting-select"
+                        value={exportTable}
+                        onChange={(e) => setExportTable(e.target.value)}
+                      />
+                    </div>
+                    <label className="summary-column">
+                      <input
+                        type="checkbox"
+                        checked={exportReplaceTable}
+                        onChange={(e) =>
+                          setExportReplaceTable(e.target.checked)
+                        }
+                      />
+                      Replace an existing table of the same name
+                    </label>
+                  </>
+                ) : null}
+                <div className="setting-item-row">
+                  <span className="setting-label">Rows</span>
+                  <select
+                    className="setting-select"
+                    value={exportSource}
+                    onChange={(e) =>
+                      setExportSource(e.target.value as ExportSourceKind)
+                    }
+                  >
+                    <option value="all">All rows</option>
+                    <option value="sorted" disabled={!sortState}>
+                      Sorted order
+                    </option>
+                    <option value="search" disabled={!searchResults}>
+                      Search results
+                    </option>
+                    <option value="duplicates" disabled={!duplicateResults}>
+                      Duplicates
+                    </option>
+                    <option value="rows" disabled={selectedCount === 0}>
+                      Selected rows
+                    </option>
+                  </select>
+                </div>
+              </div>
+              <div className="setting-group">
+                <h3>Columns</h3>
+                <div className="export-columns">
+                  {exportColumns.map((item, position) => (
+                    <div key={item.column} className="export-column">
+                      <label className="summary-column">
+                        <input
+                          type="checkbox"
+                          checked={item.included}
+                          onChange={(e) => {
+                            const included = e.target.checked;
+                            setExportColumns((prev) =>
+                              prev.map((entry, idx) =>
+                                idx === position
+                                  ? { ...entry, included }
+                                  : entry,
+                              ),
+                            );
+                          }}
+                        />
+                        {headers[item.column] || `Column ${item.column + 1}`}
+                      </label>
+                      {exportFormat === "sqlite" ? (
+                        <label className="summary-column">
+                          <input
+                            type="checkbox"
+                            checked={item.indexed}
+                            disabled={!item.included}
+                            onChange={(e) => {
+                              const indexed = e.target.checked;
+                              setExportColumns((prev) =>
+                                prev.map((entry, idx) =>
+                                  idx === position
+                                    ? { ...entry, indexed }
+                                    : entry,
+                                ),
+                              );
+                            }}
+                          />
+                          Index
+                        </label>
+                      ) : null}
+                      <button
+                        className="btn secondary small"
+                        disabled={position === 0}
+                        onClick={() => moveExportColumn(position, -1)}
+                      >
+                        Up
+                      </button>
+                      <button
+                        className="btn secondary small"
+                        disabled={position === exportColumns.length - 1}
+                        onClick={() => moveExportColumn(position, 1)}
+                      >
+                        Down
+                      </button>
+                    </div>
+                  ))}
+                </div>
+              </div>
+              {exportError ? (
+                <p className="profile-status">{exportError}</p>
+              ) : exportRunning ? (
+                <p className="profile-status">
+                  {exportJob && exportJob.total
+                    ? `Exporting ${Math.floor(
+                        (exportJob.processed / exportJob.total) * 100,
+                      )}%...`
+                    : "Exporting..."}
+                </p>
+              ) : null}
+            </div>
+            <div className="modal-footer summary-footer">
+              <button
+                className="btn primary"
+                disabled={
+                  !filePath ||
+                  !rowCountReady ||
+                  exportRunning ||
+                  !exportColumns.some((item) => item.included) ||
+                  (exportFormat === "sqlite" && !exportTable.trim())
+                }
+                onClick={() => void runExport()}
+              >
+                Export
+              </button>
+            </div>
+          </div>
+        </div>
+      ) : null}
+      {zipPicker ? (
+        <div className="modal-overlay" onClick={() => setZipPicker(null)}>
+          <div className="modal-content" onClick={(e) => e.stopPropagation()}>
+            <div className="modal-header">
+              <h2>Open from {getFileNameFromPath(zipPicker.path)}</h2>
+              <button
+                className="close-button"
+                onClick={() => setZipPicker(null)}
+              >
+                &times;
+              </button>
+            </div>
+            <div className="modal-body">
+              <div className="recent-list">
+                {zipPicker.members.map((member) => (
+                  <button
+                    key={member.name}
+                    type="button"
+                    className="recent-item"
+                    title={member.name}
+                    onClick={() => {
+                      setZipPicker(null);
+                      handleOpenPath(
+                        zipPicker.path,
+                        undefined,
+                        false,
+                        member.name,
+                      );
+                    }}
+                  >
+                    <span className="recent-name">{member.name}</span>
+                    <span className="recent-path">
+                      {formatFileSize(member.size)}
+                    </span>
+                  </button>
+                ))}
+              </div>
+            </div>
+          </div>
+        </div>
+      ) : null}
+      {sqliteImport ? (
+        <div className="modal-overlay" onClick={closeSqliteImport}>
+          <div className="modal-content" onClick={(e) => e.stopPropagation()}>
+            <div className="modal-header">
+              <h2>Import SQLite</h2>
+              <button className="close-button" onClick={closeSqliteImport}>
+                &times;
+              </button>
+            </div>
+            <div className="modal-body">
+              <div className="setting-group">
+                <div className="setting-item-row">
+                  <span className="setting-label">Table</span>
+                  <select
+                    className="setting-select"
+                    value={sqliteImportTable}
+                    disabled={!!sqliteImportQuery.trim()}
+                    onChange={(e) => setSqliteImportTable(e.target.value)}
+                  >
+                    {sqliteImport.tables.map((table) => (
+                      <option key={table.name} value={table.name}>
+                        {table.kind === "view"
+                          ? `${table.name} (view)`
+                          : table.name}
+                      </option>
+                    ))}
+                  </select>
+                </div>
+              </div>
+              <div className="setting-group">
+                <h3>Query</h3>
+                <textarea
+                  className="setting-select"
+                  rows={4}
+                  placeholder="SELECT ... (overrides the table)"
+                  value={sqliteImportQuery}
+                  onChange={(e) => setSqliteImportQuery(e.target.value)}
+                />
+              </div>
+              {sqliteImportError ? (
+                <p className="profile-status">{sqliteImportError}</p>
+              ) : sqliteImportRunning ? (
+                <p className="profile-status">
+                  {importedRows
+                    ? `Importing ${importedRows.toLocaleString()} rows...`
+                    : "Importing..."}
+                </p>
+              ) : null}
+            </div>
+            <div className="modal-footer summary-footer">
+              <button
+                className="btn primary"
+                disabled={
+                  sqliteImportRunning ||
+                  (!sqliteImportTable && !sqliteImportQuery.trim())
+                }
+                onClick={() => void runSqliteImport()}
+              >
+                Import
+              </button>
+            </div>
+          </div>
+        </div>
+      ) : null}
       {contextMenu ? (
         <div
           className="context-menu"
@@ -3861,6 +5928,7 @@ function App() {
                   setContextMenu(null);
                   void deleteSelectedRows();
                 }}
+                disabled={fileReadOnly}
               >
                 Delete selected
               </button>
@@ -3871,6 +5939,7 @@ function App() {
                   setContextMenu(null);
                   void restoreSelectedRows();
                 }}
+                disabled={fileReadOnly}
               >
                 Restore selected
               </button>
@@ -3906,12 +5975,10 @@ function App() {
                     contextMenu.rowIndex,
                     contextMenu.columnIndex,
                     contextMenu.cellText,
-                    dataRef.current.get(contextMenu.rowIndex)?.[
-                      contextMenu.columnIndex
-                    ] ?? contextMenu.cellText,
                   );
                 }}
                 disabled={
+                  fileReadOnly ||
                   contextMenu.cellText === null ||
                   contextMenu.rowIndex === null ||
                   contextMenu.columnIndex === null ||
@@ -3934,10 +6001,58 @@ function App() {
                   }
                   deleteRow(contextMenu.rowIndex);
                 }}
-                disabled={contextMenu.rowIndex === null}
+                disabled={fileReadOnly || contextMenu.rowIndex === null}
               >
                 {contextMenuRowDeleted ? "Restore row" : "Delete row"}
               </button>
+              <button
+                type="button"
+                className="context-menu-item"
+                onClick={() => {
+                  setContextMenu(null);
+                  insertRow();
+                }}
+                disabled={fileReadOnly || !rowCountReady}
+              >
+                Insert row at end
+              </button>
+              <button
+                type="button"
+                className="context-menu-item"
+                onClick={() => {
+                  if (contextMenu.columnIndex === null) {
+                    return;
+                  }
+                  setContextMenu(null);
+                  addColumn(contextMenu.columnIndex + 1);
+                }}
+                disabled={
+                  fileReadOnly ||
+                  contextMenu.columnIndex === null ||
+                  !rowCountReady
+                }
+              >
+                Add column after
+              </button>
+              <button
+                type="button"
+                className="context-menu-item"
+                onClick={() => {
+                  if (contextMenu.columnIndex === null) {
+                    return;
+                  }
+                  setContextMenu(null);
+                  removeColumn(contextMenu.columnIndex);
+                }}
+                disabled={
+                  fileReadOnly ||
+                  contextMenu.columnIndex === null ||
+                  headers.length <= 1 ||
+                  !rowCountReady
+                }
+              >
+                Remove column
+              </button>
               <button
                 type="button"
                 className="context-menu-item"
@@ -3960,6 +6075,20 @@ function App() {
               >
                 Search for this
               </button>
+              <button
+                type="button"
+                className="context-menu-item"
+                onClick={() => {
+                  if (contextMenu.columnIndex === null) {
+                    return;
+                  }
+                  setContextMenu(null);
+                  setValueCountsColumn(contextMenu.columnIndex);
+                }}
+                disabled={contextMenu.columnIndex === null || !rowCountReady}
+              >
+                Value counts
+              </button>
             </>
           )}
         </div>
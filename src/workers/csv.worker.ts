@@ -16,7 +16,12 @@ ctx.addEventListener("message", (event) => {
     return;
   }
 
-  const lookup = new Uint32Array(message.order.length);
+  // Deleted rows are left out of the order, so ids can exceed its length
+  let size = 0;
+  for (const row of message.order) {
+    size = Math.max(size, row + 1);
+  }
+  const lookup = new Uint32Array(size);
   for (let i = 0; i < message.order.length; i += 1) {
     lookup[message.order[i]] = i;
   }